@@ -0,0 +1,365 @@
+use crate::service::{AutoTagConfig, CyclePolicy, DuplicateTitlePolicy, ExportOptions, FileNaming, ListOrder, NoteFormat, NoteRules, SearchOrder, WikilinkSync};
+use crate::service::audit::AuditConfig;
+use crate::service::hooks::HookConfig;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+pub mod state;
+
+/// User configuration, read from `config.json` in the jjzettel config directory
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Vault location, used when `JJZETTEL_REPO` is not set
+    pub repo_path: Option<String>,
+    /// Key overrides per mode, e.g. `{"view": {"edit": "i"}}`
+    pub keybindings: HashMap<String, HashMap<String, KeySpec>>,
+    /// Highlight unrecognized words in View mode from startup (toggle with `S`)
+    pub spellcheck: bool,
+    /// Word list for spell checking, one word per line (default: system word list)
+    pub dictionary: Option<String>,
+    /// What to do when saving a note that has a title line but no body
+    pub empty_body: EmptyBodyPolicy,
+    /// Ask "Link 'a' → 'b'?" before creating a link in Link-Select mode
+    pub confirm_links: bool,
+    /// Whether linking to a note that already links back is allowed, warned about or refused
+    pub cycles: CyclePolicy,
+    /// Whether creating a note under a title another note has is allowed, asked about or refused
+    pub duplicate_titles: DuplicateTitlePolicy,
+    /// Tag and word-count rules checked (as warnings) when notes are saved
+    pub rules: NoteRules,
+    /// Tags added on save to notes whose content matches a pattern
+    pub auto_tags: AutoTagConfig,
+    /// Commands run in the background after notes are created, updated or deleted
+    pub hooks: HookConfig,
+    /// Fields and front-matter format of markdown exports (`E` in View mode)
+    pub export: ExportOptions,
+    /// How note text is fitted to the window in View, Edit and Create mode
+    pub wrap: WrapMode,
+    /// Whether URLs in View mode are emitted as clickable terminal hyperlinks (OSC 8)
+    pub hyperlinks: HyperlinkMode,
+    /// Title of the note Ctrl+N quick capture appends to (default "Inbox")
+    pub inbox: Option<String>,
+    /// JSON-lines log of every note operation, off by default
+    pub audit: AuditConfig,
+    /// Whether `[[Title]]` wikilinks add (and optionally remove) links when notes are saved
+    pub wikilinks: WikilinkSync,
+    /// JSON layout of note files: `pretty` (default) or `compact`
+    pub note_format: NoteFormat,
+    /// Note file names: `id` (default, `{id}.json`) or `slug` (`{slug}-{short id}.json`)
+    pub file_names: FileNaming,
+    /// Commit every change as it is made (default on). Off, changes are only written and
+    /// committed with `W` or outside jjzettel.
+    pub auto_commit: Option<bool>,
+    /// Keep `index.json` (titles and ids in alphabetical order) in the vault, off by default
+    pub index_file: bool,
+    /// Characters of the note id shown in View mode (default 8, 0 hides it)
+    pub short_id_length: Option<usize>,
+    /// Show the time and note/tag counts under the title bar (default on, toggle with `K`)
+    pub clock: Option<bool>,
+    /// Screen shown on launch: the note `list` (default) or the `dashboard`
+    pub start_screen: StartScreen,
+    /// What note lists show under each title: the first `line` of the content (default)
+    /// or the first `paragraph` after the title
+    pub preview: PreviewMode,
+    /// Paragraph mode: View joins lines broken inside a paragraph, markdown style, except in
+    /// notes marked strict with `B` (off by default)
+    pub paragraphs: bool,
+    /// Let `J` in View mode edit a note's raw JSON, off by default
+    pub raw_json: bool,
+    /// Keep the selected note selected when `r` reloads the list, wherever it moved to, with
+    /// the list scrolled along so it stays on the same row (default on). Off, the selection
+    /// and the scroll position stay where they were in the list.
+    pub refresh_keeps_selection: Option<bool>,
+    /// Notes not updated in this many days count as stale (default 90, 0 turns it off)
+    pub stale_days: Option<u32>,
+    /// Keep a "Referenced by" block listing the notes that link to a note at the end of
+    /// its content, off by default
+    pub referenced_by: bool,
+    /// Order of search results until toggled: `relevance` (default) or `recent`
+    pub search_order: SearchOrder,
+    /// Start Search mode with fuzzy matching (letters in order) instead of substring
+    /// matching, off by default
+    pub fuzzy_search: bool,
+    /// Order of the note list until toggled: `recent` (default) or `manual`
+    pub list_order: ListOrder,
+}
+
+/// Text shown under each title in note lists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PreviewMode {
+    /// The first line of the content, which is usually the title line
+    #[default]
+    Line,
+    /// The first paragraph after the title line
+    Paragraph,
+}
+
+/// First screen after launch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StartScreen {
+    /// The note list
+    #[default]
+    List,
+    /// Pinned notes, recently updated notes and saved searches
+    Dashboard,
+}
+
+/// Handling of notes saved with nothing after the title line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmptyBodyPolicy {
+    /// Ask before saving
+    #[default]
+    Warn,
+    /// Save without asking
+    Allow,
+}
+
+/// Line wrapping for note text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WrapMode {
+    /// Wrap long lines and drop leading whitespace on each line
+    #[default]
+    Trim,
+    /// Wrap long lines but keep indentation
+    Keep,
+    /// Never wrap; long lines scroll sideways
+    Off,
+}
+
+/// Terminal hyperlinks for URLs in note text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HyperlinkMode {
+    /// Only in terminals known to support them
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// One key (`"e"`) or several keys (`["j", "Down"]`) bound to an action
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum KeySpec {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeySpec {
+    pub fn keys(&self) -> Vec<String> {
+        match self {
+            KeySpec::One(key) => vec![key.clone()],
+            KeySpec::Many(keys) => keys.clone(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the config from the default location, or defaults if no file exists
+    pub fn load() -> Result<Self> {
+        match Self::default_path() {
+            Some(path) if path.exists() => Self::load_from(&path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    /// Load the config from a specific file
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    /// Config file location: `JJZETTEL_CONFIG`, then `$XDG_CONFIG_HOME/jjzettel/config.json`,
+    /// then `~/.config/jjzettel/config.json`
+    pub fn default_path() -> Option<PathBuf> {
+        if let Ok(path) = std::env::var("JJZETTEL_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        if let Ok(dir) = std::env::var("XDG_CONFIG_HOME") {
+            return Some(PathBuf::from(dir).join("jjzettel").join("config.json"));
+        }
+        std::env::var("HOME")
+            .or_else(|_| std::env::var("USERPROFILE"))
+            .ok()
+            .map(|home| PathBuf::from(home).join(".config").join("jjzettel").join("config.json"))
+    }
+}
+
+/// Command-line options
+#[derive(Debug, Clone, Default)]
+pub struct CliArgs {
+    /// `--repo <path>`, takes precedence over `JJZETTEL_REPO`
+    pub repo: Option<String>,
+    /// `--help` was given
+    pub help: bool,
+    /// `--backfill`: commit notes with uncommitted changes, then exit
+    pub backfill: bool,
+}
+
+impl CliArgs {
+    pub const USAGE: &'static str = "Usage: jjzettel [--repo <path>] [--backfill]
+
+Options:
+  --repo <path>  Vault location (overrides JJZETTEL_REPO and the config file)
+  --backfill     Commit every note whose current state has no commit yet, then exit
+  -h, --help     Print this help
+
+Environment:
+  JJZETTEL_REPO       Vault location
+  JJZETTEL_NOTES_DIR  Notes directory (default: <repo>/notes, relative paths are inside the repo)
+  JJZETTEL_CONFIG     Config file path
+  JJZETTEL_STATE      State file path (search history)";
+
+    /// Parse arguments, excluding the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut cli = CliArgs::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--repo" => match args.next() {
+                    // `--repo --backfill` is a forgotten path, not a vault named --backfill
+                    Some(path) if !path.starts_with('-') => cli.repo = Some(path),
+                    _ => anyhow::bail!("--repo needs a path\n\n{}", Self::USAGE),
+                },
+                "-h" | "--help" => cli.help = true,
+                "--backfill" => cli.backfill = true,
+                _ => match arg.strip_prefix("--repo=") {
+                    Some("") => anyhow::bail!("--repo needs a path\n\n{}", Self::USAGE),
+                    Some(path) => cli.repo = Some(path.to_string()),
+                    None => anyhow::bail!("Unknown argument '{}'\n\n{}", arg, Self::USAGE),
+                },
+            }
+        }
+        Ok(cli)
+    }
+}
+
+/// Resolve where the vault lives, in order: `--repo`, `JJZETTEL_REPO`, `repo_path` from the config,
+/// then `~/.jjzettel` via `HOME` or `USERPROFILE`. Never falls back to the current directory.
+/// `env` looks up an environment variable so callers can inject one.
+pub fn resolve_repo_path(cli_repo: Option<&str>, env: impl Fn(&str) -> Option<String>, config: &Config) -> Result<String> {
+    let env = |name: &str| env(name).filter(|value| !value.trim().is_empty());
+
+    if let Some(path) = cli_repo
+        && !path.trim().is_empty()
+    {
+        return Ok(path.to_string());
+    }
+    if let Some(path) = env("JJZETTEL_REPO") {
+        return Ok(path);
+    }
+    if let Some(ref path) = config.repo_path
+        && !path.trim().is_empty()
+    {
+        return Ok(path.clone());
+    }
+    if let Some(home) = env("HOME").or_else(|| env("USERPROFILE")) {
+        return Ok(format!("{}/.jjzettel", home));
+    }
+
+    anyhow::bail!(
+        "Cannot determine where to store notes: JJZETTEL_REPO, HOME and USERPROFILE are all unset \
+         and the config has no repo_path. Set JJZETTEL_REPO=/path/to/vault, pass --repo or add \"repo_path\" to the config file."
+    )
+}
+
+/// Resolve the notes directory: `JJZETTEL_NOTES_DIR` (relative paths are inside the repo),
+/// otherwise `notes/` in the repo
+pub fn resolve_notes_dir(env: impl Fn(&str) -> Option<String>, repo_path: &str) -> PathBuf {
+    match env("JJZETTEL_NOTES_DIR").filter(|value| !value.trim().is_empty()) {
+        Some(dir) => Path::new(repo_path).join(dir),
+        None => Path::new(repo_path).join("notes"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Environment lookup over the given variables only
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| vars.iter().find(|(var, _)| *var == name).map(|(_, value)| value.to_string())
+    }
+
+    fn config_with_repo(path: &str) -> Config {
+        Config { repo_path: Some(path.to_string()), ..Config::default() }
+    }
+
+    fn parse(args: &[&str]) -> Result<CliArgs> {
+        CliArgs::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn repo_is_taken_from_either_flag_form() {
+        assert_eq!(parse(&["--repo", "/vault"]).unwrap().repo.as_deref(), Some("/vault"));
+        assert_eq!(parse(&["--repo=/vault"]).unwrap().repo.as_deref(), Some("/vault"));
+        assert_eq!(parse(&[]).unwrap().repo, None);
+    }
+
+    #[test]
+    fn last_repo_flag_wins_and_other_flags_mix_in() {
+        let cli = parse(&["--repo", "/first", "--backfill", "--repo=/second", "-h"]).unwrap();
+        assert_eq!(cli.repo.as_deref(), Some("/second"));
+        assert!(cli.backfill && cli.help);
+    }
+
+    #[test]
+    fn repo_flag_without_a_path_is_an_error() {
+        for args in [&["--repo"][..], &["--repo="], &["--repo", "--backfill"]] {
+            let err = parse(args).unwrap_err();
+            assert!(err.to_string().starts_with("--repo needs a path"), "{:?}: {}", args, err);
+        }
+        assert!(parse(&["--rpeo", "/vault"]).unwrap_err().to_string().starts_with("Unknown argument '--rpeo'"));
+    }
+
+    #[test]
+    fn repo_flag_overrides_the_environment_and_config() {
+        let cli = parse(&["--repo", "/cli/vault"]).unwrap();
+        let vars = [("JJZETTEL_REPO", "/env/vault")];
+        assert_eq!(resolve_repo_path(cli.repo.as_deref(), env(&vars), &config_with_repo("/config/vault")).unwrap(), "/cli/vault");
+    }
+
+    #[test]
+    fn repo_path_precedence_is_cli_env_config_home() {
+        let vars = [("JJZETTEL_REPO", "/env/vault"), ("HOME", "/home/me")];
+        let config = config_with_repo("/config/vault");
+
+        assert_eq!(resolve_repo_path(Some("/cli/vault"), env(&vars), &config).unwrap(), "/cli/vault");
+        assert_eq!(resolve_repo_path(None, env(&vars), &config).unwrap(), "/env/vault");
+        assert_eq!(resolve_repo_path(None, env(&vars[1..]), &config).unwrap(), "/config/vault");
+        assert_eq!(resolve_repo_path(None, env(&vars[1..]), &Config::default()).unwrap(), "/home/me/.jjzettel");
+        assert_eq!(
+            resolve_repo_path(None, env(&[("USERPROFILE", "C:/Users/me")]), &Config::default()).unwrap(),
+            "C:/Users/me/.jjzettel"
+        );
+    }
+
+    #[test]
+    fn blank_values_fall_through_to_the_next_source() {
+        let vars = [("JJZETTEL_REPO", "  "), ("HOME", "/home/me")];
+        assert_eq!(resolve_repo_path(Some(""), env(&vars), &config_with_repo(" ")).unwrap(), "/home/me/.jjzettel");
+    }
+
+    #[test]
+    fn no_repo_and_no_home_is_an_error() {
+        let err = resolve_repo_path(None, env(&[]), &Config::default()).unwrap_err();
+        assert!(err.to_string().contains("HOME and USERPROFILE are all unset"), "{}", err);
+    }
+
+    #[test]
+    fn notes_dir_is_inside_the_repo_unless_absolute() {
+        assert_eq!(resolve_notes_dir(env(&[]), "/vault"), PathBuf::from("/vault/notes"));
+        assert_eq!(resolve_notes_dir(env(&[("JJZETTEL_NOTES_DIR", "zettel")]), "/vault"), PathBuf::from("/vault/zettel"));
+        assert_eq!(resolve_notes_dir(env(&[("JJZETTEL_NOTES_DIR", "/elsewhere")]), "/vault"), PathBuf::from("/elsewhere"));
+        assert_eq!(resolve_notes_dir(env(&[("JJZETTEL_NOTES_DIR", "")]), "/vault"), PathBuf::from("/vault/notes"));
+    }
+}
@@ -0,0 +1,240 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Most search queries kept in the history
+const SEARCH_HISTORY_LIMIT: usize = 50;
+
+/// What jjzettel remembers between runs, kept in `state.json` in the jjzettel state directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppState {
+    /// Submitted search queries, oldest first
+    pub search_history: Vec<String>,
+    /// The first-run walkthrough was finished or skipped
+    pub tutorial_done: bool,
+    /// Named search queries, in the order they were saved
+    pub saved_searches: Vec<SavedSearch>,
+    /// Ids of the notes pinned to the dashboard, in the order they were pinned
+    pub pinned_notes: Vec<String>,
+    /// File the state was loaded from and is saved to
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+/// A search query kept under a name, e.g. `#meeting` as "Meetings"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+}
+
+impl AppState {
+    /// Load the state from the default location. Missing or unreadable state is not an
+    /// error; it only means nothing is remembered yet.
+    pub fn load() -> Self {
+        match Self::default_path() {
+            Some(path) => Self::load_or_default(path),
+            None => AppState::default(),
+        }
+    }
+
+    /// Load the state kept in `path`, or start empty there if it is missing or unreadable
+    pub fn load_or_default(path: PathBuf) -> Self {
+        let mut state = Self::load_from(&path).unwrap_or_default();
+        state.path = Some(path);
+        state
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read state file {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid state file {}", path.display()))
+    }
+
+    /// Write the state to the file it came from, creating its directory if needed
+    pub fn save(&self) -> Result<()> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Cannot determine the state file location"))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write state file {}", path.display()))
+    }
+
+    /// State file location: `JJZETTEL_STATE`, then `$XDG_STATE_HOME/jjzettel/state.json`,
+    /// then `~/.local/state/jjzettel/state.json`
+    pub fn default_path() -> Option<PathBuf> {
+        if let Ok(path) = std::env::var("JJZETTEL_STATE") {
+            return Some(PathBuf::from(path));
+        }
+        if let Ok(dir) = std::env::var("XDG_STATE_HOME") {
+            return Some(PathBuf::from(dir).join("jjzettel").join("state.json"));
+        }
+        std::env::var("HOME")
+            .or_else(|_| std::env::var("USERPROFILE"))
+            .ok()
+            .map(|home| PathBuf::from(home).join(".local").join("state").join("jjzettel").join("state.json"))
+    }
+
+    /// Whether to offer the walkthrough: an empty vault and no state file yet, so this is
+    /// most likely the very first start. Once shown, the state records it as done.
+    pub fn is_first_run(&self, note_count: usize) -> bool {
+        !self.tutorial_done && note_count == 0 && self.path.as_ref().is_none_or(|path| !path.exists())
+    }
+
+    /// Save `query` under `name`, replacing the query of a saved search with the same name
+    /// (ignoring case) in place
+    pub fn save_search(&mut self, name: &str, query: &str) {
+        let search = SavedSearch { name: name.to_string(), query: query.to_string() };
+        match self.saved_searches.iter_mut().find(|s| s.name.eq_ignore_ascii_case(name)) {
+            Some(existing) => *existing = search,
+            None => self.saved_searches.push(search),
+        }
+    }
+
+    /// Pin the note `id` to the dashboard, or unpin it if it already is.
+    /// Returns whether it is pinned now.
+    pub fn toggle_pin(&mut self, id: &str) -> bool {
+        match self.pinned_notes.iter().position(|pinned| pinned == id) {
+            Some(i) => {
+                self.pinned_notes.remove(i);
+                false
+            }
+            None => {
+                self.pinned_notes.push(id.to_string());
+                true
+            }
+        }
+    }
+
+    /// Remember a submitted query. Repeating the last query does not add it again,
+    /// and the oldest queries are dropped past the limit.
+    pub fn push_search(&mut self, query: &str) {
+        if self.search_history.last().is_some_and(|last| last == query) {
+            return;
+        }
+        self.search_history.push(query.to_string());
+        if self.search_history.len() > SEARCH_HISTORY_LIMIT {
+            let excess = self.search_history.len() - SEARCH_HISTORY_LIMIT;
+            self.search_history.drain(..excess);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Path of a state file in a fresh temporary directory that does not exist yet
+    fn temp_state_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("jjzettel-state-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join("nested").join("state.json")
+    }
+
+    fn remove(path: &Path) {
+        std::fs::remove_dir_all(path.parent().unwrap().parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn repeated_queries_are_kept_once_in_a_row() {
+        let mut state = AppState::default();
+        for query in ["a", "b", "b", "a"] {
+            state.push_search(query);
+        }
+        assert_eq!(state.search_history, ["a", "b", "a"]);
+    }
+
+    #[test]
+    fn history_drops_the_oldest_queries_past_the_limit() {
+        let mut state = AppState::default();
+        for i in 0..SEARCH_HISTORY_LIMIT + 5 {
+            state.push_search(&format!("q{}", i));
+        }
+        assert_eq!(state.search_history.len(), SEARCH_HISTORY_LIMIT);
+        assert_eq!(state.search_history.first().map(String::as_str), Some("q5"));
+        assert_eq!(state.search_history.last().map(String::as_str), Some("q54"));
+    }
+
+    #[test]
+    fn search_history_survives_a_save_and_load() {
+        let path = temp_state_path("history");
+        let mut state = AppState::load_or_default(path.clone());
+        state.push_search("#rust");
+        state.push_search("title:plan");
+        state.save().unwrap();
+
+        let loaded = AppState::load_or_default(path.clone());
+        remove(&path);
+        assert_eq!(loaded.search_history, ["#rust", "title:plan"]);
+        assert_eq!(loaded.path.as_deref(), Some(path.as_path()));
+    }
+
+    #[test]
+    fn corrupt_state_file_starts_empty_and_is_replaced_on_save() {
+        let path = temp_state_path("corrupt");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "{ not json").unwrap();
+
+        let err = AppState::load_from(&path).unwrap_err();
+        assert!(err.to_string().starts_with("Invalid state file"), "{}", err);
+        let mut state = AppState::load_or_default(path.clone());
+        assert!(state.search_history.is_empty() && !state.tutorial_done);
+
+        state.push_search("after");
+        state.save().unwrap();
+        let reloaded = AppState::load_from(&path).unwrap();
+        remove(&path);
+        assert_eq!(reloaded.search_history, ["after"]);
+    }
+
+    fn saved(state: &AppState) -> Vec<(&str, &str)> {
+        state.saved_searches.iter().map(|s| (s.name.as_str(), s.query.as_str())).collect()
+    }
+
+    #[test]
+    fn saving_under_a_taken_name_replaces_it_in_place() {
+        let mut state = AppState::default();
+        state.save_search("Meetings", "#meeting");
+        state.save_search("Drafts", "status:draft");
+        state.save_search("meetings", "#meeting #2025");
+        assert_eq!(saved(&state), [("meetings", "#meeting #2025"), ("Drafts", "status:draft")]);
+    }
+
+    #[test]
+    fn saved_searches_survive_a_save_and_load() {
+        let path = temp_state_path("saved-searches");
+        let mut state = AppState::load_or_default(path.clone());
+        state.save_search("Meetings", "#meeting");
+        state.save_search("Inbox", "#inbox");
+        state.save().unwrap();
+
+        let loaded = AppState::load_or_default(path.clone());
+        remove(&path);
+        assert_eq!(saved(&loaded), [("Meetings", "#meeting"), ("Inbox", "#inbox")]);
+    }
+
+    #[test]
+    fn state_from_before_saved_searches_loads_without_them() {
+        let path = temp_state_path("old-format");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, r#"{"search_history": ["old"]}"#).unwrap();
+
+        let loaded = AppState::load_from(&path).unwrap();
+        remove(&path);
+        assert_eq!(loaded.search_history, ["old"]);
+        assert!(loaded.saved_searches.is_empty());
+    }
+
+    #[test]
+    fn state_without_a_location_cannot_be_saved() {
+        let err = AppState::default().save().unwrap_err();
+        assert_eq!(err.to_string(), "Cannot determine the state file location");
+    }
+}
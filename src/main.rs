@@ -1,42 +1,91 @@
 use anyhow::Result;
 use crossterm::event::{self, Event, KeyEventKind};
+use crossterm::event::{DisableBracketedPaste, EnableBracketedPaste};
 use crossterm::execute;
 use crossterm::terminal::{
     EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
 };
 use ratatui::prelude::*;
 use std::io;
+use std::time::Duration;
 
+mod config;
 mod storage;
 mod service;
 mod tui;
 
+use config::CliArgs;
 use tui::app::App;
 
 fn main() -> Result<()> {
-    // Setup terminal
-    enable_raw_mode().map_err(|e| anyhow::anyhow!("Failed to enable raw mode: {}. Make sure you're running in a terminal.", e))?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen).map_err(|e| anyhow::anyhow!("Failed to enter alternate screen: {}. Make sure you're running in a terminal.", e))?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend).map_err(|e| anyhow::anyhow!("Failed to create terminal: {}. Make sure you're running in a terminal.", e))?;
+    let cli = CliArgs::parse(std::env::args().skip(1))?;
+    if cli.help {
+        println!("{}", CliArgs::USAGE);
+        return Ok(());
+    }
+
+    // Create app before touching the terminal so config errors are readable
+    let mut app = App::new(&cli)?;
+
+    if cli.backfill {
+        let committed = app.service.backfill_history()?;
+        println!("Backfilled {} note{}", committed, if committed == 1 { "" } else { "s" });
+        return Ok(());
+    }
 
-    // Create app
-    let mut app = App::new()?;
+    // Setup terminal; it is restored when `_session` is dropped, however main returns
+    let _session = TerminalSession::start()?;
+    let backend = CrosstermBackend::new(io::stdout());
+    let mut terminal = Terminal::new(backend).map_err(|e| anyhow::anyhow!("Failed to create terminal: {}. Make sure you're running in a terminal.", e))?;
 
     // Main loop
     while !app.should_quit {
+        app.tick();
         terminal.draw(|f| app.render(f))?;
 
-        if let Event::Key(key) = event::read()? {
-            if key.kind == KeyEventKind::Press {
-                app.handle_key(key.code, key.modifiers)?;
+        // Wake up now and then so background hook results and the clock update without a key press
+        if event::poll(Duration::from_millis(250))? {
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    app.handle_key(key.code, key.modifiers)?;
+                }
+                Event::Paste(text) => app.handle_paste(&text)?,
+                _ => {}
             }
         }
+
+        // Ctrl+E while editing: the editor gets the terminal until it exits
+        if let Some(edit) = app.take_external_edit() {
+            disable_raw_mode()?;
+            execute!(terminal.backend_mut(), DisableBracketedPaste, LeaveAlternateScreen)?;
+            let result = edit.run();
+            enable_raw_mode()?;
+            execute!(terminal.backend_mut(), EnterAlternateScreen, EnableBracketedPaste)?;
+            terminal.clear()?;
+            app.finish_external_edit(edit, result);
+        }
     }
 
-    // Restore terminal
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
     Ok(())
 }
+
+/// Raw mode and the alternate screen for the TUI, left again when dropped so that an error
+/// returned from the main loop still gives the user their terminal back
+struct TerminalSession;
+
+impl TerminalSession {
+    fn start() -> Result<Self> {
+        enable_raw_mode().map_err(|e| anyhow::anyhow!("Failed to enable raw mode: {}. Make sure you're running in a terminal.", e))?;
+        // From here on a failure is undone by the drop
+        let session = TerminalSession;
+        execute!(io::stdout(), EnterAlternateScreen, EnableBracketedPaste).map_err(|e| anyhow::anyhow!("Failed to enter alternate screen: {}. Make sure you're running in a terminal.", e))?;
+        Ok(session)
+    }
+}
+
+impl Drop for TerminalSession {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), DisableBracketedPaste, LeaveAlternateScreen);
+    }
+}
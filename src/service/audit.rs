@@ -0,0 +1,131 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Audit trail settings. When enabled, every note operation appends one JSON line to the
+/// log, separate from the Jujutsu history (which can be rewritten).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AuditConfig {
+    pub enabled: bool,
+    /// Log file; relative paths are inside the repo (default `audit.log`)
+    pub path: Option<String>,
+}
+
+impl AuditConfig {
+    /// The log to write for a vault at `repo_path`, if auditing is enabled
+    pub fn log_for(&self, repo_path: &Path) -> Option<AuditLog> {
+        if !self.enabled {
+            return None;
+        }
+        let path = self.path.as_deref().unwrap_or("audit.log");
+        Some(AuditLog::new(repo_path.join(path)))
+    }
+}
+
+/// One line of the audit log
+#[derive(Debug, Serialize)]
+pub struct AuditEntry {
+    pub timestamp: String,
+    pub action: String,
+    pub note_id: String,
+    /// What the action applied to, e.g. the linked note id or the tag
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Append-only JSON-lines log of note operations
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        AuditLog { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an entry for `action` on `note_id`
+    pub fn record(&self, action: &str, note_id: &str, detail: Option<&str>) -> Result<()> {
+        let entry = AuditEntry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            action: action.to_string(),
+            note_id: note_id.to_string(),
+            detail: detail.map(str::to_string),
+        };
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open audit log {}", self.path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)
+            .with_context(|| format!("Failed to write audit log {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_log(name: &str) -> AuditLog {
+        let dir = std::env::temp_dir().join(format!("jjzettel-audit-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        AuditLog::new(dir.join("audit.log"))
+    }
+
+    fn lines(log: &AuditLog) -> Vec<serde_json::Value> {
+        let content = std::fs::read_to_string(log.path()).unwrap();
+        std::fs::remove_dir_all(log.path().parent().unwrap()).unwrap();
+        content.lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+    }
+
+    #[test]
+    fn each_record_is_one_json_line() {
+        let log = temp_log("format");
+        log.record("add_tag", "abc123", Some("rust")).unwrap();
+        log.record("delete", "abc123", None).unwrap();
+
+        let entries = lines(&log);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["action"], "add_tag");
+        assert_eq!(entries[0]["note_id"], "abc123");
+        assert_eq!(entries[0]["detail"], "rust");
+        assert!(chrono::DateTime::parse_from_rfc3339(entries[0]["timestamp"].as_str().unwrap()).is_ok());
+        assert_eq!(entries[1]["action"], "delete");
+        assert!(entries[1].get("detail").is_none(), "no detail field without a detail");
+    }
+
+    #[test]
+    fn records_are_appended_to_what_is_there() {
+        let log = temp_log("append");
+        std::fs::write(log.path(), "{\"action\":\"earlier\"}\n").unwrap();
+        log.record("create", "one", None).unwrap();
+        log.record("update", "two", None).unwrap();
+
+        let actions: Vec<String> = lines(&log).iter().map(|entry| entry["action"].as_str().unwrap().to_string()).collect();
+        assert_eq!(actions, ["earlier", "create", "update"]);
+    }
+
+    #[test]
+    fn unwritable_log_names_the_file() {
+        let log = AuditLog::new("/nonexistent-dir/audit.log");
+        let err = log.record("create", "one", None).unwrap_err();
+        assert_eq!(err.to_string(), "Failed to open audit log /nonexistent-dir/audit.log");
+    }
+
+    #[test]
+    fn log_is_only_kept_when_enabled_and_inside_the_repo_by_default() {
+        let repo = Path::new("/vault");
+        assert!(AuditConfig::default().log_for(repo).is_none());
+        let enabled = AuditConfig { enabled: true, path: None };
+        assert_eq!(enabled.log_for(repo).unwrap().path(), Path::new("/vault/audit.log"));
+        let elsewhere = AuditConfig { enabled: true, path: Some("/var/log/zettel.log".to_string()) };
+        assert_eq!(elsewhere.log_for(repo).unwrap().path(), Path::new("/var/log/zettel.log"));
+    }
+}
@@ -0,0 +1,171 @@
+use serde::Deserialize;
+use std::path::Path;
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// Commands run after notes change, e.g. to sync or re-index the vault.
+/// Each is a program path that gets `<note id> <note file>` as arguments and
+/// `JJZETTEL_EVENT`, `JJZETTEL_NOTE_ID` and `JJZETTEL_NOTE_PATH` in its environment.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HookConfig {
+    pub on_create: Option<String>,
+    pub on_update: Option<String>,
+    pub on_delete: Option<String>,
+}
+
+/// Note change that can trigger a hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    Create,
+    Update,
+    Delete,
+}
+
+impl HookEvent {
+    pub fn name(self) -> &'static str {
+        match self {
+            HookEvent::Create => "create",
+            HookEvent::Update => "update",
+            HookEvent::Delete => "delete",
+        }
+    }
+}
+
+/// Runs configured hooks on background threads and collects their failures
+pub struct HookRunner {
+    config: HookConfig,
+    sender: Sender<String>,
+    failures: Receiver<String>,
+}
+
+impl HookRunner {
+    pub fn new(config: HookConfig) -> Self {
+        let (sender, failures) = mpsc::channel();
+        HookRunner { config, sender, failures }
+    }
+
+    /// Start the hook for `event`, if one is configured, without waiting for it
+    pub fn fire(&self, event: HookEvent, note_id: &str, note_file: &Path) {
+        let command = match event {
+            HookEvent::Create => &self.config.on_create,
+            HookEvent::Update => &self.config.on_update,
+            HookEvent::Delete => &self.config.on_delete,
+        };
+        let Some(program) = command.clone() else {
+            return;
+        };
+
+        let note_id = note_id.to_string();
+        let note_file = note_file.to_path_buf();
+        let sender = self.sender.clone();
+        std::thread::spawn(move || {
+            let result = Command::new(&program)
+                .arg(&note_id)
+                .arg(&note_file)
+                .env("JJZETTEL_EVENT", event.name())
+                .env("JJZETTEL_NOTE_ID", &note_id)
+                .env("JJZETTEL_NOTE_PATH", &note_file)
+                .output();
+            let failure = match result {
+                Ok(output) if output.status.success() => return,
+                Ok(output) => {
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    let detail = stderr.lines().next().unwrap_or("").trim();
+                    if detail.is_empty() {
+                        format!("{} hook failed ({})", event.name(), output.status)
+                    } else {
+                        format!("{} hook failed ({}): {}", event.name(), output.status, detail)
+                    }
+                }
+                Err(e) => format!("{} hook could not start {}: {}", event.name(), program, e),
+            };
+            // The app may already be gone; nothing left to report to then
+            let _ = sender.send(failure);
+        });
+    }
+
+    /// Failures reported by finished hooks since the last call
+    pub fn take_failures(&self) -> Vec<String> {
+        self.failures.try_iter().collect()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+
+    /// Fresh directory with an executable `hook.sh` running `script`
+    fn hook_script(name: &str, script: &str) -> (PathBuf, String) {
+        let dir = std::env::temp_dir().join(format!("jjzettel-hooks-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hook.sh");
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        (dir, path.to_string_lossy().into_owned())
+    }
+
+    /// Wait up to five seconds for `done`
+    fn wait_until(mut done: impl FnMut() -> bool) {
+        let start = Instant::now();
+        while !done() {
+            assert!(start.elapsed() < Duration::from_secs(5), "hook did not finish");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn hook_gets_the_note_as_arguments_and_environment() {
+        let (dir, program) = hook_script(
+            "args",
+            r#"echo "$1|$2|$JJZETTEL_EVENT|$JJZETTEL_NOTE_ID|$JJZETTEL_NOTE_PATH" > "$(dirname "$0")/out.tmp"
+mv "$(dirname "$0")/out.tmp" "$(dirname "$0")/out""#,
+        );
+        let runner = HookRunner::new(HookConfig { on_create: Some(program), ..HookConfig::default() });
+
+        runner.fire(HookEvent::Create, "abc123", Path::new("/vault/notes/abc123.json"));
+        let out = dir.join("out");
+        wait_until(|| out.exists());
+        let written = std::fs::read_to_string(&out).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(written.trim(), "abc123|/vault/notes/abc123.json|create|abc123|/vault/notes/abc123.json");
+        assert!(runner.take_failures().is_empty());
+    }
+
+    #[test]
+    fn failing_hook_reports_its_status_and_first_error_line() {
+        let (dir, program) = hook_script("fail", "echo 'sync refused' >&2\necho 'more detail' >&2\nexit 3");
+        let runner = HookRunner::new(HookConfig { on_update: Some(program), ..HookConfig::default() });
+
+        runner.fire(HookEvent::Update, "abc123", Path::new("note.json"));
+        let mut failures = Vec::new();
+        wait_until(|| {
+            failures.extend(runner.take_failures());
+            !failures.is_empty()
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(failures, ["update hook failed (exit status: 3): sync refused"]);
+    }
+
+    #[test]
+    fn missing_program_is_reported_and_unset_hooks_do_nothing() {
+        let runner = HookRunner::new(HookConfig { on_delete: Some("/nonexistent/jjzettel-hook".to_string()), ..HookConfig::default() });
+
+        runner.fire(HookEvent::Create, "abc123", Path::new("note.json"));
+        runner.fire(HookEvent::Delete, "abc123", Path::new("note.json"));
+        let mut failures = Vec::new();
+        wait_until(|| {
+            failures.extend(runner.take_failures());
+            !failures.is_empty()
+        });
+
+        assert_eq!(failures.len(), 1, "{:?}", failures);
+        assert!(failures[0].starts_with("delete hook could not start /nonexistent/jjzettel-hook: "), "{}", failures[0]);
+    }
+}
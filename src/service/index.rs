@@ -0,0 +1,72 @@
+use anyhow::{Context, Result};
+use crate::storage::note::Note;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// `index.json` in the vault: every note's title and id in alphabetical order, so tools
+/// can list the vault without opening each note file. Each entry remembers when its file
+/// was last modified, which tells a refresh which files need reading again.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NoteIndex {
+    pub notes: Vec<IndexEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub title: String,
+    pub id: String,
+    /// File name in the notes directory
+    pub file: String,
+    /// Modification time of the file when the entry was read, RFC 3339 in nanoseconds
+    pub modified: String,
+}
+
+impl NoteIndex {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read index {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid index {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to write index {}", path.display()))
+    }
+
+    /// Bring the entries in line with `note_files`, the note files in the notes directory:
+    /// files that are new or were modified since their entry was read are read again,
+    /// entries of removed files are dropped. Returns whether anything changed.
+    pub fn refresh(&mut self, note_files: &[PathBuf]) -> Result<bool> {
+        let mut known: HashMap<String, IndexEntry> =
+            self.notes.drain(..).map(|entry| (entry.file.clone(), entry)).collect();
+        let mut changed = false;
+
+        for path in note_files {
+            let Some(file) = path.file_name().and_then(|s| s.to_str()).map(str::to_string) else {
+                continue;
+            };
+            let modified: chrono::DateTime<chrono::Utc> = std::fs::metadata(path)?.modified()?.into();
+            let modified = modified.to_rfc3339_opts(chrono::SecondsFormat::Nanos, true);
+            if let Some(entry) = known.remove(&file)
+                && entry.modified == modified
+            {
+                self.notes.push(entry);
+                continue;
+            }
+            let content = std::fs::read_to_string(path)?;
+            let note = Note::from_json(&content)
+                .with_context(|| format!("Invalid note file {}", path.display()))?;
+            self.notes.push(IndexEntry { title: note.title, id: note.id, file, modified });
+            changed = true;
+        }
+
+        // Whatever is left in `known` belongs to files that are gone
+        changed |= !known.is_empty();
+        self.notes.sort_by(|a, b| {
+            a.title.to_lowercase().cmp(&b.title.to_lowercase()).then_with(|| a.id.cmp(&b.id))
+        });
+        Ok(changed)
+    }
+}
@@ -1,4 +1,8 @@
+pub mod audit;
+pub mod hooks;
+pub mod index;
 pub mod note_service;
+pub mod spellcheck;
 
-pub use note_service::NoteService;
+pub use note_service::{extract_tasks, has_body, slugify_title, AutoTagConfig, AutoTagger, Cancelled, CyclePolicy, DuplicateTitle, DuplicateTitlePolicy, ExportOptions, FileNaming, ListOrder, NoteFormat, NoteRules, NoteService, SearchOrder, Stub, StubReason, TagVariants, Task, UndoPoint, VaultHealth, WikilinkSync, MIN_ID_PREFIX, UNTAGGED_GROUP};
 
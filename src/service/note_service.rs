@@ -1,62 +1,908 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use crate::service::audit::AuditLog;
+use crate::service::index::NoteIndex;
 use crate::storage::jujutsu::Jujutsu;
-use crate::storage::note::Note;
-use std::path::PathBuf;
+use crate::storage::note::{is_stale, normalized_body, Note, NoteSummary};
+use crate::storage::template::{self, Template};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
 
+/// Error of a long operation stopped early because its cancel flag was set. Callers tell it
+/// apart from real failures with `error.is::<Cancelled>()`.
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Error of creating a note under a title another note already has, refused by
+/// `DuplicateTitlePolicy::Block`. `existing` is that note, so callers can offer to open it.
+#[derive(Debug)]
+pub struct DuplicateTitle {
+    pub existing: Note,
+}
+
+impl std::fmt::Display for DuplicateTitle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "A note titled '{}' already exists", self.existing.title.trim())
+    }
+}
+
+impl std::error::Error for DuplicateTitle {}
+
+/// Name of the tag group holding notes without tags
+pub const UNTAGGED_GROUP: &str = "Untagged";
+
+/// How to treat a link whose target already links back (A → B while B → A)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CyclePolicy {
+    /// Link without comment
+    #[default]
+    Allow,
+    /// Link, but report the cycle
+    Warn,
+    /// Refuse the link
+    Forbid,
+}
+
+/// How to treat a new note whose title another note already has (compared like `resolve_title`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateTitlePolicy {
+    /// Create it without asking
+    #[default]
+    Allow,
+    /// Ask before creating it (the app asks; the service creates it)
+    Warn,
+    /// Refuse to create it
+    Block,
+}
+
+/// How `[[Title]]` wikilinks in note content update the note's `links` on save
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WikilinkSync {
+    /// Wikilinks are plain text
+    #[default]
+    Off,
+    /// A new wikilink to an existing note adds a link
+    Add,
+    /// Like `Add`, and deleting a wikilink also removes its link
+    Sync,
+}
+
+/// Layout of the JSON in note files. Both read back the same; compact files are about
+/// half the size, pretty ones are easier to read and edit by hand.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteFormat {
+    /// Indented, one field per line
+    #[default]
+    Pretty,
+    /// Everything on one line
+    Compact,
+}
+
+/// How note files are named in the notes directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileNaming {
+    /// `{id}.json`
+    #[default]
+    Id,
+    /// `{slug}-{short id}.json`, e.g. `Meeting_notes-3fa2b1c9.json`, so the folder and
+    /// diffs show which note a file is
+    Slug,
+}
+
+/// Which fields a markdown export carries above the content, and in what format
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ExportOptions {
+    pub format: FrontMatter,
+    pub id: bool,
+    pub dates: bool,
+    pub tags: bool,
+    pub links: bool,
+    pub metadata: bool,
+    /// Word, link and backlink counts, for tools that index exports (off by default)
+    pub stats: bool,
+    /// How links to other notes are written, in the links field and in the content
+    pub link_style: LinkStyle,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            format: FrontMatter::Bold,
+            id: true,
+            dates: true,
+            tags: true,
+            links: true,
+            metadata: true,
+            stats: false,
+            link_style: LinkStyle::default(),
+        }
+    }
+}
+
+/// Layout of the exported metadata block
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrontMatter {
+    /// `**Key:** value` lines between `---` rules, after the title heading
+    #[default]
+    Bold,
+    /// YAML front matter at the top of the file, as read by static site generators
+    Yaml,
+}
+
+/// How exports write links to other notes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkStyle {
+    /// `[[Title]]`, as in the notes themselves
+    #[default]
+    Wikilink,
+    /// `[Title](Title.md)`, pointing at the file a single-note export writes
+    Markdown,
+    /// `Title[^1]`, with `[^1]: Title` footnotes after the note
+    Footnote,
+}
+
+/// Order of search results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchOrder {
+    /// Best matches first: the title equal to the query, then titles starting with it,
+    /// titles containing it, and notes mentioning it most often. Queries with no text to
+    /// rank by (tags, ids, fields) and ties stay most recently updated first.
+    #[default]
+    Relevance,
+    /// Most recently updated first
+    Recent,
+}
+
+impl SearchOrder {
+    pub fn next(self) -> Self {
+        match self {
+            SearchOrder::Relevance => SearchOrder::Recent,
+            SearchOrder::Recent => SearchOrder::Relevance,
+        }
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            SearchOrder::Relevance => "by relevance",
+            SearchOrder::Recent => "newest first",
+        }
+    }
+}
+
+/// Order of the note list when it is not showing search results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListOrder {
+    /// Most recently updated first
+    #[default]
+    Recent,
+    /// Notes with an `order` first, lowest order first, then the rest most recently
+    /// updated first
+    Manual,
+}
+
+impl ListOrder {
+    pub fn next(self) -> Self {
+        match self {
+            ListOrder::Recent => ListOrder::Manual,
+            ListOrder::Manual => ListOrder::Recent,
+        }
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            ListOrder::Recent => "newest first",
+            ListOrder::Manual => "in manual order",
+        }
+    }
+
+    /// Sort `notes` into this order. Notes that tie (the same `order`, or none) end up most
+    /// recently updated first.
+    pub fn sort(self, notes: &mut [Note]) {
+        notes.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        if self == ListOrder::Manual {
+            notes.sort_by_key(|note| (note.order.is_none(), note.order));
+        }
+    }
+}
+
+/// Repo state captured just before a destructive operation, so the operation can be undone
+#[derive(Debug, Clone)]
+pub struct UndoPoint {
+    pub commit_id: String,
+    pub description: String, // What the operation did, e.g. "Delete 'Title'"
+}
+
+#[derive(Clone)]
 pub struct NoteService {
     jujutsu: Jujutsu,
     notes_dir: PathBuf,
+    templates_dir: PathBuf,
+    rules: NoteRules,
+    auto_tags: AutoTagger,
+    export: ExportOptions,
+    cycles: CyclePolicy,
+    duplicate_titles: DuplicateTitlePolicy,
+    inbox_title: String,
+    audit: Option<AuditLog>,
+    wikilinks: WikilinkSync,
+    format: NoteFormat,
+    naming: FileNaming,
+    auto_commit: bool,
+    index_file: PathBuf,
+    keep_index: bool,
+    read_only: bool,
+    referenced_by: bool,
+}
+
+/// Title of the note quick captures go to unless configured otherwise
+pub const DEFAULT_INBOX_TITLE: &str = "Inbox";
+
+/// Optional per-vault rules checked when a note is saved. Violations are reported as
+/// warnings and never block the save. Everything is off by default.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct NoteRules {
+    /// Warn when a note has more tags than this
+    pub max_tags: Option<usize>,
+    /// Warn when a note has no tags
+    pub require_tag: bool,
+    /// Warn when the content has fewer words than this
+    pub min_words: Option<usize>,
+    /// Warn when the content has more words than this
+    pub max_words: Option<usize>,
+}
+
+impl NoteRules {
+    /// Human-readable rule violations for a note (empty if it passes)
+    pub fn check(&self, note: &Note) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.require_tag && note.tags.is_empty() {
+            warnings.push("no tags (at least one required)".to_string());
+        }
+        if let Some(max_tags) = self.max_tags
+            && note.tags.len() > max_tags
+        {
+            warnings.push(format!("{} tags (limit {})", note.tags.len(), max_tags));
+        }
+        let words = note.content.split_whitespace().count();
+        if let Some(min_words) = self.min_words
+            && words < min_words
+        {
+            warnings.push(format!("{} words (goal at least {})", words, min_words));
+        }
+        if let Some(max_words) = self.max_words
+            && words > max_words
+        {
+            warnings.push(format!("{} words (limit {})", words, max_words));
+        }
+        warnings
+    }
+}
+
+/// A rule adding `tag` to notes whose content matches `pattern`, a regular expression
+/// (a plain keyword like `TODO` works as is)
+#[derive(Debug, Clone, Deserialize)]
+pub struct AutoTagRule {
+    pub pattern: String,
+    pub tag: String,
+}
+
+/// Tags added on save to notes whose content matches a rule. Tags are only ever added,
+/// never removed; `enabled: false` keeps the rules but stops applying them.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AutoTagConfig {
+    pub enabled: bool,
+    pub rules: Vec<AutoTagRule>,
+}
+
+impl Default for AutoTagConfig {
+    fn default() -> Self {
+        AutoTagConfig { enabled: true, rules: Vec::new() }
+    }
+}
+
+/// Auto-tag rules with their patterns compiled
+#[derive(Debug, Clone, Default)]
+pub struct AutoTagger {
+    rules: Vec<(Regex, String)>,
+}
+
+impl AutoTagger {
+    /// Compile the configured rules; a disabled config applies none
+    pub fn new(config: &AutoTagConfig) -> Result<Self> {
+        if !config.enabled {
+            return Ok(AutoTagger::default());
+        }
+        let rules = config
+            .rules
+            .iter()
+            .filter(|rule| !rule.tag.trim().trim_start_matches('#').is_empty())
+            .map(|rule| {
+                let pattern = Regex::new(&rule.pattern)
+                    .with_context(|| format!("Invalid auto_tags pattern '{}'", rule.pattern))?;
+                Ok((pattern, rule.tag.trim().trim_start_matches('#').to_string()))
+            })
+            .collect::<Result<_>>()?;
+        Ok(AutoTagger { rules })
+    }
+
+    /// Add the tag of every rule matching the note's content that the note does not have
+    /// yet (ignoring case). Returns the tags added.
+    pub fn apply(&self, note: &mut Note) -> Vec<String> {
+        let mut added = Vec::new();
+        for (pattern, tag) in &self.rules {
+            let tag_lower = tag.to_lowercase();
+            if pattern.is_match(&note.content) && !note.tags.iter().any(|t| t.to_lowercase() == tag_lower) {
+                note.tags.push(tag.clone());
+                added.push(tag.clone());
+            }
+        }
+        added
+    }
 }
 
 impl NoteService {
-    pub fn new(repo_path: impl Into<String>) -> Self {
+    /// Service for the vault at `repo_path`, storing note files in `notes_dir`
+    pub fn new(repo_path: impl Into<String>, notes_dir: impl Into<PathBuf>) -> Self {
         let repo_path_str = repo_path.into();
-        let notes_dir = PathBuf::from(&repo_path_str).join("notes");
+        let notes_dir = notes_dir.into();
+        let templates_dir = PathBuf::from(&repo_path_str).join("templates");
+        let index_file = PathBuf::from(&repo_path_str).join("index.json");
         
         NoteService {
             jujutsu: Jujutsu::new(&repo_path_str),
             notes_dir,
+            templates_dir,
+            rules: NoteRules::default(),
+            auto_tags: AutoTagger::default(),
+            export: ExportOptions::default(),
+            cycles: CyclePolicy::default(),
+            duplicate_titles: DuplicateTitlePolicy::default(),
+            inbox_title: DEFAULT_INBOX_TITLE.to_string(),
+            audit: None,
+            wikilinks: WikilinkSync::default(),
+            format: NoteFormat::default(),
+            naming: FileNaming::default(),
+            auto_commit: true,
+            index_file,
+            keep_index: false,
+            read_only: false,
+            referenced_by: false,
+        }
+    }
+
+    /// Use the given save-time rules
+    pub fn with_rules(mut self, rules: NoteRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Tag notes matching these rules whenever they are saved
+    pub fn with_auto_tags(mut self, auto_tags: AutoTagger) -> Self {
+        self.auto_tags = auto_tags;
+        self
+    }
+
+    /// Use the given markdown export options
+    pub fn with_export_options(mut self, export: ExportOptions) -> Self {
+        self.export = export;
+        self
+    }
+
+    /// Use the given policy for links that close a direct cycle
+    pub fn with_cycle_policy(mut self, cycles: CyclePolicy) -> Self {
+        self.cycles = cycles;
+        self
+    }
+
+    /// Use the given policy for new notes whose title is already taken
+    pub fn with_duplicate_title_policy(mut self, duplicate_titles: DuplicateTitlePolicy) -> Self {
+        self.duplicate_titles = duplicate_titles;
+        self
+    }
+
+    /// Append quick captures to the note with this title
+    pub fn with_inbox_title(mut self, title: impl Into<String>) -> Self {
+        self.inbox_title = title.into();
+        self
+    }
+
+    /// Write note files in this JSON layout (files already written keep theirs until saved)
+    pub fn with_note_format(mut self, format: NoteFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Name note files this way. Files named otherwise are still found and get renamed
+    /// the next time their note is saved.
+    pub fn with_file_naming(mut self, naming: FileNaming) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Commit every change as it is made (the default), or only write the files and leave
+    /// committing to `commit_now` or the user's own jj workflow
+    pub fn with_auto_commit(mut self, auto_commit: bool) -> Self {
+        self.auto_commit = auto_commit;
+        self
+    }
+
+    /// Whether changes are committed as they are made
+    pub fn auto_commit(&self) -> bool {
+        self.auto_commit
+    }
+
+    /// Refuse every change with a clear error instead of failing on the file system, for
+    /// vaults that can only be read (see `initialize`)
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Whether changes are refused
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Fail if the vault is read-only; called before anything is written
+    fn ensure_writable(&self) -> Result<()> {
+        if self.read_only {
+            anyhow::bail!("The vault is read-only; changes are disabled");
+        }
+        Ok(())
+    }
+
+    /// Fail if `note` is locked; called before anything changes the note's text, title,
+    /// tags or links, or deletes or moves it
+    fn ensure_unlocked(&self, note: &Note) -> Result<()> {
+        if note.locked {
+            anyhow::bail!("'{}' is locked; unlock it first", note.title);
+        }
+        Ok(())
+    }
+
+    /// Keep `index.json` in the vault up to date with every change (off by default)
+    pub fn with_index_file(mut self, keep_index: bool) -> Self {
+        self.keep_index = keep_index;
+        self
+    }
+
+    /// Keep a managed "Referenced by" block at the end of every linked note's content,
+    /// listing the titles of the notes linking to it (off by default)
+    pub fn with_referenced_by(mut self, referenced_by: bool) -> Self {
+        self.referenced_by = referenced_by;
+        self
+    }
+
+    /// Rewrite the "Referenced by" block of each note in `ids` to list the notes linking
+    /// to it now, if the block is kept. Notes whose block is already right and locked
+    /// notes are not written, and `updated_at` is left alone since the user did not change
+    /// anything.
+    fn refresh_referenced_by<'a>(&self, ids: impl IntoIterator<Item = &'a String>) -> Result<()> {
+        if !self.referenced_by {
+            return Ok(());
+        }
+        let notes = self.list_notes()?;
+        for id in ids {
+            let Some(mut note) = notes.iter().find(|n| n.id == *id && !n.locked).cloned() else {
+                continue;
+            };
+            let mut titles: Vec<String> = notes
+                .iter()
+                .filter(|n| n.id != note.id && n.links.contains(&note.id))
+                .map(|n| n.title.clone())
+                .collect();
+            titles.sort_by_key(|title| title.to_lowercase());
+            if let Some(content) = with_referenced_by(&note.content, &titles) {
+                note.content = content;
+                self.write_note(&note)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Bring `index.json` in line with the note files, if it is kept. Only files changed
+    /// since the index last saw them are read; a missing or unreadable index is rebuilt.
+    fn refresh_index(&self) -> Result<()> {
+        if !self.keep_index {
+            return Ok(());
+        }
+        let (mut index, existed) = match NoteIndex::load(&self.index_file) {
+            Ok(index) => (index, true),
+            Err(_) => (NoteIndex::default(), false),
+        };
+        if index.refresh(&self.note_files()?)? || !existed {
+            index.save(&self.index_file)?;
+        }
+        Ok(())
+    }
+
+    /// Commit the working copy after a change to `file_path`, unless auto-commit is off
+    fn commit_file(&self, message: &str, file_path: &str) -> Result<()> {
+        self.refresh_index()?;
+        if self.auto_commit {
+            self.jujutsu.create_commit_for_file(message, file_path)?;
+        }
+        Ok(())
+    }
+
+    /// Commit the working copy after a change, unless auto-commit is off
+    fn commit(&self, message: &str) -> Result<()> {
+        self.refresh_index()?;
+        if self.auto_commit {
+            self.jujutsu.commit_working_copy(message)?;
+        }
+        Ok(())
+    }
+
+    /// Commit everything changed in the working copy with `message`, whatever the auto-commit
+    /// setting. Returns the new commit's id, or `None` if there was nothing to commit.
+    pub fn commit_now(&self, message: &str) -> Result<Option<String>> {
+        self.ensure_writable()?;
+        if !self.jujutsu.has_changes()? {
+            return Ok(None);
+        }
+        self.jujutsu.commit_working_copy(message).map(Some)
+    }
+
+    /// Give every note whose file has uncommitted changes (notes written while auto-commit
+    /// was off, or by tag and link operations, which do not commit) a commit of its own,
+    /// "Backfill: {title} [#{id}]", so its history shows its current state. Notes already
+    /// committed are left alone, so running it again commits nothing. Returns the number of
+    /// commits made.
+    pub fn backfill_history(&self) -> Result<usize> {
+        self.ensure_writable()?;
+        let mut notes = self.notes_missing_history(&self.list_notes()?)?;
+        notes.sort_by(|a, b| a.title.to_lowercase().cmp(&b.title.to_lowercase()).then_with(|| a.id.cmp(&b.id)));
+
+        let mut committed = 0;
+        for note in notes {
+            let Some(note_file) = self.find_note_file(&note.id)? else {
+                continue;
+            };
+            let note_file = note_file.canonicalize().unwrap_or(note_file);
+            let message = format!("Backfill: {} {}", note.title, Jujutsu::note_marker(&note.id));
+            self.jujutsu.commit_files(&message, &[note_file])?;
+            committed += 1;
+        }
+        Ok(committed)
+    }
+
+    /// Notes in `notes` whose file has changes no commit has yet (never committed, or
+    /// written without a commit), so their history does not show them as they are.
+    /// These are the notes `backfill_history` commits.
+    pub fn notes_missing_history(&self, notes: &[Note]) -> Result<Vec<Note>> {
+        let changed = self.jujutsu.changed_files()?;
+        let mut missing = Vec::new();
+        for note in notes {
+            let Some(note_file) = self.find_note_file(&note.id)? else {
+                continue;
+            };
+            if changed.contains(&note_file.canonicalize().unwrap_or(note_file)) {
+                missing.push(note.clone());
+            }
+        }
+        Ok(missing)
+    }
+
+    /// A note serialized for its file, in the configured layout
+    fn note_json(&self, note: &Note) -> Result<String> {
+        Ok(match self.format {
+            NoteFormat::Pretty => serde_json::to_string_pretty(note)?,
+            NoteFormat::Compact => serde_json::to_string(note)?,
+        })
+    }
+
+    /// Reconcile links with `[[wikilinks]]` in the content when notes are saved
+    pub fn with_wikilink_sync(mut self, wikilinks: WikilinkSync) -> Self {
+        self.wikilinks = wikilinks;
+        self
+    }
+
+    /// Add a link for every wikilink in `note`'s content that names an existing note and,
+    /// under `WikilinkSync::Sync`, drop links whose wikilink is in `old_content` but no
+    /// longer in the content. Links added by hand are never removed.
+    fn sync_wikilinks(&self, note: &mut Note, old_content: &str) -> Result<()> {
+        if self.wikilinks == WikilinkSync::Off {
+            return Ok(());
+        }
+        let titles: Vec<String> = wikilink_titles(&note.content).iter().map(|t| t.to_lowercase()).collect();
+        let removed: Vec<String> = if self.wikilinks == WikilinkSync::Sync {
+            wikilink_titles(old_content)
+                .iter()
+                .map(|t| t.to_lowercase())
+                .filter(|t| !titles.contains(t))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        if titles.is_empty() && removed.is_empty() {
+            return Ok(());
+        }
+        
+        let others: Vec<Note> = self.list_notes()?.into_iter().filter(|n| n.id != note.id).collect();
+        for title in &titles {
+            // A title shared by several notes is left for the user to pick (see `ambiguous_wikilinks`)
+            let [target] = wikilink_candidates(&others, title)[..] else {
+                continue;
+            };
+            let closes_cycle = self.cycles == CyclePolicy::Forbid && target.links.contains(&note.id);
+            if !note.links.contains(&target.id) && !closes_cycle {
+                note.links.push(target.id.clone());
+                self.audit("link", &note.id, Some(&target.id))?;
+            }
+        }
+        for title in &removed {
+            for target in wikilink_candidates(&others, title) {
+                if note.links.contains(&target.id) {
+                    note.links.retain(|id| *id != target.id);
+                    self.audit("unlink", &note.id, Some(&target.id))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// `base`, or `base (2)`, `base (3)`, ... if that title is taken (compared like `resolve_title`).
+    /// A base that already ends in a number suffix continues from there: `X (2)` becomes `X (3)`.
+    pub fn unique_title(&self, base: &str) -> Result<String> {
+        let taken: std::collections::HashSet<String> = self
+            .list_note_summaries()?
+            .into_iter()
+            .map(|n| n.title.trim().to_lowercase())
+            .collect();
+        let base = base.trim();
+        if !taken.contains(&base.to_lowercase()) {
+            return Ok(base.to_string());
+        }
+        let (stem, first) = split_number_suffix(base);
+        let title = (first..)
+            .map(|n| format!("{} ({})", stem, n))
+            .find(|title| !taken.contains(&title.to_lowercase()))
+            .unwrap_or_default();
+        Ok(title)
+    }
+
+    /// Notes titled `title`, compared trimmed and ignoring case. Several notes can share a
+    /// title, so this returns every candidate, oldest first (then by id) so the order is stable.
+    pub fn resolve_title(&self, title: &str) -> Result<Vec<Note>> {
+        let notes = self.list_notes()?;
+        Ok(title_candidates(&notes, title).into_iter().cloned().collect())
+    }
+
+    /// The oldest note titled `title` (compared like `resolve_title`), if the title is taken
+    pub fn title_taken(&self, title: &str) -> Result<Option<Note>> {
+        Ok(self.resolve_title(title)?.into_iter().next())
+    }
+
+    /// Wikilink titles in `note` that match more than one other note, none of which it links to
+    /// yet. Syncing skips these; the caller asks which note was meant. Empty when syncing is off.
+    pub fn ambiguous_wikilinks(&self, note: &Note) -> Result<Vec<String>> {
+        if self.wikilinks == WikilinkSync::Off {
+            return Ok(Vec::new());
+        }
+        let titles = wikilink_titles(&note.content);
+        if titles.is_empty() {
+            return Ok(Vec::new());
+        }
+        let others: Vec<Note> = self.list_notes()?.into_iter().filter(|n| n.id != note.id).collect();
+        Ok(titles
+            .into_iter()
+            .filter(|title| {
+                let candidates = title_candidates(&others, title);
+                candidates.len() > 1 && !candidates.iter().any(|c| note.links.contains(&c.id))
+            })
+            .collect())
+    }
+
+    /// Append every note operation to this audit log
+    pub fn with_audit_log(mut self, audit: Option<AuditLog>) -> Self {
+        self.audit = audit;
+        self
+    }
+
+    /// Record an operation in the audit log, if one is configured
+    fn audit(&self, action: &str, note_id: &str, detail: Option<&str>) -> Result<()> {
+        match self.audit {
+            Some(ref log) => log.record(action, note_id, detail),
+            None => Ok(()),
         }
     }
 
-    /// Initialize the service (create repo if needed)
+    /// Rule violations for a note that was just saved
+    pub fn rule_warnings(&self, note: &Note) -> Vec<String> {
+        self.rules.check(note)
+    }
+
+    /// Initialize the service (create repo if needed). Fails with a clear error, before
+    /// anything else is tried, when the notes directory cannot be written.
     pub fn initialize(&self) -> Result<()> {
+        // Ensure notes directory exists; a dangling symlink is reported below instead
+        if !self.notes_dir.is_dir() && !self.notes_dir.is_symlink() {
+            std::fs::create_dir_all(&self.notes_dir)
+                .with_context(|| format!("Failed to create notes directory {}", self.notes_dir.display()))?;
+        }
+        self.check_writable()?;
+
         if !self.jujutsu.repo_exists() {
             self.jujutsu.init()?;
         }
+
+        // Catch up with changes made outside jjzettel since the index was written
+        self.refresh_index()?;
         
-        // Ensure notes directory exists
-        std::fs::create_dir_all(&self.notes_dir)?;
-        
         Ok(())
     }
 
+    /// Whether note files can be written to the notes directory
+    pub fn notes_dir_writable(&self) -> bool {
+        self.check_writable().is_ok()
+    }
+
+    /// Whether note files can be written, found by creating and removing a probe file.
+    /// The error says why not: a dangling symlink, something other than a directory, or a
+    /// directory that cannot be written (e.g. a read-only mount).
+    fn check_writable(&self) -> Result<()> {
+        let dir = &self.notes_dir;
+        if dir.is_symlink() && !dir.exists() {
+            let target = std::fs::read_link(dir).map(|t| t.display().to_string()).unwrap_or_default();
+            anyhow::bail!("Notes directory {} is a symlink to {}, which does not exist", dir.display(), target);
+        }
+        if !dir.is_dir() {
+            anyhow::bail!("Notes directory {} does not exist or is not a directory", dir.display());
+        }
+        let probe = dir.join(format!(".jjzettel-write-test-{}", std::process::id()));
+        std::fs::write(&probe, b"")
+            .with_context(|| format!("Notes directory {} is not writable", dir.display()))?;
+        let _ = std::fs::remove_file(&probe);
+        Ok(())
+    }
+
+    /// Directory holding note templates (`templates/*.md` in the repo)
+    pub fn templates_dir(&self) -> &std::path::Path {
+        &self.templates_dir
+    }
+
+    /// List available note templates
+    pub fn list_templates(&self) -> Result<Vec<Template>> {
+        template::load_templates(&self.templates_dir)
+    }
+
     /// Create a new note
     pub fn create_note(&self, title: String, content: String) -> Result<Note> {
-        let note = Note::new(title.clone(), content.clone());
+        self.insert_note(Note::new(title, content), None)
+    }
+
+    /// Create a new note and link the note `source_id` to it, committing both notes together
+    pub fn create_linked_note(&self, source_id: &str, title: String, content: String) -> Result<Note> {
+        self.insert_note(Note::new(title, content), Some(source_id))
+    }
+
+    /// New note titled `title` that links to every note in `results`, e.g. a search's matches,
+    /// and lists them as `[[wikilinks]]` under the query so the text matches the links
+    pub fn create_results_note(&self, title: String, query: &str, results: &[Note]) -> Result<Note> {
+        let mut content = format!("{}\n\nResults for `{}`:\n", title, query);
+        let mut links: Vec<String> = Vec::new();
+        for result in results {
+            if !links.contains(&result.id) {
+                content.push_str(&format!("\n- [[{}]]", result.title));
+                links.push(result.id.clone());
+            }
+        }
+        let mut note = Note::new(title, content);
+        note.links = links;
+        self.insert_note(note, None)
+    }
+
+    /// Save a note that is not in the vault yet and commit it, together with the link from
+    /// the note `linked_from` if given. Under `DuplicateTitlePolicy::Block`, a title that is
+    /// already taken fails with `DuplicateTitle`.
+    fn insert_note(&self, mut note: Note, linked_from: Option<&str>) -> Result<Note> {
+        if self.duplicate_titles == DuplicateTitlePolicy::Block
+            && let Some(existing) = self.title_taken(&note.title)?
+        {
+            return Err(DuplicateTitle { existing }.into());
+        }
+        let source = match linked_from {
+            Some(id) => Some(self.get_note(id)?.ok_or_else(|| anyhow::anyhow!("Note not found: {}", id))?),
+            None => None,
+        };
+        self.sync_wikilinks(&mut note, "")?;
+        let auto_tagged = self.auto_tags.apply(&mut note);
         
         // Save note to file first
-        let note_file = self.notes_dir.join(format!("{}.json", note.id));
-        let note_json = serde_json::to_string_pretty(&note)?;
-        std::fs::write(&note_file, note_json)?;
+        let note_file = self.write_note(&note)?;
+        if let Some(ref source) = source
+            && let Err(e) = self.link_notes(&source.id, &note.id)
+        {
+            // The note was asked for with the link, so it does not stay without it
+            let _ = std::fs::remove_file(&note_file);
+            return Err(e);
+        }
+        self.refresh_referenced_by(&note.links)?;
         
-        // Create commit in Jujutsu for the actual JSON file
+        // Create commit in Jujutsu for the actual JSON file (and the linking note)
         let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        let commit_message = format!("Note: {} ({})", title, timestamp);
+        let linked = source
+            .map(|source| format!(", linked from {} {}", source.title, Jujutsu::note_marker(&source.id)))
+            .unwrap_or_default();
+        let commit_message = format!("Note: {} {}{} ({})", note.title, Jujutsu::note_marker(&note.id), linked, timestamp);
         let file_path_str = note_file.to_string_lossy().to_string();
-        let _commit_id = self.jujutsu.create_commit_for_file(&commit_message, &file_path_str)?;
+        self.commit_file(&commit_message, &file_path_str)?;
+        self.audit("create", &note.id, None)?;
+        for tag in &auto_tagged {
+            self.audit("tag", &note.id, Some(tag))?;
+        }
         
         Ok(note)
     }
 
-    /// Duplicate a note (creates a copy with a new ID)
-    pub fn duplicate_note(&self, note_id: &str) -> Result<Note> {
+    /// Append `text` as a timestamped bullet to the inbox note and commit. The inbox is the
+    /// oldest note with the inbox title (case-insensitive) and is created if there is none.
+    pub fn append_to_inbox(&self, text: &str) -> Result<Note> {
+        let text = text.trim();
+        if text.is_empty() {
+            anyhow::bail!("Nothing to capture");
+        }
+        let bullet = format!("- [{}] {}", chrono::Local::now().format("%Y-%m-%d %H:%M"), text);
+        
+        let inbox_lower = self.inbox_title.to_lowercase();
+        let existing = self
+            .list_notes()?
+            .into_iter()
+            .filter(|note| note.title.to_lowercase() == inbox_lower)
+            .min_by(|a, b| a.created_at.cmp(&b.created_at));
+        let Some(mut inbox) = existing else {
+            return self.create_note(self.inbox_title.clone(), bullet);
+        };
+        self.ensure_unlocked(&inbox)?;
+        
+        if !inbox.content.is_empty() && !inbox.content.ends_with('\n') {
+            inbox.content.push('\n');
+        }
+        inbox.content.push_str(&bullet);
+        inbox.updated_at = chrono::Utc::now().to_rfc3339();
+        
+        let note_file = self.write_note(&inbox)?;
+        
+        let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let commit_message = format!("Capture: {} {} ({})", inbox.title, Jujutsu::note_marker(&inbox.id), timestamp);
+        self.commit_file(&commit_message, &note_file.to_string_lossy())?;
+        self.audit("update", &inbox.id, Some("capture"))?;
+        
+        Ok(inbox)
+    }
+
+    /// Duplicate a note (creates a copy with a new ID). With `copy_backlinks`, every note
+    /// linking to the original also links to the copy, e.g. to fork a hub note.
+    pub fn duplicate_note(&self, note_id: &str, copy_backlinks: bool) -> Result<Note> {
         let original_note = self.get_note(note_id)?
             .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
         
-        // Create new note with "Copy of" prefix
-        let new_title = format!("Copy of {}", original_note.title);
+        // Create new note with "Copy of" prefix, numbered if that copy already exists
+        let new_title = self.unique_title(&format!("Copy of {}", original_note.title))?;
         let new_note = Note::new(new_title.clone(), original_note.content.clone());
         
         // Copy tags but not links (user can link manually)
@@ -64,15 +910,26 @@ impl NoteService {
         new_note_with_tags.tags = original_note.tags.clone();
         
         // Save duplicated note
-        let note_file = self.notes_dir.join(format!("{}.json", new_note_with_tags.id));
-        let note_json = serde_json::to_string_pretty(&new_note_with_tags)?;
-        std::fs::write(&note_file, note_json)?;
+        let note_file = self.write_note(&new_note_with_tags)?;
+        
+        // Point the original's backlinks at the copy too, in the same commit, except from
+        // locked notes. The copy has no links of its own, so these can never close a cycle.
+        if copy_backlinks {
+            let notes = self.list_notes()?;
+            if let Some(sources) = self.backlink_index(&notes).get(note_id) {
+                let locked: HashSet<&str> = notes.iter().filter(|n| n.locked).map(|n| n.id.as_str()).collect();
+                for source_id in sources.iter().filter(|id| !locked.contains(id.as_str())) {
+                    self.link_notes(source_id, &new_note_with_tags.id)?;
+                }
+            }
+        }
         
         // Create commit in Jujutsu for the actual JSON file
         let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        let commit_message = format!("Duplicate: {} ({})", new_title, timestamp);
+        let commit_message = format!("Duplicate: {} {} ({})", new_title, Jujutsu::note_marker(&new_note_with_tags.id), timestamp);
         let file_path_str = note_file.to_string_lossy().to_string();
-        let _commit_id = self.jujutsu.create_commit_for_file(&commit_message, &file_path_str)?;
+        self.commit_file(&commit_message, &file_path_str)?;
+        self.audit("create", &new_note_with_tags.id, Some(&format!("duplicate of {}", note_id)))?;
         
         Ok(new_note_with_tags)
     }
@@ -81,19 +938,11 @@ impl NoteService {
     pub fn list_notes(&self) -> Result<Vec<Note>> {
         let mut notes = Vec::new();
         
-        if !self.notes_dir.exists() {
-            return Ok(notes);
-        }
-        
-        for entry in std::fs::read_dir(&self.notes_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            
-            if path.extension().and_then(|s| s.to_str()) == Some("json") {
-                let content = std::fs::read_to_string(&path)?;
-                let note: Note = serde_json::from_str(&content)?;
-                notes.push(note);
-            }
+        for path in self.note_files()? {
+            let content = std::fs::read_to_string(&path)?;
+            let note = Note::from_json(&content)
+                .with_context(|| format!("Invalid note file {}", path.display()))?;
+            notes.push(note);
         }
         
         // Sort by updated_at (most recent first)
@@ -102,46 +951,506 @@ impl NoteService {
         Ok(notes)
     }
 
-    /// Get a note by ID
-    pub fn get_note(&self, id: &str) -> Result<Option<Note>> {
-        let note_file = self.notes_dir.join(format!("{}.json", id));
-        
-        if !note_file.exists() {
-            return Ok(None);
+    /// Summaries of all notes, most recently updated first. Each file's content is
+    /// dropped as soon as it is summarized, so only what a list needs stays in memory.
+    pub fn list_note_summaries(&self) -> Result<Vec<NoteSummary>> {
+        let mut summaries = Vec::new();
+
+        for path in self.note_files()? {
+            let content = std::fs::read_to_string(&path)?;
+            let note = Note::from_json(&content)
+                .with_context(|| format!("Invalid note file {}", path.display()))?;
+            summaries.push(note.summary());
         }
-        
-        let content = std::fs::read_to_string(&note_file)?;
-        let note: Note = serde_json::from_str(&content)?;
-        Ok(Some(note))
+
+        summaries.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        Ok(summaries)
     }
 
-    /// Get all notes that link to the given note (backlinks)
-    pub fn get_backlinks(&self, note_id: &str) -> Result<Vec<Note>> {
-        let all_notes = self.list_notes()?;
-        
-        let backlinks: Vec<Note> = all_notes
-            .into_iter()
-            .filter(|note| note.links.contains(&note_id.to_string()))
-            .collect();
-        
-        Ok(backlinks)
+    /// Capture the current vault state before running the operation described
+    pub fn undo_point(&self, description: impl Into<String>) -> Result<UndoPoint> {
+        Ok(UndoPoint {
+            commit_id: self.jujutsu.working_copy_commit_id()?,
+            description: description.into(),
+        })
     }
 
-    /// Update a note
-    pub fn update_note(&self, mut note: Note, new_content: String) -> Result<Note> {
-        note.content = new_content;
-        note.updated_at = chrono::Utc::now().to_rfc3339();
+    /// Put every file back the way it was at the undo point and commit that as a new change
+    pub fn undo(&self, point: &UndoPoint) -> Result<()> {
+        self.ensure_writable()?;
+        // The audit trail must not be rolled back with the notes
+        let audit_log = self
+            .audit
+            .as_ref()
+            .and_then(|log| std::fs::read(log.path()).ok().map(|content| (log.path(), content)));
+        self.jujutsu.restore_from_commit(&point.commit_id)?;
+        if let Some((path, content)) = audit_log {
+            std::fs::write(path, content)?;
+        }
+        let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        self.commit(&format!("Undo: {} ({})", point.description, timestamp))?;
+        Ok(())
+    }
+
+    /// Path of a note's JSON file (which may not exist)
+    pub fn note_path(&self, id: &str) -> PathBuf {
+        self.find_note_file(id)
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| self.notes_dir.join(format!("{}.json", id)))
+    }
+
+    /// Every note file: the `.json` files in the notes directory, except `index.json` when
+    /// the notes directory is the vault itself
+    fn note_files(&self) -> Result<Vec<PathBuf>> {
+        let index_here = self.index_file.parent().and_then(|dir| dir.canonicalize().ok())
+            == self.notes_dir.canonicalize().ok();
+        let index_name = self.index_file.file_name();
+        Ok(files_with_extension(&self.notes_dir, "json")?
+            .into_iter()
+            .filter(|path| !(index_here && path.file_name() == index_name))
+            .collect())
+    }
+
+    /// The file holding the note with exactly this id, whichever way it is named
+    fn find_note_file(&self, id: &str) -> Result<Option<PathBuf>> {
+        let id_file = self.notes_dir.join(format!("{}.json", id));
+        if id_file.exists() {
+            return Ok(Some(id_file));
+        }
+        Ok(self
+            .files_with_id_prefix(id)?
+            .into_iter()
+            .find(|(file_id, _)| file_id == id)
+            .map(|(_, path)| path))
+    }
+
+    /// (full id, file) of every note whose id starts with `prefix`. File names only narrow
+    /// down the files to read: a slug name carries just the start of the id, and its slug
+    /// may itself look like an id.
+    fn files_with_id_prefix(&self, prefix: &str) -> Result<Vec<(String, PathBuf)>> {
+        let mut files = Vec::new();
+        for path in self.note_files()? {
+            if !path.file_stem().and_then(|s| s.to_str()).is_some_and(|stem| may_hold_id(stem, prefix)) {
+                continue;
+            }
+            let content = std::fs::read_to_string(&path)?;
+            let note = Note::from_json(&content)
+                .with_context(|| format!("Invalid note file {}", path.display()))?;
+            if note.id.starts_with(prefix) {
+                files.push((note.id, path));
+            }
+        }
+        Ok(files)
+    }
+
+    /// File `note` belongs in under the configured naming. Two notes whose slug and short
+    /// id both match are told apart by the full id.
+    fn file_for(&self, note: &Note) -> PathBuf {
+        let slug = match self.naming {
+            FileNaming::Id => return self.notes_dir.join(format!("{}.json", note.id)),
+            FileNaming::Slug => slugify_title(&note.title),
+        };
+        let short_id: String = note.id.chars().take(FILE_ID_CHARS).collect();
+        let path = self.notes_dir.join(format!("{}-{}.json", slug, short_id));
+        let taken_by_other = path.exists()
+            && std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|content| Note::from_json(&content).ok())
+                .is_none_or(|other| other.id != note.id);
+        if taken_by_other {
+            self.notes_dir.join(format!("{}-{}.json", slug, note.id))
+        } else {
+            path
+        }
+    }
+
+    /// Write `note` to its file and return the file. When the name no longer fits, because
+    /// the title or the naming setting changed, the old file is removed, so the commit
+    /// records a rename.
+    fn write_note(&self, note: &Note) -> Result<PathBuf> {
+        self.ensure_writable()?;
+        let existing = self.find_note_file(&note.id)?;
+        let note_file = self.file_for(note);
+        std::fs::write(&note_file, self.note_json(note)?)?;
+        if let Some(old_file) = existing
+            && old_file != note_file
+        {
+            std::fs::remove_file(&old_file)
+                .with_context(|| format!("Failed to remove {}", old_file.display()))?;
+        }
+        Ok(note_file)
+    }
+
+    /// Compact working-copy state of the vault repo for the title bar
+    pub fn vcs_status(&self) -> Result<String> {
+        self.jujutsu.status_summary()
+    }
+
+    /// Full id of the note `id` refers to: the id itself, or the one id it is a prefix of
+    /// (like a git short hash, at least `MIN_ID_PREFIX` characters). `None` if no note matches;
+    /// an error if the prefix is ambiguous.
+    pub fn resolve_id(&self, id: &str) -> Result<Option<String>> {
+        if self.notes_dir.join(format!("{}.json", id)).exists() {
+            return Ok(Some(id.to_string()));
+        }
+        if id.chars().count() < MIN_ID_PREFIX || id.contains(['/', '\\', '.']) || !self.notes_dir.exists() {
+            return Ok(None);
+        }
+        
+        let mut matches: Vec<String> = self.files_with_id_prefix(id)?.into_iter().map(|(file_id, _)| file_id).collect();
+        if matches.iter().any(|file_id| file_id == id) {
+            return Ok(Some(id.to_string()));
+        }
+        match matches.len() {
+            0 => Ok(None),
+            1 => Ok(matches.pop()),
+            n => {
+                matches.sort();
+                anyhow::bail!("Id prefix '{}' is ambiguous: it matches {} notes ({})", id, n, matches.join(", "))
+            }
+        }
+    }
+
+    /// Get a note by ID or unique ID prefix (see `resolve_id`)
+    pub fn get_note(&self, id: &str) -> Result<Option<Note>> {
+        let Some(id) = self.resolve_id(id)? else {
+            return Ok(None);
+        };
+        let Some(note_file) = self.find_note_file(&id)? else {
+            return Ok(None);
+        };
+        
+        let content = std::fs::read_to_string(&note_file)?;
+        let note = Note::from_json(&content)
+            .with_context(|| format!("Invalid note file {}", note_file.display()))?;
+        Ok(Some(note))
+    }
+
+    /// MD5 of a note's file as it is on disk, `None` if the file is gone.
+    /// Compared before saving an edit to notice changes made outside the app (e.g. by sync).
+    pub fn note_fingerprint(&self, id: &str) -> Result<Option<String>> {
+        let Some(note_file) = self.find_note_file(id)? else {
+            return Ok(None);
+        };
+        let bytes = std::fs::read(&note_file)
+            .with_context(|| format!("Failed to read {}", note_file.display()))?;
+        Ok(Some(format!("{:x}", md5::compute(bytes))))
+    }
+
+    /// Backlinks of every note: note id → ids of the notes linking to it, in `notes` order.
+    /// Built in one pass over already-loaded notes instead of re-reading the vault per note;
+    /// notes nobody links to are absent from the map.
+    pub fn backlink_index(&self, notes: &[Note]) -> HashMap<String, Vec<String>> {
+        let mut index: HashMap<String, Vec<String>> = HashMap::new();
+        for note in notes {
+            for link_id in &note.links {
+                let sources = index.entry(link_id.clone()).or_default();
+                // A note linking twice still counts as one backlink
+                if !sources.contains(&note.id) {
+                    sources.push(note.id.clone());
+                }
+            }
+        }
+        index
+    }
+
+    /// Notes grouped by tag: (tag, note ids in `notes` order), sorted by tag, with an
+    /// "Untagged" group last. Tags are grouped case-insensitively (first spelling wins) and
+    /// a note with several tags is listed under each of them.
+    pub fn tag_groups(&self, notes: &[Note]) -> Vec<(String, Vec<String>)> {
+        let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+        let mut untagged = Vec::new();
+        for note in notes {
+            if note.tags.is_empty() {
+                untagged.push(note.id.clone());
+            }
+            for tag in &note.tags {
+                let lower = tag.to_lowercase();
+                let position = groups.iter().position(|(name, _)| name.to_lowercase() == lower);
+                let ids = match position {
+                    Some(i) => &mut groups[i].1,
+                    None => {
+                        groups.push((tag.clone(), Vec::new()));
+                        &mut groups.last_mut().expect("just pushed").1
+                    }
+                };
+                // The same tag twice in different case is still one membership
+                if !ids.contains(&note.id) {
+                    ids.push(note.id.clone());
+                }
+            }
+        }
+        groups.sort_by_key(|(name, _)| name.to_lowercase());
+        if !untagged.is_empty() {
+            groups.push((UNTAGGED_GROUP.to_string(), untagged));
+        }
+        groups
+    }
+
+    /// Every tag with the number of notes using it, sorted by tag. Tags differing only in
+    /// case are counted together under their first spelling.
+    pub fn tag_counts(&self, notes: &[Note]) -> Vec<(String, usize)> {
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for note in notes {
+            let mut seen: Vec<String> = Vec::new();
+            for tag in &note.tags {
+                let lower = tag.to_lowercase();
+                if seen.contains(&lower) {
+                    continue;
+                }
+                match counts.iter_mut().find(|(name, _)| name.to_lowercase() == lower) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((tag.clone(), 1)),
+                }
+                seen.push(lower);
+            }
+        }
+        counts.sort_by_key(|(name, _)| name.to_lowercase());
+        counts
+    }
+
+    /// Tags spelled in more than one case across `notes` (e.g. `Rust` and `rust`), sorted by tag
+    pub fn case_variant_tags(&self, notes: &[Note]) -> Vec<TagVariants> {
+        let mut spellings: Vec<(String, usize)> = Vec::new();
+        for note in notes {
+            let mut seen: Vec<&String> = Vec::new();
+            for tag in &note.tags {
+                if seen.contains(&tag) {
+                    continue;
+                }
+                seen.push(tag);
+                match spellings.iter_mut().find(|(name, _)| name == tag) {
+                    Some((_, count)) => *count += 1,
+                    None => spellings.push((tag.clone(), 1)),
+                }
+            }
+        }
+        let mut groups: Vec<TagVariants> = Vec::new();
+        for (tag, count) in spellings {
+            let lower = tag.to_lowercase();
+            match groups.iter_mut().find(|group| group.spellings[0].0.to_lowercase() == lower) {
+                Some(group) => group.spellings.push((tag, count)),
+                None => groups.push(TagVariants { spellings: vec![(tag, count)] }),
+            }
+        }
+        groups.retain(|group| group.spellings.len() > 1);
+        for group in &mut groups {
+            group.spellings.sort_by(|(a, a_count), (b, b_count)| {
+                b_count
+                    .cmp(a_count)
+                    .then_with(|| (*b == b.to_lowercase()).cmp(&(*a == a.to_lowercase())))
+                    .then_with(|| a.cmp(b))
+            });
+        }
+        groups.sort_by_key(|group| group.spellings[0].0.to_lowercase());
+        groups
+    }
+
+    /// Rename a tag (case-insensitively) on every unlocked note and commit once. A note that
+    /// already has the new tag just loses the old one.
+    pub fn rename_tag_everywhere(&self, old: &str, new: &str) -> Result<TagChange> {
+        let new = new.trim_start_matches('#').trim();
+        if new.is_empty() {
+            anyhow::bail!("Tag cannot be empty");
+        }
+        let from = [old.to_string()];
+        let detail = format!("{} → {}", old, new);
+        let change = self.rewrite_tags(&from, ("retag", &detail), |tags| replace_tags(tags, &from, new))?;
+        self.commit_tag_change(&format!("Rename tag: #{} → #{} ({} notes)", old, new, change.changed), change.changed)?;
+        Ok(change)
+    }
+
+    /// Replace all the `from` tags (case-insensitively) with `into` on every unlocked note,
+    /// keeping one `into` per note, and commit once. `into` may be one of `from`, e.g. to
+    /// fold `ml` and `machine-learning` into `ml`.
+    pub fn merge_tags(&self, from: &[String], into: &str) -> Result<TagChange> {
+        let into = into.trim_start_matches('#').trim();
+        if into.is_empty() {
+            anyhow::bail!("Tag cannot be empty");
+        }
+        let detail = format!("{} → {}", from.join(", "), into);
+        let change = self.rewrite_tags(from, ("retag", &detail), |tags| replace_tags(tags, from, into))?;
+        let merged: Vec<String> = from.iter().map(|t| format!("#{}", t)).collect();
+        self.commit_tag_change(&format!("Merge tags: {} → #{} ({} notes)", merged.join(", "), into, change.changed), change.changed)?;
+        Ok(change)
+    }
+
+    /// Remove a tag (case-insensitively) from every unlocked note and commit once
+    pub fn delete_tag_everywhere(&self, tag: &str) -> Result<TagChange> {
+        let tag_lower = tag.to_lowercase();
+        let change = self.rewrite_tags(&[tag.to_string()], ("untag", tag), |tags| {
+            let before = tags.len();
+            tags.retain(|t| t.to_lowercase() != tag_lower);
+            tags.len() != before
+        })?;
+        self.commit_tag_change(&format!("Delete tag: #{} ({} notes)", tag, change.changed), change.changed)?;
+        Ok(change)
+    }
+
+    /// Apply `change` to the tags of every note carrying one of `tags` and save (and audit
+    /// as `(action, detail)`) the notes for which it returns true. Locked notes are left
+    /// alone and counted instead.
+    fn rewrite_tags(
+        &self,
+        tags: &[String],
+        (action, detail): (&str, &str),
+        mut change: impl FnMut(&mut Vec<String>) -> bool,
+    ) -> Result<TagChange> {
+        let tags_lower: Vec<String> = tags.iter().map(|t| t.to_lowercase()).collect();
+        let mut result = TagChange::default();
+        for mut note in self.list_notes()? {
+            if !note.tags.iter().any(|t| tags_lower.contains(&t.to_lowercase())) {
+                continue;
+            }
+            if note.locked {
+                result.locked += 1;
+                continue;
+            }
+            if !change(&mut note.tags) {
+                continue;
+            }
+            note.updated_at = chrono::Utc::now().to_rfc3339();
+            self.write_note(&note)?;
+            self.audit(action, &note.id, Some(detail))?;
+            result.changed += 1;
+        }
+        Ok(result)
+    }
+
+    /// Commit a vault-wide tag change, if it touched any note
+    fn commit_tag_change(&self, summary: &str, changed: usize) -> Result<()> {
+        if changed > 0 {
+            let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            self.commit(&format!("{} ({})", summary, timestamp))?;
+        }
+        Ok(())
+    }
+
+    /// Task items of all `notes`, grouped by note in `notes` order; done items only with `include_done`
+    pub fn tasks(&self, notes: &[Note], include_done: bool) -> Vec<Task> {
+        notes
+            .iter()
+            .flat_map(|note| {
+                note.content.lines().enumerate().filter_map(move |(line, text)| {
+                    let (done, text) = parse_task(text)?;
+                    (include_done || !done).then(|| Task {
+                        note_id: note.id.clone(),
+                        note_title: note.title.clone(),
+                        line,
+                        done,
+                        text: text.to_string(),
+                    })
+                })
+            })
+            .collect()
+    }
+
+    /// Flip the checkbox of `task` in its note and commit. Fails if the line no longer
+    /// holds that task, e.g. because the note was edited meanwhile.
+    pub fn toggle_task(&self, task: &Task) -> Result<Note> {
+        let note = self.get_note(&task.note_id)?
+            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", task.note_id))?;
+        let mut lines: Vec<String> = note.content.lines().map(str::to_string).collect();
+        let current = lines.get(task.line).and_then(|line| parse_task(line));
+        if current.is_none_or(|(done, text)| done != task.done || text != task.text) {
+            anyhow::bail!("'{}' changed; the task is no longer on line {}", note.title, task.line + 1);
+        }
+        
+        // The first `[` on a task line opens its checkbox
+        let line = &mut lines[task.line];
+        let box_start = line.find('[').unwrap_or(0);
+        line.replace_range(box_start + 1..box_start + 2, if task.done { " " } else { "x" });
+        
+        let mut content = lines.join("\n");
+        if note.content.ends_with('\n') {
+            content.push('\n');
+        }
+        self.update_note(note, content)
+    }
+
+    /// Update a note
+    pub fn update_note(&self, mut note: Note, new_content: String) -> Result<Note> {
+        // The stored note decides, since the caller's copy may be older than a lock
+        if let Some(stored) = self.get_note(&note.id)? {
+            self.ensure_unlocked(&stored)?;
+        }
+        let old_content = std::mem::replace(&mut note.content, new_content);
+        let old_links = note.links.clone();
+        self.sync_wikilinks(&mut note, &old_content)?;
+        let auto_tagged = self.auto_tags.apply(&mut note);
+        note.updated_at = chrono::Utc::now().to_rfc3339();
         
         // Save updated note
-        let note_file = self.notes_dir.join(format!("{}.json", note.id));
-        let note_json = serde_json::to_string_pretty(&note)?;
-        std::fs::write(&note_file, note_json)?;
+        let note_file = self.write_note(&note)?;
+        let changed_links = old_links.iter().chain(&note.links).filter(|id| old_links.contains(id) != note.links.contains(id));
+        self.refresh_referenced_by(changed_links)?;
+        
+        // Create commit in Jujutsu for the actual JSON file
+        let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let commit_message = format!("Update: {} {} ({})", note.title, Jujutsu::note_marker(&note.id), timestamp);
+        let file_path_str = note_file.to_string_lossy().to_string();
+        self.commit_file(&commit_message, &file_path_str)?;
+        self.audit("update", &note.id, None)?;
+        for tag in &auto_tagged {
+            self.audit("tag", &note.id, Some(tag))?;
+        }
+        
+        Ok(note)
+    }
+
+    /// The note `id` as pretty-printed JSON, for fixing fields by hand
+    pub fn raw_note_json(&self, id: &str) -> Result<String> {
+        let note = self.get_note(id)?
+            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", id))?;
+        Ok(serde_json::to_string_pretty(&note)?)
+    }
+
+    /// Replace the note `id` with hand-edited JSON. The text has to parse as a note with the
+    /// same id and a title; it is then written as given, without touching `updated_at` or
+    /// the wikilinks.
+    pub fn save_raw_note(&self, id: &str, json: &str) -> Result<Note> {
+        let note = Note::from_json(json).map_err(|e| anyhow::anyhow!("Invalid note JSON: {}", e))?;
+        if note.id != id {
+            anyhow::bail!("The id cannot be changed; it has to stay {}", id);
+        }
+        if note.title.trim().is_empty() {
+            anyhow::bail!("The title cannot be empty");
+        }
+        if let Some(stored) = self.get_note(id)? {
+            self.ensure_unlocked(&stored)?;
+        }
+        
+        let note_file = self.write_note(&note)?;
+        
+        let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let commit_message = format!("Edit JSON: {} {} ({})", note.title, Jujutsu::note_marker(&note.id), timestamp);
+        let file_path_str = note_file.to_string_lossy().to_string();
+        self.commit_file(&commit_message, &file_path_str)?;
+        self.audit("update", &note.id, None)?;
+        
+        Ok(note)
+    }
+
+    /// Rename a note. History keeps following it because commits are keyed by note ID.
+    pub fn rename_note(&self, note_id: &str, new_title: String) -> Result<Note> {
+        let mut note = self.get_note(note_id)?
+            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
+        self.ensure_unlocked(&note)?;
+        
+        let old_title = std::mem::replace(&mut note.title, new_title);
+        note.updated_at = chrono::Utc::now().to_rfc3339();
+        
+        // Save renamed note
+        let note_file = self.write_note(&note)?;
+        self.refresh_referenced_by(&note.links)?;
         
         // Create commit in Jujutsu for the actual JSON file
         let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        let commit_message = format!("Update: {} ({})", note.title, timestamp);
+        let commit_message = format!("Rename: {} → {} {} ({})", old_title, note.title, Jujutsu::note_marker(&note.id), timestamp);
         let file_path_str = note_file.to_string_lossy().to_string();
-        self.jujutsu.create_commit_for_file(&commit_message, &file_path_str)?;
+        self.commit_file(&commit_message, &file_path_str)?;
+        self.audit("rename", &note.id, Some(&note.title))?;
         
         Ok(note)
     }
@@ -150,16 +1459,16 @@ impl NoteService {
     pub fn add_tag(&self, note_id: &str, tag: String) -> Result<Note> {
         let mut note = self.get_note(note_id)?
             .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
+        self.ensure_unlocked(&note)?;
         
         let tag_lower = tag.to_lowercase();
         if !note.tags.iter().any(|t| t.to_lowercase() == tag_lower) {
-            note.tags.push(tag);
+            note.tags.push(tag.clone());
             note.updated_at = chrono::Utc::now().to_rfc3339();
             
             // Save updated note
-            let note_file = self.notes_dir.join(format!("{}.json", note.id));
-            let note_json = serde_json::to_string_pretty(&note)?;
-            std::fs::write(&note_file, note_json)?;
+            self.write_note(&note)?;
+            self.audit("tag", &note.id, Some(&tag))?;
         }
         
         Ok(note)
@@ -169,194 +1478,2377 @@ impl NoteService {
     pub fn remove_tag(&self, note_id: &str, tag: &str) -> Result<Note> {
         let mut note = self.get_note(note_id)?
             .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
+        self.ensure_unlocked(&note)?;
         
         let tag_lower = tag.to_lowercase();
         note.tags.retain(|t| t.to_lowercase() != tag_lower);
         note.updated_at = chrono::Utc::now().to_rfc3339();
         
         // Save updated note
-        let note_file = self.notes_dir.join(format!("{}.json", note.id));
-        let note_json = serde_json::to_string_pretty(&note)?;
-        std::fs::write(&note_file, note_json)?;
+        self.write_note(&note)?;
+        self.audit("untag", &note.id, Some(tag))?;
         
         Ok(note)
     }
 
-    /// Search notes by tags
-    pub fn search_by_tag(&self, tag: &str) -> Result<Vec<Note>> {
-        let all_notes = self.list_notes()?;
-        let tag_lower = tag.to_lowercase();
+    /// Set a custom metadata field on a note (overwrites an existing value)
+    pub fn set_metadata(&self, note_id: &str, key: String, value: String) -> Result<Note> {
+        let mut note = self.get_note(note_id)?
+            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
+        self.ensure_unlocked(&note)?;
         
-        let filtered: Vec<Note> = all_notes
-            .into_iter()
-            .filter(|note| {
-                note.tags.iter().any(|t| t.to_lowercase() == tag_lower)
-            })
-            .collect();
+        note.metadata.insert(key.clone(), value);
+        note.updated_at = chrono::Utc::now().to_rfc3339();
         
-        Ok(filtered)
+        // Save updated note
+        self.write_note(&note)?;
+        self.audit("metadata", &note.id, Some(&key))?;
+        
+        Ok(note)
     }
 
-    /// Delete a note
-    pub fn delete_note(&self, id: &str) -> Result<()> {
-        let note_file = self.notes_dir.join(format!("{}.json", id));
+    /// Set or clear a note's color label. The label only marks the note in the list, so
+    /// `updated_at` stays as it is.
+    pub fn set_color(&self, note_id: &str, color: Option<String>) -> Result<Note> {
+        let mut note = self.get_note(note_id)?
+            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
         
-        if note_file.exists() {
-            // Delete the file
-            std::fs::remove_file(&note_file)?;
-            
-            // Create commit in Jujutsu for deletion
-            let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
-            let commit_message = format!("Delete note: {} ({})", id, timestamp);
-            // Just describe the deletion - the file is already removed
-            Command::new("jj")
-                .arg("describe")
-                .arg("-m")
-                .arg(&commit_message)
-                .current_dir(&self.jujutsu.repo_path())
-                .output()?;
-        }
+        note.color = color;
         
-        Ok(())
+        // Save updated note
+        self.write_note(&note)?;
+        
+        Ok(note)
     }
 
-    /// Search notes by title or content, or by tag if query starts with #
-    pub fn search_notes(&self, query: &str) -> Result<Vec<Note>> {
-        let all_notes = self.list_notes()?;
-        
-        // If query starts with #, search by tag
-        if query.starts_with('#') {
-            let tag = query.trim_start_matches('#').trim();
-            if tag.is_empty() {
-                return Ok(all_notes);
-            }
-            return self.search_by_tag(tag);
-        }
+    /// Show the note line for line in paragraph mode (`strict`), or with soft breaks joined
+    pub fn set_strict_line_breaks(&self, note_id: &str, strict: bool) -> Result<Note> {
+        let mut note = self.get_note(note_id)?
+            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
         
-        // Otherwise search by title or content
-        let query_lower = query.to_lowercase();
+        note.strict_line_breaks = strict;
+        note.updated_at = chrono::Utc::now().to_rfc3339();
         
-        let filtered: Vec<Note> = all_notes
-            .into_iter()
-            .filter(|note| {
-                note.title.to_lowercase().contains(&query_lower) ||
-                note.content.to_lowercase().contains(&query_lower)
-            })
-            .collect();
+        // Save updated note
+        self.write_note(&note)?;
         
-        Ok(filtered)
+        Ok(note)
     }
 
-    /// Link two notes together
-    pub fn link_notes(&self, note_id: &str, linked_note_id: &str) -> Result<()> {
+    /// Move the note one place up or down the manual list order, renumbering the ordered
+    /// notes 1, 2, 3… as it goes. Moving up a note without an order adds it at the end of
+    /// the order; moving down the last one takes it out again. Like the color label, the
+    /// order only arranges the list: nothing is committed and `updated_at` stays as it is.
+    pub fn move_in_order(&self, note_id: &str, up: bool) -> Result<Note> {
         let mut note = self.get_note(note_id)?
             .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
-        
-        if !note.links.contains(&linked_note_id.to_string()) {
-            note.links.push(linked_note_id.to_string());
-            note.updated_at = chrono::Utc::now().to_rfc3339();
-            
-            // Save updated note
-            let note_file = self.notes_dir.join(format!("{}.json", note.id));
-            let note_json = serde_json::to_string_pretty(&note)?;
-            std::fs::write(&note_file, note_json)?;
+
+        let mut ordered: Vec<Note> = self.list_notes()?.into_iter().filter(|n| n.order.is_some()).collect();
+        ListOrder::Manual.sort(&mut ordered);
+        match ordered.iter().position(|n| n.id == note.id) {
+            None if up => ordered.push(note.clone()),
+            None => return Ok(note),
+            Some(i) if up => ordered.swap(i, i.saturating_sub(1)),
+            Some(i) if i + 1 < ordered.len() => ordered.swap(i, i + 1),
+            Some(i) => {
+                ordered.remove(i);
+                note.order = None;
+                self.write_note(&note)?;
+            }
         }
-        
-        Ok(())
+
+        for (position, ordered_note) in ordered.iter_mut().enumerate() {
+            let order = Some(position as i64 + 1);
+            if ordered_note.order != order {
+                ordered_note.order = order;
+                self.write_note(ordered_note)?;
+            }
+            if ordered_note.id == note.id {
+                note.order = order;
+            }
+        }
+
+        Ok(note)
     }
 
-    /// Unlink two notes
-    pub fn unlink_notes(&self, note_id: &str, linked_note_id: &str) -> Result<()> {
+    /// Mark a commit from the note's history as its canonical version, or clear the mark.
+    /// This only records the choice; the note itself is unchanged.
+    pub fn set_canonical_commit(&self, note_id: &str, commit_id: Option<String>) -> Result<Note> {
         let mut note = self.get_note(note_id)?
             .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
         
-        note.links.retain(|id| id != linked_note_id);
+        note.canonical_commit = commit_id;
         note.updated_at = chrono::Utc::now().to_rfc3339();
         
         // Save updated note
-        let note_file = self.notes_dir.join(format!("{}.json", note.id));
-        let note_json = serde_json::to_string_pretty(&note)?;
-        std::fs::write(&note_file, note_json)?;
+        self.write_note(&note)?;
         
-        Ok(())
+        Ok(note)
     }
 
-    /// Export note to markdown format
-    pub fn export_note_to_markdown(&self, note: &Note) -> String {
-        let mut md = String::new();
+    /// Bring the note back to its canonical version and commit. The canonical mark itself
+    /// is kept, even though the old version predates it.
+    pub fn restore_canonical(&self, note_id: &str) -> Result<Note> {
+        let current = self.get_note(note_id)?
+            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
+        self.ensure_unlocked(&current)?;
+        let Some(commit_id) = current.canonical_commit.clone() else {
+            anyhow::bail!("'{}' has no canonical version", current.title);
+        };
         
-        // Title
-        md.push_str(&format!("# {}\n\n", note.title));
+        let mut note = self.note_at_commit(&commit_id, &current.id)?;
+        note.canonical_commit = Some(commit_id.clone());
+        note.updated_at = chrono::Utc::now().to_rfc3339();
         
-        // Metadata
-        md.push_str("---\n");
-        md.push_str(&format!("**ID:** {}\n", note.id));
-        md.push_str(&format!("**Created:** {}\n", note.created_at));
-        md.push_str(&format!("**Updated:** {}\n", note.updated_at));
+        let note_file = self.write_note(&note)?;
         
-        if !note.tags.is_empty() {
-            md.push_str(&format!("**Tags:** {}\n", note.tags.join(", ")));
-        }
+        let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let commit_message = format!(
+            "Restore canonical: {} {} from {} ({})",
+            note.title,
+            Jujutsu::note_marker(&note.id),
+            commit_id,
+            timestamp
+        );
+        self.commit_file(&commit_message, &note_file.to_string_lossy())?;
+        self.audit("restore", &note.id, Some(&commit_id))?;
         
-        if !note.links.is_empty() {
-            md.push_str("**Links:** ");
-            let link_titles: Vec<String> = note.links
-                .iter()
-                .filter_map(|link_id| {
-                    self.get_note(link_id).ok().flatten()
-                        .map(|n| format!("[[{}]]", n.title))
-                })
-                .collect();
-            md.push_str(&link_titles.join(", "));
-            md.push('\n');
+        Ok(note)
+    }
+
+    /// A note as of a commit. Its file then may have had another name than now (a different
+    /// title or naming setting), so other files whose name fits the id are tried too.
+    fn note_at_commit(&self, commit_id: &str, id: &str) -> Result<Note> {
+        let current_file = self.note_path(id);
+        let mut files = vec![current_file.clone()];
+        files.extend(
+            self.jujutsu
+                .files_at_commit(commit_id, &self.notes_dir.to_string_lossy())?
+                .into_iter()
+                .filter(|path| *path != current_file)
+                .filter(|path| path.file_stem().and_then(|s| s.to_str()).is_some_and(|stem| may_hold_id(stem, id))),
+        );
+        for file in files {
+            let Ok(content) = self.jujutsu.file_at_commit(commit_id, &file.to_string_lossy()) else {
+                continue;
+            };
+            let note = Note::from_json(&content)
+                .with_context(|| format!("Invalid note at {}", commit_id))?;
+            if note.id == id {
+                return Ok(note);
+            }
         }
+        anyhow::bail!("Note {} is not in commit {}", id, commit_id)
+    }
+
+    /// Set or clear a note's due date, given as RFC 3339 (see `parse_due` for user input)
+    pub fn set_due(&self, note_id: &str, due: Option<String>) -> Result<Note> {
+        let mut note = self.get_note(note_id)?
+            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
         
-        md.push_str("---\n\n");
+        note.due = due;
+        note.updated_at = chrono::Utc::now().to_rfc3339();
         
-        // Content
-        md.push_str(&note.content);
-        md.push('\n');
+        // Save updated note
+        self.write_note(&note)?;
         
-        md
+        Ok(note)
     }
 
-    /// Get commit history for a note
-    pub fn get_note_history(&self, note_id: &str) -> Result<Vec<crate::storage::CommitInfo>> {
-        // Get the note to extract its title for matching
-        let note = self.get_note(note_id)?;
-        let note_title = note.as_ref().map(|n| n.title.as_str()).unwrap_or("");
+    /// Lock a note against edits, tags, links and deletion, or unlock it, and commit
+    pub fn set_locked(&self, note_id: &str, locked: bool) -> Result<Note> {
+        self.ensure_writable()?;
+        let mut note = self.get_note(note_id)?
+            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
         
-        // Get the full path to the note file
-        let note_file = self.notes_dir.join(format!("{}.json", note_id));
-        let note_file_str = note_file.to_string_lossy().to_string();
-        self.jujutsu.get_file_history_with_title(&note_file_str, note_title)
+        note.locked = locked;
+        note.updated_at = chrono::Utc::now().to_rfc3339();
+        
+        // Save updated note
+        let note_file = self.write_note(&note)?;
+        
+        let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let verb = if locked { "Lock" } else { "Unlock" };
+        let commit_message = format!("{}: {} {} ({})", verb, note.title, Jujutsu::note_marker(&note.id), timestamp);
+        let file_path_str = note_file.to_string_lossy().to_string();
+        self.commit_file(&commit_message, &file_path_str)?;
+        self.audit(if locked { "lock" } else { "unlock" }, &note.id, None)?;
+        
+        Ok(note)
     }
 
-    /// Get statistics about the knowledge base
-    pub fn get_statistics(&self) -> Result<NoteStatistics> {
-        let all_notes = self.list_notes()?;
-        let total_notes = all_notes.len();
-        let total_links: usize = all_notes.iter().map(|n| n.links.len()).sum();
-        let total_tags: usize = all_notes.iter().map(|n| n.tags.len()).sum();
-        let unique_tags: std::collections::HashSet<String> = all_notes
-            .iter()
-            .flat_map(|n| n.tags.iter().map(|t| t.to_lowercase()))
-            .collect();
-        
-        Ok(NoteStatistics {
-            total_notes,
-            total_links,
-            total_tags,
-            unique_tags_count: unique_tags.len(),
-        })
+    /// Notes with a due date, soonest (or longest overdue) first
+    pub fn due_notes(&self, notes: &[Note]) -> Vec<Note> {
+        let mut due: Vec<Note> = notes.iter().filter(|note| note.due_at().is_some()).cloned().collect();
+        due.sort_by_key(|note| note.due_at());
+        due
     }
+
+    /// Notes with nothing past their title, or with the same body text as another note
+    /// (e.g. a template's boilerplate left unfilled), in the order of `notes`
+    pub fn stub_notes(&self, notes: &[Note]) -> Vec<Stub> {
+        let bodies: Vec<String> = notes.iter().map(|note| normalized_body(&note.content)).collect();
+        let mut by_body: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, body) in bodies.iter().enumerate() {
+            by_body.entry(body.as_str()).or_default().push(i);
+        }
+        let mut stubs = Vec::new();
+        for (i, note) in notes.iter().enumerate() {
+            let reason = if bodies[i].is_empty() {
+                StubReason::Empty
+            } else if let Some(&other) = by_body[bodies[i].as_str()].iter().find(|&&j| j != i) {
+                StubReason::SameAs(notes[other].id.clone())
+            } else {
+                continue;
+            };
+            stubs.push(Stub { note: note.clone(), reason });
+        }
+        stubs
+    }
+
+    /// Fold the note `id` into the note `into_id` and delete it. Its body is appended to
+    /// the other note's content unless that already has the same text, its tags and links
+    /// are added to the other note's, and notes linking to it link to the other note
+    /// instead. Everything is committed as one change. Returns the note merged into.
+    pub fn merge_note_into(&self, id: &str, into_id: &str) -> Result<Note> {
+        self.ensure_writable()?;
+        let stub = self.get_note(id)?
+            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", id))?;
+        let mut into = self.get_note(into_id)?
+            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", into_id))?;
+        if stub.id == into.id {
+            anyhow::bail!("A note cannot be merged into itself");
+        }
+        self.ensure_unlocked(&stub)?;
+        self.ensure_unlocked(&into)?;
+        
+        let body = stub.content.trim_start().split_once('\n').map_or("", |(_, rest)| rest).trim();
+        if !body.is_empty() && normalized_body(&stub.content) != normalized_body(&into.content) {
+            into.content = format!("{}\n\n{}\n", into.content.trim_end(), body);
+        }
+        for tag in &stub.tags {
+            let tag_lower = tag.to_lowercase();
+            if !into.tags.iter().any(|t| t.to_lowercase() == tag_lower) {
+                into.tags.push(tag.clone());
+            }
+        }
+        into.links.retain(|link_id| *link_id != stub.id);
+        for link_id in &stub.links {
+            if *link_id != into.id && !into.links.contains(link_id) {
+                into.links.push(link_id.clone());
+            }
+        }
+        into.updated_at = chrono::Utc::now().to_rfc3339();
+        self.write_note(&into)?;
+        
+        // Links to the stub now point at the note it went into; locked notes keep theirs
+        for mut note in self.list_notes()? {
+            if note.id == stub.id || note.id == into.id || note.locked || !note.links.contains(&stub.id) {
+                continue;
+            }
+            if note.links.contains(&into.id) {
+                note.links.retain(|link_id| *link_id != stub.id);
+            } else {
+                for link_id in note.links.iter_mut().filter(|link_id| **link_id == stub.id) {
+                    *link_id = into.id.clone();
+                }
+            }
+            note.updated_at = chrono::Utc::now().to_rfc3339();
+            self.write_note(&note)?;
+        }
+        if let Some(stub_file) = self.find_note_file(&stub.id)? {
+            std::fs::remove_file(&stub_file)?;
+        }
+        
+        let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let commit_message = format!("Merge: {} → {} {} ({})", stub.title, into.title, Jujutsu::note_marker(&into.id), timestamp);
+        self.commit(&commit_message)?;
+        self.audit("merge", &stub.id, Some(&into.id))?;
+        
+        Ok(into)
+    }
+
+    /// Notes not updated in `days` days or more, least recently updated first; notes whose
+    /// update time cannot be read come last
+    pub fn stale_notes(&self, notes: &[Note], days: u32, now: chrono::DateTime<chrono::Utc>) -> Vec<Note> {
+        let mut stale: Vec<Note> = notes.iter().filter(|note| is_stale(&note.updated_at, now, days)).cloned().collect();
+        stale.sort_by_key(|note| {
+            let updated = chrono::DateTime::parse_from_rfc3339(&note.updated_at).ok();
+            (updated.is_none(), updated)
+        });
+        stale
+    }
+
+    /// Notes that link to no other note and that no other note links to, in the order of
+    /// `notes`. Links to missing notes and a note's links to itself do not count.
+    pub fn orphan_notes(&self, notes: &[Note]) -> Vec<Note> {
+        let ids: HashSet<&str> = notes.iter().map(|note| note.id.as_str()).collect();
+        let backlinks = self.backlink_index(notes);
+        notes
+            .iter()
+            .filter(|note| !note.links.iter().any(|id| *id != note.id && ids.contains(id.as_str())))
+            .filter(|note| backlinks.get(&note.id).is_none_or(|sources| sources.iter().all(|id| *id == note.id)))
+            .cloned()
+            .collect()
+    }
+
+    /// Notes linking to ids no note in `notes` has (e.g. a note deleted outside jjzettel),
+    /// each with those ids, in the order of `notes`
+    pub fn broken_links(&self, notes: &[Note]) -> Vec<(Note, Vec<String>)> {
+        let ids: HashSet<&str> = notes.iter().map(|note| note.id.as_str()).collect();
+        notes
+            .iter()
+            .filter_map(|note| {
+                let missing: Vec<String> = note.links.iter().filter(|id| !ids.contains(id.as_str())).cloned().collect();
+                (!missing.is_empty()).then(|| (note.clone(), missing))
+            })
+            .collect()
+    }
+
+    /// Notes sharing a title (compared like `resolve_title`), grouped, oldest first within
+    /// a group; groups are in the order their first note appears in `notes`
+    pub fn duplicate_titles(&self, notes: &[Note]) -> Vec<Vec<Note>> {
+        let mut groups: Vec<(String, Vec<Note>)> = Vec::new();
+        for note in notes {
+            let title = note.title.trim().to_lowercase();
+            match groups.iter_mut().find(|(t, _)| *t == title) {
+                Some((_, group)) => group.push(note.clone()),
+                None => groups.push((title, vec![note.clone()])),
+            }
+        }
+        groups
+            .into_iter()
+            .map(|(_, mut group)| {
+                group.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+                group
+            })
+            .filter(|group| group.len() > 1)
+            .collect()
+    }
+
+    /// Set or clear a note's scratch line. It is kept out of the content, so search and
+    /// export never see it.
+    pub fn set_scratch(&self, note_id: &str, scratch: Option<String>) -> Result<Note> {
+        let mut note = self.get_note(note_id)?
+            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
+        
+        note.scratch = scratch;
+        note.updated_at = chrono::Utc::now().to_rfc3339();
+        
+        // Save updated note
+        self.write_note(&note)?;
+        
+        Ok(note)
+    }
+
+    /// Remove a custom metadata field from a note
+    pub fn remove_metadata(&self, note_id: &str, key: &str) -> Result<Note> {
+        let mut note = self.get_note(note_id)?
+            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
+        self.ensure_unlocked(&note)?;
+        
+        if note.metadata.remove(key).is_some() {
+            note.updated_at = chrono::Utc::now().to_rfc3339();
+            
+            // Save updated note
+            self.write_note(&note)?;
+            self.audit("metadata", &note.id, Some(key))?;
+        }
+        
+        Ok(note)
+    }
+
+    /// Search notes by a metadata field, matching values case-insensitively.
+    /// An empty value matches every note that has the field.
+    pub fn search_by_metadata(&self, field: &str, value: &str) -> Result<Vec<Note>> {
+        let all_notes = self.list_notes()?;
+        let field_lower = field.to_lowercase();
+        let value_lower = value.to_lowercase();
+        
+        let filtered: Vec<Note> = all_notes
+            .into_iter()
+            .filter(|note| {
+                note.metadata.iter().any(|(k, v)| {
+                    k.to_lowercase() == field_lower && v.to_lowercase().contains(&value_lower)
+                })
+            })
+            .collect();
+        
+        Ok(filtered)
+    }
+
+    /// Search notes by tags
+    pub fn search_by_tag(&self, tag: &str) -> Result<Vec<Note>> {
+        let all_notes = self.list_notes()?;
+        let tag_lower = tag.to_lowercase();
+        
+        let filtered: Vec<Note> = all_notes
+            .into_iter()
+            .filter(|note| {
+                note.tags.iter().any(|t| t.to_lowercase() == tag_lower)
+            })
+            .collect();
+        
+        Ok(filtered)
+    }
+
+    /// Search notes by the start of a tag name, so `proj` finds `project`. When no tag starts
+    /// with the query, tags containing its letters in order are matched instead (`prj`).
+    pub fn search_by_tag_prefix(&self, query: &str) -> Result<Vec<Note>> {
+        let all_notes = self.list_notes()?;
+        let query_lower = query.to_lowercase();
+        
+        let is_prefix = |tag: &String| tag.to_lowercase().starts_with(&query_lower);
+        let any_prefix = all_notes.iter().any(|note| note.tags.iter().any(is_prefix));
+        let filtered: Vec<Note> = all_notes
+            .into_iter()
+            .filter(|note| {
+                note.tags.iter().any(|t| {
+                    if any_prefix {
+                        is_prefix(t)
+                    } else {
+                        is_subsequence(&query_lower, &t.to_lowercase())
+                    }
+                })
+            })
+            .collect();
+        
+        Ok(filtered)
+    }
+
+    /// Delete a note
+    pub fn delete_note(&self, id: &str) -> Result<()> {
+        self.ensure_writable()?;
+        if let Some(note_file) = self.find_note_file(id)? {
+            let note = self.get_note(id)?;
+            if let Some(ref note) = note {
+                self.ensure_unlocked(note)?;
+            }
+            let (title, links) = note.map(|n| (n.title, n.links)).unwrap_or_default();
+            
+            // Delete the file
+            std::fs::remove_file(&note_file)?;
+            self.refresh_referenced_by(&links)?;
+            
+            // Create commit in Jujutsu for deletion
+            let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            let commit_message = format!("Delete: {} {} ({})", title, Jujutsu::note_marker(id), timestamp);
+            self.commit(&commit_message)?;
+            self.audit("delete", id, None)?;
+        }
+        
+        Ok(())
+    }
+
+    /// Move a note into another vault, keeping its id, and commit in both repos.
+    /// Links other notes in the source vault had to it are removed. Its own links are kept
+    /// only when the target also exists in the destination; the rest are stripped since
+    /// links cannot point across vaults. Returns the number of stripped links.
+    pub fn move_note_to(&self, id: &str, other: &NoteService) -> Result<usize> {
+        self.ensure_writable()?;
+        let mut note = self.get_note(id)?
+            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", id))?;
+        self.ensure_unlocked(&note)?;
+        let id = note.id.clone();
+        let id = id.as_str();
+        
+        let same_vault = match (self.notes_dir.canonicalize(), other.notes_dir.canonicalize()) {
+            (Ok(source), Ok(destination)) => source == destination,
+            _ => self.notes_dir == other.notes_dir,
+        };
+        if same_vault {
+            anyhow::bail!("Destination is the same vault");
+        }
+        if other.get_note(id)?.is_some() {
+            anyhow::bail!("Destination already has a note with id {}", id);
+        }
+        note.title = other.unique_title(&note.title)?;
+        
+        // Strip links that would point into the source vault
+        let links_before = note.links.len();
+        note.links.retain(|link_id| matches!(other.get_note(link_id), Ok(Some(_))));
+        let stripped = links_before - note.links.len();
+        note.updated_at = chrono::Utc::now().to_rfc3339();
+        
+        let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let marker = Jujutsu::note_marker(&note.id);
+        
+        // Write into the destination first so a failure never loses the note
+        std::fs::create_dir_all(&other.notes_dir)?;
+        let destination_file = other.write_note(&note)?;
+        let commit_message = format!("Move in: {} {} ({})", note.title, marker, timestamp);
+        other.commit_file(&commit_message, &destination_file.to_string_lossy())?;
+        
+        // Drop backlinks to the moved note from the remaining notes; locked notes keep theirs
+        for mut source_note in self.list_notes()? {
+            if source_note.id != note.id && !source_note.locked && source_note.links.iter().any(|link_id| link_id == id) {
+                source_note.links.retain(|link_id| link_id != id);
+                source_note.updated_at = chrono::Utc::now().to_rfc3339();
+                self.write_note(&source_note)?;
+            }
+        }
+        
+        std::fs::remove_file(self.note_path(id))?;
+        let commit_message = format!("Move out: {} {} ({})", note.title, marker, timestamp);
+        self.commit(&commit_message)?;
+        self.audit("move", id, Some(&other.notes_dir.to_string_lossy()))?;
+        
+        Ok(stripped)
+    }
+
+    /// Search notes by title or content, only titles with a `title:` prefix, by tag if the
+    /// query starts with `#` or `tag:` (prefix match; `#=tag` for the exact tag), or by
+    /// metadata field if query looks like `field:value`, with the results in `order`
+    pub fn search_notes(&self, query: &str, order: SearchOrder) -> Result<Vec<Note>> {
+        let mut results = self.matching_notes(query)?;
+        results.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        if order == SearchOrder::Relevance
+            && let Some(text) = ranked_text(query)
+        {
+            // Stable, so notes ranked the same stay newest first
+            results.sort_by_cached_key(|note| std::cmp::Reverse(relevance(note, &text)));
+        }
+        Ok(results)
+    }
+
+    /// Search notes loosely: the query's letters in order but not necessarily together, so
+    /// `prjplan` finds "Project Planning". Best matches come first: title matches before
+    /// content matches, then letters that follow each other or start words before scattered
+    /// ones; ties stay most recently updated first. `title:` matches titles only. Tag, id and
+    /// field queries have no text to match loosely and search as in `search_notes`.
+    pub fn search_notes_fuzzy(&self, query: &str) -> Result<Vec<Note>> {
+        let pattern: Vec<char> = match ranked_text(query) {
+            Some(text) => text.chars().filter(|c| !c.is_whitespace()).collect(),
+            None => return self.search_notes(query, SearchOrder::Recent),
+        };
+        if pattern.is_empty() {
+            return self.search_notes(query, SearchOrder::Recent);
+        }
+        let titles_only = strip_prefix_ignore_case(query.trim_start(), "title:").is_some();
+        let mut notes = self.list_notes()?;
+        notes.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        let mut scored: Vec<((bool, i64), Note)> = notes
+            .into_iter()
+            .filter_map(|note| {
+                let in_title = fuzzy_score(&pattern, &note.title).map(|score| (true, score));
+                let in_content = if titles_only {
+                    None
+                } else {
+                    note.content.lines().filter_map(|line| fuzzy_score(&pattern, line)).max().map(|score| (false, score))
+                };
+                in_title.or(in_content).map(|rank| (rank, note))
+            })
+            .collect();
+        // Stable, so notes ranked the same stay newest first
+        scored.sort_by_key(|(rank, _)| std::cmp::Reverse(*rank));
+        Ok(scored.into_iter().map(|(_, note)| note).collect())
+    }
+
+    /// Notes matching `query`, as described for `search_notes`, in no particular order
+    fn matching_notes(&self, query: &str) -> Result<Vec<Note>> {
+        let all_notes = self.list_notes()?;
+        
+        // If query starts with # or tag:, search by tag
+        let tag_query = query
+            .strip_prefix('#')
+            .or_else(|| strip_prefix_ignore_case(query.trim_start(), "tag:"));
+        if let Some(tag) = tag_query {
+            let tag = tag.trim_start_matches('#').trim();
+            if let Some(exact) = tag.strip_prefix('=') {
+                return self.search_by_tag(exact.trim());
+            }
+            if tag.is_empty() {
+                return Ok(all_notes);
+            }
+            return self.search_by_tag_prefix(tag);
+        }
+        
+        // id: lists the notes whose id starts with the given prefix
+        if let Some(id) = strip_prefix_ignore_case(query.trim_start(), "id:") {
+            let id = id.trim();
+            return Ok(all_notes.into_iter().filter(|note| note.id.starts_with(id)).collect());
+        }
+        
+        // title: matches titles only, ignoring note bodies
+        if let Some(title) = strip_prefix_ignore_case(query.trim_start(), "title:") {
+            let title_lower = title.trim().to_lowercase();
+            return Ok(all_notes
+                .into_iter()
+                .filter(|note| note.title.to_lowercase().contains(&title_lower))
+                .collect());
+        }
+        
+        // field:value searches metadata (field names are single words)
+        if is_field_query(query)
+            && let Some((field, value)) = query.trim().split_once(':')
+        {
+            return self.search_by_metadata(field, value.trim());
+        }
+        
+        // Otherwise search by title or content
+        let query_lower = query.to_lowercase();
+        
+        let filtered: Vec<Note> = all_notes
+            .into_iter()
+            .filter(|note| {
+                note.title.to_lowercase().contains(&query_lower) ||
+                note.content.to_lowercase().contains(&query_lower)
+            })
+            .collect();
+        
+        Ok(filtered)
+    }
+
+    /// Link two notes together
+    /// Self-links are always rejected; a link back to a note that already links here is
+    /// rejected only under `CyclePolicy::Forbid`.
+    pub fn link_notes(&self, note_id: &str, linked_note_id: &str) -> Result<()> {
+        let mut note = self.get_note(note_id)?
+            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
+        self.ensure_unlocked(&note)?;
+        let note_id = note.id.clone();
+        let note_id = note_id.as_str();
+        let linked_note_id = self.resolve_id(linked_note_id)?.unwrap_or_else(|| linked_note_id.to_string());
+        let linked_note_id = linked_note_id.as_str();
+        if note_id == linked_note_id {
+            anyhow::bail!("A note cannot link to itself");
+        }
+        if self.cycles == CyclePolicy::Forbid
+            && let Some(target) = self.get_note(linked_note_id)?
+            && target.links.iter().any(|id| id == note_id)
+        {
+            anyhow::bail!("'{}' already links to '{}' and cycles are forbidden", target.title, note.title);
+        }
+        
+        if !note.links.contains(&linked_note_id.to_string()) {
+            note.links.push(linked_note_id.to_string());
+            note.updated_at = chrono::Utc::now().to_rfc3339();
+            
+            // Save updated note
+            self.write_note(&note)?;
+            self.refresh_referenced_by([&linked_note_id.to_string()])?;
+            self.audit("link", &note.id, Some(linked_note_id))?;
+        }
+        
+        Ok(())
+    }
+
+    /// Move the link at position `from` to position `to` in a note's links (the order links
+    /// are listed and numbered in) and commit
+    pub fn reorder_link(&self, note_id: &str, from: usize, to: usize) -> Result<Note> {
+        let mut note = self.get_note(note_id)?
+            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
+        self.ensure_unlocked(&note)?;
+        if from >= note.links.len() || to >= note.links.len() {
+            anyhow::bail!("Link position out of range");
+        }
+        if from == to {
+            return Ok(note);
+        }
+        
+        let link = note.links.remove(from);
+        note.links.insert(to, link);
+        note.updated_at = chrono::Utc::now().to_rfc3339();
+        
+        let note_file = self.write_note(&note)?;
+        
+        let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let commit_message = format!("Reorder links: {} {} ({})", note.title, Jujutsu::note_marker(&note.id), timestamp);
+        self.commit_file(&commit_message, &note_file.to_string_lossy())?;
+        self.audit("reorder_links", &note.id, None)?;
+        
+        Ok(note)
+    }
+
+    /// Unlink two notes
+    pub fn unlink_notes(&self, note_id: &str, linked_note_id: &str) -> Result<()> {
+        let mut note = self.get_note(note_id)?
+            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
+        self.ensure_unlocked(&note)?;
+        
+        note.links.retain(|id| id != linked_note_id);
+        note.updated_at = chrono::Utc::now().to_rfc3339();
+        
+        // Save updated note
+        self.write_note(&note)?;
+        self.refresh_referenced_by([&linked_note_id.to_string()])?;
+        self.audit("unlink", &note.id, Some(linked_note_id))?;
+        
+        Ok(())
+    }
+
+    /// Export note to markdown format. `backlinks` (see `backlink_index`) is only read for
+    /// the backlink count of the `stats` option.
+    pub fn export_note_to_markdown(&self, note: &Note, backlinks: &HashMap<String, Vec<String>>) -> String {
+        self.note_markdown(note, backlinks, &mut LinkRefs::new(self.export.link_style))
+    }
+
+    /// Markdown of one note, writing its links with `refs` so footnotes stay numbered
+    /// across the notes of one export
+    fn note_markdown(&self, note: &Note, backlinks: &HashMap<String, Vec<String>>, refs: &mut LinkRefs) -> String {
+        let options = &self.export;
+        // Footnote marks mean nothing inside YAML, so the front matter lists plain titles
+        let plain_links = options.format == FrontMatter::Yaml && options.link_style == LinkStyle::Footnote;
+        let link_titles: Vec<String> = if options.links {
+            note.links
+                .iter()
+                .filter_map(|link_id| self.get_note(link_id).ok().flatten())
+                .map(|n| if plain_links { n.title } else { refs.link(&n.title, &n.title) })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        // (YAML key, bold label, value) for the chosen fields, in a fixed order
+        let mut fields: Vec<(&str, &str, &str)> = Vec::new();
+        if options.id {
+            fields.push(("id", "ID", &note.id));
+        }
+        if options.dates {
+            fields.push(("created", "Created", &note.created_at));
+            fields.push(("updated", "Updated", &note.updated_at));
+        }
+        if note.format() != "markdown" {
+            fields.push(("format", "Format", note.format()));
+        }
+        // (YAML key, bold label, count), written after the links
+        let mut counts: Vec<(&str, &str, usize)> = Vec::new();
+        if options.stats {
+            counts.push(("word_count", "Words", note.content.split_whitespace().count()));
+            counts.push(("link_count", "Link count", note.links.len()));
+            counts.push(("backlink_count", "Backlink count", backlinks.get(&note.id).map_or(0, Vec::len)));
+        }
+
+        let mut md = String::new();
+        match options.format {
+            FrontMatter::Bold => {
+                // Title
+                md.push_str(&format!("# {}\n\n", note.title));
+
+                // Metadata
+                let mut block = String::new();
+                for (_, label, value) in &fields {
+                    block.push_str(&format!("**{}:** {}\n", label, value));
+                }
+                if options.tags && !note.tags.is_empty() {
+                    block.push_str(&format!("**Tags:** {}\n", note.tags.join(", ")));
+                }
+                if options.metadata {
+                    for (key, value) in &note.metadata {
+                        block.push_str(&format!("**{}:** {}\n", key, value));
+                    }
+                }
+                if !link_titles.is_empty() {
+                    block.push_str(&format!("**Links:** {}\n", link_titles.join(", ")));
+                }
+                for (_, label, count) in &counts {
+                    block.push_str(&format!("**{}:** {}\n", label, count));
+                }
+                if !block.is_empty() {
+                    md.push_str("---\n");
+                    md.push_str(&block);
+                    md.push_str("---\n\n");
+                }
+            }
+            FrontMatter::Yaml => {
+                md.push_str("---\n");
+                md.push_str(&format!("title: {}\n", yaml_string(&note.title)));
+                for (key, _, value) in &fields {
+                    md.push_str(&format!("{}: {}\n", key, yaml_string(value)));
+                }
+                if options.tags && !note.tags.is_empty() {
+                    md.push_str(&format!("tags: {}\n", yaml_list(&note.tags)));
+                }
+                if options.metadata {
+                    for (key, value) in &note.metadata {
+                        md.push_str(&format!("{}: {}\n", yaml_key(key), yaml_string(value)));
+                    }
+                }
+                if !link_titles.is_empty() {
+                    md.push_str(&format!("links: {}\n", yaml_list(&link_titles)));
+                }
+                for (key, _, count) in &counts {
+                    md.push_str(&format!("{}: {}\n", key, count));
+                }
+                md.push_str("---\n\n");
+                md.push_str(&format!("# {}\n\n", note.title));
+            }
+        }
+
+        // Content; other formats go in a fenced block so markdown tools leave them alone
+        if note.format() == "markdown" {
+            md.push_str(&self.export_wikilinks(&note.content, refs));
+            md.push('\n');
+        } else {
+            let fence = code_fence(&note.content);
+            md.push_str(&format!("{}{}\n{}\n{}\n", fence, note.format(), note.content.trim_end_matches('\n'), fence));
+        }
+        let footnotes = refs.take_footnotes();
+        if !footnotes.is_empty() {
+            md.push('\n');
+            md.push_str(&footnotes);
+        }
+
+        md
+    }
+
+    /// `content` with its `[[Title]]` and `[[Title|label]]` wikilinks in the export link
+    /// style; fenced code is left as it is. `[[id:…]]` links are written with the title of
+    /// the note they mean.
+    fn export_wikilinks(&self, content: &str, refs: &mut LinkRefs) -> String {
+        if refs.style == LinkStyle::Wikilink || !content.contains("[[") {
+            return content.to_string();
+        }
+        let notes = self.list_notes().unwrap_or_default();
+        let mut out = String::new();
+        let mut in_code = false;
+        for line in content.split_inclusive('\n') {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_code = !in_code;
+            }
+            if in_code || !line.contains("[[") {
+                out.push_str(line);
+                continue;
+            }
+            let mut rest = line;
+            while let Some(start) = rest.find("[[") {
+                let Some(end) = rest[start + 2..].find("]]").map(|end| start + 2 + end) else {
+                    break;
+                };
+                let inner = &rest[start + 2..end];
+                let (target, label) = match inner.split_once('|') {
+                    Some((target, label)) => (target.trim(), label.trim()),
+                    None => (inner.trim(), inner.trim()),
+                };
+                out.push_str(&rest[..start]);
+                if target.is_empty() {
+                    out.push_str(&rest[start..end + 2]);
+                } else {
+                    let title = wikilink_candidates(&notes, target).first().map_or(target, |n| n.title.as_str());
+                    let label = if label == target { title } else { label };
+                    out.push_str(&refs.link(title, label));
+                }
+                rest = &rest[end + 2..];
+            }
+            out.push_str(rest);
+        }
+        out
+    }
+
+    /// Markdown list of `results` (e.g. a search's matches) with each note's tags and the
+    /// first line shown in the note list
+    pub fn export_results_markdown(&self, query: &str, results: &[Note]) -> String {
+        let mut md = format!("# Search results: `{}`\n\n{} notes\n\n", query, results.len());
+        for result in results {
+            let summary = result.summary();
+            md.push_str(&format!("- **{}**", summary.title));
+            if !summary.tags.is_empty() {
+                let tags: Vec<String> = summary.tags.iter().map(|tag| format!("#{}", tag)).collect();
+                md.push_str(&format!(" {}", tags.join(" ")));
+            }
+            if !summary.first_line.is_empty() && summary.first_line != summary.title {
+                md.push_str(&format!(" — {}", summary.first_line));
+            }
+            md.push('\n');
+        }
+        md
+    }
+
+    /// Markdown of the note `root_id` followed by every note it reaches within `depth` link
+    /// hops, nearest first, each exported like `export_note_to_markdown` and separated by a
+    /// rule. A note reached along several paths (or a cycle) is included once; links to
+    /// missing notes are skipped. Depth 0 exports the note alone. `progress` hears
+    /// `(exported, total)` after each note; setting `cancel` stops with `Cancelled`.
+    pub fn export_subgraph_markdown(
+        &self,
+        root_id: &str,
+        depth: usize,
+        progress: &mut dyn FnMut(usize, usize),
+        cancel: &AtomicBool,
+    ) -> Result<String> {
+        let root = self.get_note(root_id)?
+            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", root_id))?;
+        let mut seen = HashSet::from([root.id.clone()]);
+        let mut queue = VecDeque::from([(root, 0)]);
+        let mut notes = Vec::new();
+        let backlinks = if self.export.stats {
+            self.backlink_index(&self.list_notes()?)
+        } else {
+            HashMap::new()
+        };
+        while let Some((note, hops)) = queue.pop_front() {
+            check_cancel(cancel)?;
+            if hops < depth {
+                for link_id in &note.links {
+                    if seen.contains(link_id) {
+                        continue;
+                    }
+                    if let Some(linked) = self.get_note(link_id)? {
+                        seen.insert(linked.id.clone());
+                        queue.push_back((linked, hops + 1));
+                    }
+                }
+            }
+            notes.push(note);
+        }
+        let mut sections = Vec::new();
+        let mut refs = LinkRefs::new(self.export.link_style);
+        for note in &notes {
+            check_cancel(cancel)?;
+            sections.push(self.note_markdown(note, &backlinks, &mut refs));
+            progress(sections.len(), notes.len());
+        }
+        Ok(sections.join("\n---\n\n"))
+    }
+
+    /// Get commit history for a note, newest first, including commits made under earlier titles
+    pub fn get_note_history(&self, note_id: &str) -> Result<Vec<crate::storage::CommitInfo>> {
+        // The title is only used to match commits made before IDs were embedded in messages
+        let note = self.get_note(note_id)?;
+        let note_title = note.as_ref().map(|n| n.title.as_str()).unwrap_or("");
+        let note_id = note.as_ref().map(|n| n.id.as_str()).unwrap_or(note_id);
+        
+        // The note's file under either naming; a slug name changes with the title, but
+        // always ends in the (short) id
+        let short_id: String = note_id.chars().take(FILE_ID_CHARS).collect();
+        let mut files = vec![
+            self.note_path(note_id),
+            self.notes_dir.join(format!("{}.json", note_id)),
+            self.notes_dir.join(format!("*-{}.json", short_id)),
+            self.notes_dir.join(format!("*-{}.json", note_id)),
+        ];
+        files.dedup();
+        self.jujutsu.get_note_history(&files, note_id, note_title)
+    }
+
+    /// Most recent commits across all notes, newest first
+    pub fn get_repo_log(&self, limit: usize) -> Result<Vec<crate::storage::CommitInfo>> {
+        self.jujutsu.get_repo_log(limit)
+    }
+
+    /// Diff of one vault commit
+    pub fn commit_diff(&self, commit_id: &str) -> Result<String> {
+        self.jujutsu.commit_diff(commit_id)
+    }
+
+    /// Write all note files and templates to a `.tar.gz` archive (as `notes/` and `templates/`).
+    /// Uses the system `tar` command. Returns the number of notes archived; `progress` hears
+    /// `(copied, total)` after each note copied into the archive. Setting `cancel` stops with
+    /// `Cancelled` before any archive is written.
+    pub fn backup_to_archive(&self, out: &Path, progress: &mut dyn FnMut(usize, usize), cancel: &AtomicBool) -> Result<usize> {
+        let out = std::path::absolute(out)?;
+        let staging = staging_dir("backup")?;
+        let result = (|| {
+            let count = copy_files(self.note_files()?, &staging.join("notes"), progress, cancel)?;
+            copy_files(files_with_extension(&self.templates_dir, "md")?, &staging.join("templates"), &mut |_, _| {}, cancel)?;
+            check_cancel(cancel)?;
+            
+            let output = Command::new("tar")
+                .arg("-czf")
+                .arg(&out)
+                .arg("-C")
+                .arg(&staging)
+                .arg("notes")
+                .arg("templates")
+                .output()
+                .context("Failed to run tar")?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                anyhow::bail!("Failed to create archive: {}", stderr);
+            }
+            Ok(count)
+        })();
+        let _ = std::fs::remove_dir_all(&staging);
+        result
+    }
+
+    /// Restore notes and templates from an archive made by `backup_to_archive` and commit them.
+    /// Notes with the same id are overwritten unless they are locked, which are left as they
+    /// are and counted; other notes in the vault are left alone. `progress` hears
+    /// `(restored, total)` after each note. Setting `cancel` stops with `Cancelled` and puts
+    /// back any note files already overwritten.
+    pub fn restore_from_archive(&self, archive: &Path, progress: &mut dyn FnMut(usize, usize), cancel: &AtomicBool) -> Result<Restored> {
+        self.ensure_writable()?;
+        if !archive.exists() {
+            anyhow::bail!("Archive does not exist: {}", archive.display());
+        }
+        let archive = std::path::absolute(archive)?;
+        let before = self.jujutsu.working_copy_commit_id()?;
+        let staging = staging_dir("restore")?;
+        let result = (|| {
+            let output = Command::new("tar")
+                .arg("-xzf")
+                .arg(&archive)
+                .arg("-C")
+                .arg(&staging)
+                .output()
+                .context("Failed to run tar")?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                anyhow::bail!("Failed to extract archive: {}", stderr);
+            }
+            
+            // Refuse archives with broken notes before touching the vault
+            let restored_notes = staging.join("notes");
+            let mut restored = Vec::new();
+            if restored_notes.exists() {
+                for entry in std::fs::read_dir(&restored_notes)? {
+                    check_cancel(cancel)?;
+                    let path = entry?.path();
+                    if path.extension().and_then(|s| s.to_str()) == Some("json") {
+                        let content = std::fs::read_to_string(&path)?;
+                        let note = Note::from_json(&content)
+                            .with_context(|| format!("Invalid note in archive: {}", path.display()))?;
+                        restored.push((note.id, path));
+                    }
+                }
+            }
+            let mut locked = 0;
+            for (id, path) in std::mem::take(&mut restored) {
+                if self.get_note(&id)?.is_some_and(|note| note.locked) {
+                    std::fs::remove_file(&path)?;
+                    locked += 1;
+                } else {
+                    restored.push((id, path));
+                }
+            }
+            
+            // A note filed under another name in the vault would otherwise end up twice
+            for (id, path) in &restored {
+                if let Some(existing) = self.find_note_file(id)?
+                    && existing.file_name() != path.file_name()
+                {
+                    std::fs::remove_file(&existing)?;
+                }
+            }
+            let notes = copy_files(files_with_extension(&restored_notes, "json")?, &self.notes_dir, progress, cancel)?;
+            copy_files(files_with_extension(&staging.join("templates"), "md")?, &self.templates_dir, &mut |_, _| {}, cancel)?;
+            Ok(Restored { notes, locked })
+        })();
+        let _ = std::fs::remove_dir_all(&staging);
+        if let Err(ref e) = result
+            && e.is::<Cancelled>()
+        {
+            // Half a restore is worse than none: back to the files from before it started
+            self.jujutsu.restore_from_commit(&before)?;
+        }
+        let restored = result?;
+        
+        let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let commit_message = format!("Restore: {} notes from {} ({})", restored.notes, archive.display(), timestamp);
+        self.commit(&commit_message)?;
+        
+        Ok(restored)
+    }
+
+    /// Get statistics about the knowledge base
+    pub fn get_statistics(&self) -> Result<NoteStatistics> {
+        let all_notes = self.list_note_summaries()?;
+        let total_notes = all_notes.len();
+        let total_links: usize = all_notes.iter().map(|n| n.link_count).sum();
+        let total_tags: usize = all_notes.iter().map(|n| n.tags.len()).sum();
+        let unique_tags: std::collections::HashSet<String> = all_notes
+            .iter()
+            .flat_map(|n| n.tags.iter().map(|t| t.to_lowercase()))
+            .collect();
+        
+        Ok(NoteStatistics {
+            total_notes,
+            total_links,
+            total_tags,
+            unique_tags_count: unique_tags.len(),
+        })
+    }
+
+    /// Circular link chains, each as the note ids in link order with the last note linking
+    /// back to the first. A depth-first walk reports one chain per link that closes a loop,
+    /// so a cycle shows up once however many of its notes the walk starts from. Links to
+    /// missing notes are skipped. Cycles are allowed; this only reports them.
+    pub fn find_cycles(&self) -> Result<Vec<Vec<String>>> {
+        let mut notes = self.list_notes()?;
+        notes.sort_by(|a, b| a.id.cmp(&b.id));
+        let links: HashMap<&str, &[String]> = notes.iter().map(|n| (n.id.as_str(), n.links.as_slice())).collect();
+
+        let mut on_path = HashMap::new();
+        let mut path = Vec::new();
+        let mut cycles = Vec::new();
+        for note in &notes {
+            if !on_path.contains_key(note.id.as_str()) {
+                walk_cycles(&note.id, &links, &mut on_path, &mut path, &mut cycles);
+            }
+        }
+        Ok(cycles)
+    }
+
+    /// Every hygiene check over `notes` in one report, for the vault health screen
+    pub fn vault_health(&self, notes: &[Note]) -> Result<VaultHealth> {
+        Ok(VaultHealth {
+            orphans: self.orphan_notes(notes),
+            broken_links: self.broken_links(notes),
+            duplicate_titles: self.duplicate_titles(notes),
+            stubs: self.stub_notes(notes),
+            cycles: self.find_cycles()?,
+            tag_variants: self.case_variant_tags(notes),
+            missing_history: self.notes_missing_history(notes)?,
+        })
+    }
+}
+
+/// A markdown task item (`- [ ]` / `- [x]`) found in a note
+#[derive(Debug, Clone)]
+pub struct Task {
+    pub note_id: String,
+    pub note_title: String,
+    pub line: usize, // 0-based line of the item in the note content
+    pub done: bool,
+    pub text: String,
+}
+
+#[derive(Debug)]
+pub struct NoteStatistics {
+    pub total_notes: usize,
+    pub total_links: usize,
+    pub total_tags: usize,
+    pub unique_tags_count: usize,
+}
+
+/// What a vault-wide tag change did
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TagChange {
+    /// Notes whose tags were changed
+    pub changed: usize,
+    /// Notes carrying the tag that were left alone because they are locked
+    pub locked: usize,
+}
+
+/// What a restore from an archive did
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Restored {
+    /// Notes written from the archive
+    pub notes: usize,
+    /// Notes in the archive that were left as they are because they are locked in the vault
+    pub locked: usize,
+}
+
+/// Spellings of one tag that differ only in case, as found by `case_variant_tags`
+#[derive(Debug, Clone)]
+pub struct TagVariants {
+    pub spellings: Vec<(String, usize)>, // Each spelling with its note count, `canonical` first
+}
+
+impl TagVariants {
+    /// Spelling to normalize to: the most used one, the lowercase one on a tie
+    pub fn canonical(&self) -> &str {
+        &self.spellings[0].0
+    }
+
+    /// Whether `tag` is one of these spellings, in any case
+    pub fn contains(&self, tag: &str) -> bool {
+        self.spellings[0].0.to_lowercase() == tag.to_lowercase()
+    }
+}
+
+/// A note with no content of its own, as found by `stub_notes`
+#[derive(Debug, Clone)]
+pub struct Stub {
+    pub note: Note,
+    pub reason: StubReason,
 }
 
-#[derive(Debug)]
-pub struct NoteStatistics {
-    pub total_notes: usize,
-    pub total_links: usize,
-    pub total_tags: usize,
-    pub unique_tags_count: usize,
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StubReason {
+    /// Nothing but the title
+    Empty,
+    /// The same body text as the note with this id
+    SameAs(String),
+}
+
+/// What each hygiene check found, as gathered by `vault_health`
+#[derive(Debug, Clone, Default)]
+pub struct VaultHealth {
+    pub orphans: Vec<Note>,
+    pub broken_links: Vec<(Note, Vec<String>)>, // Each note with the ids it links to that no note has
+    pub duplicate_titles: Vec<Vec<Note>>,
+    pub stubs: Vec<Stub>,
+    pub cycles: Vec<Vec<String>>,
+    pub tag_variants: Vec<TagVariants>,
+    pub missing_history: Vec<Note>,
+}
+
+/// Replace every tag in `from` (ignoring case) with `into`, placed where the first of them
+/// was, unless the note already has `into`. Returns whether the tags changed.
+fn replace_tags(tags: &mut Vec<String>, from: &[String], into: &str) -> bool {
+    let from_lower: Vec<String> = from.iter().map(|f| f.to_lowercase()).collect();
+    let is_from = |t: &String| from_lower.contains(&t.to_lowercase());
+    let Some(position) = tags.iter().position(is_from) else {
+        return false;
+    };
+    let before = tags.clone();
+    tags.retain(|t| !is_from(t));
+    let into_lower = into.to_lowercase();
+    if !tags.iter().any(|t| t.to_lowercase() == into_lower) {
+        tags.insert(position.min(tags.len()), into.to_string());
+    }
+    *tags != before
+}
+
+/// One step of `find_cycles`: visit `id` and the notes it links to. `on_path` holds every
+/// visited note, `true` while it is still on the current `path`; a link to such a note
+/// closes a cycle.
+fn walk_cycles<'a>(
+    id: &'a str,
+    links: &HashMap<&'a str, &'a [String]>,
+    on_path: &mut HashMap<&'a str, bool>,
+    path: &mut Vec<&'a str>,
+    cycles: &mut Vec<Vec<String>>,
+) {
+    on_path.insert(id, true);
+    path.push(id);
+    let targets = links.get(id).copied().unwrap_or_default();
+    for (i, target) in targets.iter().enumerate() {
+        let target = target.as_str();
+        // Dangling links lead nowhere, and a repeated link is the same edge again
+        if !links.contains_key(target) || targets[..i].iter().any(|t| t == target) {
+            continue;
+        }
+        match on_path.get(target) {
+            Some(true) => {
+                if let Some(start) = path.iter().position(|p| *p == target) {
+                    cycles.push(path[start..].iter().map(|p| p.to_string()).collect());
+                }
+            }
+            Some(false) => {}
+            None => walk_cycles(target, links, on_path, path, cycles),
+        }
+    }
+    path.pop();
+    on_path.insert(id, false);
+}
+
+/// Characters of the id in `{slug}-{short id}.json` file names
+const FILE_ID_CHARS: usize = 8;
+
+/// Whether a note file named `stem` may hold a note whose id starts with `prefix`: an
+/// `{id}` stem that starts with it, or a `{slug}-{short id}` stem whose id part fits it.
+/// Only the file content can tell for sure.
+fn may_hold_id(stem: &str, prefix: &str) -> bool {
+    stem.starts_with(prefix)
+        || stem.rsplit_once('-').is_some_and(|(_, file_id)| {
+            file_id.starts_with(prefix) || (file_id.len() >= FILE_ID_CHARS && prefix.starts_with(file_id))
+        })
+}
+
+/// Shortest id prefix `resolve_id` accepts, so a stray character never picks a note
+pub const MIN_ID_PREFIX: usize = 4;
+
+/// Longest file name stem `slugify_title` produces, in characters
+const MAX_SLUG_CHARS: usize = 100;
+
+/// File name stem for a note title that is safe on every platform: spaces become `_`,
+/// path separators and other characters filesystems reject become `_`, leading dots
+/// (hidden files, `..`) and trailing dots are dropped, and device names like `CON` are
+/// prefixed. A title with nothing usable left becomes `untitled`.
+pub fn slugify_title(title: &str) -> String {
+    let replaced: String = title
+        .trim()
+        .chars()
+        .map(|c| match c {
+            ' ' | '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(MAX_SLUG_CHARS)
+        .collect();
+    let slug = replaced.trim_start_matches(['.', '_']).trim_end_matches(['.', '_']);
+    if slug.is_empty() {
+        return "untitled".to_string();
+    }
+    // Windows reserves these names even with an extension
+    let stem = slug.split('.').next().unwrap_or(slug).to_uppercase();
+    let reserved = matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || ((stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.len() == 4
+            && stem[3..].chars().all(|c| c.is_ascii_digit()));
+    if reserved {
+        format!("_{}", slug)
+    } else {
+        slug.to_string()
+    }
+}
+
+/// Fresh empty temporary directory for staging archive contents
+fn staging_dir(purpose: &str) -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!(
+        "jjzettel-{}-{}-{}",
+        purpose,
+        std::process::id(),
+        chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)
+    ));
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// `Cancelled` once `cancel` is set, for long loops to check between steps
+fn check_cancel(cancel: &AtomicBool) -> Result<()> {
+    if cancel.load(Ordering::Relaxed) {
+        return Err(Cancelled.into());
+    }
+    Ok(())
+}
+
+/// The files in `dir` with the given extension; none if `dir` does not exist
+fn files_with_extension(dir: &Path, extension: &str) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if !dir.exists() {
+        return Ok(files);
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|s| s.to_str()) == Some(extension) {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Copy `files` into `to` (created if needed) under their own names. Returns the number of
+/// files copied, after telling `progress` `(copied, total)` for each; stops with
+/// `Cancelled` once `cancel` is set.
+fn copy_files(
+    files: Vec<PathBuf>,
+    to: &Path,
+    progress: &mut dyn FnMut(usize, usize),
+    cancel: &AtomicBool,
+) -> Result<usize> {
+    std::fs::create_dir_all(to)?;
+    for (i, path) in files.iter().enumerate() {
+        check_cancel(cancel)?;
+        if let Some(name) = path.file_name() {
+            std::fs::copy(path, to.join(name))?;
+        }
+        progress(i + 1, files.len());
+    }
+    Ok(files.len())
+}
+
+/// Writes links to notes in an export's link style. Footnotes are numbered across all notes
+/// exported into one file, so each title keeps one number and one definition.
+struct LinkRefs {
+    style: LinkStyle,
+    footnotes: Vec<String>, // Footnoted titles, `[^1]` first
+    defined: usize, // Footnotes whose `[^n]: Title` line was already written
+}
+
+impl LinkRefs {
+    fn new(style: LinkStyle) -> Self {
+        LinkRefs { style, footnotes: Vec::new(), defined: 0 }
+    }
+
+    /// Link to the note titled `title`, shown as `label`
+    fn link(&mut self, title: &str, label: &str) -> String {
+        match self.style {
+            LinkStyle::Wikilink if label == title => format!("[[{}]]", title),
+            LinkStyle::Wikilink => format!("[[{}|{}]]", title, label),
+            LinkStyle::Markdown => format!("[{}]({}.md)", label, slugify_title(title)),
+            LinkStyle::Footnote => {
+                let number = match self.footnotes.iter().position(|t| t == title) {
+                    Some(i) => i + 1,
+                    None => {
+                        self.footnotes.push(title.to_string());
+                        self.footnotes.len()
+                    }
+                };
+                format!("{}[^{}]", label, number)
+            }
+        }
+    }
+
+    /// `[^n]: Title` lines for the footnotes first referenced since the last call
+    fn take_footnotes(&mut self) -> String {
+        let start = self.defined;
+        self.defined = self.footnotes.len();
+        self.footnotes[start..]
+            .iter()
+            .enumerate()
+            .map(|(i, title)| format!("[^{}]: {}\n", start + i + 1, title))
+            .collect()
+    }
+}
+
+/// Backtick fence longer than any backtick run in `content`, at least three
+fn code_fence(content: &str) -> String {
+    let longest = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+/// Double-quoted YAML scalar (JSON string syntax is valid YAML)
+fn yaml_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| format!("\"{}\"", value))
+}
+
+/// Metadata field name as a YAML key, quoted unless it is a plain word
+fn yaml_key(key: &str) -> String {
+    if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+        key.to_string()
+    } else {
+        yaml_string(key)
+    }
+}
+
+/// Flow-style YAML list of quoted strings
+fn yaml_list(values: &[String]) -> String {
+    let items: Vec<String> = values.iter().map(|v| yaml_string(v)).collect();
+    format!("[{}]", items.join(", "))
+}
+
+/// `text` without `prefix`, compared case-insensitively (so `Title:` works like `title:`)
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    text.get(..prefix.len())
+        .filter(|head| head.eq_ignore_ascii_case(prefix))
+        .map(|_| &text[prefix.len()..])
+}
+
+/// Whether all chars of `needle` appear in `haystack` in the same order
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut rest = haystack.chars();
+    needle.chars().all(|c| rest.any(|h| h == c))
+}
+
+/// Lines delimiting the "Referenced by" block jjzettel keeps up to date in note content;
+/// HTML comments, so rendered markdown only shows the heading and the list
+const REFERENCED_BY_START: &str = "<!-- jjzettel:referenced-by -->";
+const REFERENCED_BY_END: &str = "<!-- /jjzettel:referenced-by -->";
+
+/// `content` with its "Referenced by" block listing `titles`: replaced where it is, added
+/// at the end if there is none, or removed when `titles` is empty. The titles are plain
+/// text rather than wikilinks, so wikilink sync does not link back. `None` if nothing
+/// changes, including when the block has lost its end marker: everything after the start
+/// marker could be the user's, so it is left for them to fix.
+fn with_referenced_by(content: &str, titles: &[String]) -> Option<String> {
+    let mut start = None;
+    let mut end = None;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        match line.trim() {
+            REFERENCED_BY_START if start.is_none() => start = Some(offset),
+            REFERENCED_BY_END if start.is_some() => {
+                end = Some(offset + line.len());
+                break;
+            }
+            _ => {}
+        }
+        offset += line.len();
+    }
+    let (before, after) = match (start, end) {
+        (Some(start), Some(end)) => (&content[..start], &content[end..]),
+        (Some(_), None) => return None,
+        (None, _) if titles.is_empty() => return None,
+        (None, _) => (content, ""),
+    };
+
+    let mut updated = before.trim_end().to_string();
+    if !titles.is_empty() {
+        if !updated.is_empty() {
+            updated.push_str("\n\n");
+        }
+        updated.push_str(REFERENCED_BY_START);
+        updated.push_str("\n## Referenced by\n\n");
+        for title in titles {
+            updated.push_str(&format!("- {}\n", title));
+        }
+        updated.push_str(REFERENCED_BY_END);
+    }
+    let after = after.trim_start_matches(['\r', '\n']);
+    if !after.is_empty() {
+        updated.push_str("\n\n");
+        updated.push_str(after);
+    } else if content.ends_with('\n') {
+        updated.push('\n');
+    }
+    (updated != content).then_some(updated)
+}
+
+/// Whether `query` is a `field:value` metadata search (field names are single words)
+fn is_field_query(query: &str) -> bool {
+    query
+        .trim()
+        .split_once(':')
+        .is_some_and(|(field, _)| !field.is_empty() && field.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-'))
+}
+
+/// The lowercase text a `query` matches titles or content against, `None` for tag, id
+/// and metadata searches
+fn ranked_text(query: &str) -> Option<String> {
+    let trimmed = query.trim_start();
+    if let Some(title) = strip_prefix_ignore_case(trimmed, "title:") {
+        return Some(title.trim().to_lowercase());
+    }
+    let other_kind = query.starts_with('#')
+        || strip_prefix_ignore_case(trimmed, "tag:").is_some()
+        || strip_prefix_ignore_case(trimmed, "id:").is_some()
+        || is_field_query(query);
+    (!other_kind).then(|| query.to_lowercase())
+}
+
+/// Points for each matched character in a fuzzy match, extra for one starting a word or
+/// following the previous match, and the cost of each character skipped between matches
+const FUZZY_MATCH: i64 = 16;
+const FUZZY_WORD_START: i64 = 8;
+const FUZZY_CONSECUTIVE: i64 = 8;
+const FUZZY_GAP: i64 = 1;
+
+/// Best score of the lowercase characters `pattern` found in order in `text`, ignoring
+/// case, or `None` if they are not all there or so spread out that the gaps cost more than
+/// the matches earn. Characters before the first match cost nothing.
+fn fuzzy_score(pattern: &[char], text: &str) -> Option<i64> {
+    let chars: Vec<char> = text.chars().collect();
+    if pattern.is_empty() || chars.len() < pattern.len() {
+        return None;
+    }
+    let lower: Vec<char> = chars.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+    let word_start = |j: usize| {
+        j == 0 || !chars[j - 1].is_alphanumeric() || (chars[j - 1].is_lowercase() && chars[j].is_uppercase())
+    };
+    let score_at = |j: usize| FUZZY_MATCH + if word_start(j) { FUZZY_WORD_START } else { 0 };
+    const NONE: i64 = i64::MIN / 2;
+    // best[j]: best score of the pattern so far with its current character matched at j
+    let mut best: Vec<i64> = (0..chars.len())
+        .map(|j| if lower[j] == pattern[0] { score_at(j) } else { NONE })
+        .collect();
+    for &p in &pattern[1..] {
+        let mut next = vec![NONE; chars.len()];
+        // Best score of the previous character matched two or more places before j, less
+        // the cost of the characters skipped up to j
+        let mut gapped = NONE;
+        for j in 1..chars.len() {
+            let from = (best[j - 1] + FUZZY_CONSECUTIVE).max(gapped);
+            if lower[j] == p && from > NONE / 2 {
+                next[j] = from + score_at(j);
+            }
+            gapped = gapped.max(best[j - 1]) - FUZZY_GAP;
+        }
+        best = next;
+    }
+    best.into_iter().max().filter(|&score| score > 0)
+}
+
+/// How well `note` matches the lowercase search text `text`, higher is better: whether
+/// the title equals, starts with or contains it, then how often the content mentions it
+fn relevance(note: &Note, text: &str) -> (u8, usize) {
+    let title = note.title.to_lowercase();
+    let title_rank = if title == text {
+        3
+    } else if title.starts_with(text) {
+        2
+    } else if title.contains(text) {
+        1
+    } else {
+        0
+    };
+    (title_rank, note.content.to_lowercase().matches(text).count())
+}
+
+/// Titles referenced as `[[Title]]` or `[[Title|label]]` in `content`, in order of appearance
+fn wikilink_titles(content: &str) -> Vec<String> {
+    let mut titles: Vec<String> = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("[[") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find("]]") else {
+            break;
+        };
+        let inner = &rest[..end];
+        rest = &rest[end + 2..];
+        let title = inner.split('|').next().unwrap_or("").trim();
+        if !title.is_empty() && !inner.contains('\n') && !titles.iter().any(|t| t == title) {
+            titles.push(title.to_string());
+        }
+    }
+    titles
 }
 
+/// Split `X (3)` into `("X", 3)`; a title without a number suffix is `(title, 2)`
+fn split_number_suffix(title: &str) -> (&str, usize) {
+    if let Some(open) = title.rfind(" (")
+        && let Some(digits) = title[open + 2..].strip_suffix(')')
+        && digits.bytes().all(|b| b.is_ascii_digit())
+        && let Ok(n) = digits.parse::<usize>()
+    {
+        return (&title[..open], n + 1);
+    }
+    (title, 2)
+}
+
+/// Notes a wikilink can mean: those titled `target`, or for `[[id:3fa2b1c9]]` the notes
+/// whose id starts with that prefix (at least `MIN_ID_PREFIX` characters)
+fn wikilink_candidates<'a>(notes: &'a [Note], target: &str) -> Vec<&'a Note> {
+    match strip_prefix_ignore_case(target, "id:") {
+        Some(prefix) => {
+            let prefix = prefix.trim().to_lowercase();
+            if prefix.chars().count() < MIN_ID_PREFIX {
+                return Vec::new();
+            }
+            notes.iter().filter(|n| n.id.to_lowercase().starts_with(&prefix)).collect()
+        }
+        None => title_candidates(notes, target),
+    }
+}
+
+/// Notes in `notes` titled `title` (trimmed, ignoring case), oldest first and then by id
+fn title_candidates<'a>(notes: &'a [Note], title: &str) -> Vec<&'a Note> {
+    let wanted = title.trim().to_lowercase();
+    let mut candidates: Vec<&Note> = notes.iter().filter(|n| n.title.trim().to_lowercase() == wanted).collect();
+    candidates.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+    candidates
+}
+
+/// Whether `content` has anything but whitespace after its title line; saving a note
+/// without a body is warned about unless the config allows it
+pub fn has_body(content: &str) -> bool {
+    content.lines().skip(1).any(|line| !line.trim().is_empty())
+}
+
+/// Task items in markdown `content`: `(done, text)` for each `- [ ]` / `- [x]` line,
+/// also with `*`, `+` or `1.` bullets and at any indentation
+pub fn extract_tasks(content: &str) -> Vec<(bool, String)> {
+    content
+        .lines()
+        .filter_map(parse_task)
+        .map(|(done, text)| (done, text.to_string()))
+        .collect()
+}
+
+/// `(done, text)` if `line` is a markdown task item
+fn parse_task(line: &str) -> Option<(bool, &str)> {
+    let line = line.trim_start();
+    let rest = if let Some(rest) = line.strip_prefix(['-', '*', '+']) {
+        rest
+    } else {
+        let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            return None;
+        }
+        line[digits..].strip_prefix(['.', ')'])?
+    };
+    let rest = rest.strip_prefix(' ')?.trim_start();
+    let done = match rest.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    Some((done, rest[3..].trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ops::Deref;
+
+    /// A vault in a fresh temporary directory, removed again when dropped
+    struct TestVault {
+        dir: PathBuf,
+        service: NoteService,
+    }
+
+    impl TestVault {
+        /// Vault without a jj repo and with auto-commit off, for tests of the note files alone
+        fn plain(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("jjzettel-test-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(dir.join("notes")).unwrap();
+            let service = NoteService::new(dir.to_string_lossy(), dir.join("notes")).with_auto_commit(false);
+            TestVault { dir, service }
+        }
+
+        /// Vault in a jj repo with auto-commit on, or `None` (skipping the test) when jj is
+        /// not installed
+        fn with_jj(name: &str) -> Option<Self> {
+            if Command::new("jj").arg("--version").output().is_err() {
+                eprintln!("jj not found, skipping");
+                return None;
+            }
+            let mut vault = Self::plain(name);
+            vault.service = NoteService::new(vault.dir.to_string_lossy(), vault.dir.join("notes"));
+            vault.service.initialize().unwrap();
+            Some(vault)
+        }
+
+        /// The same vault with its service changed by `configure`, e.g. `with_audit_log`
+        fn configured(mut self, configure: impl FnOnce(NoteService) -> NoteService) -> Self {
+            let service = std::mem::replace(&mut self.service, NoteService::new(String::new(), PathBuf::new()));
+            self.service = configure(service);
+            self
+        }
+    }
+
+    impl Deref for TestVault {
+        type Target = NoteService;
+
+        fn deref(&self) -> &NoteService {
+            &self.service
+        }
+    }
+
+    impl Drop for TestVault {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn history_follows_a_note_across_a_rename() {
+        let Some(vault) = TestVault::with_jj("history") else { return };
+        let note = vault.create_note("Draft".into(), "Draft\n\nfirst".into()).unwrap();
+        let renamed = vault.rename_note(&note.id, "Final".into()).unwrap();
+        vault.update_note(renamed, "Final\n\nsecond".into()).unwrap();
+
+        let history = vault.get_note_history(&note.id).unwrap();
+        let messages: Vec<&str> = history.iter().map(|c| c.message.as_str()).collect();
+        assert_eq!(messages.len(), 3, "{:?}", messages);
+        assert!(messages[0].starts_with("Update: Final"), "{:?}", messages);
+        assert!(messages[1].starts_with("Rename: Draft → Final"), "{:?}", messages);
+        assert!(messages[2].starts_with("Note: Draft"), "{:?}", messages);
+    }
+
+    #[test]
+    fn moved_note_leaves_the_source_vault_for_the_destination() {
+        let source = TestVault::plain("move-source");
+        let destination = TestVault::plain("move-destination");
+        let moved = source.create_note("Moved".into(), "Moved".into()).unwrap();
+        let stays = source.create_note("Stays".into(), "Stays".into()).unwrap();
+        source.link_notes(&moved.id, &stays.id).unwrap();
+        source.link_notes(&stays.id, &moved.id).unwrap();
+
+        let stripped = source.move_note_to(&moved.id, &destination).unwrap();
+
+        assert_eq!(stripped, 1, "the link to a note left behind is stripped");
+        assert!(source.get_note(&moved.id).unwrap().is_none());
+        assert!(source.get_note(&stays.id).unwrap().unwrap().links.is_empty(), "backlink cleaned up");
+        let arrived = destination.get_note(&moved.id).unwrap().expect("note in the destination");
+        assert_eq!(arrived.title, "Moved");
+        assert!(arrived.links.is_empty());
+        assert_eq!(destination.list_notes().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn backup_restores_into_an_empty_vault() {
+        let Some(restored) = TestVault::with_jj("restore") else { return };
+        let original = TestVault::plain("backup");
+        let a = original.create_note("Alpha".into(), "Alpha\n\none".into()).unwrap();
+        let b = original.create_note("Beta".into(), "Beta\n\ntwo".into()).unwrap();
+        original.link_notes(&a.id, &b.id).unwrap();
+        original.add_tag(&b.id, "greek".into()).unwrap();
+        let archive = original.dir.join("backup.tar.gz");
+        let cancel = AtomicBool::new(false);
+
+        let archived = original.backup_to_archive(&archive, &mut |_, _| {}, &cancel).unwrap();
+        let count = restored.restore_from_archive(&archive, &mut |_, _| {}, &cancel).unwrap();
+
+        assert_eq!((archived, count), (2, Restored { notes: 2, locked: 0 }));
+        let notes = |vault: &TestVault| {
+            let mut notes = vault.list_notes().unwrap();
+            notes.sort_by(|x, y| x.id.cmp(&y.id));
+            notes.into_iter().map(|n| serde_json::to_string(&n).unwrap()).collect::<Vec<_>>()
+        };
+        assert_eq!(notes(&restored), notes(&original));
+    }
+
+    #[test]
+    fn linked_note_is_committed_with_its_link() {
+        let Some(vault) = TestVault::with_jj("create-linked") else { return };
+        let source = vault.create_note("Source".into(), "Source".into()).unwrap();
+        let note = vault.create_linked_note(&source.id, "Target".into(), "Target".into()).unwrap();
+
+        assert_eq!(vault.get_note(&source.id).unwrap().unwrap().links, vec![note.id.clone()]);
+        assert!(!vault.jujutsu.has_changes().unwrap(), "nothing left uncommitted");
+        let history = vault.get_note_history(&source.id).unwrap();
+        assert!(history[0].message.starts_with("Note: Target"), "{:?}", history);
+    }
+
+    #[test]
+    fn undo_brings_back_a_deleted_note() {
+        let Some(vault) = TestVault::with_jj("undo") else { return };
+        let note = vault.create_note("Keep me".into(), "Keep me\n\nimportant".into()).unwrap();
+        let file = vault.note_path(&note.id);
+        let before = std::fs::read_to_string(&file).unwrap();
+
+        let point = vault.undo_point("Delete 'Keep me'").unwrap();
+        vault.delete_note(&note.id).unwrap();
+        assert!(!file.exists());
+        vault.undo(&point).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), before);
+        assert_eq!(vault.get_note(&note.id).unwrap().unwrap().content, "Keep me\n\nimportant");
+    }
+
+    #[test]
+    fn note_operations_append_json_lines_to_the_audit_log() {
+        let vault = TestVault::plain("audit");
+        let log = vault.dir.join("audit.log");
+        let vault = vault.configured(|service| service.with_audit_log(Some(AuditLog::new(&log))));
+        let a = vault.create_note("A".into(), "A".into()).unwrap();
+        let b = vault.create_note("B".into(), "B".into()).unwrap();
+        let a = vault.update_note(a, "A\n\nmore".into()).unwrap();
+        vault.link_notes(&a.id, &b.id).unwrap();
+        vault.add_tag(&a.id, "idea".into()).unwrap();
+        vault.set_metadata(&a.id, "status".into(), "draft".into()).unwrap();
+        vault.delete_note(&b.id).unwrap();
+
+        let entries: Vec<serde_json::Value> = std::fs::read_to_string(&log)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("each line is one JSON object"))
+            .collect();
+        let actions: Vec<(&str, &str, Option<&str>)> = entries
+            .iter()
+            .map(|e| (e["action"].as_str().unwrap(), e["note_id"].as_str().unwrap(), e["detail"].as_str()))
+            .collect();
+        assert_eq!(actions, vec![
+            ("create", a.id.as_str(), None),
+            ("create", b.id.as_str(), None),
+            ("update", a.id.as_str(), None),
+            ("link", a.id.as_str(), Some(b.id.as_str())),
+            ("tag", a.id.as_str(), Some("idea")),
+            ("metadata", a.id.as_str(), Some("status")),
+            ("delete", b.id.as_str(), None),
+        ]);
+        for entry in &entries {
+            chrono::DateTime::parse_from_rfc3339(entry["timestamp"].as_str().unwrap()).expect("RFC 3339 timestamp");
+        }
+    }
+
+    #[test]
+    fn new_wikilink_adds_a_link_and_removing_it_keeps_the_link() {
+        let vault = TestVault::plain("wikilink-add").configured(|service| service.with_wikilink_sync(WikilinkSync::Add));
+        let target = vault.create_note("Target".into(), "Target".into()).unwrap();
+        let note = vault.create_note("Source".into(), "Source".into()).unwrap();
+
+        let note = vault.update_note(note, "Source\n\nsee [[target]]".into()).unwrap();
+        assert_eq!(note.links, vec![target.id.clone()]);
+        let note = vault.update_note(note, "Source\n\nno wikilink".into()).unwrap();
+        assert_eq!(note.links, vec![target.id], "removal is opt-in");
+    }
+
+    #[test]
+    fn sync_removes_links_whose_wikilink_was_deleted() {
+        let vault = TestVault::plain("wikilink-sync").configured(|service| service.with_wikilink_sync(WikilinkSync::Sync));
+        let target = vault.create_note("Target".into(), "Target".into()).unwrap();
+        let manual = vault.create_note("Manual".into(), "Manual".into()).unwrap();
+        let note = vault.create_note("Source".into(), "Source\n\nsee [[Target]]".into()).unwrap();
+        assert_eq!(note.links, vec![target.id.clone()]);
+        vault.link_notes(&note.id, &manual.id).unwrap();
+        let note = vault.get_note(&note.id).unwrap().unwrap();
+
+        let note = vault.update_note(note, "Source\n\nno wikilink".into()).unwrap();
+        assert_eq!(note.links, vec![manual.id], "links added by hand stay");
+    }
+
+    #[test]
+    fn extract_tasks_reads_checked_unchecked_and_indented_items() {
+        let content = "Plan\n\n- [ ] open\n- [x] done\n* [X] shouted\n    - [ ] nested\n\t+ [ ] tabbed\n1. [x] numbered\n2) [ ] other number";
+        assert_eq!(extract_tasks(content), vec![
+            (false, "open".to_string()),
+            (true, "done".to_string()),
+            (true, "shouted".to_string()),
+            (false, "nested".to_string()),
+            (false, "tabbed".to_string()),
+            (true, "numbered".to_string()),
+            (false, "other number".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn extract_tasks_skips_lines_that_only_look_like_tasks() {
+        let content = "- plain item\n[ ] no bullet\n-[ ] no space\n- [y] unknown mark\n- []\ntext - [ ] in the middle";
+        assert!(extract_tasks(content).is_empty(), "{:?}", extract_tasks(content));
+    }
+
+    #[test]
+    fn unique_title_counts_up_past_every_taken_title() {
+        let vault = TestVault::plain("unique-title");
+        assert_eq!(vault.unique_title("Ideas").unwrap(), "Ideas");
+        vault.create_note("Ideas".into(), "Ideas".into()).unwrap();
+        assert_eq!(vault.unique_title("Ideas").unwrap(), "Ideas (2)");
+        vault.create_note("ideas (2)".into(), "ideas".into()).unwrap();
+        vault.create_note("Ideas (3)".into(), "Ideas".into()).unwrap();
+        assert_eq!(vault.unique_title(" IDEAS ").unwrap(), "IDEAS (4)", "compared trimmed and ignoring case");
+        assert_eq!(vault.unique_title("Ideas (2)").unwrap(), "Ideas (4)", "a numbered base continues from its number");
+    }
+
+    #[test]
+    fn duplicates_are_numbered_in_sequence() {
+        let vault = TestVault::plain("duplicate-title");
+        let note = vault.create_note("Plan".into(), "Plan".into()).unwrap();
+        let titles: Vec<String> = (0..3).map(|_| vault.duplicate_note(&note.id, false).unwrap().title).collect();
+        assert_eq!(titles, vec!["Copy of Plan", "Copy of Plan (2)", "Copy of Plan (3)"]);
+    }
+
+    /// Note titled `title` with exactly the id `id`, written straight to the vault
+    fn note_with_id(vault: &TestVault, id: &str, title: &str) -> Note {
+        let mut note = Note::new(title.to_string(), title.to_string());
+        note.id = id.to_string();
+        vault.write_note(&note).unwrap();
+        note
+    }
+
+    #[test]
+    fn unique_id_prefix_resolves_to_its_note() {
+        let vault = TestVault::plain("id-prefix");
+        note_with_id(&vault, "abcd1234ef", "First");
+        note_with_id(&vault, "abce5678ef", "Second");
+        assert_eq!(vault.resolve_id("abcd").unwrap().as_deref(), Some("abcd1234ef"));
+        assert_eq!(vault.get_note("abce56").unwrap().unwrap().title, "Second");
+        assert_eq!(vault.resolve_id("abcd1234ef").unwrap().as_deref(), Some("abcd1234ef"));
+        assert_eq!(vault.resolve_id("abc").unwrap(), None, "shorter than MIN_ID_PREFIX");
+        assert_eq!(vault.resolve_id("ffff").unwrap(), None);
+    }
+
+    #[test]
+    fn ambiguous_id_prefix_is_rejected() {
+        let vault = TestVault::plain("id-ambiguous");
+        note_with_id(&vault, "abcd1234ef", "First");
+        note_with_id(&vault, "abcd5678ef", "Second");
+        let err = vault.get_note("abcd").unwrap_err();
+        assert_eq!(err.to_string(), "Id prefix 'abcd' is ambiguous: it matches 2 notes (abcd1234ef, abcd5678ef)");
+        assert_eq!(vault.get_note("abcd5").unwrap().unwrap().title, "Second");
+    }
+
+    #[test]
+    fn history_of_a_slug_named_note_finds_unmarked_commits_under_every_name() {
+        let Some(vault) = TestVault::with_jj("history-slug") else { return };
+        let vault = vault.configured(|service| service.with_file_naming(FileNaming::Slug));
+        let note = vault.create_note("Draft".into(), "Draft".into()).unwrap();
+        let mut edited = note.clone();
+        edited.content = "Draft\n\nby hand".into();
+        vault.write_note(&edited).unwrap();
+        vault.jujutsu.commit_working_copy("tweak before the rename").unwrap();
+        vault.rename_note(&note.id, "Final".into()).unwrap();
+        assert!(vault.note_path(&note.id).ends_with(format!("Final-{}.json", &note.id[..FILE_ID_CHARS])));
+        let mut edited = vault.get_note(&note.id).unwrap().unwrap();
+        edited.content = "Final\n\nby hand".into();
+        vault.write_note(&edited).unwrap();
+        vault.jujutsu.commit_working_copy("tweak after the rename").unwrap();
+
+        let history = vault.get_note_history(&note.id).unwrap();
+        let messages: Vec<&str> = history.iter().map(|c| c.message.as_str()).collect();
+        assert_eq!(messages.len(), 4, "{:?}", messages);
+        assert_eq!(messages[0], "tweak after the rename");
+        assert!(messages[1].starts_with("Rename: Draft → Final"), "{:?}", messages);
+        assert_eq!(messages[2], "tweak before the rename");
+        assert!(messages[3].starts_with("Note: Draft"), "{:?}", messages);
+    }
+
+    #[test]
+    fn cycle_is_reported_once() {
+        let vault = TestVault::plain("cycles");
+        for (id, links) in [("aaaa0001", ["bbbb0002"]), ("bbbb0002", ["cccc0003"]), ("cccc0003", ["aaaa0001"]), ("dddd0004", ["aaaa0001"])] {
+            let mut note = Note::new(id.to_string(), id.to_string());
+            note.id = id.to_string();
+            note.links = links.iter().map(|l| l.to_string()).collect();
+            vault.write_note(&note).unwrap();
+        }
+        assert_eq!(vault.find_cycles().unwrap(), vec![vec!["aaaa0001", "bbbb0002", "cccc0003"]]);
+    }
+
+    #[test]
+    fn read_only_notes_dir_degrades_to_view_only() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+        // Root writes regardless of permission bits, so there is nothing to test then.
+        // /proc/self belongs to the effective user.
+        if std::fs::metadata("/proc/self").is_ok_and(|proc| proc.uid() == 0) {
+            eprintln!("running as root, skipping");
+            return;
+        }
+        let vault = TestVault::plain("read-only");
+        let note = vault.create_note("Readable".into(), "Readable".into()).unwrap();
+        let notes_dir = vault.dir.join("notes");
+        std::fs::set_permissions(&notes_dir, std::fs::Permissions::from_mode(0o555)).unwrap();
+        let restore = || std::fs::set_permissions(&notes_dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        // What App::new checks before opening the vault read-only
+        let err = vault.initialize().unwrap_err();
+        assert!(err.to_string().contains("is not writable"), "{}", err);
+        assert!(notes_dir.is_dir() && !vault.notes_dir_writable());
+        let vault = vault.configured(|service| service.with_read_only(true));
+        assert_eq!(vault.get_note(&note.id).unwrap().unwrap().title, "Readable");
+        let err = vault.create_note("New".into(), "New".into()).unwrap_err();
+        assert_eq!(err.to_string(), "The vault is read-only; changes are disabled");
+        restore();
+    }
+
+    #[test]
+    fn dangling_notes_dir_symlink_is_reported() {
+        let vault = TestVault::plain("dangling");
+        let link = vault.dir.join("linked-notes");
+        std::os::unix::fs::symlink(vault.dir.join("missing"), &link).unwrap();
+        let service = NoteService::new(vault.dir.to_string_lossy(), &link).with_auto_commit(false);
+        let err = service.initialize().unwrap_err();
+        assert!(err.to_string().contains("is a symlink to"), "{}", err);
+        assert!(!service.notes_dir_writable());
+    }
+
+    #[test]
+    fn stale_notes_are_listed_oldest_first_with_unreadable_dates_last() {
+        let vault = TestVault::plain("stale");
+        let now = chrono::DateTime::parse_from_rfc3339("2025-03-31T12:00:00Z").unwrap().with_timezone(&chrono::Utc);
+        let note = |title: &str, updated_at: &str| {
+            let mut note = Note::new(title.to_string(), title.to_string());
+            note.updated_at = updated_at.to_string();
+            note
+        };
+        let notes = vec![
+            note("Boundary", "2025-03-01T12:00:00Z"),
+            note("Fresh", "2025-03-01T12:00:01Z"),
+            note("Broken", "not a date"),
+            note("Oldest", "2024-01-01T00:00:00Z"),
+        ];
+        let titles: Vec<String> = vault.stale_notes(&notes, 30, now).into_iter().map(|n| n.title).collect();
+        assert_eq!(titles, vec!["Oldest", "Boundary", "Broken"]);
+    }
+
+    /// Note titled `title` that is locked
+    fn locked_note(vault: &TestVault, title: &str) -> Note {
+        let note = vault.create_note(title.to_string(), title.to_string()).unwrap();
+        vault.set_locked(&note.id, true).unwrap()
+    }
+
+    #[test]
+    fn locked_note_refuses_every_edit() {
+        let vault = TestVault::plain("locked");
+        let other = vault.create_note("Other".into(), "Other".into()).unwrap();
+        let note = locked_note(&vault, "Fixed");
+        let refused = |result: Result<()>| {
+            let err = result.expect_err("locked note changed");
+            assert_eq!(err.to_string(), "'Fixed' is locked; unlock it first");
+        };
+        refused(vault.update_note(note.clone(), "Fixed\n\nchanged".into()).map(drop));
+        refused(vault.rename_note(&note.id, "Moved".into()).map(drop));
+        refused(vault.add_tag(&note.id, "tag".into()).map(drop));
+        refused(vault.set_metadata(&note.id, "k".into(), "v".into()).map(drop));
+        refused(vault.link_notes(&note.id, &other.id));
+        refused(vault.delete_note(&note.id));
+        refused(vault.merge_note_into(&other.id, &note.id).map(drop));
+        refused(vault.save_raw_note(&note.id, &serde_json::to_string(&Note { locked: false, ..note.clone() }).unwrap()).map(drop));
+        assert_eq!(vault.get_note(&note.id).unwrap().unwrap().content, "Fixed");
+
+        // Organizing stays possible, and other notes can still link to it
+        vault.set_due(&note.id, Some("2030-01-01T00:00:00Z".into())).unwrap();
+        vault.link_notes(&other.id, &note.id).unwrap();
+        let unlocked = vault.set_locked(&note.id, false).unwrap();
+        vault.update_note(unlocked, "Fixed\n\nchanged".into()).unwrap();
+    }
+
+    #[test]
+    fn tag_changes_everywhere_skip_locked_notes() {
+        let vault = TestVault::plain("locked-tags");
+        let open = vault.create_note("Open".into(), "Open".into()).unwrap();
+        vault.add_tag(&open.id, "old".into()).unwrap();
+        let fixed = vault.create_note("Fixed".into(), "Fixed".into()).unwrap();
+        vault.add_tag(&fixed.id, "old".into()).unwrap();
+        vault.set_locked(&fixed.id, true).unwrap();
+
+        assert_eq!(vault.rename_tag_everywhere("old", "new").unwrap(), TagChange { changed: 1, locked: 1 });
+        assert_eq!(vault.get_note(&open.id).unwrap().unwrap().tags, vec!["new"]);
+        assert_eq!(vault.get_note(&fixed.id).unwrap().unwrap().tags, vec!["old"]);
+        assert_eq!(vault.merge_tags(&["old".to_string(), "new".to_string()], "new").unwrap(), TagChange { changed: 0, locked: 1 });
+        assert_eq!(vault.delete_tag_everywhere("old").unwrap(), TagChange { changed: 0, locked: 1 });
+    }
+
+    #[test]
+    fn allow_and_warn_policies_create_a_note_with_a_taken_title() {
+        for policy in [DuplicateTitlePolicy::Allow, DuplicateTitlePolicy::Warn] {
+            let vault = TestVault::plain(&format!("duplicate-{:?}", policy))
+                .configured(|service| service.with_duplicate_title_policy(policy));
+            let first = vault.create_note("Plan".into(), "Plan".into()).unwrap();
+            assert_eq!(vault.title_taken(" plan ").unwrap().map(|n| n.id), Some(first.id.clone()), "{:?}", policy);
+            let second = vault.create_note("plan".into(), "plan".into()).unwrap();
+            assert_ne!(second.id, first.id);
+            assert_eq!(vault.list_notes().unwrap().len(), 2, "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn block_policy_refuses_a_taken_title_and_names_the_existing_note() {
+        let vault = TestVault::plain("duplicate-block")
+            .configured(|service| service.with_duplicate_title_policy(DuplicateTitlePolicy::Block));
+        let first = vault.create_note("Plan".into(), "Plan".into()).unwrap();
+        let error = vault.create_note(" PLAN".into(), "PLAN".into()).unwrap_err();
+        let duplicate = error.downcast_ref::<DuplicateTitle>().expect("a DuplicateTitle error");
+        assert_eq!(duplicate.existing.id, first.id);
+        assert_eq!(vault.list_notes().unwrap().len(), 1);
+        vault.create_note("Plan B".into(), "Plan B".into()).unwrap();
+    }
+
+    #[test]
+    fn case_variants_are_grouped_counted_and_normalized_as_one_tag() {
+        let vault = TestVault::plain("tag-variants");
+        for (title, tags) in [("A", ["Rust", "Ärger"]), ("B", ["rust", "ärger"]), ("C", ["rust", "RUST"])] {
+            let note = vault.create_note(title.into(), title.into()).unwrap();
+            for tag in tags {
+                vault.add_tag(&note.id, tag.into()).unwrap();
+            }
+        }
+        let notes = vault.list_notes().unwrap();
+        assert_eq!(vault.get_note(&notes.iter().find(|n| n.title == "C").unwrap().id).unwrap().unwrap().tags, vec!["rust"]);
+
+        let variants = vault.case_variant_tags(&notes);
+        let spellings: Vec<Vec<(String, usize)>> = variants.iter().map(|v| v.spellings.clone()).collect();
+        assert_eq!(spellings, vec![
+            vec![("rust".to_string(), 2), ("Rust".to_string(), 1)],
+            vec![("ärger".to_string(), 1), ("Ärger".to_string(), 1)],
+        ]);
+        assert!(variants[0].contains("RUST") && variants[1].contains("ÄRGER"));
+        let counts: Vec<(String, usize)> = vault.tag_counts(&notes).into_iter().map(|(tag, n)| (tag.to_lowercase(), n)).collect();
+        assert_eq!(counts, vec![("rust".to_string(), 3), ("ärger".to_string(), 2)], "one count per tag, whatever the case");
+
+        for group in &variants {
+            let from: Vec<String> = group.spellings.iter().map(|(tag, _)| tag.clone()).collect();
+            vault.merge_tags(&from, group.canonical()).unwrap();
+        }
+        let notes = vault.list_notes().unwrap();
+        assert!(vault.case_variant_tags(&notes).is_empty());
+        assert_eq!(vault.tag_counts(&notes), vec![("rust".to_string(), 3), ("ärger".to_string(), 2)]);
+    }
+
+    /// Note titled `title` last updated on 2025-03-`day`, placed `order` in the manual order
+    fn dated_note(title: &str, day: u32, order: Option<i64>) -> Note {
+        let mut note = Note::new(title.to_string(), title.to_string());
+        note.updated_at = format!("2025-03-{:02}T12:00:00Z", day);
+        note.order = order;
+        note
+    }
+
+    #[test]
+    fn manual_order_puts_ordered_notes_first_and_the_rest_newest_first() {
+        let mut notes = vec![
+            dated_note("Old loose", 1, None),
+            dated_note("Second", 2, Some(2)),
+            dated_note("New loose", 9, None),
+            dated_note("First", 3, Some(1)),
+            dated_note("Middle loose", 5, None),
+        ];
+        let titles = |notes: &[Note]| notes.iter().map(|n| n.title.clone()).collect::<Vec<_>>();
+        ListOrder::Manual.sort(&mut notes);
+        assert_eq!(titles(&notes), vec!["First", "Second", "New loose", "Middle loose", "Old loose"]);
+        ListOrder::Recent.sort(&mut notes);
+        assert_eq!(titles(&notes), vec!["New loose", "Middle loose", "First", "Second", "Old loose"]);
+    }
+
+    #[test]
+    fn moving_in_the_manual_order_renumbers_and_can_leave_it() {
+        let vault = TestVault::plain("manual-order");
+        for note in [dated_note("A", 1, Some(1)), dated_note("B", 2, Some(2)), dated_note("Loose", 3, None)] {
+            vault.write_note(&note).unwrap();
+        }
+        let id = |title: &str| vault.list_notes().unwrap().into_iter().find(|n| n.title == title).unwrap().id;
+        let order = || {
+            let mut notes = vault.list_notes().unwrap();
+            ListOrder::Manual.sort(&mut notes);
+            notes.into_iter().map(|n| (n.title, n.order)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(vault.move_in_order(&id("Loose"), false).unwrap().order, None, "down does not add a note");
+        assert_eq!(vault.move_in_order(&id("Loose"), true).unwrap().order, Some(3), "up adds it at the end");
+        vault.move_in_order(&id("Loose"), true).unwrap();
+        assert_eq!(order(), vec![("A".into(), Some(1)), ("Loose".into(), Some(2)), ("B".into(), Some(3))]);
+        assert_eq!(vault.move_in_order(&id("A"), true).unwrap().order, Some(1), "first stays first");
+        vault.move_in_order(&id("B"), false).unwrap();
+        assert_eq!(order(), vec![("A".into(), Some(1)), ("Loose".into(), Some(2)), ("B".into(), None)]);
+        assert_eq!(vault.get_note(&id("B")).unwrap().unwrap().updated_at, "2025-03-02T12:00:00Z");
+    }
+
+    #[test]
+    fn vault_health_counts_every_problem_in_a_crafted_vault() {
+        let Some(vault) = TestVault::with_jj("health") else { return };
+        let create = |title: &str, body: &str| vault.create_note(title.into(), format!("{}\n\n{}", title, body)).unwrap();
+        let hub = create("Hub", "hub body");
+        let leaf = create("Leaf", "leaf body");
+        vault.link_notes(&hub.id, &leaf.id).unwrap();
+        vault.add_tag(&hub.id, "Rust".into()).unwrap();
+        vault.add_tag(&leaf.id, "rust".into()).unwrap();
+        let ping = create("Ping", "ping body");
+        let pong = create("Pong", "pong body");
+        vault.link_notes(&ping.id, &pong.id).unwrap();
+        vault.link_notes(&pong.id, &ping.id).unwrap();
+        create("Plan", "same text");
+        create("plan", "same text");
+        vault.create_note("Empty".into(), "Empty".into()).unwrap();
+        let mut broken = Note::new("Broken".into(), "Broken\n\nbroken body".into());
+        broken.links.push("missing-note".into());
+        vault.write_note(&broken).unwrap();
+
+        let health = vault.vault_health(&vault.list_notes().unwrap()).unwrap();
+        let titles = |notes: Vec<&Note>| {
+            let mut titles: Vec<String> = notes.into_iter().map(|n| n.title.clone()).collect();
+            titles.sort();
+            titles
+        };
+        assert_eq!(titles(health.orphans.iter().collect()), vec!["Broken", "Empty", "Plan", "plan"]);
+        assert_eq!(health.broken_links.iter().map(|(n, ids)| (n.title.as_str(), ids.clone())).collect::<Vec<_>>(),
+            vec![("Broken", vec!["missing-note".to_string()])]);
+        assert_eq!(health.duplicate_titles.len(), 1);
+        assert_eq!(titles(health.duplicate_titles[0].iter().collect()), vec!["Plan", "plan"]);
+        assert_eq!(titles(health.stubs.iter().map(|s| &s.note).collect()), vec!["Empty", "Plan", "plan"]);
+        assert_eq!(health.stubs.iter().find(|s| s.note.title == "Empty").unwrap().reason, StubReason::Empty);
+        assert_eq!(health.cycles.len(), 1);
+        let mut cycle = health.cycles[0].clone();
+        cycle.sort();
+        let mut expected = vec![ping.id, pong.id];
+        expected.sort();
+        assert_eq!(cycle, expected);
+        assert_eq!(health.tag_variants.len(), 1);
+        assert_eq!(health.tag_variants[0].spellings.len(), 2);
+        assert_eq!(titles(health.missing_history.iter().collect()), vec!["Broken"], "written without a commit");
+    }
+
+    #[test]
+    fn capture_refuses_a_locked_inbox() {
+        let vault = TestVault::plain("locked-inbox");
+        let inbox = vault.append_to_inbox("first").unwrap();
+        vault.set_locked(&inbox.id, true).unwrap();
+
+        let err = vault.append_to_inbox("second").expect_err("captured into a locked inbox");
+        assert_eq!(err.to_string(), "'Inbox' is locked; unlock it first");
+        let inbox = vault.get_note(&inbox.id).unwrap().unwrap();
+        assert!(inbox.content.ends_with("first") && !inbox.content.contains("second"), "{:?}", inbox.content);
+        assert_eq!(vault.list_notes().unwrap().len(), 1, "no second inbox created");
+    }
+
+    /// Backup of `vault` as it is now, written into its directory
+    fn backup_of(vault: &TestVault) -> PathBuf {
+        let archive = vault.dir.join("backup.tar.gz");
+        vault.backup_to_archive(&archive, &mut |_, _| {}, &AtomicBool::new(false)).unwrap();
+        archive
+    }
+
+    #[test]
+    fn restore_leaves_locked_notes_alone_and_counts_them() {
+        let Some(vault) = TestVault::with_jj("restore-locked") else { return };
+        let open = vault.create_note("Open".into(), "Open\n\nbacked up".into()).unwrap();
+        let fixed = vault.create_note("Fixed".into(), "Fixed\n\nbacked up".into()).unwrap();
+        let archive = backup_of(&vault);
+        vault.update_note(open.clone(), "Open\n\nchanged".into()).unwrap();
+        let fixed = vault.update_note(fixed, "Fixed\n\nchanged".into()).unwrap();
+        vault.set_locked(&fixed.id, true).unwrap();
+
+        let restored = vault.restore_from_archive(&archive, &mut |_, _| {}, &AtomicBool::new(false)).unwrap();
+
+        assert_eq!(restored, Restored { notes: 1, locked: 1 });
+        assert_eq!(vault.get_note(&open.id).unwrap().unwrap().content, "Open\n\nbacked up");
+        let kept = vault.get_note(&fixed.id).unwrap().unwrap();
+        assert_eq!((kept.content.as_str(), kept.locked), ("Fixed\n\nchanged", true));
+    }
+
+    #[test]
+    fn restore_keeps_a_locked_note_filed_under_another_name() {
+        let Some(vault) = TestVault::with_jj("restore-locked-renamed") else { return };
+        let note = vault.create_note("Draft".into(), "Draft".into()).unwrap();
+        let archive = backup_of(&vault);
+        let vault = vault.configured(|service| service.with_file_naming(FileNaming::Slug));
+        let note = vault.rename_note(&note.id, "Final".into()).unwrap();
+        vault.set_locked(&note.id, true).unwrap();
+        let file = vault.find_note_file(&note.id).unwrap().unwrap();
+
+        let restored = vault.restore_from_archive(&archive, &mut |_, _| {}, &AtomicBool::new(false)).unwrap();
+
+        assert_eq!(restored, Restored { notes: 0, locked: 1 });
+        assert!(file.exists(), "the locked note's file is not removed");
+        assert_eq!(vault.list_notes().unwrap().iter().map(|n| n.title.as_str()).collect::<Vec<_>>(), vec!["Final"]);
+    }
+
+    #[test]
+    fn color_label_leaves_the_update_time_alone() {
+        let vault = TestVault::plain("color");
+        vault.write_note(&dated_note("Old", 1, None)).unwrap();
+        let id = vault.list_notes().unwrap()[0].id.clone();
+
+        let colored = vault.set_color(&id, Some("red".into())).unwrap();
+
+        assert_eq!(colored.color.as_deref(), Some("red"));
+        let stored = vault.get_note(&id).unwrap().unwrap();
+        assert_eq!((stored.color.as_deref(), stored.updated_at.as_str()), (Some("red"), "2025-03-01T12:00:00Z"));
+    }
+
+    #[test]
+    fn index_in_a_vault_that_is_its_own_notes_directory_is_not_a_note() {
+        let vault = TestVault::plain("index-in-root");
+        let dir = vault.dir.clone();
+        // As with JJZETTEL_NOTES_DIR=.
+        let vault = vault.configured(|_| NoteService::new(dir.to_string_lossy(), dir.join(".")).with_auto_commit(false).with_index_file(true));
+        let first = vault.create_note("First".into(), "First".into()).unwrap();
+        vault.create_note("Second".into(), "Second".into()).unwrap();
+        assert!(vault.dir.join("index.json").exists());
+
+        let mut titles: Vec<String> = vault.list_notes().unwrap().into_iter().map(|n| n.title).collect();
+        titles.sort();
+        assert_eq!(titles, vec!["First", "Second"]);
+        assert_eq!(vault.list_note_summaries().unwrap().len(), 2);
+        assert_eq!(vault.resolve_id(&first.id[..MIN_ID_PREFIX]).unwrap(), Some(first.id));
+        let index = NoteIndex::load(&vault.dir.join("index.json")).unwrap();
+        assert_eq!(index.notes.iter().map(|e| e.title.as_str()).collect::<Vec<_>>(), vec!["First", "Second"]);
+
+        let archive = vault.dir.join("backup.tar.gz");
+        assert_eq!(vault.backup_to_archive(&archive, &mut |_, _| {}, &AtomicBool::new(false)).unwrap(), 2);
+    }
+
+    #[test]
+    fn title_only_and_blank_content_has_no_body() {
+        assert!(!has_body("Title"));
+        assert!(!has_body("Title\n"));
+        assert!(!has_body("Title\n\n   \n\t"));
+        assert!(!has_body(""));
+        assert!(!has_body("   "));
+    }
+
+    #[test]
+    fn any_text_after_the_title_line_is_a_body() {
+        assert!(has_body("Title\n\nBody"));
+        assert!(has_body("Title\n- [ ] one task"));
+        assert!(has_body("# Title\n\n  indented"));
+        assert!(has_body("Title\n\n\n.\n"));
+    }
+
+    #[test]
+    fn slug_replaces_separators_and_reserved_characters() {
+        assert_eq!(slugify_title("a/b"), "a_b");
+        assert_eq!(slugify_title(r"C:\notes\x"), "C__notes_x");
+        assert_eq!(slugify_title("  Project plan: v2?  "), "Project_plan__v2");
+        assert_eq!(slugify_title("tab\there"), "tab_here");
+        assert_eq!(slugify_title("Café ☕"), "Café_☕");
+    }
+
+    #[test]
+    fn slug_never_makes_a_hidden_or_parent_path() {
+        assert_eq!(slugify_title(".."), "untitled");
+        assert_eq!(slugify_title("../../etc/passwd"), "etc_passwd");
+        assert_eq!(slugify_title(".hidden"), "hidden");
+        assert_eq!(slugify_title("trailing..."), "trailing");
+    }
+
+    #[test]
+    fn slug_of_nothing_usable_is_untitled() {
+        assert_eq!(slugify_title(""), "untitled");
+        assert_eq!(slugify_title("   "), "untitled");
+        assert_eq!(slugify_title("/?*:<>|"), "untitled");
+        assert_eq!(slugify_title("._."), "untitled");
+    }
+
+    #[test]
+    fn slug_prefixes_windows_device_names() {
+        assert_eq!(slugify_title("CON.txt"), "_CON.txt");
+        assert_eq!(slugify_title("con"), "_con");
+        assert_eq!(slugify_title("COM1"), "_COM1");
+        assert_eq!(slugify_title("lpt9.md"), "_lpt9.md");
+        assert_eq!(slugify_title("COM10"), "COM10");
+        assert_eq!(slugify_title("Console"), "Console");
+    }
+
+    #[test]
+    fn slug_is_cut_at_100_characters() {
+        assert_eq!(slugify_title(&"x".repeat(150)), "x".repeat(100));
+        let slug = slugify_title(&"é".repeat(150));
+        assert_eq!(slug.chars().count(), 100, "counted in characters, not bytes");
+        assert_eq!(slugify_title(&format!("{}.tail", "y".repeat(99))), "y".repeat(99), "no dot left at the cut");
+    }
+}
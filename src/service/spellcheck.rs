@@ -0,0 +1,150 @@
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Word lists tried when the config does not name a dictionary
+const SYSTEM_DICTIONARIES: [&str; 2] = ["/usr/share/dict/words", "/usr/dict/words"];
+
+/// Set of known words, one per line in a plain word-list file
+pub struct Dictionary {
+    words: HashSet<String>,
+}
+
+impl Dictionary {
+    /// Load a word list (one word per line, case-insensitive)
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read dictionary {}", path.display()))?;
+        let words = content
+            .lines()
+            .map(|word| word.trim().to_lowercase())
+            .filter(|word| !word.is_empty())
+            .collect();
+        Ok(Dictionary { words })
+    }
+
+    /// Load the configured dictionary, or the first system word list that exists
+    pub fn load_default(configured: Option<&str>) -> Result<Self> {
+        let path = match configured {
+            Some(path) => PathBuf::from(path),
+            None => SYSTEM_DICTIONARIES
+                .iter()
+                .map(PathBuf::from)
+                .find(|path| path.exists())
+                .ok_or_else(|| anyhow::anyhow!("No dictionary found; set \"dictionary\" in the config file"))?,
+        };
+        Self::load(&path)
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        let word = word.to_lowercase();
+        self.words.contains(&word)
+            || word.strip_suffix("'s").is_some_and(|stem| self.words.contains(stem))
+    }
+}
+
+/// Split a line into `(text, is_word)` segments. Words are runs of letters and apostrophes;
+/// tags (`#tag`), inline code, `[[wikilinks]]`, URLs and tokens containing digits are returned
+/// as non-words.
+pub fn word_segments(line: &str) -> Vec<(&str, bool)> {
+    let mut segments = Vec::new();
+    let mut rest = line;
+    while !rest.is_empty() {
+        // Skip whole tokens that should never be checked
+        let skip = if let Some(code) = rest.strip_prefix('`') {
+            Some(code.find('`').map_or(rest.len(), |end| end + 2))
+        } else if let Some(link) = rest.strip_prefix("[[") {
+            // A linked note's title is checked where it is written, not in every link to it
+            Some(link.find("]]").map_or(rest.len(), |end| end + 4))
+        } else if rest.starts_with('#') || rest.starts_with("http://") || rest.starts_with("https://") {
+            Some(rest.find(char::is_whitespace).unwrap_or(rest.len()))
+        } else {
+            None
+        };
+        if let Some(len) = skip {
+            segments.push((&rest[..len], false));
+            rest = &rest[len..];
+            continue;
+        }
+
+        let first = rest.chars().next().unwrap_or(' ');
+        if first.is_alphanumeric() {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '\''))
+                .unwrap_or(rest.len());
+            let token = &rest[..len];
+            let is_word = !token.chars().any(|c| c.is_ascii_digit());
+            segments.push((token, is_word));
+            rest = &rest[len..];
+        } else {
+            let len = rest
+                .find(|c: char| c.is_alphanumeric() || c == '`' || c == '#' || c == '[')
+                .filter(|&len| len > 0)
+                .unwrap_or(first.len_utf8());
+            segments.push((&rest[..len], false));
+            rest = &rest[len..];
+        }
+    }
+    segments
+}
+
+/// Unrecognized words as `(line index, word)`, skipping fenced code blocks
+pub fn spellcheck(content: &str, dictionary: &Dictionary) -> Vec<(usize, String)> {
+    let mut misspelled = Vec::new();
+    let mut in_code_block = false;
+    for (line_index, line) in content.lines().enumerate() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+        for (segment, is_word) in word_segments(line) {
+            let word = segment.trim_matches('\'');
+            if is_word && word.chars().count() > 1 && !dictionary.contains(word) {
+                misspelled.push((line_index, word.to_string()));
+            }
+        }
+    }
+    misspelled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary(words: &[&str]) -> Dictionary {
+        Dictionary { words: words.iter().map(|w| w.to_string()).collect() }
+    }
+
+    #[test]
+    fn unknown_words_are_reported_with_their_line() {
+        let dict = dictionary(&["the", "cat", "sat", "on", "mat"]);
+        let found = spellcheck("The cat sat\n\non the matt, the dgo!", &dict);
+        assert_eq!(found, [(2, "matt".to_string()), (2, "dgo".to_string())]);
+    }
+
+    #[test]
+    fn case_possessives_and_punctuation_do_not_count() {
+        let dict = dictionary(&["the", "cat", "is", "it", "here"]);
+        assert!(spellcheck("THE Cat's here. Is it? 'cat' (the) cat,", &dict).is_empty());
+        assert!(spellcheck("a I", &dict).is_empty(), "single letters are never checked");
+    }
+
+    #[test]
+    fn code_tags_links_urls_and_numbers_are_skipped() {
+        let dict = dictionary(&["see", "and"]);
+        let content = "see `qwzx` and #qwzx and [[Qwzx Note]] and https://qwzx.example and v2qwzx\n\
+                       ```\nqwzx qwzx\n```\nsee qwzx";
+        assert_eq!(spellcheck(content, &dict), [(4, "qwzx".to_string())]);
+    }
+
+    #[test]
+    fn unclosed_link_or_code_runs_to_the_end_of_the_line() {
+        let dict = dictionary(&["see"]);
+        assert!(spellcheck("see [[Qwzx and `zzq", &dict).is_empty());
+        assert!(spellcheck("see `zzq and [[Qwzx", &dict).is_empty());
+        assert_eq!(spellcheck("see [qwzx]", &dict), [(0, "qwzx".to_string())], "single brackets are plain text");
+    }
+}
@@ -1,6 +1,7 @@
 use anyhow::{Context, Result};
 use std::process::Command;
 
+#[derive(Clone)]
 pub struct Jujutsu {
     repo_path: String,
 }
@@ -12,10 +13,6 @@ impl Jujutsu {
         }
     }
 
-    pub fn repo_path(&self) -> &str {
-        &self.repo_path
-    }
-
     /// Initialize a new Jujutsu repository
     pub fn init(&self) -> Result<()> {
         let repo_path_buf = std::path::Path::new(&self.repo_path);
@@ -62,12 +59,17 @@ impl Jujutsu {
         } else {
             std::env::current_dir()
                 .ok()
-                .and_then(|cwd| Some(cwd.join(repo_path_buf)))
+                .map(|cwd| cwd.join(repo_path_buf))
                 .unwrap_or(repo_path_buf.to_path_buf())
         };
         repo_path_abs.join(".jj").exists()
     }
 
+    /// Marker embedded in commit messages so history can follow a note across renames
+    pub fn note_marker(note_id: &str) -> String {
+        format!("[#{}]", note_id)
+    }
+
     /// Create a new commit for a file
     pub fn create_commit_for_file(&self, message: &str, file_path: &str) -> Result<String> {
         // Ensure the file exists (should already be written by caller)
@@ -75,17 +77,33 @@ impl Jujutsu {
             anyhow::bail!("File does not exist: {}", file_path);
         }
 
-        // Ensure repo path is absolute
-        let repo_path_buf = std::path::Path::new(&self.repo_path);
-        let repo_path_abs = if repo_path_buf.is_absolute() {
-            repo_path_buf.canonicalize()
-                .context("Failed to canonicalize repo path")?
-        } else {
-            std::env::current_dir()?
-                .join(repo_path_buf)
-                .canonicalize()
-                .context("Failed to canonicalize repo path")?
-        };
+        self.commit_working_copy(message)
+    }
+
+    /// Commit all working-copy changes with the given message and return the commit ID
+    pub fn commit_working_copy(&self, message: &str) -> Result<String> {
+        self.commit_paths(message, &[])
+    }
+
+    /// Commit only the changes to `files`, leaving any other changes in the working copy
+    pub fn commit_files(&self, message: &str, files: &[std::path::PathBuf]) -> Result<String> {
+        let repo_path_abs = self.absolute_repo_path()?;
+        let mut filesets = Vec::new();
+        for file in files {
+            let file_abs = std::path::absolute(file)?;
+            let file_abs = file_abs.canonicalize().unwrap_or(file_abs);
+            let relative_path = file_abs
+                .strip_prefix(&repo_path_abs)
+                .with_context(|| format!("{} is outside the repo", file.display()))?;
+            // A quoted `file:` pattern, so names with fileset operators in them stay literal
+            filesets.push(format!("file:{}", serde_json::to_string(&relative_path.to_string_lossy())?));
+        }
+        self.commit_paths(message, &filesets)
+    }
+
+    /// `jj commit` of the given filesets (all changes if empty); returns the new commit's id
+    fn commit_paths(&self, message: &str, filesets: &[String]) -> Result<String> {
+        let repo_path_abs = self.absolute_repo_path()?;
         
         // Ensure repo is initialized
         if !repo_path_abs.join(".jj").exists() {
@@ -103,13 +121,13 @@ impl Jujutsu {
             }
         }
 
-        // Create a new commit with the file
-        // For new files, we create a commit from the working copy
-        // Jujutsu will automatically include all changes in the working copy
+        // `jj commit` describes the working-copy change (which holds the file edits)
+        // and starts a fresh empty change on top, so the message stays with the edits
         let output = Command::new("jj")
-            .arg("new")
+            .arg("commit")
             .arg("-m")
             .arg(message)
+            .args(filesets)
             .current_dir(&repo_path_abs)
             .output()
             .context("Failed to create commit")?;
@@ -122,10 +140,10 @@ impl Jujutsu {
         let output = Command::new("jj")
             .arg("log")
             .arg("-r")
-            .arg("@")
+            .arg("@-")
             .arg("--no-graph")
             .arg("--template")
-            .arg("{commit_id}")
+            .arg("commit_id")
             .current_dir(&repo_path_abs)
             .output()
             .context("Failed to get commit hash")?;
@@ -138,57 +156,65 @@ impl Jujutsu {
         Ok(commit_id)
     }
 
-    /// Get commit history for a specific file with optional title filtering
-    pub fn get_file_history_with_title(&self, file_path: &str, note_title: &str) -> Result<Vec<CommitInfo>> {
-        // Ensure repo path is absolute
-        let repo_path_buf = std::path::Path::new(&self.repo_path);
-        let repo_path_abs = if repo_path_buf.is_absolute() {
-            repo_path_buf.canonicalize()
-                .context("Failed to canonicalize repo path")?
-        } else {
-            std::env::current_dir()?
-                .join(repo_path_buf)
-                .canonicalize()
-                .context("Failed to canonicalize repo path")?
-        };
+    /// Get commit history for a note, newest first.
+    /// Commits are matched by the note's `[#id]` marker, so the history survives title changes.
+    /// Older commits without a marker are matched by the note's files or by title. `files`
+    /// are the names the note's file has or had, and may use `*` for the part of a name
+    /// that changes, such as the slug of a renamed note.
+    pub fn get_note_history(&self, files: &[std::path::PathBuf], note_id: &str, note_title: &str) -> Result<Vec<CommitInfo>> {
+        let repo_path_abs = self.absolute_repo_path()?;
         
         // Check if repo exists
         if !repo_path_abs.join(".jj").exists() {
             return Ok(Vec::new());
         }
         
-        let file_path_buf = std::path::Path::new(file_path);
-        let file_path_abs = if file_path_buf.is_absolute() {
-            file_path_buf.canonicalize()
-                .context("Failed to canonicalize file path")?
-        } else {
-            std::env::current_dir()?
-                .join(file_path_buf)
-                .canonicalize()
-                .context("Failed to canonicalize file path")?
-        };
-        
-        let relative_path = file_path_abs.strip_prefix(&repo_path_abs)
-            .context("File is not in repo")?
-            .to_string_lossy()
-            .to_string();
-
-        // Try to get history for the specific file first
-        let file_output = Command::new("jj")
-            .arg("log")
-            .arg("--no-graph")
-            .arg("-T")
-            .arg(r#"commit_id.short() ++ " | " ++ if(description == "", "(empty)", description) ++ " | " ++ author.name()"#)
-            .arg(&relative_path)
-            .current_dir(&repo_path_abs)
-            .output();
+        // Commits that touched the note's files (which may be deleted or renamed by now), so
+        // only their directory is resolved
+        let mut filesets = Vec::new();
+        for file in files {
+            let file_abs = std::path::absolute(file)?;
+            let (Some(dir), Some(name)) = (file_abs.parent(), file_abs.file_name()) else {
+                continue;
+            };
+            let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+            if let Ok(relative_dir) = dir.strip_prefix(&repo_path_abs) {
+                // Only names with `*` are patterns; others stay literal whatever they contain
+                let path = relative_dir.join(name).to_string_lossy().into_owned();
+                let kind = if name.to_string_lossy().contains('*') { "glob" } else { "file" };
+                filesets.push(format!("{}:{}", kind, serde_json::to_string(&path)?));
+            }
+        }
+        let mut file_commit_ids = Vec::new();
+        if !filesets.is_empty() {
+            let file_output = Command::new("jj")
+                .arg("log")
+                .arg("-r")
+                .arg("::@")
+                .arg("--no-graph")
+                .arg("-T")
+                .arg(r#"commit_id.short() ++ "\n""#)
+                .args(&filesets)
+                .current_dir(&repo_path_abs)
+                .output();
+            if let Ok(output) = file_output
+                && output.status.success()
+            {
+                file_commit_ids = String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .map(|line| line.trim().to_string())
+                    .filter(|line| !line.is_empty())
+                    .collect();
+            }
+        }
         
-        // Get all commits as fallback
         let all_output = Command::new("jj")
             .arg("log")
+            .arg("-r")
+            .arg("::@")
             .arg("--no-graph")
             .arg("-T")
-            .arg(r#"commit_id.short() ++ " | " ++ if(description == "", "(empty)", description) ++ " | " ++ author.name()"#)
+            .arg(LOG_TEMPLATE)
             .current_dir(&repo_path_abs)
             .output()
             .context("Failed to get commit history")?;
@@ -196,144 +222,325 @@ impl Jujutsu {
         let all_output_str = String::from_utf8(all_output.stdout)
             .context("Failed to parse commit history")?;
 
-        let mut commits = Vec::new();
+        let marker = Self::note_marker(note_id);
+        let title_lower = note_title.to_lowercase();
         
-        // If file-specific lookup worked, use that (but still filter by note title)
-        if let Ok(output) = file_output {
-            if output.status.success() {
-                if let Ok(output_str) = String::from_utf8(output.stdout) {
-                    // Normalize the output - handle lines that start with " | " or "| "
-                    // Jujutsu sometimes wraps output with continuation lines starting with " | "
-                    let normalized = output_str
-                        .lines()
-                        .map(|line| {
-                            let trimmed = line.trim();
-                            if trimmed.starts_with("| ") {
-                                // This is a continuation line, remove the leading "| "
-                                trimmed.strip_prefix("| ").unwrap_or(trimmed).to_string()
-                            } else if trimmed.starts_with(" | ") {
-                                // Handle lines starting with " | " (space-pipe-space)
-                                trimmed.strip_prefix(" | ").unwrap_or(trimmed).to_string()
-                            } else {
-                                trimmed.to_string()
-                            }
-                        })
-                        .filter(|line| !line.is_empty())
-                        .collect::<Vec<String>>()
-                        .join("\n");
-                    
-                    for line in normalized.lines() {
-                        if line.is_empty() || line.trim().is_empty() {
-                            continue;
-                        }
-                        let parts: Vec<&str> = line.split(" | ").collect();
-                        if parts.len() >= 2 {
-                            let id = parts[0].trim();
-                            let message = parts[1].trim();
-                            let author = if parts.len() >= 3 {
-                                parts[2].trim()
-                            } else {
-                                ""
-                            };
-                            
-                            // Filter by note title if provided (case-insensitive)
-                            let should_include = if message == "(empty)" {
-                                false
-                            } else if !note_title.is_empty() {
-                                let message_lower = message.to_lowercase();
-                                let title_lower = note_title.to_lowercase();
-                                message_lower.contains(&title_lower)
-                            } else {
-                                true
-                            };
-                            
-                            if should_include && !id.is_empty() {
-                                commits.push(CommitInfo {
-                                    id: id.to_string(),
-                                    message: message.to_string(),
-                                    author: author.to_string(),
-                                    timestamp: if parts.len() >= 4 {
-                                        parts[3].trim().to_string()
-                                    } else {
-                                        String::new()
-                                    },
-                                });
-                            }
-                        }
-                    }
-                    if !commits.is_empty() {
-                        return Ok(commits);
-                    }
-                }
-            }
-        }
-        
-        // Otherwise, filter all commits by checking if they mention this file
-        // or if the commit message matches note patterns
-        // Since commits are created from working copy, we need to check all commits
-        // and filter by commit message patterns
-        for line in all_output_str.lines() {
-            if line.is_empty() || line.trim().is_empty() {
-                continue;
-            }
-            let parts: Vec<&str> = line.split(" | ").collect();
-            if parts.len() >= 2 {
-                let id = parts[0].trim();
-                let message = parts[1].trim();
-                let author = if parts.len() >= 3 {
-                    parts[2].trim()
-                } else {
-                    ""
-                };
-                
-                // Include commits that match this specific note
-                // Check if the commit message contains the note title
-                // Commit messages are like "Note: {title}", "Update: {title}", etc.
-                // Include commits that match this specific note title
-                // Exclude "(empty)" commits
-                let should_include = if message == "(empty)" {
+        // jj log lists commits newest first, which is the order we want to show
+        let commits = parse_commit_log(&all_output_str)
+            .into_iter()
+            .filter(|commit| {
+                if commit.message == "(empty)" {
+                    false
+                } else if commit.message.contains(&marker) {
+                    true
+                } else if commit.message.contains("[#") {
+                    // Marked commit for a different note
                     false
-                } else if !note_title.is_empty() {
-                    // If we have a note title, match commits that contain it (case-insensitive)
-                    let message_lower = message.to_lowercase();
-                    let title_lower = note_title.to_lowercase();
-                    message_lower.contains(&title_lower)
                 } else {
-                    // Fallback: include commits with note-related prefixes
-                    message.contains("Note:")
-                        || message.contains("Update:")
-                        || message.contains("Duplicate:")
-                };
-                
-                if should_include && !id.is_empty() {
-                    commits.push(CommitInfo {
-                        id: id.to_string(),
-                        message: message.to_string(),
-                        author: author.to_string(),
-                        timestamp: if parts.len() >= 4 {
-                            parts[3].trim().to_string()
-                        } else {
-                            String::new()
-                        },
-                    });
+                    // Legacy commit from before markers: match by file, id or title
+                    file_commit_ids.contains(&commit.id)
+                        || commit.message.contains(note_id)
+                        || (!title_lower.is_empty() && commit.message.to_lowercase().contains(&title_lower))
                 }
-            }
+            })
+            .collect();
+
+        Ok(commits)
+    }
+
+    /// Most recent commits across the whole vault, newest first, leaving out
+    /// undescribed ones such as the empty working-copy change
+    pub fn get_repo_log(&self, limit: usize) -> Result<Vec<CommitInfo>> {
+        let repo_path_abs = self.absolute_repo_path()?;
+        if !repo_path_abs.join(".jj").exists() {
+            return Ok(Vec::new());
         }
-        
-        // Jujutsu returns commits in chronological order (oldest first)
-        // Reverse to show newest first
-        commits.reverse();
 
+        let output = Command::new("jj")
+            .arg("log")
+            .arg("-r")
+            .arg("::@")
+            .arg("--no-graph")
+            .arg("--limit")
+            .arg(limit.to_string())
+            .arg("-T")
+            .arg(LOG_TEMPLATE)
+            .current_dir(&repo_path_abs)
+            .output()
+            .context("Failed to get repo log")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to get repo log: {}", stderr);
+        }
 
-        Ok(commits)
+        Ok(parse_commit_log(&String::from_utf8_lossy(&output.stdout))
+            .into_iter()
+            .filter(|commit| commit.message != "(empty)")
+            .collect())
+    }
+
+    /// Changes a commit made, as a git-style diff
+    pub fn commit_diff(&self, commit_id: &str) -> Result<String> {
+        let repo_path_abs = self.absolute_repo_path()?;
+        let output = Command::new("jj")
+            .arg("diff")
+            .arg("-r")
+            .arg(commit_id)
+            .arg("--git")
+            .arg("--color")
+            .arg("never")
+            .current_dir(&repo_path_abs)
+            .output()
+            .context("Failed to run jj diff")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to get diff for {}: {}", commit_id, stderr);
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Commit id of the working-copy change (`@`), snapshotting any pending file edits first
+    pub fn working_copy_commit_id(&self) -> Result<String> {
+        let repo_path_abs = self.absolute_repo_path()?;
+        let output = Command::new("jj")
+            .arg("log")
+            .arg("-r")
+            .arg("@")
+            .arg("--no-graph")
+            .arg("--template")
+            .arg("commit_id")
+            .current_dir(&repo_path_abs)
+            .output()
+            .context("Failed to get working-copy commit")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to get working-copy commit: {}", stderr);
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Make the working copy's files match the given commit (which may since have been rewritten)
+    pub fn restore_from_commit(&self, commit_id: &str) -> Result<()> {
+        let repo_path_abs = self.absolute_repo_path()?;
+        let output = Command::new("jj")
+            .arg("restore")
+            .arg("--from")
+            .arg(commit_id)
+            .current_dir(&repo_path_abs)
+            .output()
+            .context("Failed to restore files")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to restore from {}: {}", commit_id, stderr);
+        }
+
+        Ok(())
+    }
+
+    /// Contents of a file as of a commit, e.g. an earlier version of a note
+    pub fn file_at_commit(&self, commit_id: &str, file_path: &str) -> Result<String> {
+        let repo_path_abs = self.absolute_repo_path()?;
+        let file_path_abs = std::path::absolute(file_path)?;
+        let file_path_abs = file_path_abs.canonicalize().unwrap_or(file_path_abs);
+        let relative_path = file_path_abs
+            .strip_prefix(&repo_path_abs)
+            .with_context(|| format!("{} is outside the repo", file_path))?;
+
+        let output = Command::new("jj")
+            .arg("file")
+            .arg("show")
+            .arg("-r")
+            .arg(commit_id)
+            .arg(relative_path)
+            .current_dir(&repo_path_abs)
+            .output()
+            .context("Failed to run jj file show")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to read {} at {}: {}", file_path, commit_id, stderr);
+        }
+
+        String::from_utf8(output.stdout).context("File is not valid UTF-8")
+    }
+
+    /// Files under `dir` as of a commit, e.g. to find a note file that has since been renamed
+    pub fn files_at_commit(&self, commit_id: &str, dir: &str) -> Result<Vec<std::path::PathBuf>> {
+        let repo_path_abs = self.absolute_repo_path()?;
+        let dir_abs = std::path::absolute(dir)?;
+        let dir_abs = dir_abs.canonicalize().unwrap_or(dir_abs);
+        let relative_dir = dir_abs
+            .strip_prefix(&repo_path_abs)
+            .with_context(|| format!("{} is outside the repo", dir))?;
+
+        let output = Command::new("jj")
+            .arg("file")
+            .arg("list")
+            .arg("-r")
+            .arg(commit_id)
+            .arg(relative_dir)
+            .current_dir(&repo_path_abs)
+            .output()
+            .context("Failed to run jj file list")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to list files at {}: {}", commit_id, stderr);
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| repo_path_abs.join(line.trim()))
+            .collect())
+    }
+
+    /// Whether the working-copy change holds any file changes, i.e. there is something to commit
+    pub fn has_changes(&self) -> Result<bool> {
+        let repo_path_abs = self.absolute_repo_path()?;
+        let output = Command::new("jj")
+            .arg("diff")
+            .arg("--summary")
+            .arg("--color")
+            .arg("never")
+            .current_dir(&repo_path_abs)
+            .output()
+            .context("Failed to run jj diff")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to check for changes: {}", stderr);
+        }
+
+        Ok(!String::from_utf8_lossy(&output.stdout).trim().is_empty())
+    }
+
+    /// Files changed in the working copy since the last commit, as absolute paths
+    pub fn changed_files(&self) -> Result<Vec<std::path::PathBuf>> {
+        let repo_path_abs = self.absolute_repo_path()?;
+        let output = Command::new("jj")
+            .arg("diff")
+            .arg("--name-only")
+            .arg("--color")
+            .arg("never")
+            .current_dir(&repo_path_abs)
+            .output()
+            .context("Failed to run jj diff")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to list changed files: {}", stderr);
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| repo_path_abs.join(line.trim()))
+            .collect())
+    }
+
+    /// One-line working-copy state from `jj status`, e.g. "✓ clean" or "● uncommitted (2 files)"
+    pub fn status_summary(&self) -> Result<String> {
+        let Ok(repo_path_abs) = self.absolute_repo_path() else {
+            return Ok(NO_REPO_STATUS.to_string());
+        };
+        if !repo_path_abs.join(".jj").exists() {
+            return Ok(NO_REPO_STATUS.to_string());
+        }
+
+        let output = Command::new("jj")
+            .arg("status")
+            .arg("--color")
+            .arg("never")
+            .current_dir(&repo_path_abs)
+            .output()
+            .context("Failed to run jj status")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to get repo status: {}", stderr);
+        }
+
+        Ok(summarize_status(&String::from_utf8_lossy(&output.stdout)))
     }
 
-    #[allow(dead_code)]
-    pub fn get_file_history(&self, file_path: &str) -> Result<Vec<CommitInfo>> {
-        self.get_file_history_with_title(file_path, "")
+    /// Absolute, canonical path of the repository
+    fn absolute_repo_path(&self) -> Result<std::path::PathBuf> {
+        let repo_path_buf = std::path::Path::new(&self.repo_path);
+        if repo_path_buf.is_absolute() {
+            repo_path_buf.canonicalize()
+                .context("Failed to canonicalize repo path")
+        } else {
+            std::env::current_dir()?
+                .join(repo_path_buf)
+                .canonicalize()
+                .context("Failed to canonicalize repo path")
+        }
     }
 }
 
+/// Status shown when the vault is not (yet) a Jujutsu repo
+const NO_REPO_STATUS: &str = "○ no repo";
+
+/// Condense `jj status` output: conflicts win over changes, no changes means clean
+fn summarize_status(output: &str) -> String {
+    if output.lines().any(|line| line.starts_with("There are unresolved conflicts")) {
+        return "✗ conflicts".to_string();
+    }
+    let changed = output
+        .lines()
+        .skip_while(|line| !line.starts_with("Working copy changes:"))
+        .skip(1)
+        .take_while(|line| {
+            let mut chars = line.chars();
+            matches!(chars.next(), Some('M' | 'A' | 'D' | 'R' | 'C')) && chars.next() == Some(' ')
+        })
+        .count();
+    match changed {
+        0 => "✓ clean".to_string(),
+        1 => "● uncommitted (1 file)".to_string(),
+        n => format!("● uncommitted ({} files)", n),
+    }
+}
+
+/// Separates the fields of one commit in `LOG_TEMPLATE` output (ASCII unit separator)
+const FIELD_SEPARATOR: char = '\x1f';
+
+/// Ends each commit in `LOG_TEMPLATE` output (ASCII record separator)
+const RECORD_SEPARATOR: char = '\x1e';
+
+/// One record per commit: short id, first line of description, author, timestamp. The
+/// separators are control characters, so no description or author name can contain them
+/// in practice, and the timestamp format is fixed rather than locale-dependent.
+const LOG_TEMPLATE: &str = r#"commit_id.short() ++ "\x1f" ++ if(description == "", "(empty)", description.first_line()) ++ "\x1f" ++ author.name() ++ "\x1f" ++ author.timestamp().format("%Y-%m-%d %H:%M") ++ "\x1e""#;
+
+/// Parse `LOG_TEMPLATE` output into commits. Should a description contain a separator
+/// after all, author and timestamp are taken from the end so the message stays whole.
+fn parse_commit_log(output: &str) -> Vec<CommitInfo> {
+    output
+        .split(RECORD_SEPARATOR)
+        .filter_map(|record| {
+            let parts: Vec<&str> = record.trim_matches(['\n', '\r']).split(FIELD_SEPARATOR).collect();
+            if parts.len() < 4 || parts[0].trim().is_empty() {
+                return None;
+            }
+            let last = parts.len() - 1;
+            Some(CommitInfo {
+                id: parts[0].trim().to_string(),
+                message: parts[1..last - 1].join(" ").trim().to_string(),
+                author: parts[last - 1].trim().to_string(),
+                timestamp: parts[last].trim().to_string(),
+            })
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub struct CommitInfo {
     pub id: String,
@@ -342,3 +549,73 @@ pub struct CommitInfo {
     pub timestamp: String,
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(commits: &[CommitInfo]) -> Vec<(&str, &str, &str, &str)> {
+        commits
+            .iter()
+            .map(|c| (c.id.as_str(), c.message.as_str(), c.author.as_str(), c.timestamp.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn status_without_changes_is_clean() {
+        let output = "The working copy has no changes.\n\
+                      Working copy  (@) : pxtovvwu 52f627b3 (empty) (no description set)\n\
+                      Parent commit (@-): zzzzzzzz 00000000 (empty) (no description set)\n";
+        assert_eq!(summarize_status(output), "✓ clean");
+        assert_eq!(summarize_status(""), "✓ clean");
+    }
+
+    #[test]
+    fn status_counts_the_changed_files() {
+        let one = "Working copy changes:\nM notes/a.json\nWorking copy  (@) : vyynzsls ba04ef5e (no description set)\n";
+        assert_eq!(summarize_status(one), "● uncommitted (1 file)");
+        let three = "Working copy changes:\nD notes/a.json\nA notes/b.json\nM index.json\n\
+                     Working copy  (@) : vyynzsls ba04ef5e (no description set)\n\
+                     Parent commit (@-): pxtovvwu 96efec8b Update: Ideas\n";
+        assert_eq!(summarize_status(three), "● uncommitted (3 files)");
+    }
+
+    #[test]
+    fn status_with_conflicts_says_so_first() {
+        let output = "Working copy changes:\nM notes/a.json\n\
+                      There are unresolved conflicts at these paths:\nnotes/a.json    2-sided conflict\n";
+        assert_eq!(summarize_status(output), "✗ conflicts");
+    }
+
+    #[test]
+    fn parses_one_commit_per_record() {
+        let output = "3fa2c1d0\x1fUpdate: Ideas [#abc] (2025-01-02 10:00:00)\x1fAda\x1f2025-01-02 10:00\x1e\n\
+                      9b1e7f22\x1f(empty)\x1fAda Lovelace\x1f2025-01-01 09:30\x1e\n";
+        assert_eq!(fields(&parse_commit_log(output)), vec![
+            ("3fa2c1d0", "Update: Ideas [#abc] (2025-01-02 10:00:00)", "Ada", "2025-01-02 10:00"),
+            ("9b1e7f22", "(empty)", "Ada Lovelace", "2025-01-01 09:30"),
+        ]);
+    }
+
+    #[test]
+    fn separator_in_a_message_keeps_author_and_timestamp() {
+        let output = "3fa2c1d0\x1fodd\x1fmessage\x1fAda\x1f2025-01-02 10:00\x1e";
+        assert_eq!(fields(&parse_commit_log(output)), vec![("3fa2c1d0", "odd message", "Ada", "2025-01-02 10:00")]);
+    }
+
+    #[test]
+    fn incomplete_records_are_skipped() {
+        assert!(parse_commit_log("").is_empty());
+        assert!(parse_commit_log("\n").is_empty());
+        assert!(parse_commit_log("3fa2c1d0\x1fno author\x1e").is_empty());
+    }
+
+    #[test]
+    fn repo_log_is_empty_without_a_repo() {
+        let dir = std::env::temp_dir().join(format!("jjzettel-test-no-repo-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = Jujutsu::new(dir.to_string_lossy()).get_repo_log(10);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(log.unwrap().is_empty());
+    }
+}
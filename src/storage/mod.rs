@@ -1,4 +1,5 @@
 pub mod note;
 pub mod jujutsu;
+pub mod template;
 
 pub use jujutsu::CommitInfo;
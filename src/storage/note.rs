@@ -1,7 +1,27 @@
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Version of the note file format this build writes. Bump it together with a step in
+/// `Note::migrate` whenever a field is added or changes meaning.
+///
+/// - 1: id, title, content, links, tags, timestamps (files without `schema_version`)
+/// - 2: `metadata`, `color` and `scratch`, all empty by default
+/// - 3: `canonical_commit`, unset by default
+/// - 4: `due`, unset by default
+/// - 5: `format`, unset (markdown) by default
+/// - 6: `locked`, false by default
+/// - 7: `strict_line_breaks`, false by default
+/// - 8: `order`, unset by default
+pub const CURRENT_SCHEMA_VERSION: u32 = 8;
+
+/// Color labels a note can be given, in the order the View-mode key cycles through them
+pub const COLOR_LABELS: [&str; 6] = ["red", "green", "yellow", "blue", "magenta", "cyan"];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Note {
+    #[serde(default = "legacy_schema_version")]
+    pub schema_version: u32, // Format the file was written in; upgraded to CURRENT_SCHEMA_VERSION on load
     pub id: String,
     pub title: String,
     pub content: String,
@@ -9,6 +29,24 @@ pub struct Note {
     pub tags: Vec<String>,
     pub created_at: String,
     pub updated_at: String,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>, // Custom fields like status, priority, source
+    #[serde(default)]
+    pub color: Option<String>, // One of COLOR_LABELS, shown as a marker in the list
+    #[serde(default)]
+    pub scratch: Option<String>, // Quick TODO line shown above the note, not part of its content
+    #[serde(default)]
+    pub canonical_commit: Option<String>, // Commit marked in History as the reference version
+    #[serde(default)]
+    pub due: Option<String>, // RFC 3339 due date, listed on the Due screen until cleared
+    #[serde(default)]
+    pub format: Option<String>, // Markup of the content: markdown (when unset), plaintext, org or asciidoc
+    #[serde(default)]
+    pub locked: bool, // Finalized: edits, tags, links and deletion are refused until it is unlocked
+    #[serde(default)]
+    pub strict_line_breaks: bool, // Shown line for line even when paragraph mode joins soft breaks
+    #[serde(default)]
+    pub order: Option<i64>, // Position in the manual list order, lowest first; unset notes follow
 }
 
 impl Note {
@@ -22,6 +60,7 @@ impl Note {
         let now = chrono::Utc::now().to_rfc3339();
 
         Note {
+            schema_version: CURRENT_SCHEMA_VERSION,
             id,
             title,
             content,
@@ -29,6 +68,299 @@ impl Note {
             tags: Vec::new(),
             created_at: now.clone(),
             updated_at: now,
+            metadata: BTreeMap::new(),
+            color: None,
+            scratch: None,
+            canonical_commit: None,
+            due: None,
+            format: None,
+            locked: false,
+            strict_line_breaks: false,
+            order: None,
+        }
+    }
+
+    /// Parse a note file, upgrading older formats to the current one in memory.
+    /// The file itself is rewritten in the current format the next time the note is saved.
+    pub fn from_json(content: &str) -> Result<Self> {
+        let mut note: Note = serde_json::from_str(content)?;
+        note.migrate()?;
+        Ok(note)
+    }
+
+    /// Upgrade the note one schema version at a time up to `CURRENT_SCHEMA_VERSION`.
+    /// Fields added by a version get their defaults from serde; a step only needs code
+    /// when existing data has to change.
+    fn migrate(&mut self) -> Result<()> {
+        if self.schema_version > CURRENT_SCHEMA_VERSION {
+            anyhow::bail!(
+                "Note {} uses format version {}, newer than this jjzettel supports ({})",
+                self.id,
+                self.schema_version,
+                CURRENT_SCHEMA_VERSION
+            );
+        }
+        if self.schema_version < 2 {
+            // metadata, color and scratch start out empty
+            self.schema_version = 2;
+        }
+        if self.schema_version < 3 {
+            // canonical_commit starts out unset
+            self.schema_version = 3;
+        }
+        if self.schema_version < 4 {
+            // due starts out unset
+            self.schema_version = 4;
         }
+        if self.schema_version < 5 {
+            // format starts out unset, which is markdown
+            self.schema_version = 5;
+        }
+        if self.schema_version < 6 {
+            // locked starts out false
+            self.schema_version = 6;
+        }
+        if self.schema_version < 7 {
+            // strict_line_breaks starts out false
+            self.schema_version = 7;
+        }
+        if self.schema_version < 8 {
+            // order starts out unset
+            self.schema_version = 8;
+        }
+        Ok(())
+    }
+
+    /// Label after the note's current one in `COLOR_LABELS`; the last label wraps around to none.
+    /// An unknown label (e.g. hand-edited) restarts the cycle.
+    pub fn next_color(&self) -> Option<String> {
+        let next = match self.color.as_deref() {
+            None => Some(0),
+            Some(color) => COLOR_LABELS
+                .iter()
+                .position(|c| *c == color)
+                .map_or(Some(0), |i| (i + 1 < COLOR_LABELS.len()).then_some(i + 1)),
+        };
+        next.map(|i| COLOR_LABELS[i].to_string())
+    }
+
+    /// Markup the content is written in, `markdown` unless the note says otherwise
+    pub fn format(&self) -> &str {
+        self.format.as_deref().unwrap_or("markdown")
+    }
+
+    /// When the note is due, `None` without a due date or with one that is not RFC 3339
+    pub fn due_at(&self) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+        parse_due_at(self.due.as_deref()?)
+    }
+
+    /// Whether the note has a due date before `now`
+    pub fn is_overdue(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.due_at().is_some_and(|due| due < now)
+    }
+
+    /// The fields the note list shows, without the rest of the content
+    pub fn summary(&self) -> NoteSummary {
+        NoteSummary {
+            id: self.id.clone(),
+            title: self.title.clone(),
+            tags: self.tags.clone(),
+            first_line: self.content.lines().next().unwrap_or("").trim().to_string(),
+            link_count: self.links.len(),
+            created_at: self.created_at.clone(),
+            updated_at: self.updated_at.clone(),
+            color: self.color.clone(),
+            due: self.due.clone(),
+            order: self.order,
+        }
+    }
+}
+
+/// Lightweight view of a note for lists and counts; load the full `Note` to show or edit it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteSummary {
+    pub id: String,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub first_line: String, // First content line, trimmed, as used for the list preview
+    pub link_count: usize,
+    pub created_at: String,
+    pub updated_at: String,
+    pub color: Option<String>,
+    pub due: Option<String>,
+    pub order: Option<i64>,
+}
+
+/// Whole days from `updated_at` (RFC 3339) to `now`, `None` if the date cannot be read
+pub fn age_days(updated_at: &str, now: chrono::DateTime<chrono::Utc>) -> Option<i64> {
+    let updated = chrono::DateTime::parse_from_rfc3339(updated_at).ok()?;
+    Some((now - updated.with_timezone(&chrono::Utc)).num_days())
+}
+
+/// Whether a note last updated at `updated_at` has gone `days` days or more without an
+/// update. A date that cannot be read counts as stale, since it needs a look anyway.
+pub fn is_stale(updated_at: &str, now: chrono::DateTime<chrono::Utc>, days: u32) -> bool {
+    age_days(updated_at, now).is_none_or(|age| age >= i64::from(days))
+}
+
+/// A stored due date as a point in time, `None` if it is not RFC 3339
+pub fn parse_due_at(due: &str) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+    chrono::DateTime::parse_from_rfc3339(due).ok()
+}
+
+/// Due date typed by the user as RFC 3339. Besides RFC 3339 itself, `YYYY-MM-DD HH:MM` and
+/// `YYYY-MM-DD` are read in local time, a bare date as the end of that day.
+pub fn parse_due(input: &str) -> Result<String> {
+    let input = input.trim();
+    if let Some(due) = parse_due_at(input) {
+        return Ok(due.to_rfc3339());
+    }
+    let local = chrono::NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M")
+        .or_else(|_| {
+            chrono::NaiveDate::parse_from_str(input, "%Y-%m-%d")
+                .map(|date| date.and_hms_opt(23, 59, 0).unwrap_or_default())
+        })
+        .map_err(|_| {
+            anyhow::anyhow!("'{}' is not a date; use YYYY-MM-DD, YYYY-MM-DD HH:MM or RFC 3339", input)
+        })?;
+    let due = local
+        .and_local_timezone(chrono::Local)
+        .earliest()
+        .ok_or_else(|| anyhow::anyhow!("{} does not exist in the local time zone", input))?;
+    Ok(due.fixed_offset().to_rfc3339())
+}
+
+/// First paragraph of the note body for list previews: the title line, blank lines and
+/// lines repeating the title are skipped, and the paragraph's lines are joined with spaces
+pub fn preview_text(content: &str) -> String {
+    body_lines(content).take_while(|line| !line.is_empty()).collect::<Vec<_>>().join(" ")
+}
+
+/// The note body as plain comparable text: lowercase words separated by single spaces,
+/// without the title line. Empty for notes that are only a title.
+pub fn normalized_body(content: &str) -> String {
+    body_lines(content)
+        .flat_map(str::split_whitespace)
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Trimmed body lines, starting after the title line and any blank lines or lines
+/// repeating the title that follow it
+fn body_lines(content: &str) -> impl Iterator<Item = &str> {
+    let mut lines = content.lines().map(str::trim).skip_while(|line| line.is_empty());
+    let title = lines.next().map_or("", |line| line.trim_start_matches('#').trim());
+    lines.skip_while(move |line| line.is_empty() || line.trim_start_matches('#').trim() == title)
+}
+
+/// Files written before versioning have no `schema_version`
+fn legacy_schema_version() -> u32 {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::parse_from_rfc3339("2025-03-31T12:00:00Z").unwrap().with_timezone(&chrono::Utc)
+    }
+
+    /// A note file as written before `schema_version` existed
+    const V1_NOTE: &str = r#"{
+        "id": "abc123",
+        "title": "Old note",
+        "content": "Old note\n\nWritten long ago",
+        "links": ["def456"],
+        "tags": ["history"],
+        "created_at": "2024-01-01T10:00:00+00:00",
+        "updated_at": "2024-01-02T10:00:00+00:00"
+    }"#;
+
+    #[test]
+    fn unversioned_note_is_upgraded_with_defaults() {
+        let note = Note::from_json(V1_NOTE).unwrap();
+        assert_eq!(note.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!((note.id.as_str(), note.title.as_str()), ("abc123", "Old note"));
+        assert_eq!(note.links, ["def456"]);
+        assert_eq!(note.tags, ["history"]);
+        assert_eq!(note.updated_at, "2024-01-02T10:00:00+00:00");
+        assert!(note.metadata.is_empty());
+        assert_eq!((note.color, note.scratch, note.canonical_commit), (None, None, None));
+        assert_eq!((note.due, note.format, note.order), (None, None, None));
+        assert!(!note.locked && !note.strict_line_breaks);
+    }
+
+    #[test]
+    fn upgraded_note_round_trips_in_the_current_format() {
+        let note = Note::from_json(V1_NOTE).unwrap();
+        let written = serde_json::to_string(&note).unwrap();
+        assert!(written.contains(&format!("\"schema_version\":{}", CURRENT_SCHEMA_VERSION)));
+        let reread = Note::from_json(&written).unwrap();
+        assert_eq!(serde_json::to_value(&reread).unwrap(), serde_json::to_value(&note).unwrap());
+    }
+
+    #[test]
+    fn fields_of_a_middle_version_survive_the_upgrade() {
+        let mut value: serde_json::Value = serde_json::from_str(V1_NOTE).unwrap();
+        value["schema_version"] = 5.into();
+        value["format"] = "org".into();
+        value["due"] = "2025-06-01T09:00:00+00:00".into();
+        let note = Note::from_json(&value.to_string()).unwrap();
+        assert_eq!(note.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(note.format.as_deref(), Some("org"));
+        assert_eq!(note.due.as_deref(), Some("2025-06-01T09:00:00+00:00"));
+    }
+
+    #[test]
+    fn newer_format_is_refused() {
+        let mut value: serde_json::Value = serde_json::from_str(V1_NOTE).unwrap();
+        value["schema_version"] = (CURRENT_SCHEMA_VERSION + 1).into();
+        let err = Note::from_json(&value.to_string()).unwrap_err();
+        assert!(err.to_string().contains("newer than this jjzettel supports"), "{}", err);
+    }
+
+    #[test]
+    fn preview_is_the_first_paragraph_after_the_title() {
+        let content = "Ideas\n\nFirst line of the idea\n  wrapped onto a second\n\nA later paragraph";
+        assert_eq!(preview_text(content), "First line of the idea wrapped onto a second");
+    }
+
+    #[test]
+    fn preview_skips_leading_blanks_and_a_repeated_title() {
+        assert_eq!(preview_text("\n\n  Ideas\n\n# Ideas\n\nThe body"), "The body");
+        assert_eq!(preview_text("# Ideas\nIdeas\nThe body"), "The body");
+        assert_eq!(preview_text("Ideas\nIdeas worth having"), "Ideas worth having", "only whole-line repeats");
+    }
+
+    #[test]
+    fn title_only_note_has_no_preview() {
+        assert_eq!(preview_text("Ideas"), "");
+        assert_eq!(preview_text("Ideas\n\n# Ideas\n\n"), "");
+        assert_eq!(preview_text(""), "");
+    }
+
+    #[test]
+    fn note_is_stale_from_exactly_n_days() {
+        assert!(is_stale("2025-03-01T12:00:00Z", now(), 30));
+        assert!(!is_stale("2025-03-01T12:00:01Z", now(), 30), "one second short of 30 days");
+        assert!(is_stale("2025-02-01T12:00:00Z", now(), 30));
+        assert!(!is_stale("2025-03-31T12:00:00Z", now(), 30));
+    }
+
+    #[test]
+    fn staleness_compares_instants_across_time_zones() {
+        // 2025-03-01T12:00:00Z, written in another offset
+        assert!(is_stale("2025-03-01T14:00:00+02:00", now(), 30));
+        assert!(!is_stale("2025-03-01T14:00:01+02:00", now(), 30));
+        assert_eq!(age_days("2025-03-01T14:00:00+02:00", now()), Some(30));
+    }
+
+    #[test]
+    fn unreadable_update_time_counts_as_stale() {
+        assert_eq!(age_days("yesterday", now()), None);
+        assert!(is_stale("yesterday", now(), 30));
+        assert!(is_stale("", now(), 30));
     }
 }
@@ -0,0 +1,98 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Marker in a template file that sets where the caret starts
+pub const CURSOR_MARKER: &str = "{{cursor}}";
+
+/// A note template loaded from `templates/*.md` in the repo
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub name: String,
+    pub content: String, // Template text with the cursor marker removed
+    pub cursor: usize,   // Byte offset in `content` where editing starts
+}
+
+impl Template {
+    /// Build a template from raw file text. The first `{{cursor}}` sets the caret position
+    /// (end of text if there is none); every marker is stripped from the content.
+    pub fn parse(name: impl Into<String>, raw: &str) -> Self {
+        let content = raw.replace(CURSOR_MARKER, "");
+        // Any other markers come after the first, so stripping does not shift its offset
+        let cursor = raw.find(CURSOR_MARKER).unwrap_or(content.len());
+
+        Template {
+            name: name.into(),
+            content,
+            cursor,
+        }
+    }
+}
+
+/// Load all `*.md` templates from a directory, sorted by name.
+/// A missing directory simply means there are no templates.
+pub fn load_templates(dir: &Path) -> Result<Vec<Template>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut templates = Vec::new();
+    for entry in std::fs::read_dir(dir).context("Failed to read templates directory")? {
+        let path = entry?.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("md") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let raw = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read template {}", path.display()))?;
+        templates.push(Template::parse(name, &raw));
+    }
+
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(templates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marker_is_removed_and_sets_the_cursor() {
+        let template = Template::parse("meeting", "Meeting\n\nAgenda:\n- {{cursor}}\n\nNotes:\n");
+        assert_eq!(template.content, "Meeting\n\nAgenda:\n- \n\nNotes:\n");
+        assert_eq!(&template.content[..template.cursor], "Meeting\n\nAgenda:\n- ");
+    }
+
+    #[test]
+    fn first_marker_wins_and_every_marker_is_removed() {
+        let template = Template::parse("twice", "Über {{cursor}}middle{{cursor}} end");
+        assert_eq!(template.content, "Über middle end");
+        assert_eq!(&template.content[..template.cursor], "Über ");
+    }
+
+    #[test]
+    fn without_a_marker_the_cursor_is_at_the_end() {
+        let template = Template::parse("plain", "Daily log\n\n");
+        assert_eq!(template.content, "Daily log\n\n");
+        assert_eq!(template.cursor, template.content.len());
+    }
+
+    #[test]
+    fn only_markdown_files_load_sorted_by_name() {
+        let dir = std::env::temp_dir().join(format!("jjzettel-templates-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(load_templates(&dir).unwrap().is_empty(), "a missing directory has no templates");
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("zettel.md"), "Z {{cursor}}").unwrap();
+        std::fs::write(dir.join("meeting.md"), "M").unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a template").unwrap();
+        let templates = load_templates(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let names: Vec<&str> = templates.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["meeting", "zettel"]);
+        assert_eq!((templates[1].content.as_str(), templates[1].cursor), ("Z ", 2));
+    }
+}
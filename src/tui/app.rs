@@ -1,13 +1,54 @@
-use crate::storage::note::Note;
-use crate::service::NoteService;
+use crate::config::state::{AppState, SavedSearch};
+use crate::config::{resolve_notes_dir, resolve_repo_path, CliArgs, Config, EmptyBodyPolicy, PreviewMode, StartScreen, WrapMode};
+use crate::storage::note::{age_days, is_stale, parse_due, parse_due_at, preview_text, Note};
+use crate::storage::CommitInfo;
+use crate::storage::template::Template;
+use crate::service::{extract_tasks, has_body, slugify_title, AutoTagger, Cancelled, CyclePolicy, DuplicateTitle, DuplicateTitlePolicy, ListOrder, SearchOrder, Stub, StubReason, TagVariants, Task, NoteService, UndoPoint, VaultHealth, MIN_ID_PREFIX, UNTAGGED_GROUP};
+use crate::service::hooks::{HookEvent, HookRunner};
+use crate::service::spellcheck::{self, Dictionary};
+use crate::tui::{clipboard, hyperlink, render};
+use crate::tui::editor::ExternalEdit;
+use crate::tui::tutorial::{TutorialStep, SAMPLE_NOTES};
+use crate::tui::keymap::{Action, KeyContext, Keymap};
 use anyhow::Result;
 use ratatui::prelude::*;
 use ratatui::widgets::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+/// Most messages kept in the message log
+const LOG_LIMIT: usize = 200;
+
+/// Smallest terminal the three-panel layouts fit in; below it only a resize hint is drawn
+const MIN_WIDTH: u16 = 20;
+const MIN_HEIGHT: u16 = 8;
+
+/// Characters of a note id shown in View mode unless configured otherwise
+const DEFAULT_SHORT_ID_LENGTH: usize = 8;
+
+/// Days without an update after which a note counts as stale unless configured otherwise
+const DEFAULT_STALE_DAYS: u32 = 90;
+
+/// Most commits loaded into the repo log screen
+const REPO_LOG_LIMIT: usize = 200;
+
+/// Recently updated notes listed on the dashboard
+const DASHBOARD_RECENT: usize = 8;
+
+/// Something a dashboard tile opens
+enum DashboardEntry<'a> {
+    Note(&'a Note),
+    Search(&'a SavedSearch),
+}
 
 pub enum AppMode {
     List,
     View,
     Edit,
+    RawEdit,
     Create,
     Search,
     DeleteConfirm,
@@ -18,6 +59,297 @@ pub enum AppMode {
     Statistics,
     Help,
     History,
+    MetadataEdit,
+    MetadataInput,
+    Prompt(PromptKind),
+    Confirm(ConfirmKind),
+    TemplateSelect,
+    TagGroups,
+    Log,
+    TagManager,
+    LinkOrder,
+    Tasks,
+    RepoLog,
+    Disambiguate,
+    SavedSearches,
+    Cycles,
+    Dashboard,
+    Due,
+    Stubs,
+    Stale,
+    Health,
+    HealthNotes, // The notes one health check found
+}
+
+/// Yes/no questions sharing one confirmation screen
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ConfirmKind {
+    SaveEmptyNewNote,
+    SaveEmptyEdit,
+    CreateLink,
+    EditConflict,
+    Undo,
+    DeleteTag,
+    RestoreCanonical,
+    UnsavedEdit,
+    DeleteStub,
+    DeleteSelected,
+    DuplicateTitle,
+}
+
+impl ConfirmKind {
+    fn title(self) -> &'static str {
+        match self {
+            ConfirmKind::SaveEmptyNewNote | ConfirmKind::SaveEmptyEdit => "Empty Note",
+            ConfirmKind::CreateLink => "Confirm Link",
+            ConfirmKind::EditConflict => "Note Changed on Disk",
+            ConfirmKind::Undo => "Undo",
+            ConfirmKind::DeleteTag => "Delete Tag",
+            ConfirmKind::RestoreCanonical => "Restore Canonical Version",
+            ConfirmKind::UnsavedEdit => "Unsaved Changes",
+            ConfirmKind::DeleteStub => "Delete Stub Note",
+            ConfirmKind::DeleteSelected => "Delete Selected Notes",
+            ConfirmKind::DuplicateTitle => "Title Already Taken",
+        }
+    }
+
+    /// Mode to return to when the question is answered with no
+    fn cancel_mode(self) -> AppMode {
+        match self {
+            ConfirmKind::SaveEmptyNewNote | ConfirmKind::DuplicateTitle => AppMode::Create,
+            ConfirmKind::SaveEmptyEdit | ConfirmKind::EditConflict | ConfirmKind::UnsavedEdit => AppMode::Edit,
+            ConfirmKind::CreateLink => AppMode::LinkSelect,
+            ConfirmKind::Undo | ConfirmKind::DeleteSelected => AppMode::List,
+            ConfirmKind::DeleteTag => AppMode::TagManager,
+            ConfirmKind::RestoreCanonical => AppMode::History,
+            ConfirmKind::DeleteStub => AppMode::Stubs,
+        }
+    }
+}
+
+/// Bulk export, backup or restore running on a background thread, so the screen keeps
+/// showing its progress. Keys other than Esc, which cancels it, are ignored until it finishes.
+pub struct BackgroundJob {
+    verb: &'static str, // Past tense for the progress line, e.g. "Exported" for "Exported 120/500"
+    reload: bool, // The job changes notes, so the list is reloaded when it finishes
+    messages: Receiver<JobMessage>,
+    cancel: Arc<AtomicBool>, // Set to ask the job to stop at its next step
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for BackgroundJob {
+    /// Quitting (or failing) with a job still running cancels it and waits for its thread,
+    /// so it never goes on writing to the vault after the app is gone
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+enum JobMessage {
+    Progress(usize, usize),
+    Finished(String), // Status message reporting the outcome
+}
+
+/// One line of the vault health report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthCheck {
+    Orphans,
+    BrokenLinks,
+    DuplicateTitles,
+    Stubs,
+    Cycles,
+    TagVariants,
+    MissingHistory,
+}
+
+impl HealthCheck {
+    /// Report order
+    const ALL: [HealthCheck; 7] = [
+        HealthCheck::Orphans,
+        HealthCheck::BrokenLinks,
+        HealthCheck::DuplicateTitles,
+        HealthCheck::Stubs,
+        HealthCheck::Cycles,
+        HealthCheck::TagVariants,
+        HealthCheck::MissingHistory,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            HealthCheck::Orphans => "Orphan notes (no links in or out)",
+            HealthCheck::BrokenLinks => "Notes with broken links (to notes that no longer exist)",
+            HealthCheck::DuplicateTitles => "Notes sharing a title",
+            HealthCheck::Stubs => "Stub notes (title only or boilerplate)",
+            HealthCheck::Cycles => "Link cycles (informational)",
+            HealthCheck::TagVariants => "Tags spelled in more than one case",
+            HealthCheck::MissingHistory => "Notes missing from history (uncommitted changes)",
+        }
+    }
+
+    fn count(self, health: &VaultHealth) -> usize {
+        match self {
+            HealthCheck::Orphans => health.orphans.len(),
+            HealthCheck::BrokenLinks => health.broken_links.len(),
+            HealthCheck::DuplicateTitles => health.duplicate_titles.iter().map(Vec::len).sum(),
+            HealthCheck::Stubs => health.stubs.len(),
+            HealthCheck::Cycles => health.cycles.len(),
+            HealthCheck::TagVariants => health.tag_variants.len(),
+            HealthCheck::MissingHistory => health.missing_history.len(),
+        }
+    }
+}
+
+/// How much of the note header (dates, tags, fields) View mode shows above the content
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ViewHeader {
+    #[default]
+    Full,
+    /// Everything on one line
+    Compact,
+    Hidden,
+}
+
+impl ViewHeader {
+    fn next(self) -> Self {
+        match self {
+            ViewHeader::Full => ViewHeader::Compact,
+            ViewHeader::Compact => ViewHeader::Hidden,
+            ViewHeader::Hidden => ViewHeader::Full,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            ViewHeader::Full => "in full",
+            ViewHeader::Compact => "as one line",
+            ViewHeader::Hidden => "hidden",
+        }
+    }
+}
+
+/// How notes are labelled in View mode's link sections
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LinkLabels {
+    #[default]
+    Titles,
+    /// `title (short id)`, to tell apart notes sharing a title
+    TitlesWithIds,
+    /// Full ids, the way they are stored in `links`
+    Ids,
+}
+
+impl LinkLabels {
+    fn next(self) -> Self {
+        match self {
+            LinkLabels::Titles => LinkLabels::TitlesWithIds,
+            LinkLabels::TitlesWithIds => LinkLabels::Ids,
+            LinkLabels::Ids => LinkLabels::Titles,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            LinkLabels::Titles => "titles",
+            LinkLabels::TitlesWithIds => "titles with ids",
+            LinkLabels::Ids => "raw ids",
+        }
+    }
+}
+
+/// Single-line text prompts sharing one input screen
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PromptKind {
+    RenameNote,
+    MoveToVault,
+    BackupArchive,
+    RestoreArchive,
+    FindInNote,
+    Scratch,
+    Due,
+    MergeStub,
+    RenameTag,
+    MergeTags,
+    NormalizeTag,
+    SaveSearch,
+    ExportWithLinks,
+    CommitNow,
+    ExportResults,
+    ExportSelected,
+    TagSelected,
+    LinkSelected,
+}
+
+impl PromptKind {
+    fn title(self) -> &'static str {
+        match self {
+            PromptKind::RenameNote => "Rename Note",
+            PromptKind::MoveToVault => "Move Note to Another Vault",
+            PromptKind::BackupArchive => "Backup Vault",
+            PromptKind::RestoreArchive => "Restore Backup",
+            PromptKind::FindInNote => "Find in Note",
+            PromptKind::Scratch => "Scratch Line",
+            PromptKind::Due => "Due Date",
+            PromptKind::MergeStub => "Merge Stub Note",
+            PromptKind::RenameTag => "Rename Tag Everywhere",
+            PromptKind::MergeTags => "Merge Tags",
+            PromptKind::NormalizeTag => "Normalize Tag Case",
+            PromptKind::SaveSearch => "Save Search",
+            PromptKind::ExportWithLinks => "Export with Linked Notes",
+            PromptKind::CommitNow => "Commit Now",
+            PromptKind::ExportResults => "Export Search Results",
+            PromptKind::ExportSelected => "Export Selected Notes",
+            PromptKind::TagSelected => "Tag Selected Notes",
+            PromptKind::LinkSelected => "Link Selected Notes",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            PromptKind::RenameNote => "New title",
+            PromptKind::MoveToVault => "Destination vault path",
+            PromptKind::BackupArchive => "Archive to write (.tar.gz)",
+            PromptKind::RestoreArchive => "Archive to restore (.tar.gz)",
+            PromptKind::FindInNote => "Find",
+            PromptKind::Scratch => "Scratch (not part of the note; empty clears it)",
+            PromptKind::Due => "Due (YYYY-MM-DD, YYYY-MM-DD HH:MM or RFC 3339; empty clears it)",
+            PromptKind::MergeStub => "Title of the note to merge it into",
+            PromptKind::RenameTag => "New tag name",
+            PromptKind::MergeTags => "Tag to merge the marked and selected tags into",
+            PromptKind::NormalizeTag => "Spelling to give every case variant of the tag",
+            PromptKind::SaveSearch => "Name for this search (an existing name is replaced)",
+            PromptKind::ExportWithLinks => "Link hops to follow (1 = directly linked notes)",
+            PromptKind::CommitNow => "Commit message",
+            PromptKind::ExportResults => "Title of a new note linking the results, or a file name ending in .md",
+            PromptKind::ExportSelected => "Title of a new note linking the selected notes, or a file name ending in .md",
+            PromptKind::TagSelected => "Tag to add to every selected note",
+            PromptKind::LinkSelected => "Title of the note every selected note should link to",
+        }
+    }
+
+    /// Mode to return to when the prompt is cancelled or submitted
+    fn return_mode(self) -> AppMode {
+        match self {
+            PromptKind::RenameNote
+            | PromptKind::MoveToVault
+            | PromptKind::FindInNote
+            | PromptKind::Scratch
+            | PromptKind::Due
+            | PromptKind::ExportWithLinks => AppMode::View,
+            PromptKind::BackupArchive
+            | PromptKind::RestoreArchive
+            | PromptKind::SaveSearch
+            | PromptKind::CommitNow
+            | PromptKind::ExportResults
+            | PromptKind::ExportSelected
+            | PromptKind::TagSelected
+            | PromptKind::LinkSelected => AppMode::List,
+            PromptKind::RenameTag | PromptKind::MergeTags | PromptKind::NormalizeTag => AppMode::TagManager,
+            PromptKind::MergeStub => AppMode::Stubs,
+        }
+    }
 }
 
 pub struct App {
@@ -27,6 +359,7 @@ pub struct App {
     pub is_searching: bool,
     pub search_query: String,
     pub selected_index: usize,
+    pub selected_ids: HashSet<String>, // Notes picked with Space in List mode for batch operations
     pub link_selected_index: usize,
     pub backlink_selected_index: usize,
     pub mode: AppMode,
@@ -34,63 +367,549 @@ pub struct App {
     pub input_buffer: String,
     pub should_quit: bool,
     pub status_message: Option<String>,
+    pub keymap: Keymap,
+    pub metadata_edit_key: Option<String>, // Field being edited in MetadataInput (None = adding)
+    pub link_number_input: String, // Digits typed in View mode to jump to a numbered link
+    pub cursor_pos: usize, // Caret byte offset in input_buffer while editing or creating
+    pub external_edit: Option<ExternalEdit>, // Editor run asked for with Ctrl+E, for the main loop to start
+    pub templates: Vec<Template>,
+    pub template_selected_index: usize,
+    pub backlink_index: HashMap<String, Vec<String>>, // Note id → ids linking to it, rebuilt on reload
+    pub spellcheck_enabled: bool,
+    pub dictionary: Option<Dictionary>, // Loaded the first time spell checking is turned on
+    pub dictionary_path: Option<String>,
+    pub view_scroll: u16, // First visible line of the View mode content pane
+    pub view_hscroll: u16, // First visible column when wrapping is off
+    pub wrap_mode: WrapMode,
+    pub preview_mode: PreviewMode,
+    pub hyperlinks: bool, // Emit OSC 8 links for URLs in View mode
+    pub paragraphs: bool, // View joins soft line breaks, except in notes with strict line breaks
+    pub history: Vec<CommitInfo>, // Current note's commits, loaded when History mode opens
+    pub history_error: Option<String>,
+    pub repo_log: Vec<CommitInfo>, // Vault-wide commits, loaded when RepoLog mode opens
+    pub repo_log_error: Option<String>,
+    pub repo_log_index: usize,
+    pub repo_log_diff: Option<String>, // Diff of the selected commit while it is shown
+    pub repo_log_scroll: u16,
+    pub cycles: Vec<Vec<String>>, // Circular link chains as note ids, found when Statistics mode opens
+    pub cycle_index: usize,
+    pub ambiguous_titles: Vec<String>, // Wikilinks of the saved note still waiting for the user to pick a target
+    pub title_candidates: Vec<Note>, // Notes sharing the first of those titles
+    pub candidate_index: usize,
+    pub tasks: Vec<Task>, // Task items across notes, rebuilt when Tasks mode opens or a task changes
+    pub task_index: usize,
+    pub show_done_tasks: bool,
+    pub due_notes: Vec<Note>, // Notes with a due date, soonest first, rebuilt when Due mode opens
+    pub stubs: Vec<Stub>, // Title-only and boilerplate notes, found when Statistics mode opens
+    pub stub_index: usize,
+    pub due_index: usize,
+    pub capture_input: Option<String>, // Quick-capture popup text, shown over any mode while Some
+    pub help_overlay: Option<KeyContext>, // Mode whose keys are listed in the help popup, shown while Some
+    pub help_scroll: u16,
+    pub help_return: KeyContext, // Mode the full help screen goes back to
+    pub find_query: String,
+    pub find_matches: Vec<TextMatch>, // In-note find results for the current note
+    pub find_index: usize,
+    pub link_source_id: Option<String>, // Note to link from when Create mode was opened with L
+    pub empty_body_policy: EmptyBodyPolicy,
+    pub confirm_links: bool,
+    pub cycle_policy: CyclePolicy,
+    pub duplicate_title_policy: DuplicateTitlePolicy,
+    pub duplicate_of: Option<Note>, // Note already holding the title of the note being created
+    pub tag_groups: Vec<(String, Vec<String>)>, // Tag → note ids, built when TagGroups mode opens
+    pub collapsed_tags: HashSet<String>,
+    pub tag_group_index: usize, // Selected row among the visible headers and notes
+    pub last_undo: Option<UndoPoint>, // State before the last delete, move or restore
+    pub hooks: HookRunner,
+    pub job: Option<BackgroundJob>,
+    pub state: AppState, // Remembered between runs (search history, tutorial done)
+    pub short_id_length: usize, // Id characters shown in View mode, 0 to hide the id
+    pub link_labels: LinkLabels, // Titles or ids in View mode's link sections, kept across notes
+    pub view_header: ViewHeader, // How much of the note header View mode shows, kept across notes
+    pub tutorial: Option<TutorialStep>, // First-run walkthrough step while it is shown
+    pub saved_search_index: usize,
+    pub dashboard_index: usize, // Selected entry across the dashboard's tiles
+    pub search_history_pos: Option<usize>, // History entry shown in Search mode while cycling with Up/Down
+    pub search_order: SearchOrder,
+    pub fuzzy_search: bool, // Search by letters in order rather than by substring, toggled with Ctrl+F
+    pub list_order: ListOrder, // Order of the unfiltered note list
+    pub tag_counts: Vec<(String, usize)>, // Tag → note count, built when TagManager mode opens
+    pub merge_marks: Vec<String>, // Tags marked in TagManager mode to merge into another
+    pub tag_variants: Vec<TagVariants>, // Tags spelled in several cases, found with the tag counts
+    pub tag_manager_index: usize,
+    pub log: VecDeque<LogEntry>, // Recent status messages, oldest first
+    pub log_selected_index: usize,
+    pub vcs_status: String, // Working-copy indicator in the title bar, refreshed on reload
+    pub vault_summary: String, // Note and tag counts under the title bar, refreshed on reload
+    pub show_clock: bool, // Time and vault summary shown under the title bar
+    pub refresh_keeps_selection: bool,
+    pub list_offset: std::cell::Cell<usize>, // First list entry on screen, kept from frame to frame
+    pub stale_days: u32, // Days without an update after which a note is stale, 0 for never
+    pub stale_notes: Vec<Note>, // Stale notes, least recently updated first, found when Statistics mode opens
+    pub stale_index: usize,
+    pub health: VaultHealth, // Findings of the last vault health check
+    pub health_index: usize,
+    pub health_check: HealthCheck, // Check whose notes HealthNotes mode lists
+    pub health_notes_index: usize,
+    pub health_review: bool, // A review screen was opened from the health report, so Esc goes back to it
+    pub trail: Vec<String>, // Ids of the notes followed by link in View mode, the current one last
+    pub crumb_cursor: Option<usize>, // Breadcrumb being picked after `b`
+    pub edit_fingerprint: Option<String>, // On-disk hash of the note when Edit mode was entered
+    rng: XorShift, // Random note picks
+    last_random_id: Option<String>,
+}
+
+/// Status message kept in the message log
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub time: String, // Local time the message was shown, HH:MM:SS
+    pub message: String,
+}
+
+/// Position of a find match: line index and byte range within that line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextMatch {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
 }
 
 impl App {
-    pub fn new() -> Result<Self> {
-        let repo_path = std::env::var("JJZETTEL_REPO").unwrap_or_else(|_| {
-            let home = std::env::var("HOME")
-                .or_else(|_| std::env::var("USERPROFILE"))
-                .unwrap_or_else(|_| ".".to_string());
-            format!("{}/.jjzettel", home)
-        });
-        let service = NoteService::new(&repo_path);
-        service.initialize()?;
+    pub fn new(cli: &CliArgs) -> Result<Self> {
+        let config = Config::load()?;
+        let env = |name: &str| std::env::var(name).ok();
+        let repo_path = resolve_repo_path(cli.repo.as_deref(), env, &config)?;
+        let notes_dir = resolve_notes_dir(env, &repo_path);
+        Self::open(&repo_path, notes_dir, config, AppState::load())
+    }
+
+    /// App over the vault at `repo_path` with its notes in `notes_dir`, set up by `config`
+    /// and remembering what `state` holds
+    fn open(repo_path: &str, notes_dir: std::path::PathBuf, config: Config, state: AppState) -> Result<Self> {
+        let mut keymap = Keymap::from_config(&config.keybindings)?;
+        if !config.raw_json {
+            keymap.unbind(Action::EditRawJson);
+        }
+        if !config.paragraphs {
+            keymap.unbind(Action::ToggleLineBreaks);
+        }
+
+        let service = NoteService::new(repo_path, notes_dir.clone())
+            .with_rules(config.rules.clone())
+            .with_auto_tags(AutoTagger::new(&config.auto_tags)?)
+            .with_export_options(config.export.clone())
+            .with_cycle_policy(config.cycles)
+            .with_duplicate_title_policy(config.duplicate_titles)
+            .with_wikilink_sync(config.wikilinks)
+            .with_note_format(config.note_format)
+            .with_file_naming(config.file_names)
+            .with_auto_commit(config.auto_commit.unwrap_or(true))
+            .with_index_file(config.index_file)
+            .with_referenced_by(config.referenced_by)
+            .with_audit_log(config.audit.log_for(std::path::Path::new(repo_path)));
+        let service = match config.inbox {
+            Some(ref title) => service.with_inbox_title(title.clone()),
+            None => service,
+        };
+        // Notes that can be read but not written open read-only instead of failing
+        let read_only = match service.initialize() {
+            Ok(()) => None,
+            Err(e) if notes_dir.is_dir() && !service.notes_dir_writable() => Some(e),
+            Err(e) => return Err(e),
+        };
+        let service = service.with_read_only(read_only.is_some());
         
-        let notes = service.list_notes()?;
+        let mut notes = service.list_notes()?;
+        config.list_order.sort(&mut notes);
         
         let filtered_notes = notes.clone();
+        let backlink_index = service.backlink_index(&notes);
+        let summary = vault_summary(&notes);
         
-        Ok(App {
+        let mut app = App {
             service,
             notes,
             filtered_notes,
             is_searching: false,
             search_query: String::new(),
             selected_index: 0,
+            selected_ids: HashSet::new(),
             link_selected_index: 0,
             backlink_selected_index: 0,
-            mode: AppMode::List,
+            mode: match config.start_screen {
+                StartScreen::List => AppMode::List,
+                StartScreen::Dashboard => AppMode::Dashboard,
+            },
             current_note: None,
             input_buffer: String::new(),
             should_quit: false,
             status_message: None,
-        })
+            keymap,
+            metadata_edit_key: None,
+            link_number_input: String::new(),
+            cursor_pos: 0,
+            external_edit: None,
+            templates: Vec::new(),
+            template_selected_index: 0,
+            backlink_index,
+            spellcheck_enabled: false,
+            dictionary: None,
+            dictionary_path: config.dictionary.clone(),
+            view_scroll: 0,
+            view_hscroll: 0,
+            wrap_mode: config.wrap,
+            preview_mode: config.preview,
+            hyperlinks: hyperlink::enabled(config.hyperlinks),
+            paragraphs: config.paragraphs,
+            history: Vec::new(),
+            history_error: None,
+            repo_log: Vec::new(),
+            repo_log_error: None,
+            repo_log_index: 0,
+            repo_log_diff: None,
+            repo_log_scroll: 0,
+            cycles: Vec::new(),
+            cycle_index: 0,
+            ambiguous_titles: Vec::new(),
+            title_candidates: Vec::new(),
+            candidate_index: 0,
+            tasks: Vec::new(),
+            task_index: 0,
+            due_notes: Vec::new(),
+            stubs: Vec::new(),
+            stub_index: 0,
+            due_index: 0,
+            show_done_tasks: false,
+            capture_input: None,
+            help_overlay: None,
+            help_scroll: 0,
+            help_return: KeyContext::List,
+            find_query: String::new(),
+            find_matches: Vec::new(),
+            find_index: 0,
+            link_source_id: None,
+            empty_body_policy: config.empty_body,
+            confirm_links: config.confirm_links,
+            cycle_policy: config.cycles,
+            duplicate_title_policy: config.duplicate_titles,
+            duplicate_of: None,
+            tag_groups: Vec::new(),
+            collapsed_tags: HashSet::new(),
+            tag_group_index: 0,
+            last_undo: None,
+            hooks: HookRunner::new(config.hooks.clone()),
+            job: None,
+            state,
+            // Shorter ids could not be looked up again, since `resolve_id` needs MIN_ID_PREFIX characters
+            short_id_length: match config.short_id_length.unwrap_or(DEFAULT_SHORT_ID_LENGTH) {
+                0 => 0,
+                n => n.max(MIN_ID_PREFIX),
+            },
+            link_labels: LinkLabels::default(),
+            view_header: ViewHeader::default(),
+            tutorial: None,
+            saved_search_index: 0,
+            dashboard_index: 0,
+            search_history_pos: None,
+            search_order: config.search_order,
+            fuzzy_search: config.fuzzy_search,
+            list_order: config.list_order,
+            tag_counts: Vec::new(),
+            merge_marks: Vec::new(),
+            tag_variants: Vec::new(),
+            tag_manager_index: 0,
+            log: VecDeque::new(),
+            log_selected_index: 0,
+            vcs_status: String::new(),
+            vault_summary: summary,
+            show_clock: config.clock.unwrap_or(true),
+            refresh_keeps_selection: config.refresh_keeps_selection.unwrap_or(true),
+            list_offset: std::cell::Cell::new(0),
+            stale_days: config.stale_days.unwrap_or(DEFAULT_STALE_DAYS),
+            stale_notes: Vec::new(),
+            stale_index: 0,
+            health: VaultHealth::default(),
+            health_index: 0,
+            health_check: HealthCheck::Orphans,
+            health_notes_index: 0,
+            health_review: false,
+            trail: Vec::new(),
+            crumb_cursor: None,
+            edit_fingerprint: None,
+            rng: XorShift::new(chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0) as u64),
+            last_random_id: None,
+        };
+        if config.spellcheck {
+            app.toggle_spellcheck();
+        }
+        if let Some(e) = read_only {
+            app.set_status(format!("⚠ {} — opened read-only, changes are disabled", e));
+        } else if app.state.is_first_run(app.notes.len()) {
+            app.tutorial = Some(TutorialStep::Welcome);
+        }
+        app.refresh_vcs_status();
+        Ok(app)
+    }
+
+    /// Turn View-mode spell checking on or off, loading the dictionary on first use
+    fn toggle_spellcheck(&mut self) {
+        if self.spellcheck_enabled {
+            self.spellcheck_enabled = false;
+            self.set_status("ℹ Spell check off".to_string());
+            return;
+        }
+        if self.dictionary.is_none() {
+            match Dictionary::load_default(self.dictionary_path.as_deref()) {
+                Ok(dictionary) => self.dictionary = Some(dictionary),
+                Err(e) => {
+                    self.set_status(format!("✗ Spell check unavailable: {}", e));
+                    return;
+                }
+            }
+        }
+        self.spellcheck_enabled = true;
+        self.set_status("ℹ Spell check on".to_string());
+    }
+
+    /// Re-read notes from disk, re-apply the active search and rebuild link counts
+    fn reload_notes(&mut self) -> Result<()> {
+        self.notes = self.service.list_notes()?;
+        self.list_order.sort(&mut self.notes);
+        if self.is_searching {
+            self.filtered_notes = self.run_search(&self.search_query)?;
+        } else {
+            self.filtered_notes = self.notes.clone();
+        }
+        self.backlink_index = self.service.backlink_index(&self.notes);
+        self.vault_summary = vault_summary(&self.notes);
+        let notes = &self.notes;
+        self.selected_ids.retain(|id| notes.iter().any(|note| note.id == *id));
+        self.refresh_vcs_status();
+        Ok(())
+    }
+
+    /// Show a message in the status bar and keep it in the message log
+    fn set_status(&mut self, message: String) {
+        if self.log.len() >= LOG_LIMIT {
+            self.log.pop_front();
+        }
+        self.log.push_back(LogEntry {
+            time: chrono::Local::now().format("%H:%M:%S").to_string(),
+            message: message.clone(),
+        });
+        self.status_message = Some(message);
+    }
+
+    /// Periodic work between key presses: report hooks that failed in the background
+    pub fn tick(&mut self) {
+        let failures = self.hooks.take_failures();
+        if !failures.is_empty() {
+            self.set_status(format!("⚠ {}", failures.join("; ")));
+        }
+        self.poll_job();
+    }
+
+    /// Run `work` on a background thread with a copy of the service. It reports progress
+    /// through the callback it is given, stops early once the flag it is given is set, and
+    /// returns the status message for its outcome.
+    fn start_job(
+        &mut self,
+        verb: &'static str,
+        reload: bool,
+        work: impl FnOnce(&NoteService, &mut dyn FnMut(usize, usize), &AtomicBool) -> String + Send + 'static,
+    ) {
+        let (sender, messages) = mpsc::channel();
+        let service = self.service.clone();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let job_cancel = Arc::clone(&cancel);
+        let thread = std::thread::spawn(move || {
+            let progress_sender = sender.clone();
+            let mut progress = move |done, total| {
+                let _ = progress_sender.send(JobMessage::Progress(done, total));
+            };
+            let status = work(&service, &mut progress, &job_cancel);
+            // The app may already be gone; nothing left to report to then
+            let _ = sender.send(JobMessage::Finished(status));
+        });
+        self.job = Some(BackgroundJob { verb, reload, messages, cancel, thread: Some(thread) });
+        self.status_message = Some(format!("ℹ {} 0 so far…", verb));
+    }
+
+    /// Ask the running job to stop; its outcome is reported once it has
+    fn cancel_job(&mut self) {
+        if let Some(ref job) = self.job {
+            job.cancel.store(true, Ordering::Relaxed);
+            self.status_message = Some("ℹ Cancelling…".to_string());
+        }
+    }
+
+    /// Show the running job's latest progress, and its outcome once it finishes
+    fn poll_job(&mut self) {
+        let Some(ref job) = self.job else {
+            return;
+        };
+        let mut finished = None;
+        for message in job.messages.try_iter() {
+            match message {
+                // Keep saying it is cancelling until it has
+                JobMessage::Progress(..) if job.cancel.load(Ordering::Relaxed) => {}
+                JobMessage::Progress(done, total) => {
+                    self.status_message = Some(format!("ℹ {} {}/{}…", job.verb, done, total));
+                }
+                JobMessage::Finished(status) => finished = Some(status),
+            }
+        }
+        let Some(status) = finished else {
+            return;
+        };
+        let reload = job.reload;
+        self.job = None;
+        if reload {
+            if let Err(e) = self.reload_notes() {
+                self.set_status(format!("✗ Failed to reload notes: {}", e));
+                return;
+            }
+            self.selected_index = 0;
+            self.refresh_vcs_status();
+        }
+        self.set_status(status);
+    }
+
+    /// Run the configured hook for a note change
+    fn fire_hook(&self, event: HookEvent, note_id: &str) {
+        self.hooks.fire(event, note_id, &self.service.note_path(note_id));
+    }
+
+    /// Re-read the repo state shown in the title bar
+    fn refresh_vcs_status(&mut self) {
+        // jj may need to write to the repo even to report its state
+        if self.service.is_read_only() {
+            self.vcs_status = "🔒 read-only".to_string();
+            return;
+        }
+        self.vcs_status = self
+            .service
+            .vcs_status()
+            .unwrap_or_else(|_| "? status unavailable".to_string());
+    }
+
+    /// Bordered block for the title bar, with the repo state on the right
+    fn title_block(&self) -> Block<'_> {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(" ADEPTUS ADMINISTRATUM ")
+            .title(Line::from(format!(" {} ", self.vcs_status)).right_aligned());
+        if !self.show_clock {
+            return block;
+        }
+        let clock = format!(" {} · {} ", chrono::Local::now().format("%H:%M"), self.vault_summary);
+        block.title_bottom(Line::from(Span::styled(clock, Style::default().fg(Color::DarkGray))).right_aligned())
+    }
+
+    /// Notes linking to the given note, resolved from the cached backlink index
+    fn backlinks_of(&self, note_id: &str) -> Vec<Note> {
+        self.backlink_index
+            .get(note_id)
+            .map(|ids| {
+                ids.iter()
+                    .filter_map(|id| self.notes.iter().find(|n| n.id == *id))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
     }
 
     pub fn handle_key(&mut self, key: crossterm::event::KeyCode, modifiers: crossterm::event::KeyModifiers) -> Result<()> {
+        // A running bulk job works on the vault until it is done or cancelled
+        if self.job.is_some() {
+            if key == crossterm::event::KeyCode::Esc {
+                self.cancel_job();
+            }
+            return Ok(());
+        }
+        let end_tour = key == crossterm::event::KeyCode::Char('t') && modifiers.contains(crossterm::event::KeyModifiers::CONTROL);
+        if let Some(step) = self.tutorial
+            && (step.is_modal() || end_tour)
+        {
+            if end_tour {
+                self.finish_tutorial();
+                return Ok(());
+            }
+            return self.handle_tutorial_key(step, key);
+        }
+        self.handle_mode_key(key, modifiers)?;
+        self.advance_tutorial();
+        Ok(())
+    }
+
+    fn handle_mode_key(&mut self, key: crossterm::event::KeyCode, modifiers: crossterm::event::KeyModifiers) -> Result<()> {
+        // Quick capture works from every mode and leaves it untouched
+        if self.capture_input.is_some() {
+            return self.handle_capture_key(key);
+        }
+        if let Some(context) = self.help_overlay {
+            self.handle_help_overlay_key(context, key);
+            return Ok(());
+        }
+        if key == crossterm::event::KeyCode::Char('n') && modifiers.contains(crossterm::event::KeyModifiers::CONTROL) {
+            if !self.refuse_read_only() {
+                self.capture_input = Some(String::new());
+            }
+            return Ok(());
+        }
         match self.mode {
-            AppMode::List => self.handle_list_key(key)?,
-            AppMode::View => self.handle_view_key(key)?,
+            AppMode::List => self.handle_list_key(key, modifiers)?,
+            AppMode::View => self.handle_view_key(key, modifiers)?,
             AppMode::Edit => self.handle_edit_key(key, modifiers)?,
+            AppMode::RawEdit => self.handle_raw_edit_key(key, modifiers)?,
             AppMode::Create => self.handle_create_key(key, modifiers)?,
-            AppMode::Search => self.handle_search_key(key)?,
+            AppMode::Search => self.handle_search_key(key, modifiers)?,
             AppMode::DeleteConfirm => self.handle_delete_confirm_key(key)?,
             AppMode::LinkSelect => self.handle_link_select_key(key)?,
             AppMode::TagAdd => self.handle_tag_add_key(key)?,
             AppMode::UnlinkConfirm => self.handle_unlink_confirm_key(key)?,
             AppMode::TagRemove => self.handle_tag_remove_key(key)?,
             AppMode::Statistics => self.handle_statistics_key(key)?,
+            AppMode::Cycles => self.handle_cycles_key(key)?,
+            AppMode::Dashboard => self.handle_dashboard_key(key)?,
             AppMode::Help => self.handle_help_key(key)?,
             AppMode::History => self.handle_history_key(key)?,
+            AppMode::MetadataEdit => self.handle_metadata_edit_key(key)?,
+            AppMode::MetadataInput => self.handle_metadata_input_key(key)?,
+            AppMode::Prompt(kind) => self.handle_prompt_key(kind, key)?,
+            AppMode::Confirm(kind) => self.handle_confirm_key(kind, key)?,
+            AppMode::TemplateSelect => self.handle_template_select_key(key)?,
+            AppMode::TagGroups => self.handle_tag_groups_key(key)?,
+            AppMode::Log => self.handle_log_key(key),
+            AppMode::RepoLog => self.handle_repo_log_key(key),
+            AppMode::TagManager => self.handle_tag_manager_key(key)?,
+            AppMode::LinkOrder => self.handle_link_order_key(key)?,
+            AppMode::Tasks => self.handle_tasks_key(key)?,
+            AppMode::Due => self.handle_due_key(key)?,
+            AppMode::Stubs => self.handle_stubs_key(key)?,
+            AppMode::Stale => self.handle_stale_key(key)?,
+            AppMode::Health => self.handle_health_key(key)?,
+            AppMode::HealthNotes => self.handle_health_notes_key(key)?,
+            AppMode::Disambiguate => self.handle_disambiguate_key(key)?,
+            AppMode::SavedSearches => self.handle_saved_searches_key(key)?,
         }
         Ok(())
     }
 
-    fn handle_list_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
-        match key {
-            crossterm::event::KeyCode::Esc => {
-                if self.is_searching {
+    fn handle_list_key(&mut self, key: crossterm::event::KeyCode, modifiers: crossterm::event::KeyModifiers) -> Result<()> {
+        self.status_message = None;
+        let Some(action) = self.keymap.action(KeyContext::List, key, modifiers) else {
+            return Ok(());
+        };
+        if action.changes_notes() && self.refuse_read_only() {
+            return Ok(());
+        }
+        match action {
+            Action::Quit => {
+                if !self.selected_ids.is_empty() {
+                    self.selected_ids.clear();
+                    self.set_status("ℹ Selection cleared".to_string());
+                } else if self.is_searching {
                     // Clear search
                     self.is_searching = false;
                     self.search_query.clear();
@@ -100,18 +919,20 @@ impl App {
                     self.should_quit = true;
                 }
             }
-            crossterm::event::KeyCode::Char('/') => {
+            Action::Search => {
                 // Start search
                 self.mode = AppMode::Search;
                 self.input_buffer = String::new();
+                self.search_history_pos = None;
             }
-            crossterm::event::KeyCode::Char('#') => {
+            Action::TagSearch => {
                 // Start tag search
                 self.mode = AppMode::Search;
                 self.input_buffer = String::new();
+                self.search_history_pos = None;
                 self.input_buffer.push('#');
             }
-            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
+            Action::MoveDown => {
                 let max_index = if self.is_searching {
                     self.filtered_notes.len().saturating_sub(1)
                 } else {
@@ -121,65 +942,272 @@ impl App {
                     self.selected_index += 1;
                 }
             }
-            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
-                if self.selected_index > 0 {
-                    self.selected_index -= 1;
+            Action::MoveUp => {
+                self.selected_index = self.selected_index.saturating_sub(1);
+            }
+            Action::ToggleSelect => {
+                let notes_to_use = if self.is_searching { &self.filtered_notes } else { &self.notes };
+                if let Some(id) = notes_to_use.get(self.selected_index).map(|n| n.id.clone())
+                    && !self.selected_ids.remove(&id)
+                {
+                    self.selected_ids.insert(id);
                 }
+                // Move on, so Space can pick several notes in a row
+                let max_index = notes_to_use.len().saturating_sub(1);
+                self.selected_index = (self.selected_index + 1).min(max_index);
             }
-            crossterm::event::KeyCode::Char('n') => {
+            Action::TagSelected | Action::LinkSelected => {
+                if self.selected_ids.is_empty() {
+                    let key = self.keymap.key_hint(KeyContext::List, Action::ToggleSelect);
+                    self.set_status(format!("ℹ Select notes with {} first", key));
+                } else {
+                    self.input_buffer = String::new();
+                    self.mode = AppMode::Prompt(if action == Action::TagSelected {
+                        PromptKind::TagSelected
+                    } else {
+                        PromptKind::LinkSelected
+                    });
+                }
+            }
+            Action::NewNote => {
                 self.mode = AppMode::Create;
                 self.input_buffer = String::new();
+                self.cursor_pos = 0;
             }
-            crossterm::event::KeyCode::Char('d') => {
+            Action::NewFromTemplate => {
+                self.templates = self.service.list_templates()?;
+                if self.templates.is_empty() {
+                    self.set_status(format!(
+                        "ℹ No templates found in {}",
+                        self.service.templates_dir().display()
+                    ));
+                } else {
+                    self.template_selected_index = 0;
+                    self.mode = AppMode::TemplateSelect;
+                }
+            }
+            Action::DeleteNote if !self.selected_ids.is_empty() => {
+                self.mode = AppMode::Confirm(ConfirmKind::DeleteSelected);
+            }
+            Action::DeleteNote => {
                 // Delete note
                 let notes_to_use = if self.is_searching { &self.filtered_notes } else { &self.notes };
-                if let Some(note) = notes_to_use.get(self.selected_index) {
-                    self.current_note = Some(note.clone());
+                if let Some(note) = notes_to_use.get(self.selected_index).cloned()
+                    && !self.refuse_locked(&note)
+                {
+                    self.current_note = Some(note);
                     self.mode = AppMode::DeleteConfirm;
                 }
             }
-            crossterm::event::KeyCode::Char('s') => {
+            Action::ShowStatistics => {
                 // Show statistics
+                match self.service.find_cycles() {
+                    Ok(cycles) => self.cycles = cycles,
+                    Err(e) => {
+                        self.cycles.clear();
+                        self.set_status(format!("✗ Failed to look for link cycles: {}", e));
+                    }
+                }
+                self.cycle_index = 0;
+                self.stubs = self.service.stub_notes(&self.notes);
+                self.stub_index = 0;
+                self.stale_notes = if self.stale_days > 0 {
+                    self.service.stale_notes(&self.notes, self.stale_days, chrono::Utc::now())
+                } else {
+                    Vec::new()
+                };
+                self.stale_index = 0;
+                self.health_review = false;
                 self.mode = AppMode::Statistics;
             }
-            crossterm::event::KeyCode::Char('r') => {
-                // Refresh notes list
-                self.notes = self.service.list_notes()?;
-                if self.is_searching {
-                    self.filtered_notes = self.service.search_notes(&self.search_query)?;
-                } else {
-                    self.filtered_notes = self.notes.clone();
+            Action::VaultHealth => {
+                self.health_index = 0;
+                self.open_health();
+            }
+            Action::Refresh => {
+                // Refresh notes list, following the selected note to wherever it is now and
+                // scrolling with it so it stays on the same row of the screen
+                let notes_before = if self.is_searching { &self.filtered_notes } else { &self.notes };
+                let selected_id = notes_before.get(self.selected_index).map(|n| n.id.clone());
+                let ids_before: HashSet<String> = self.notes.iter().map(|n| n.id.clone()).collect();
+                self.reload_notes()?;
+                
+                let notes_after = if self.is_searching { &self.filtered_notes } else { &self.notes };
+                let moved_to = selected_id
+                    .filter(|_| self.refresh_keeps_selection)
+                    .and_then(|id| notes_after.iter().position(|n| n.id == id));
+                if let Some(index) = moved_to {
+                    self.list_offset.set((self.list_offset.get() + index).saturating_sub(self.selected_index));
                 }
-                self.status_message = Some("✓ Notes refreshed".to_string());
+                self.selected_index = moved_to.unwrap_or(self.selected_index.min(notes_after.len().saturating_sub(1)));
+                
+                let added = self.notes.iter().filter(|n| !ids_before.contains(&n.id)).count();
+                let removed = ids_before.len() - (self.notes.len() - added);
+                self.set_status(match (added, removed) {
+                    (0, 0) => "✓ Notes refreshed, no notes added or removed".to_string(),
+                    _ => format!("✓ Notes refreshed: {} added, {} removed", added, removed),
+                });
             }
-            crossterm::event::KeyCode::Char('c') => {
+            Action::DuplicateNote | Action::DuplicateWithBacklinks => {
                 // Duplicate note
+                let copy_backlinks = action == Action::DuplicateWithBacklinks;
                 let notes_to_use = if self.is_searching { &self.filtered_notes } else { &self.notes };
                 if let Some(note) = notes_to_use.get(self.selected_index) {
-                    match self.service.duplicate_note(&note.id) {
+                    match self.service.duplicate_note(&note.id, copy_backlinks) {
                         Ok(duplicated_note) => {
-                            self.notes = self.service.list_notes()?;
-                            if self.is_searching {
-                                self.filtered_notes = self.service.search_notes(&self.search_query)?;
+                            self.reload_notes()?;
+                            if copy_backlinks {
+                                let inbound = self.backlinks_of(&duplicated_note.id).len();
+                                self.set_status(format!("✓ Duplicated: {} ({} backlink(s) copied)", duplicated_note.title, inbound));
                             } else {
-                                self.filtered_notes = self.notes.clone();
+                                self.set_status(format!("✓ Duplicated: {}", duplicated_note.title));
                             }
-                            self.status_message = Some(format!("✓ Duplicated: {}", duplicated_note.title));
                         }
                         Err(e) => {
-                            self.status_message = Some(format!("✗ Failed to duplicate: {}", e));
+                            self.set_status(format!("✗ Failed to duplicate: {}", e));
                         }
                     }
                 }
             }
-            crossterm::event::KeyCode::Char('?') => {
-                // Show help
-                self.mode = AppMode::Help;
+            Action::BackupVault => {
+                self.input_buffer = format!("jjzettel-backup-{}.tar.gz", chrono::Local::now().format("%Y-%m-%d"));
+                self.mode = AppMode::Prompt(PromptKind::BackupArchive);
             }
-            crossterm::event::KeyCode::Enter => {
+            Action::CommitNow => {
+                self.input_buffer = String::new();
+                self.mode = AppMode::Prompt(PromptKind::CommitNow);
+            }
+            Action::RestoreBackup => {
+                self.input_buffer = String::new();
+                self.mode = AppMode::Prompt(PromptKind::RestoreArchive);
+            }
+            Action::RandomNote => {
+                // Open a random note from the visible set, avoiding the previous pick
+                let notes_to_use = if self.is_searching { &self.filtered_notes } else { &self.notes };
+                let previous = self
+                    .last_random_id
+                    .as_ref()
+                    .and_then(|id| notes_to_use.iter().position(|n| n.id == *id));
+                if let Some(index) = self.rng.pick_index(notes_to_use.len(), previous) {
+                    let note = notes_to_use[index].clone();
+                    self.selected_index = index;
+                    self.last_random_id = Some(note.id.clone());
+                    self.open_note(note);
+                    self.mode = AppMode::View;
+                }
+            }
+            Action::TagGroups => {
+                self.tag_groups = self.service.tag_groups(&self.notes);
+                self.tag_group_index = 0;
+                self.mode = AppMode::TagGroups;
+            }
+            Action::ShowTasks => {
+                self.tasks = self.service.tasks(&self.notes, self.show_done_tasks);
+                self.task_index = 0;
+                self.mode = AppMode::Tasks;
+            }
+            Action::ShowDue => {
+                self.due_notes = self.service.due_notes(&self.notes);
+                self.due_index = 0;
+                self.mode = AppMode::Due;
+            }
+            Action::ManageTags => {
+                self.tag_counts = self.service.tag_counts(&self.notes);
+                self.tag_variants = self.service.case_variant_tags(&self.notes);
+                self.tag_manager_index = 0;
+                self.merge_marks.clear();
+                self.health_review = false;
+                self.mode = AppMode::TagManager;
+                if !self.tag_variants.is_empty() {
+                    self.set_status(format!(
+                        "⚠ {} tags are spelled in more than one case (marked ⚠); n on one normalizes it",
+                        self.tag_variants.len()
+                    ));
+                }
+            }
+            Action::SaveSearch => {
+                if self.is_searching {
+                    self.input_buffer = String::new();
+                    self.mode = AppMode::Prompt(PromptKind::SaveSearch);
+                } else {
+                    self.set_status(format!("ℹ {}, then press {} to save the search", self.search_first_hint(), self.keymap.key_hint(KeyContext::List, Action::SaveSearch)));
+                }
+            }
+            Action::ExportResults => {
+                if !self.selected_ids.is_empty() {
+                    self.input_buffer = "Selected notes".to_string();
+                    self.mode = AppMode::Prompt(PromptKind::ExportSelected);
+                } else if self.is_searching {
+                    self.input_buffer = format!("Search: {}", self.search_query);
+                    self.mode = AppMode::Prompt(PromptKind::ExportResults);
+                } else {
+                    self.set_status(format!("ℹ {}, then press {} to export the results", self.search_first_hint(), self.keymap.key_hint(KeyContext::List, Action::ExportResults)));
+                }
+            }
+            Action::ToggleSearchOrder => self.toggle_search_order()?,
+            Action::ToggleListOrder => {
+                let notes_to_use = if self.is_searching { &self.filtered_notes } else { &self.notes };
+                let selected_id = notes_to_use.get(self.selected_index).map(|n| n.id.clone());
+                self.list_order = self.list_order.next();
+                self.reload_notes()?;
+                self.reselect(selected_id.as_deref());
+                self.set_status(if self.is_searching {
+                    format!("ℹ The list is sorted {} once the search is cleared", self.list_order.describe())
+                } else {
+                    format!("ℹ Notes sorted {}", self.list_order.describe())
+                });
+            }
+            Action::OrderUp | Action::OrderDown => {
+                let notes_to_use = if self.is_searching { &self.filtered_notes } else { &self.notes };
+                if let Some(note) = notes_to_use.get(self.selected_index).cloned() {
+                    // Bumping a note only shows once the list is in manual order
+                    self.list_order = ListOrder::Manual;
+                    self.move_in_order(&note, action == Action::OrderUp)?;
+                    self.reselect(Some(&note.id));
+                }
+            }
+            Action::SavedSearches => {
+                self.saved_search_index = 0;
+                self.mode = AppMode::SavedSearches;
+            }
+            Action::ShowRepoLog => {
+                match self.service.get_repo_log(REPO_LOG_LIMIT) {
+                    Ok(commits) => {
+                        self.repo_log = commits;
+                        self.repo_log_error = None;
+                    }
+                    Err(e) => {
+                        self.repo_log.clear();
+                        self.repo_log_error = Some(e.to_string());
+                    }
+                }
+                self.repo_log_index = 0;
+                self.repo_log_diff = None;
+                self.mode = AppMode::RepoLog;
+            }
+            Action::ShowLog => {
+                self.log_selected_index = self.log.len().saturating_sub(1);
+                self.mode = AppMode::Log;
+            }
+            Action::UndoLast => {
+                if self.last_undo.is_some() {
+                    self.mode = AppMode::Confirm(ConfirmKind::Undo);
+                } else {
+                    self.set_status("ℹ Nothing to undo".to_string());
+                }
+            }
+            Action::ToggleClock => {
+                self.show_clock = !self.show_clock;
+            }
+            Action::ShowDashboard => {
+                self.dashboard_index = 0;
+                self.mode = AppMode::Dashboard;
+            }
+            Action::ShowHelp => self.open_help_overlay(KeyContext::List),
+            Action::Open => {
                 let notes_to_use = if self.is_searching { &self.filtered_notes } else { &self.notes };
                 if let Some(note) = notes_to_use.get(self.selected_index) {
-                    self.current_note = Some(note.clone());
+                    self.open_note(note.clone());
                     self.mode = AppMode::View;
                 }
             }
@@ -188,104 +1216,354 @@ impl App {
         Ok(())
     }
 
-    fn handle_view_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
-        match key {
-            crossterm::event::KeyCode::Esc => {
+    fn handle_view_key(&mut self, key: crossterm::event::KeyCode, modifiers: crossterm::event::KeyModifiers) -> Result<()> {
+        if self.crumb_cursor.is_some() {
+            return self.handle_crumb_key(key);
+        }
+        let action = self.keymap.action(KeyContext::View, key, modifiers);
+        
+        // Unbound digits jump to a numbered link
+        if action.is_none()
+            && let crossterm::event::KeyCode::Char(c) = key
+            && c.is_ascii_digit()
+        {
+            return self.push_link_number(c);
+        }
+        // A pending multi-digit number is finished with Enter or dropped with Esc
+        if !self.link_number_input.is_empty() {
+            let input = std::mem::take(&mut self.link_number_input);
+            match key {
+                crossterm::event::KeyCode::Enter => {
+                    if let Ok(number) = input.parse::<usize>() {
+                        self.open_link_number(number);
+                    }
+                    return Ok(());
+                }
+                crossterm::event::KeyCode::Esc => {
+                    self.status_message = None;
+                    return Ok(());
+                }
+                _ => self.status_message = None,
+            }
+        }
+        
+        let Some(action) = action else {
+            return Ok(());
+        };
+        if action.changes_notes() && self.refuse_read_only() {
+            return Ok(());
+        }
+        if action.edits_locked_note()
+            && let Some(note) = self.current_note.clone()
+            && self.refuse_locked(&note)
+        {
+            return Ok(());
+        }
+        match action {
+            Action::Back if !self.find_matches.is_empty() || !self.find_query.is_empty() => {
+                // First Esc only clears an active find
+                self.clear_find();
+                self.status_message = None;
+            }
+            Action::Back => {
                 self.mode = AppMode::List;
                 self.current_note = None;
                 self.link_selected_index = 0;
                 self.backlink_selected_index = 0;
                 self.status_message = None; // Clear status on exit
             }
-            crossterm::event::KeyCode::Char('e') => {
+            Action::ShowHelp => self.open_help_overlay(KeyContext::View),
+            Action::EditNote | Action::ExternalEdit => {
                 self.mode = AppMode::Edit;
                 if let Some(ref note) = self.current_note {
                     self.input_buffer = note.content.clone();
+                    self.edit_fingerprint = self.service.note_fingerprint(&note.id)?;
                 }
+                self.cursor_pos = self.input_buffer.len();
                 self.status_message = None; // Clear status on action
+                if action == Action::ExternalEdit {
+                    self.open_external_editor();
+                }
             }
-            crossterm::event::KeyCode::Char('l') => {
+            Action::LinkNote => {
                 // Link to another note
                 self.mode = AppMode::LinkSelect;
                 self.selected_index = 0;
                 self.status_message = None; // Clear status on action
             }
-            crossterm::event::KeyCode::Char('t') => {
+            Action::AddTag => {
                 // Add tag
                 self.mode = AppMode::TagAdd;
                 self.input_buffer = String::new();
                 self.status_message = None; // Clear status on action
             }
-            crossterm::event::KeyCode::Char('u') => {
+            Action::UnlinkNote => {
                 // Unlink note (if viewing a linked note)
-                if let Some(ref note) = self.current_note {
-                    if !note.links.is_empty() && self.link_selected_index < note.links.len() {
-                        if let Some(link_id) = note.links.get(self.link_selected_index) {
-                            self.input_buffer = link_id.clone();
-                            self.mode = AppMode::UnlinkConfirm;
-                        }
-                    }
+                if let Some(ref note) = self.current_note
+                    && let Some(link_id) = note.links.get(self.link_selected_index)
+                {
+                    self.input_buffer = link_id.clone();
+                    self.mode = AppMode::UnlinkConfirm;
+                }
+            }
+            Action::ReorderLinks => {
+                if let Some(ref note) = self.current_note
+                    && note.links.len() > 1
+                {
+                    self.link_selected_index = self.link_selected_index.min(note.links.len() - 1);
+                    self.mode = AppMode::LinkOrder;
+                    self.status_message = None;
                 }
             }
-            crossterm::event::KeyCode::Char('x') => {
+            Action::RemoveTag => {
                 // Remove tag (show tag selection)
-                if let Some(ref note) = self.current_note {
-                    if !note.tags.is_empty() {
-                        self.mode = AppMode::TagRemove;
-                        self.selected_index = 0;
-                    }
+                if let Some(ref note) = self.current_note
+                    && !note.tags.is_empty()
+                {
+                    self.mode = AppMode::TagRemove;
+                    self.selected_index = 0;
                 }
             }
-            crossterm::event::KeyCode::Char('E') => {
+            Action::ExportNote => {
                 // Export note to markdown
                 if let Some(ref note) = self.current_note {
-                    let md = self.service.export_note_to_markdown(note);
-                    let filename = format!("{}.md", note.title.replace(" ", "_"));
+                    let md = self.service.export_note_to_markdown(note, &self.backlink_index);
+                    let filename = format!("{}.md", slugify_title(&note.title));
                     match std::fs::write(&filename, md) {
                         Ok(_) => {
-                            self.status_message = Some(format!("✓ Exported to {}", filename));
+                            self.set_status(format!("✓ Exported to {}", filename));
                         }
                         Err(e) => {
-                            self.status_message = Some(format!("✗ Export failed: {}", e));
+                            self.set_status(format!("✗ Export failed: {}", e));
                         }
                     }
                 }
             }
-            crossterm::event::KeyCode::Char('h') => {
-                // Show commit history
-                if let Some(_) = self.current_note {
-                    self.mode = AppMode::History;
-                    self.selected_index = 0;
-                }
+            Action::ExportWithLinks if self.current_note.is_some() => {
+                self.input_buffer = "1".to_string();
+                self.mode = AppMode::Prompt(PromptKind::ExportWithLinks);
+                self.status_message = None;
             }
-            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
-                // Navigate linked notes or backlinks
+            Action::CopyNote => {
                 if let Some(ref note) = self.current_note {
-                    // Check if we have backlinks to navigate
-                    if let Ok(backlinks) = self.service.get_backlinks(&note.id) {
-                        if !backlinks.is_empty() && self.backlink_selected_index < backlinks.len() {
-                            self.backlink_selected_index += 1;
-                            return Ok(());
-                        }
-                    }
-                    // Otherwise navigate forward links
-                    if !note.links.is_empty() {
-                        let max_index = note.links.len().saturating_sub(1);
-                        if self.link_selected_index < max_index {
-                            self.link_selected_index += 1;
-                        }
+                    let md = self.service.export_note_to_markdown(note, &self.backlink_index);
+                    let title = note.title.clone();
+                    match clipboard::write(&md) {
+                        Ok(()) => self.set_status(format!("✓ Copied '{}' as markdown", title)),
+                        Err(e) => self.set_status(format!("✗ Copy failed: {}", e)),
                     }
                 }
             }
-            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
-                // Navigate linked notes or backlinks
+            Action::EditMetadata if self.current_note.is_some() => {
+                // Edit custom metadata fields
+                self.mode = AppMode::MetadataEdit;
+                self.selected_index = 0;
+                self.status_message = None;
+            }
+            Action::RenameNote => {
+                // Rename note, starting from the current title
                 if let Some(ref note) = self.current_note {
-                    // Check if we're in backlinks section
-                    if let Ok(backlinks) = self.service.get_backlinks(&note.id) {
-                        if !backlinks.is_empty() && self.backlink_selected_index > 0 {
-                            self.backlink_selected_index -= 1;
+                    self.input_buffer = note.title.clone();
+                    self.mode = AppMode::Prompt(PromptKind::RenameNote);
+                    self.status_message = None;
+                }
+            }
+            Action::EditScratch => {
+                if let Some(ref note) = self.current_note {
+                    self.input_buffer = note.scratch.clone().unwrap_or_default();
+                    self.mode = AppMode::Prompt(PromptKind::Scratch);
+                    self.status_message = None;
+                }
+            }
+            Action::SetDue => {
+                if let Some(ref note) = self.current_note {
+                    // Offer the current due date in the short local form it can be typed in
+                    self.input_buffer = note
+                        .due_at()
+                        .map(|due| due.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                        .unwrap_or_default();
+                    self.mode = AppMode::Prompt(PromptKind::Due);
+                    self.status_message = None;
+                }
+            }
+            Action::EditRawJson => {
+                if let Some(ref note) = self.current_note {
+                    match self.service.raw_note_json(&note.id) {
+                        Ok(json) => {
+                            self.input_buffer = json;
+                            self.cursor_pos = 0;
+                            self.mode = AppMode::RawEdit;
+                            self.status_message = None;
+                        }
+                        Err(e) => self.set_status(format!("✗ Could not read the note's JSON: {}", e)),
+                    }
+                }
+            }
+            Action::TogglePin => {
+                if let Some(ref note) = self.current_note {
+                    let pinned = self.state.toggle_pin(&note.id);
+                    let title = note.title.clone();
+                    match self.state.save() {
+                        Ok(()) if pinned => self.set_status(format!("✓ Pinned '{}' to the dashboard", title)),
+                        Ok(()) => self.set_status(format!("✓ Unpinned '{}'", title)),
+                        Err(e) => self.set_status(format!("✗ Could not save state: {}", e)),
+                    }
+                }
+            }
+            Action::ToggleLock => {
+                if let Some(note) = self.current_note.clone() {
+                    let locked = match self.service.set_locked(&note.id, !note.locked) {
+                        Ok(locked) => locked,
+                        Err(e) => {
+                            self.set_status(format!("✗ Failed to change the lock: {}", e));
+                            return Ok(());
+                        }
+                    };
+                    self.set_status(if locked.locked {
+                        let unlock = match self.keymap.key_label(KeyContext::View, Action::ToggleLock) {
+                            Some(key) => format!("until {} unlocks it", key),
+                            None => "until it is unlocked".to_string(),
+                        };
+                        format!("🔒 Locked '{}'; editing, tagging, linking and deleting it are refused {}", locked.title, unlock)
+                    } else {
+                        format!("✓ Unlocked '{}'", locked.title)
+                    });
+                    self.current_note = Some(locked);
+                    self.reload_notes()?;
+                }
+            }
+            Action::OrderUp | Action::OrderDown => {
+                if let Some(note) = self.current_note.clone() {
+                    let moved = self.move_in_order(&note, action == Action::OrderUp)?;
+                    self.current_note = Some(moved);
+                }
+            }
+            Action::ToggleLineBreaks => {
+                if let Some(note) = self.current_note.clone() {
+                    let updated = match self.service.set_strict_line_breaks(&note.id, !note.strict_line_breaks) {
+                        Ok(updated) => updated,
+                        Err(e) => {
+                            self.set_status(format!("✗ Failed to change line breaks: {}", e));
+                            return Ok(());
+                        }
+                    };
+                    self.set_status(if updated.strict_line_breaks {
+                        "✓ Line breaks shown as written".to_string()
+                    } else {
+                        "✓ Lines within a paragraph joined".to_string()
+                    });
+                    self.current_note = Some(updated);
+                    self.reload_notes()?;
+                }
+            }
+            Action::MoveNote if self.current_note.is_some() => {
+                self.input_buffer = String::new();
+                self.mode = AppMode::Prompt(PromptKind::MoveToVault);
+                self.status_message = None;
+            }
+            Action::CreateLinkedNote => {
+                // New note that the current note will link to once saved
+                if let Some(ref note) = self.current_note {
+                    self.link_source_id = Some(note.id.clone());
+                    self.input_buffer = String::new();
+                    self.cursor_pos = 0;
+                    self.mode = AppMode::Create;
+                    self.status_message = None;
+                }
+            }
+            Action::FindInNote if self.current_note.is_some() => {
+                self.input_buffer = self.find_query.clone();
+                self.mode = AppMode::Prompt(PromptKind::FindInNote);
+                self.status_message = None;
+            }
+            Action::NextMatch if !self.find_matches.is_empty() => {
+                self.find_index = (self.find_index + 1) % self.find_matches.len();
+                self.scroll_to_match();
+            }
+            Action::PrevMatch if !self.find_matches.is_empty() => {
+                self.find_index = (self.find_index + self.find_matches.len() - 1) % self.find_matches.len();
+                self.scroll_to_match();
+            }
+            Action::ScrollDown => {
+                self.view_scroll = self.view_scroll.saturating_add(10);
+            }
+            Action::ScrollUp => {
+                self.view_scroll = self.view_scroll.saturating_sub(10);
+            }
+            Action::ScrollRight if self.wrap_mode == WrapMode::Off => {
+                self.view_hscroll = self.view_hscroll.saturating_add(8);
+            }
+            Action::ScrollLeft if self.wrap_mode == WrapMode::Off => {
+                self.view_hscroll = self.view_hscroll.saturating_sub(8);
+            }
+            Action::ToggleSpellcheck => {
+                self.toggle_spellcheck();
+            }
+            Action::CycleLinkLabels => {
+                self.link_labels = self.link_labels.next();
+                self.set_status(format!("ℹ Links show {}", self.link_labels.describe()));
+            }
+            Action::CycleHeader => {
+                self.view_header = self.view_header.next();
+                self.set_status(format!("ℹ Note header shown {}", self.view_header.describe()));
+                // Find jumps scroll to content lines, which moved with the header
+                if !self.find_matches.is_empty() {
+                    self.scroll_to_match();
+                }
+            }
+            Action::CycleColor => {
+                if let Some(ref note) = self.current_note {
+                    let updated = match self.service.set_color(&note.id, note.next_color()) {
+                        Ok(updated) => updated,
+                        Err(e) => {
+                            self.set_status(format!("✗ Failed to set the color label: {}", e));
                             return Ok(());
                         }
+                    };
+                    self.set_status(match updated.color {
+                        Some(ref color) => format!("✓ Color label: {}", color),
+                        None => "✓ Color label cleared".to_string(),
+                    });
+                    self.current_note = Some(updated);
+                    self.reload_notes()?;
+                }
+            }
+            Action::ShowHistory if self.current_note.is_some() => {
+                // Show commit history
+                self.load_history();
+                self.mode = AppMode::History;
+                self.selected_index = 0;
+            }
+            Action::MoveDown => {
+                // Navigate linked notes or backlinks
+                if let Some(ref note) = self.current_note {
+                    // Check if we have backlinks to navigate
+                    let backlinks = self.backlinks_of(&note.id);
+                    if !backlinks.is_empty()
+                        && self.backlink_selected_index < backlinks.len()
+                    {
+                        self.backlink_selected_index += 1;
+                        return Ok(());
+                    }
+                    // Otherwise navigate forward links
+                    if !note.links.is_empty() {
+                        let max_index = note.links.len().saturating_sub(1);
+                        if self.link_selected_index < max_index {
+                            self.link_selected_index += 1;
+                        }
+                    }
+                }
+            }
+            Action::MoveUp => {
+                // Navigate linked notes or backlinks
+                if let Some(ref note) = self.current_note {
+                    // Check if we're in backlinks section
+                    let backlinks = self.backlinks_of(&note.id);
+                    if !backlinks.is_empty()
+                        && self.backlink_selected_index > 0
+                    {
+                        self.backlink_selected_index -= 1;
+                        return Ok(());
                     }
                     // Otherwise navigate forward links
                     if !note.links.is_empty() && self.link_selected_index > 0 {
@@ -293,232 +1571,835 @@ impl App {
                     }
                 }
             }
-            crossterm::event::KeyCode::Enter => {
+            Action::Open => {
                 // Navigate to selected note (backlink or forward link)
                 if let Some(ref note) = self.current_note {
                     // Check if we have a selected backlink
-                    if let Ok(backlinks) = self.service.get_backlinks(&note.id) {
-                        if !backlinks.is_empty() {
-                            if let Some(backlink) = backlinks.get(self.backlink_selected_index) {
-                                self.current_note = Some(backlink.clone());
-                                self.link_selected_index = 0;
-                                self.backlink_selected_index = 0;
-                                self.status_message = None;
-                                return Ok(());
-                            }
-                        }
+                    if let Some(backlink) = self.backlinks_of(&note.id).get(self.backlink_selected_index)
+                    {
+                        self.follow_link(backlink.clone());
+                        return Ok(());
                     }
                     // Otherwise navigate to forward link
-                    if let Some(link_id) = note.links.get(self.link_selected_index) {
-                        if let Ok(Some(linked_note)) = self.service.get_note(link_id) {
-                            self.current_note = Some(linked_note);
-                            self.link_selected_index = 0;
-                            self.backlink_selected_index = 0;
-                            self.status_message = None;
-                        }
+                    if let Some(link_id) = note.links.get(self.link_selected_index)
+                        && let Ok(Some(linked_note)) = self.service.get_note(link_id)
+                    {
+                        self.follow_link(linked_note);
                     }
                 }
             }
+            Action::TrailBack if self.trail.len() > 1 => {
+                self.jump_to_crumb(self.trail.len() - 2)?;
+            }
+            Action::PickBreadcrumb if self.trail.len() > 1 => {
+                self.crumb_cursor = Some(self.trail.len() - 2);
+                self.status_message = Some("ℹ ←/→: pick a note | Enter: go back to it | Esc: cancel".to_string());
+            }
+            Action::TrailBack | Action::PickBreadcrumb => {
+                self.set_status("ℹ No breadcrumb trail yet; follow a link with Enter or 1-9".to_string());
+            }
             _ => {}
         }
         Ok(())
     }
 
-    fn handle_edit_key(&mut self, key: crossterm::event::KeyCode, modifiers: crossterm::event::KeyModifiers) -> Result<()> {
-        match key {
-            crossterm::event::KeyCode::Esc => {
-                self.mode = AppMode::View;
-            }
-            crossterm::event::KeyCode::Char('s') if modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
-                // Ctrl+S to save
-                if let Some(ref mut note) = self.current_note {
-                    *note = self.service.update_note(note.clone(), self.input_buffer.clone())?;
-                    self.mode = AppMode::View;
-                    // Refresh notes list
-                    self.notes = self.service.list_notes()?;
-                    if self.is_searching {
-                        self.filtered_notes = self.service.search_notes(&self.search_query)?;
-                    } else {
-                        self.filtered_notes = self.notes.clone();
-                    }
-                }
-            }
-            crossterm::event::KeyCode::Char(c) => {
-                self.input_buffer.push(c);
+    /// Forward links of a note that resolve to existing notes, in display order
+    fn forward_link_notes(&self, note: &Note) -> Vec<Note> {
+        note.links
+            .iter()
+            .filter_map(|link_id| self.service.get_note(link_id).ok().flatten())
+            .collect()
+    }
+    
+    /// Label of a linked note in the link sections, per `link_labels`
+    fn link_label(&self, note: &Note) -> String {
+        match self.link_labels {
+            LinkLabels::Titles => note.title.clone(),
+            LinkLabels::TitlesWithIds => {
+                let length = if self.short_id_length > 0 { self.short_id_length } else { DEFAULT_SHORT_ID_LENGTH };
+                format!("{} ({})", note.title, note.id.chars().take(length).collect::<String>())
             }
-            crossterm::event::KeyCode::Backspace => {
-                self.input_buffer.pop();
+            LinkLabels::Ids => note.id.clone(),
+        }
+    }
+    
+    /// All numbered link targets of the current note: forward links, then backlinks
+    fn numbered_links(&self) -> Vec<Note> {
+        let Some(ref note) = self.current_note else {
+            return Vec::new();
+        };
+        let mut targets = self.forward_link_notes(note);
+        targets.extend(self.backlinks_of(&note.id));
+        targets
+    }
+    
+    /// Add a digit to the link number; opens right away once no longer number is possible.
+    /// Numbers start at 1, so a leading 0 is out of range at once.
+    fn push_link_number(&mut self, digit: char) -> Result<()> {
+        let total = self.numbered_links().len();
+        if total == 0 {
+            return Ok(());
+        }
+        self.link_number_input.push(digit);
+        let number: usize = self.link_number_input.parse().unwrap_or(0);
+        if number == 0 || number * 10 > total {
+            self.link_number_input.clear();
+            self.open_link_number(number);
+        } else {
+            self.status_message = Some(format!("ℹ Go to link {}… (Enter to open)", self.link_number_input));
+        }
+        Ok(())
+    }
+    
+    /// Open the 1-based numbered link of the current note
+    fn open_link_number(&mut self, number: usize) {
+        let targets = self.numbered_links();
+        match number.checked_sub(1).and_then(|i| targets.into_iter().nth(i)) {
+            Some(target) => {
+                self.follow_link(target);
             }
-            crossterm::event::KeyCode::Enter => {
-                self.input_buffer.push('\n');
+            None => {
+                self.set_status(format!("✗ No link #{}", number));
             }
-            _ => {}
         }
-        Ok(())
     }
 
-    fn handle_search_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+    /// Keys while picking a breadcrumb: move along the trail, jump back or cancel
+    fn handle_crumb_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        let Some(cursor) = self.crumb_cursor else {
+            return Ok(());
+        };
         match key {
-            crossterm::event::KeyCode::Esc => {
-                self.mode = AppMode::List;
-                self.input_buffer.clear();
-                self.is_searching = false;
-                self.search_query.clear();
-                self.filtered_notes = self.notes.clone();
-                self.selected_index = 0;
+            crossterm::event::KeyCode::Left | crossterm::event::KeyCode::Char('h') => {
+                self.crumb_cursor = Some(cursor.saturating_sub(1));
             }
-            crossterm::event::KeyCode::Enter => {
-                // Apply search
-                if self.input_buffer.trim().is_empty() {
-                    self.is_searching = false;
-                    self.search_query.clear();
-                    self.filtered_notes = self.notes.clone();
-                } else {
-                    self.search_query = self.input_buffer.clone();
-                    self.filtered_notes = self.service.search_notes(&self.input_buffer)?;
-                    self.is_searching = true;
-                }
-                self.selected_index = 0;
-                self.input_buffer.clear();
-                self.mode = AppMode::List;
+            crossterm::event::KeyCode::Right | crossterm::event::KeyCode::Char('l') => {
+                self.crumb_cursor = Some((cursor + 1).min(self.trail.len().saturating_sub(1)));
             }
-            crossterm::event::KeyCode::Char(c) => {
-                self.input_buffer.push(c);
-                // Live search as you type
-                if !self.input_buffer.trim().is_empty() {
-                    self.filtered_notes = self.service.search_notes(&self.input_buffer)?;
-                    self.is_searching = true;
-                } else {
-                    self.filtered_notes = self.notes.clone();
-                    self.is_searching = false;
-                }
-                self.selected_index = 0;
+            crossterm::event::KeyCode::Enter => {
+                self.crumb_cursor = None;
+                self.jump_to_crumb(cursor)?;
             }
-            crossterm::event::KeyCode::Backspace => {
-                self.input_buffer.pop();
-                // Live search as you type
-                if !self.input_buffer.trim().is_empty() {
-                    self.filtered_notes = self.service.search_notes(&self.input_buffer)?;
-                    self.is_searching = true;
-                } else {
-                    self.filtered_notes = self.notes.clone();
-                    self.is_searching = false;
-                }
-                self.selected_index = 0;
+            crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Char('b') => {
+                self.crumb_cursor = None;
+                self.status_message = None;
             }
             _ => {}
         }
         Ok(())
     }
 
-    fn handle_delete_confirm_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
-        match key {
-            crossterm::event::KeyCode::Char('y') | crossterm::event::KeyCode::Enter => {
-                // Confirm deletion
-                if let Some(ref note) = self.current_note {
-                    self.service.delete_note(&note.id)?;
-                    // Refresh notes
-                    self.notes = self.service.list_notes()?;
-                    if self.is_searching {
-                        self.filtered_notes = self.service.search_notes(&self.search_query)?;
-                    } else {
-                        self.filtered_notes = self.notes.clone();
-                    }
-                    // Adjust selected index
-                    if self.selected_index >= self.filtered_notes.len() && !self.filtered_notes.is_empty() {
-                        self.selected_index = self.filtered_notes.len() - 1;
-                    }
-                }
-                self.mode = AppMode::List;
-                self.current_note = None;
-            }
-            crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Char('n') => {
-                // Cancel deletion
-                self.mode = AppMode::List;
-                self.current_note = None;
+    /// Go back to the note at `index` on the breadcrumb trail, dropping the crumbs after it
+    fn jump_to_crumb(&mut self, index: usize) -> Result<()> {
+        let Some(id) = self.trail.get(index).cloned() else {
+            return Ok(());
+        };
+        match self.service.get_note(&id)? {
+            Some(note) => self.open_note(note),
+            None => {
+                self.trail.remove(index);
+                self.set_status("✗ That note no longer exists; it was taken off the trail".to_string());
             }
-            _ => {}
         }
         Ok(())
     }
 
-    fn handle_link_select_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+    /// Open a note reached by following a link, adding it to the breadcrumb trail
+    fn follow_link(&mut self, note: Note) {
+        if let Some(ref current) = self.current_note
+            && self.trail.last() != Some(&current.id)
+        {
+            self.trail = vec![current.id.clone()];
+        }
+        if !self.trail.contains(&note.id) {
+            self.trail.push(note.id.clone());
+        }
+        self.open_note(note);
+    }
+
+    /// Title of a note on the breadcrumb trail
+    fn crumb_title(&self, id: &str) -> String {
+        match self.notes.iter().find(|note| note.id == id) {
+            Some(note) => truncate_chars(&note.title, 24),
+            None => id.chars().take(8).collect(),
+        }
+    }
+
+    /// Show another note in View mode, resetting per-note view state. Coming back to a note
+    /// on the breadcrumb trail keeps the trail up to it; any other note starts a new one.
+    fn open_note(&mut self, note: Note) {
+        match self.trail.iter().position(|id| *id == note.id) {
+            Some(i) => self.trail.truncate(i + 1),
+            None => self.trail = vec![note.id.clone()],
+        }
+        self.crumb_cursor = None;
+        self.current_note = Some(note);
+        self.link_selected_index = 0;
+        self.backlink_selected_index = 0;
+        self.status_message = None;
+        self.view_scroll = 0;
+        self.view_hscroll = 0;
+        self.clear_find();
+    }
+    
+    /// Rows of the note's content in View mode when paragraph mode joins its soft breaks,
+    /// `None` when it is shown line for line
+    fn paragraph_rows(&self, note: &Note) -> Option<Vec<std::ops::Range<usize>>> {
+        (self.paragraphs && !note.strict_line_breaks).then(|| render::paragraph_rows(&note.content))
+    }
+
+    fn clear_find(&mut self) {
+        self.find_query.clear();
+        self.find_matches.clear();
+        self.find_index = 0;
+    }
+    
+    /// Scroll the View content so the current find match is visible and report its position
+    fn scroll_to_match(&mut self) {
+        let (Some(note), Some(found)) = (&self.current_note, self.find_matches.get(self.find_index)) else {
+            return;
+        };
+        // Keep a couple of lines of context above the match
+        let row = match self.paragraph_rows(note) {
+            Some(rows) => rows.iter().position(|row| row.contains(&found.line)).unwrap_or(found.line),
+            None => found.line,
+        };
+        let line = view_content_offset(note, self.view_header) + row;
+        self.view_scroll = line.saturating_sub(2) as u16;
+        self.status_message = Some(format!(
+            "ℹ Match {}/{} for \"{}\" (n/N: next/previous, Esc: clear)",
+            self.find_index + 1,
+            self.find_matches.len(),
+            self.find_query
+        ));
+    }
+
+    fn handle_edit_key(&mut self, key: crossterm::event::KeyCode, modifiers: crossterm::event::KeyModifiers) -> Result<()> {
+        self.status_message = None;
         match key {
             crossterm::event::KeyCode::Esc => {
-                self.mode = AppMode::View;
+                self.leave_edit();
             }
-            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
-                let max_index = self.notes.len().saturating_sub(1);
-                if self.selected_index < max_index {
-                    self.selected_index += 1;
-                }
+            crossterm::event::KeyCode::Char('v') if modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
+                self.paste_clipboard();
             }
-            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
-                if self.selected_index > 0 {
-                    self.selected_index -= 1;
-                }
+            crossterm::event::KeyCode::Char('s') if modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
+                // Ctrl+S to save
+                self.request_edit_save()?;
+            }
+            crossterm::event::KeyCode::Char('g') if modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
+                self.tag_word_at_cursor(false)?;
+            }
+            crossterm::event::KeyCode::Char('e') if modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
+                self.open_external_editor();
+            }
+            crossterm::event::KeyCode::Char('g') if modifiers.contains(crossterm::event::KeyModifiers::ALT) => {
+                self.tag_word_at_cursor(true)?;
+            }
+            crossterm::event::KeyCode::Char(c) => {
+                self.insert_at_cursor(c);
+            }
+            crossterm::event::KeyCode::Backspace => {
+                self.delete_before_cursor();
             }
             crossterm::event::KeyCode::Enter => {
-                // Link current note to selected note
-                if let Some(ref current_note) = self.current_note {
-                    if let Some(target_note) = self.notes.get(self.selected_index) {
-                        if current_note.id != target_note.id {
-                            self.service.link_notes(&current_note.id, &target_note.id)?;
-                            // Refresh current note
-                            if let Some(updated_note) = self.service.get_note(&current_note.id)? {
-                                self.current_note = Some(updated_note);
-                            }
-                            // Refresh notes list
-                            self.notes = self.service.list_notes()?;
-                            if self.is_searching {
-                                self.filtered_notes = self.service.search_notes(&self.search_query)?;
-                            } else {
-                                self.filtered_notes = self.notes.clone();
-                            }
-                            self.status_message = Some("✓ Note linked".to_string());
-                        }
-                    }
-                }
-                self.mode = AppMode::View;
+                self.insert_at_cursor('\n');
+            }
+            crossterm::event::KeyCode::Delete => {
+                self.delete_at_cursor();
             }
+            crossterm::event::KeyCode::Left
+            | crossterm::event::KeyCode::Right
+            | crossterm::event::KeyCode::Up
+            | crossterm::event::KeyCode::Down
+            | crossterm::event::KeyCode::Home
+            | crossterm::event::KeyCode::End => self.move_cursor(key),
             _ => {}
         }
         Ok(())
     }
 
-    fn handle_tag_add_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+    /// Keys while editing a note's raw JSON: the Edit mode keys, minus tagging
+    fn handle_raw_edit_key(&mut self, key: crossterm::event::KeyCode, modifiers: crossterm::event::KeyModifiers) -> Result<()> {
+        let ctrl = modifiers.contains(crossterm::event::KeyModifiers::CONTROL);
         match key {
             crossterm::event::KeyCode::Esc => {
                 self.mode = AppMode::View;
-                self.input_buffer = String::new();
+                self.set_status("ℹ JSON edit discarded".to_string());
             }
-            crossterm::event::KeyCode::Enter => {
-                // Add tag
-                if let Some(ref mut note) = self.current_note {
-                    let tag = self.input_buffer.trim().to_string();
-                    if !tag.is_empty() {
-                        let updated_note = self.service.add_tag(&note.id, tag)?;
-                        self.current_note = Some(updated_note);
-                        // Refresh notes list
-                        self.notes = self.service.list_notes()?;
-                        if self.is_searching {
-                            self.filtered_notes = self.service.search_notes(&self.search_query)?;
-                        } else {
-                            self.filtered_notes = self.notes.clone();
-                        }
-                        self.status_message = Some("✓ Tag added".to_string());
-                    }
-                }
-                self.input_buffer = String::new();
+            crossterm::event::KeyCode::Char('s') if ctrl => self.save_raw_json()?,
+            crossterm::event::KeyCode::Char('g') if ctrl || modifiers.contains(crossterm::event::KeyModifiers::ALT) => {}
+            _ => self.handle_edit_key(key, modifiers)?,
+        }
+        Ok(())
+    }
+
+    /// Ctrl+S while editing raw JSON: save it if it is a valid note, otherwise keep editing
+    fn save_raw_json(&mut self) -> Result<()> {
+        let Some(note_id) = self.current_note.as_ref().map(|note| note.id.clone()) else {
+            return Ok(());
+        };
+        match self.service.save_raw_note(&note_id, &self.input_buffer) {
+            Ok(saved) => {
+                self.current_note = Some(saved);
                 self.mode = AppMode::View;
+                self.fire_hook(HookEvent::Update, &note_id);
+                self.set_status("✓ Note JSON saved".to_string());
+                self.clear_find();
+                self.reload_notes()?;
             }
-            crossterm::event::KeyCode::Char(c) => {
-                self.input_buffer.push(c);
+            Err(e) => self.set_status(format!("✗ {} — fix it and save again, or Esc to discard", e)),
+        }
+        Ok(())
+    }
+
+    /// Ctrl+G in Edit mode: tag the note with the word at the caret. With `cut` (Alt+G) the
+    /// word, and a `#` in front of it, is also taken out of the text. The tag is written right
+    /// away like `t` in View mode; the text change waits for the save as usual.
+    fn tag_word_at_cursor(&mut self, cut: bool) -> Result<()> {
+        let Some(note_id) = self.current_note.as_ref().map(|note| note.id.clone()) else {
+            return Ok(());
+        };
+        let Some(range) = word_range(&self.input_buffer, self.cursor_pos) else {
+            self.set_status("ℹ No word at the cursor".to_string());
+            return Ok(());
+        };
+        let tag = self.input_buffer[range.clone()].to_string();
+        
+        // Writing the tag changes the file; that must not look like an outside change on save
+        let unchanged_on_disk = self.service.note_fingerprint(&note_id)? == self.edit_fingerprint;
+        let tagged = match self.service.add_tag(&note_id, tag.clone()) {
+            Ok(tagged) => tagged,
+            Err(e) => {
+                self.set_status(format!("✗ Failed to add tag: {}", e));
+                return Ok(());
             }
-            crossterm::event::KeyCode::Backspace => {
-                self.input_buffer.pop();
+        };
+        if unchanged_on_disk {
+            self.edit_fingerprint = self.service.note_fingerprint(&note_id)?;
+        }
+        let tag_lower = tag.to_lowercase();
+        let already_tagged = self
+            .current_note
+            .as_ref()
+            .is_some_and(|note| note.tags.iter().any(|t| t.to_lowercase() == tag_lower));
+        // The buffer keeps the unsaved edits; only the tags come from the saved note
+        if let Some(ref mut note) = self.current_note {
+            note.tags = tagged.tags;
+            note.updated_at = tagged.updated_at;
+        }
+        
+        if cut {
+            let mut start = range.start;
+            if self.input_buffer[..start].ends_with('#') {
+                start -= 1;
             }
-            _ => {}
+            let mut end = range.end;
+            // Drop one of the spaces around the word so none are doubled
+            if self.input_buffer[end..].starts_with(' ')
+                && (start == 0 || self.input_buffer[..start].ends_with([' ', '\n']))
+            {
+                end += 1;
+            } else if self.input_buffer[..start].ends_with(' ') {
+                start -= 1;
+            }
+            self.input_buffer.replace_range(start..end, "");
+            self.cursor_pos = start;
+        }
+        
+        self.set_status(match (already_tagged, cut) {
+            (true, false) => format!("ℹ Already tagged #{}", tag),
+            (true, true) => format!("ℹ Already tagged #{}; removed it from the text", tag),
+            (false, false) => format!("✓ Tagged #{}", tag),
+            (false, true) => format!("✓ Tagged #{} and removed it from the text", tag),
+        });
+        Ok(())
+    }
+
+    /// Insert a character at the caret in the edit buffer
+    fn insert_at_cursor(&mut self, c: char) {
+        self.cursor_pos = self.cursor_pos.min(self.input_buffer.len());
+        self.input_buffer.insert(self.cursor_pos, c);
+        self.cursor_pos += c.len_utf8();
+    }
+    
+    /// Insert text at the caret in the edit buffer, normalizing line endings
+    fn insert_text_at_cursor(&mut self, text: &str) {
+        let text = clipboard::normalize(text);
+        self.cursor_pos = self.cursor_pos.min(self.input_buffer.len());
+        self.input_buffer.insert_str(self.cursor_pos, &text);
+        self.cursor_pos += text.len();
+    }
+
+    /// Ctrl+V in Edit/Create mode: insert the system clipboard at the caret
+    fn paste_clipboard(&mut self) {
+        match clipboard::read() {
+            Ok(text) => self.insert_text_at_cursor(&text),
+            Err(e) => self.set_status(format!("✗ Paste failed: {}", e)),
+        }
+    }
+
+    /// Text pasted into the terminal (bracketed paste): multi-line into the editor,
+    /// joined onto one line for single-line inputs
+    pub fn handle_paste(&mut self, text: &str) -> Result<()> {
+        if self.job.is_some() {
+            return Ok(());
+        }
+        let single_line = || clipboard::normalize(text).lines().collect::<Vec<_>>().join(" ");
+        if let Some(ref mut input) = self.capture_input {
+            input.push_str(&single_line());
+            return Ok(());
+        }
+        match self.mode {
+            AppMode::Edit | AppMode::RawEdit | AppMode::Create => self.insert_text_at_cursor(text),
+            AppMode::Prompt(_) => self.input_buffer.push_str(&single_line()),
+            AppMode::Search => {
+                self.input_buffer.push_str(&single_line());
+                self.live_search()?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Delete the character before the caret in the edit buffer
+    fn delete_before_cursor(&mut self) {
+        if let Some((i, _)) = self.input_buffer[..self.cursor_pos].char_indices().next_back() {
+            self.input_buffer.remove(i);
+            self.cursor_pos = i;
+        }
+    }
+
+    /// Ctrl+E while editing: hand the edit buffer to `$EDITOR`. The main loop runs the editor
+    /// once this key is handled and passes the result to `finish_external_edit`.
+    fn open_external_editor(&mut self) {
+        // Named after the note's format, so the editor highlights it
+        let extension = match self.mode {
+            AppMode::RawEdit => "json",
+            AppMode::Create => "md",
+            _ => match self.current_note.as_ref().and_then(|note| note.format.as_deref()) {
+                Some("org") => "org",
+                Some("asciidoc") => "adoc",
+                Some("plaintext") => "txt",
+                _ => "md",
+            },
+        };
+        match ExternalEdit::prepare(&self.input_buffer, extension) {
+            Ok(edit) => self.external_edit = Some(edit),
+            Err(e) => self.set_status(format!("✗ {}", e)),
+        }
+    }
+
+    /// The editor run asked for with Ctrl+E, if any, for the main loop to start
+    pub fn take_external_edit(&mut self) -> Option<ExternalEdit> {
+        self.external_edit.take()
+    }
+
+    /// Load the text back from the editor once it has exited with `result`. Nothing is
+    /// saved yet: the text is back in the edit buffer, to save or discard as usual.
+    pub fn finish_external_edit(&mut self, edit: ExternalEdit, result: Result<()>) {
+        match result.and_then(|()| edit.read_back()) {
+            Ok(text) if text == self.input_buffer => {
+                self.set_status(format!("ℹ No changes made in {}", edit.program()));
+            }
+            Ok(text) => {
+                self.input_buffer = text;
+                self.cursor_pos = self.input_buffer.len();
+                self.set_status(format!("✓ Text from {} loaded; Ctrl+S saves it", edit.program()));
+            }
+            Err(e) => self.set_status(format!("✗ {}; the text is unchanged", e)),
+        }
+    }
+
+    /// Delete the character after the caret in the edit buffer
+    fn delete_at_cursor(&mut self) {
+        if self.cursor_pos < self.input_buffer.len() {
+            self.input_buffer.remove(self.cursor_pos);
+        }
+    }
+
+    /// Move the caret in the edit buffer with an arrow key, Home or End. Up and Down move
+    /// by lines of the text rather than wrapped rows, keeping the column where they can.
+    fn move_cursor(&mut self, key: crossterm::event::KeyCode) {
+        let text = &self.input_buffer;
+        let pos = self.cursor_pos.min(text.len());
+        self.cursor_pos = match key {
+            crossterm::event::KeyCode::Left => text[..pos].char_indices().next_back().map_or(0, |(i, _)| i),
+            crossterm::event::KeyCode::Right => pos + text[pos..].chars().next().map_or(0, char::len_utf8),
+            crossterm::event::KeyCode::Up => line_step(text, pos, true),
+            crossterm::event::KeyCode::Down => line_step(text, pos, false),
+            crossterm::event::KeyCode::Home => line_start(text, pos),
+            crossterm::event::KeyCode::End => line_end(text, pos),
+            _ => pos,
+        };
+    }
+    
+    /// Text shown under a note's title in lists, per the configured preview mode
+    fn preview(&self, note: &Note) -> String {
+        match self.preview_mode {
+            PreviewMode::Line => note.content.lines().next().unwrap_or("").trim().to_string(),
+            PreviewMode::Paragraph => preview_text(&note.content),
+        }
+    }
+
+    /// Apply the configured wrap mode to a paragraph of note text
+    fn wrap_note_text<'a>(&self, paragraph: Paragraph<'a>) -> Paragraph<'a> {
+        match self.wrap_mode {
+            WrapMode::Trim => paragraph.wrap(Wrap { trim: true }),
+            WrapMode::Keep => paragraph.wrap(Wrap { trim: false }),
+            WrapMode::Off => paragraph,
+        }
+    }
+
+    /// Row and column of the caret in the edit buffer as laid out in a pane `width` columns
+    /// wide, before scrolling. A pasted URL or minified blob wraps into many rows, so with
+    /// wrapping on the row is counted in wrapped rows rather than lines.
+    fn caret_position(&self, width: usize) -> (usize, usize) {
+        let pos = self.cursor_pos.min(self.input_buffer.len());
+        let start = line_start(&self.input_buffer, pos);
+        let column = self.input_buffer[start..pos].chars().count();
+        let lines_above = self.input_buffer[..start].lines();
+        if self.wrap_mode == WrapMode::Off {
+            return (lines_above.count(), column);
+        }
+        let trim = self.wrap_mode == WrapMode::Trim;
+        let rows_above: usize = lines_above.map(|line| wrapped_rows(line, width, trim)).sum();
+        let line = &self.input_buffer[start..line_end(&self.input_buffer, pos)];
+        let (row, column) = wrapped_position(line, width, trim, column);
+        (rows_above + row, column)
+    }
+
+    /// Sideways scroll that keeps the caret's `column` inside a bordered pane of `width`
+    /// when wrapping is off
+    fn caret_hscroll(&self, column: usize, width: u16) -> u16 {
+        if self.wrap_mode != WrapMode::Off {
+            return 0;
+        }
+        let visible = width.saturating_sub(3) as usize; // Borders plus the caret itself
+        column.saturating_sub(visible).min(u16::MAX as usize) as u16
+    }
+
+    /// Downward scroll that keeps the caret's `row`, and the row after it, inside a bordered pane
+    fn caret_vscroll(&self, row: usize, area: Rect) -> u16 {
+        let height = area.height.saturating_sub(2) as usize;
+        (row + 2).saturating_sub(height).min(u16::MAX as usize) as u16
+    }
+
+    /// The edit buffer in a bordered pane, scrolled to keep the caret in view, with the
+    /// terminal cursor placed on the caret
+    fn render_edit_buffer(&self, frame: &mut Frame, area: Rect, title: String, color: Color) {
+        let (row, column) = self.caret_position(area.width.saturating_sub(2) as usize);
+        let vscroll = self.caret_vscroll(row, area);
+        let hscroll = self.caret_hscroll(column, area.width);
+        let content = self
+            .wrap_note_text(Paragraph::new(self.input_buffer.as_str()))
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .scroll((vscroll, hscroll))
+            .style(Style::default().fg(color));
+        frame.render_widget(content, area);
+
+        let x = area.x as usize + 1 + column.saturating_sub(hscroll as usize);
+        let y = area.y as usize + 1 + row.saturating_sub(vscroll as usize);
+        if x < (area.x + area.width) as usize && y + 1 < (area.y + area.height) as usize {
+            frame.set_cursor_position(Position::new(x as u16, y as u16));
+        }
+    }
+
+    fn handle_search_key(&mut self, key: crossterm::event::KeyCode, modifiers: crossterm::event::KeyModifiers) -> Result<()> {
+        match key {
+            crossterm::event::KeyCode::Esc => {
+                self.mode = AppMode::List;
+                self.input_buffer.clear();
+                self.is_searching = false;
+                self.search_query.clear();
+                self.filtered_notes = self.notes.clone();
+                self.selected_index = 0;
+            }
+            crossterm::event::KeyCode::Enter => {
+                // Apply search, keeping the highlighted note
+                self.live_search()?;
+                if self.is_searching {
+                    self.search_query = self.input_buffer.clone();
+                    self.state.push_search(&self.search_query);
+                    if let Err(e) = self.state.save() {
+                        self.set_status(format!("⚠ Search history not saved: {}", e));
+                    }
+                } else {
+                    self.search_query.clear();
+                }
+                self.input_buffer.clear();
+                self.mode = AppMode::List;
+            }
+            crossterm::event::KeyCode::Tab => self.toggle_search_order()?,
+            crossterm::event::KeyCode::Char('f') if modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
+                self.fuzzy_search = !self.fuzzy_search;
+                self.live_search()?;
+                self.set_status(if self.fuzzy_search {
+                    "ℹ Fuzzy search: the query's letters in order, e.g. prjplan finds Project Planning".to_string()
+                } else {
+                    "ℹ Substring search: the query as typed".to_string()
+                });
+            }
+            crossterm::event::KeyCode::Char(c) => {
+                self.input_buffer.push(c);
+                self.search_history_pos = None;
+                self.live_search()?;
+            }
+            crossterm::event::KeyCode::Backspace => {
+                self.input_buffer.pop();
+                self.search_history_pos = None;
+                self.live_search()?;
+            }
+            crossterm::event::KeyCode::Up if !self.state.search_history.is_empty() => {
+                // Step back through earlier queries, stopping at the oldest
+                let pos = match self.search_history_pos {
+                    Some(pos) => pos.saturating_sub(1),
+                    None => self.state.search_history.len() - 1,
+                };
+                self.search_history_pos = Some(pos);
+                self.input_buffer = self.state.search_history[pos].clone();
+                self.live_search()?;
+            }
+            crossterm::event::KeyCode::Down if self.search_history_pos.is_some() => {
+                // Step forward; past the newest query the input is empty again
+                let next = self.search_history_pos.map_or(0, |pos| pos + 1);
+                if next < self.state.search_history.len() {
+                    self.search_history_pos = Some(next);
+                    self.input_buffer = self.state.search_history[next].clone();
+                } else {
+                    self.search_history_pos = None;
+                    self.input_buffer.clear();
+                }
+                self.live_search()?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Move `note` one place up or down the manual order, reload the list and say where
+    /// it ended up
+    fn move_in_order(&mut self, note: &Note, up: bool) -> Result<Note> {
+        let moved = match self.service.move_in_order(&note.id, up) {
+            Ok(moved) => moved,
+            Err(e) => {
+                self.set_status(format!("✗ Failed to reorder: {}", e));
+                return Ok(note.clone());
+            }
+        };
+        self.set_status(match moved.order {
+            Some(_) if moved.order == note.order => format!("ℹ '{}' is already first in the manual order", moved.title),
+            Some(order) => format!("✓ '{}' is #{} in the manual order", moved.title, order),
+            None if note.order.is_some() => format!("✓ '{}' taken out of the manual order", moved.title),
+            None => {
+                let context = if matches!(self.mode, AppMode::View) { KeyContext::View } else { KeyContext::List };
+                format!("ℹ '{}' is not in the manual order; {} adds it", moved.title, self.keymap.key_hint(context, Action::OrderUp))
+            }
+        });
+        self.reload_notes()?;
+        Ok(moved)
+    }
+
+    /// Select the note `id` in the list if it is shown, else keep the position in range
+    fn reselect(&mut self, id: Option<&str>) {
+        let notes = if self.is_searching { &self.filtered_notes } else { &self.notes };
+        self.selected_index = id
+            .and_then(|id| notes.iter().position(|n| n.id == id))
+            .unwrap_or(self.selected_index.min(notes.len().saturating_sub(1)));
+    }
+
+    /// Switch search results between relevance and recency order, re-sorting the current
+    /// results with the selected note kept selected
+    fn toggle_search_order(&mut self) -> Result<()> {
+        self.search_order = self.search_order.next();
+        if matches!(self.mode, AppMode::Search) {
+            self.live_search()?;
+        } else if self.is_searching {
+            let selected_id = self.filtered_notes.get(self.selected_index).map(|n| n.id.clone());
+            self.filtered_notes = self.run_search(&self.search_query)?;
+            self.selected_index = selected_id
+                .and_then(|id| self.filtered_notes.iter().position(|n| n.id == id))
+                .unwrap_or(0);
+        }
+        self.set_status(format!("ℹ Search results sorted {}", self.search_order.describe()));
+        Ok(())
+    }
+
+    /// Notes matching `query` by substring or, with fuzzy search on, loosely (best matches
+    /// first by relevance), in the current search order
+    fn run_search(&self, query: &str) -> Result<Vec<Note>> {
+        if !self.fuzzy_search {
+            return self.service.search_notes(query, self.search_order);
+        }
+        let mut notes = self.service.search_notes_fuzzy(query)?;
+        if self.search_order == SearchOrder::Recent {
+            notes.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        }
+        Ok(notes)
+    }
+
+    /// How search results are found and sorted, for result headers
+    fn search_description(&self) -> String {
+        if self.fuzzy_search {
+            format!("fuzzy, {}", self.search_order.describe())
+        } else {
+            self.search_order.describe().to_string()
+        }
+    }
+
+    /// Filter the list by `query` as if it had been searched for
+    fn apply_search(&mut self, query: &str) -> Result<()> {
+        self.search_query = query.to_string();
+        self.filtered_notes = self.run_search(query)?;
+        self.is_searching = true;
+        self.selected_index = 0;
+        self.mode = AppMode::List;
+        Ok(())
+    }
+
+    /// Filter the list by the Search mode input as it is typed, keeping the selected note
+    /// highlighted if it still matches (otherwise the first result is selected)
+    fn live_search(&mut self) -> Result<()> {
+        let selected_id = self.filtered_notes.get(self.selected_index).map(|n| n.id.clone());
+        if !self.input_buffer.trim().is_empty() {
+            self.filtered_notes = self.run_search(&self.input_buffer)?;
+            self.is_searching = true;
+        } else {
+            self.filtered_notes = self.notes.clone();
+            self.is_searching = false;
+        }
+        self.selected_index = selected_id
+            .and_then(|id| self.filtered_notes.iter().position(|n| n.id == id))
+            .unwrap_or(0);
+        Ok(())
+    }
+
+    fn handle_delete_confirm_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        match key {
+            crossterm::event::KeyCode::Char('y') | crossterm::event::KeyCode::Enter => {
+                // Confirm deletion
+                if let Some(note) = self.current_note.clone() {
+                    self.record_undo(format!("Delete '{}'", note.title));
+                    if let Err(e) = self.service.delete_note(&note.id) {
+                        self.set_status(format!("✗ Failed to delete: {}", e));
+                        self.mode = AppMode::List;
+                        self.current_note = None;
+                        return Ok(());
+                    }
+                    self.fire_hook(HookEvent::Delete, &note.id);
+                    // Refresh notes
+                    self.reload_notes()?;
+                    // Adjust selected index
+                    if self.selected_index >= self.filtered_notes.len() && !self.filtered_notes.is_empty() {
+                        self.selected_index = self.filtered_notes.len() - 1;
+                    }
+                }
+                self.mode = AppMode::List;
+                self.current_note = None;
+            }
+            crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Char('n') => {
+                // Cancel deletion
+                self.mode = AppMode::List;
+                self.current_note = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_link_select_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        match key {
+            crossterm::event::KeyCode::Esc => {
+                self.mode = AppMode::View;
+            }
+            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
+                let max_index = self.notes.len().saturating_sub(1);
+                if self.selected_index < max_index {
+                    self.selected_index += 1;
+                }
+            }
+            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                self.selected_index = self.selected_index.saturating_sub(1);
+            }
+            crossterm::event::KeyCode::Enter => {
+                let is_other_note = match (&self.current_note, self.notes.get(self.selected_index)) {
+                    (Some(current_note), Some(target_note)) => current_note.id != target_note.id,
+                    _ => false,
+                };
+                if self.confirm_links && is_other_note {
+                    self.mode = AppMode::Confirm(ConfirmKind::CreateLink);
+                } else {
+                    self.link_selected_note()?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Link the current note to the note selected in Link-Select mode and return to View
+    fn link_selected_note(&mut self) -> Result<()> {
+        self.mode = AppMode::View;
+        let (Some(current_note), Some(target_note)) = (self.current_note.clone(), self.notes.get(self.selected_index).cloned()) else {
+            return Ok(());
+        };
+        if let Err(e) = self.service.link_notes(&current_note.id, &target_note.id) {
+            self.set_status(format!("✗ Not linked: {}", e));
+            return Ok(());
+        }
+        // Refresh current note
+        if let Some(updated_note) = self.service.get_note(&current_note.id)? {
+            self.current_note = Some(updated_note);
+        }
+        // Refresh notes list
+        self.reload_notes()?;
+        if self.cycle_policy == CyclePolicy::Warn && target_note.links.contains(&current_note.id) {
+            self.set_status(format!("⚠ Note linked, but '{}' already links back (cycle)", target_note.title));
+        } else {
+            self.set_status("✓ Note linked".to_string());
+        }
+        Ok(())
+    }
+
+    fn handle_tag_add_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        match key {
+            crossterm::event::KeyCode::Esc => {
+                self.mode = AppMode::View;
+                self.input_buffer = String::new();
+            }
+            crossterm::event::KeyCode::Enter => {
+                // Add tag
+                if let Some(ref mut note) = self.current_note {
+                    let tag = self.input_buffer.trim().to_string();
+                    if !tag.is_empty() {
+                        let updated_note = match self.service.add_tag(&note.id, tag) {
+                            Ok(updated_note) => updated_note,
+                            Err(e) => {
+                                self.set_status(format!("✗ Failed to add tag: {}", e));
+                                self.input_buffer = String::new();
+                                self.mode = AppMode::View;
+                                return Ok(());
+                            }
+                        };
+                        let message = self
+                            .rule_status(&updated_note, "Tag added")
+                            .unwrap_or_else(|| "✓ Tag added".to_string());
+                        self.set_status(message);
+                        self.current_note = Some(updated_note);
+                        // Refresh notes list
+                        self.reload_notes()?;
+                    }
+                }
+                self.input_buffer = String::new();
+                self.mode = AppMode::View;
+            }
+            crossterm::event::KeyCode::Char(c) => {
+                self.input_buffer.push(c);
+            }
+            crossterm::event::KeyCode::Backspace => {
+                self.input_buffer.pop();
+            }
+            _ => {}
         }
         Ok(())
     }
@@ -535,13 +2416,8 @@ impl App {
                         self.current_note = Some(updated_note);
                     }
                     // Refresh notes list
-                    self.notes = self.service.list_notes()?;
-                    if self.is_searching {
-                        self.filtered_notes = self.service.search_notes(&self.search_query)?;
-                    } else {
-                        self.filtered_notes = self.notes.clone();
-                    }
-                    self.status_message = Some("✓ Note unlinked".to_string());
+                    self.reload_notes()?;
+                    self.set_status("✓ Note unlinked".to_string());
                 }
                 self.input_buffer.clear();
                 self.mode = AppMode::View;
@@ -571,9 +2447,7 @@ impl App {
                 }
             }
             crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
-                if self.selected_index > 0 {
-                    self.selected_index -= 1;
-                }
+                self.selected_index = self.selected_index.saturating_sub(1);
             }
             crossterm::event::KeyCode::Enter => {
                 // Remove selected tag
@@ -582,19 +2456,14 @@ impl App {
                         let updated_note = self.service.remove_tag(&note.id, tag)?;
                         self.current_note = Some(updated_note);
                         // Refresh notes list
-                        self.notes = self.service.list_notes()?;
-                        if self.is_searching {
-                            self.filtered_notes = self.service.search_notes(&self.search_query)?;
-                        } else {
-                            self.filtered_notes = self.notes.clone();
-                        }
+                        self.reload_notes()?;
                         // Adjust selection
-                        if self.selected_index >= self.current_note.as_ref().unwrap().tags.len() {
-                            if !self.current_note.as_ref().unwrap().tags.is_empty() {
-                                self.selected_index = self.current_note.as_ref().unwrap().tags.len() - 1;
-                            }
+                        if self.selected_index >= self.current_note.as_ref().unwrap().tags.len()
+                            && !self.current_note.as_ref().unwrap().tags.is_empty()
+                        {
+                            self.selected_index = self.current_note.as_ref().unwrap().tags.len() - 1;
                         }
-                        self.status_message = Some("✓ Tag removed".to_string());
+                        self.set_status("✓ Tag removed".to_string());
                     }
                     if self.current_note.as_ref().unwrap().tags.is_empty() {
                         self.mode = AppMode::View;
@@ -607,88 +2476,413 @@ impl App {
     }
 
     fn handle_create_key(&mut self, key: crossterm::event::KeyCode, modifiers: crossterm::event::KeyModifiers) -> Result<()> {
+        self.status_message = None;
         match key {
             crossterm::event::KeyCode::Esc => {
-                self.mode = AppMode::List;
+                // Create-and-link returns to the note it was started from
+                self.mode = if self.link_source_id.take().is_some() { AppMode::View } else { AppMode::List };
                 self.input_buffer = String::new();
             }
+            crossterm::event::KeyCode::Char('v') if modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
+                self.paste_clipboard();
+            }
             crossterm::event::KeyCode::Char('s') if modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
                 // Ctrl+S to save/create note
                 if self.input_buffer.trim().is_empty() {
                     return Ok(());
                 }
-                
-                // Create note with title from first line, content from entire buffer
-                let lines: Vec<&str> = self.input_buffer.lines().collect();
-                let title = lines.first().map(|s| s.to_string()).unwrap_or_else(|| "Untitled".to_string());
-                let content = self.input_buffer.clone();
-                
-                let note = self.service.create_note(title, content)?;
-                self.notes = self.service.list_notes()?;
-                if self.is_searching {
-                    self.filtered_notes = self.service.search_notes(&self.search_query)?;
+                if self.should_warn_empty_body() {
+                    self.mode = AppMode::Confirm(ConfirmKind::SaveEmptyNewNote);
                 } else {
-                    self.filtered_notes = self.notes.clone();
+                    self.create_from_buffer()?;
                 }
-                self.mode = AppMode::View;
-                self.current_note = Some(note);
-                self.input_buffer = String::new();
+            }
+            crossterm::event::KeyCode::Char('e') if modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
+                self.open_external_editor();
             }
             crossterm::event::KeyCode::Char(c) => {
-                self.input_buffer.push(c);
+                self.insert_at_cursor(c);
             }
             crossterm::event::KeyCode::Backspace => {
-                self.input_buffer.pop();
+                self.delete_before_cursor();
             }
             crossterm::event::KeyCode::Enter => {
-                self.input_buffer.push('\n');
+                self.insert_at_cursor('\n');
             }
+            crossterm::event::KeyCode::Delete => {
+                self.delete_at_cursor();
+            }
+            crossterm::event::KeyCode::Left
+            | crossterm::event::KeyCode::Right
+            | crossterm::event::KeyCode::Up
+            | crossterm::event::KeyCode::Down
+            | crossterm::event::KeyCode::Home
+            | crossterm::event::KeyCode::End => self.move_cursor(key),
             _ => {}
         }
         Ok(())
     }
 
-    pub fn render(&self, frame: &mut Frame) {
-        match self.mode {
-            AppMode::List => self.render_list(frame),
-            AppMode::View => self.render_view(frame),
-            AppMode::Edit => self.render_edit(frame),
-            AppMode::Create => self.render_create(frame),
-            AppMode::Search => self.render_search(frame),
-            AppMode::DeleteConfirm => self.render_delete_confirm(frame),
-            AppMode::LinkSelect => self.render_link_select(frame),
-            AppMode::TagAdd => self.render_tag_add(frame),
-            AppMode::UnlinkConfirm => self.render_unlink_confirm(frame),
-            AppMode::TagRemove => self.render_tag_remove(frame),
-            AppMode::Statistics => self.render_statistics(frame),
-            AppMode::Help => self.render_help(frame),
-            AppMode::History => self.render_history(frame),
-        }
+    /// Whether saving the edit buffer should first ask about the missing body
+    fn should_warn_empty_body(&self) -> bool {
+        self.empty_body_policy == EmptyBodyPolicy::Warn && !has_body(&self.input_buffer)
+    }
+    
+    /// Title of the note in the Create mode buffer: its first line
+    fn new_note_title(&self) -> String {
+        self.input_buffer.lines().next().map(|s| s.to_string()).unwrap_or_else(|| "Untitled".to_string())
     }
 
-    fn render_list(&self, frame: &mut Frame) {
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
-            .split(frame.area());
+    /// Create the note in the Create mode buffer, unless its title is taken and the
+    /// duplicate-title policy asks first
+    fn create_from_buffer(&mut self) -> Result<()> {
+        if self.duplicate_title_policy == DuplicateTitlePolicy::Warn
+            && let Some(existing) = self.service.title_taken(&self.new_note_title())?
+        {
+            self.duplicate_of = Some(existing);
+            self.mode = AppMode::Confirm(ConfirmKind::DuplicateTitle);
+            return Ok(());
+        }
+        self.save_new_note()
+    }
 
-        // Title bar - Warhammer 40k theme
-        let title_text = if self.is_searching {
-            format!("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔ (Search: {})", self.search_query)
-        } else {
-            "⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔".to_string()
+    /// Create a note from the Create mode buffer: first line is the title, the whole buffer the content
+    fn save_new_note(&mut self) -> Result<()> {
+        let title = self.new_note_title();
+        let content = self.input_buffer.clone();
+        
+        let created = match self.link_source_id.as_deref() {
+            Some(source_id) => self.service.create_linked_note(source_id, title, content),
+            None => self.service.create_note(title, content),
         };
-        let title = Paragraph::new(title_text)
-            .block(Block::default().borders(Borders::ALL).title(" ADEPTUS ADMINISTRATUM "))
-            .style(Style::default().fg(Color::White).bg(Color::DarkGray));
-        frame.render_widget(title, chunks[0]);
-
-        // Notes list with enhanced formatting
+        let note = match created {
+            Ok(note) => note,
+            Err(e) => {
+                match e.downcast::<DuplicateTitle>() {
+                    // Refused by `block`: offer the note that has the title instead
+                    Ok(duplicate) => {
+                        self.duplicate_of = Some(duplicate.existing);
+                        self.mode = AppMode::Confirm(ConfirmKind::DuplicateTitle);
+                    }
+                    Err(e) => self.set_status(format!("✗ Failed to create note: {}", e)),
+                }
+                return Ok(());
+            }
+        };
+        self.fire_hook(HookEvent::Create, &note.id);
+        self.mode = AppMode::View;
+        self.input_buffer = String::new();
+        if let Some(source_id) = self.link_source_id.take() {
+            // The source note links to the new one now; go back to it
+            self.reload_notes()?;
+            if let Some(source) = self.service.get_note(&source_id)? {
+                self.open_note(source);
+            }
+            let linked = format!("Created and linked: {}", note.title);
+            let message = self.rule_status(&note, &linked).unwrap_or_else(|| format!("✓ {}", linked));
+            self.set_status(message);
+        } else {
+            self.reload_notes()?;
+            self.open_note(note.clone());
+            self.status_message = None;
+            if let Some(tags) = auto_tag_list(&[], &note) {
+                self.set_status(format!("✓ Note created; auto-tagged {}", tags));
+            }
+            // Plain creation keeps the chosen title; a clash is only pointed out
+            if self.service.resolve_title(&note.title)?.len() > 1 {
+                self.set_status(format!("⚠ Note created, but another note is also titled '{}'", note.title.trim()));
+            }
+            if let Some(warning) = self.rule_status(&note, "Note created") {
+                self.set_status(warning);
+            }
+            self.start_disambiguation(&note)?;
+        }
+        Ok(())
+    }
+
+    /// Warning status for a saved note that breaks the configured rules, `None` if it passes
+    fn rule_status(&self, note: &Note, saved: &str) -> Option<String> {
+        let warnings = self.service.rule_warnings(note);
+        if warnings.is_empty() {
+            None
+        } else {
+            Some(format!("⚠ {}, but it has {}", saved, warnings.join(", ")))
+        }
+    }
+    
+    /// Whether the Edit mode buffer differs from the content of the note being edited
+    fn edit_is_dirty(&self) -> bool {
+        self.current_note.as_ref().is_some_and(|note| note.content != self.input_buffer)
+    }
+
+    /// The one way out of Edit mode. Unsaved changes are never dropped silently:
+    /// with a dirty buffer this asks whether to save or discard them first.
+    fn leave_edit(&mut self) {
+        if self.edit_is_dirty() {
+            self.mode = AppMode::Confirm(ConfirmKind::UnsavedEdit);
+        } else {
+            self.mode = AppMode::View;
+        }
+    }
+
+    /// Save the Edit mode buffer, asking first when it would leave the note empty
+    fn request_edit_save(&mut self) -> Result<()> {
+        if self.should_warn_empty_body() {
+            self.mode = AppMode::Confirm(ConfirmKind::SaveEmptyEdit);
+            Ok(())
+        } else {
+            self.save_edited_note()
+        }
+    }
+
+    /// Save the Edit mode buffer, first asking what to do if the file changed on disk since editing began
+    fn save_edited_note(&mut self) -> Result<()> {
+        if let Some(ref note) = self.current_note
+            && self.service.note_fingerprint(&note.id)? != self.edit_fingerprint
+        {
+            self.mode = AppMode::Confirm(ConfirmKind::EditConflict);
+            return Ok(());
+        }
+        self.write_edited_note()
+    }
+
+    /// Write the Edit mode buffer as the current note's content
+    fn write_edited_note(&mut self) -> Result<()> {
+        if let Some(note) = self.current_note.clone() {
+            let links_before = note.links.clone();
+            let tags_before = note.tags.clone();
+            // A failed save stays in Edit mode so the buffer is not lost
+            let saved = match self.service.update_note(note, self.input_buffer.clone()) {
+                Ok(saved) => saved,
+                Err(e) => {
+                    self.set_status(format!("✗ Failed to save note: {}", e));
+                    return Ok(());
+                }
+            };
+            self.current_note = Some(saved.clone());
+            self.mode = AppMode::View;
+            self.fire_hook(HookEvent::Update, &saved.id);
+            self.status_message = None;
+            let added = saved.links.iter().filter(|id| !links_before.contains(id)).count();
+            let removed = links_before.iter().filter(|id| !saved.links.contains(id)).count();
+            if added + removed > 0 {
+                self.set_status(format!("✓ Note saved; wikilinks added {} and removed {} links", added, removed));
+            }
+            if let Some(tags) = auto_tag_list(&tags_before, &saved) {
+                self.set_status(format!("✓ Note saved; auto-tagged {}", tags));
+            }
+            if let Some(warning) = self.rule_status(&saved, "Note saved") {
+                self.set_status(warning);
+            }
+            // Match positions are stale once the content changes
+            self.clear_find();
+            // Refresh notes list
+            self.reload_notes()?;
+            self.start_disambiguation(&saved)?;
+        }
+        Ok(())
+    }
+
+    /// After a save, ask which note each ambiguous wikilink of `note` means
+    fn start_disambiguation(&mut self, note: &Note) -> Result<()> {
+        self.ambiguous_titles = self.service.ambiguous_wikilinks(note)?;
+        self.next_disambiguation()
+    }
+
+    /// Show the picker for the next ambiguous title, or go back to View once none are left
+    fn next_disambiguation(&mut self) -> Result<()> {
+        let current_id = self.current_note.as_ref().map(|n| n.id.clone()).unwrap_or_default();
+        while let Some(title) = self.ambiguous_titles.first() {
+            let candidates: Vec<Note> = self
+                .service
+                .resolve_title(title)?
+                .into_iter()
+                .filter(|n| n.id != current_id)
+                .collect();
+            if candidates.len() > 1 {
+                self.title_candidates = candidates;
+                self.candidate_index = 0;
+                self.mode = AppMode::Disambiguate;
+                return Ok(());
+            }
+            self.ambiguous_titles.remove(0);
+        }
+        self.title_candidates.clear();
+        self.mode = AppMode::View;
+        Ok(())
+    }
+
+    pub fn render(&self, frame: &mut Frame) {
+        let area = frame.area();
+        if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+            self.render_too_small(frame);
+            return;
+        }
+        match self.mode {
+            AppMode::List => self.render_list(frame),
+            AppMode::View => self.render_view(frame),
+            AppMode::Edit => self.render_edit(frame),
+            AppMode::RawEdit => self.render_raw_edit(frame),
+            AppMode::Create => self.render_create(frame),
+            AppMode::Search => self.render_search(frame),
+            AppMode::DeleteConfirm => self.render_delete_confirm(frame),
+            AppMode::LinkSelect => self.render_link_select(frame),
+            AppMode::TagAdd => self.render_tag_add(frame),
+            AppMode::UnlinkConfirm => self.render_unlink_confirm(frame),
+            AppMode::TagRemove => self.render_tag_remove(frame),
+            AppMode::Statistics => self.render_statistics(frame),
+            AppMode::Cycles => self.render_cycles(frame),
+            AppMode::Dashboard => self.render_dashboard(frame),
+            AppMode::Help => self.render_help(frame),
+            AppMode::History => self.render_history(frame),
+            AppMode::MetadataEdit => self.render_metadata_edit(frame),
+            AppMode::MetadataInput => self.render_metadata_input(frame),
+            AppMode::Prompt(kind) => self.render_prompt(frame, kind),
+            AppMode::Confirm(kind) => self.render_confirm(frame, kind),
+            AppMode::TemplateSelect => self.render_template_select(frame),
+            AppMode::TagGroups => self.render_tag_groups(frame),
+            AppMode::Log => self.render_log(frame),
+            AppMode::RepoLog => self.render_repo_log(frame),
+            AppMode::TagManager => self.render_tag_manager(frame),
+            AppMode::LinkOrder => self.render_link_order(frame),
+            AppMode::Tasks => self.render_tasks(frame),
+            AppMode::Due => self.render_due(frame),
+            AppMode::Stubs => self.render_stubs(frame),
+            AppMode::Stale => self.render_stale(frame),
+            AppMode::Health => self.render_health(frame),
+            AppMode::HealthNotes => self.render_health_notes(frame),
+            AppMode::Disambiguate => self.render_disambiguate(frame),
+            AppMode::SavedSearches => self.render_saved_searches(frame),
+        }
+        if let Some(context) = self.help_overlay {
+            self.render_help_overlay(frame, context);
+        }
+        if self.capture_input.is_some() {
+            self.render_capture(frame);
+        }
+        if let Some(step) = self.tutorial {
+            self.render_tutorial(frame, step);
+        }
+    }
+
+    /// Keys for the walkthrough's own steps: Enter moves on, Esc ends it
+    fn handle_tutorial_key(&mut self, step: TutorialStep, key: crossterm::event::KeyCode) -> Result<()> {
+        match key {
+            crossterm::event::KeyCode::Enter if step == TutorialStep::Welcome => {
+                self.create_sample_notes()?;
+                self.tutorial = step.next();
+            }
+            crossterm::event::KeyCode::Enter | crossterm::event::KeyCode::Esc => self.finish_tutorial(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Create the walkthrough's sample notes and open the welcome note
+    fn create_sample_notes(&mut self) -> Result<()> {
+        let mut last = None;
+        for (title, content) in SAMPLE_NOTES {
+            let note = self.service.create_note(title.to_string(), content.to_string())?;
+            self.fire_hook(HookEvent::Create, &note.id);
+            last = Some(note);
+        }
+        self.reload_notes()?;
+        if let Some(note) = last {
+            self.open_note(note);
+            self.mode = AppMode::View;
+        }
+        Ok(())
+    }
+
+    /// Move the walkthrough on once the user did what the current step asks
+    fn advance_tutorial(&mut self) {
+        let Some(step) = self.tutorial else {
+            return;
+        };
+        let done = match step {
+            TutorialStep::AddTag => self.current_note.as_ref().is_some_and(|n| !n.tags.is_empty()),
+            TutorialStep::AddLink => self.current_note.as_ref().is_some_and(|n| !n.links.is_empty()),
+            TutorialStep::Search => self.is_searching && matches!(self.mode, AppMode::List),
+            TutorialStep::Welcome | TutorialStep::Finished => false,
+        };
+        if done {
+            self.tutorial = step.next();
+        }
+    }
+
+    /// Close the walkthrough for good, remembering that in the state file
+    fn finish_tutorial(&mut self) {
+        self.tutorial = None;
+        self.state.tutorial_done = true;
+        if let Err(e) = self.state.save() {
+            self.set_status(format!("⚠ Could not save state: {}", e));
+        }
+    }
+
+    /// Walkthrough panel drawn above the help bar of the current screen
+    fn render_tutorial(&self, frame: &mut Frame, step: TutorialStep) {
+        let area = frame.area();
+        let width = area.width.saturating_sub(4).min(64);
+        let height = 7.min(area.height.saturating_sub(3));
+        let popup = Rect::new(
+            area.x + area.width.saturating_sub(width + 2),
+            area.y + area.height.saturating_sub(height + 3),
+            width,
+            height,
+        );
+        let footer = if step.is_modal() { "" } else { " Ctrl+T: end the tour " };
+        let panel = Paragraph::new(step.text(&self.keymap))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Tour ")
+                    .title_bottom(Line::from(footer).right_aligned())
+                    .border_style(Style::default().fg(Color::Cyan)),
+            )
+            .wrap(Wrap { trim: true })
+            .style(Style::default().fg(Color::White).bg(Color::Black));
+        frame.render_widget(Clear, popup);
+        frame.render_widget(panel, popup);
+    }
+
+    /// Single-panel fallback for terminals too small for the normal layout
+    fn render_too_small(&self, frame: &mut Frame) {
+        let message = Paragraph::new(format!(
+            "Terminal too small — resize to at least {}x{}",
+            MIN_WIDTH, MIN_HEIGHT
+        ))
+        .wrap(Wrap { trim: true })
+        .style(Style::default().fg(Color::Yellow).bg(Color::Black));
+        frame.render_widget(message, frame.area());
+    }
+
+    fn render_list(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.area());
+
+        // Title bar - Warhammer 40k theme
+        let title_text = if self.is_searching {
+            format!("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔ (Search: {})", self.search_query)
+        } else {
+            "⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔".to_string()
+        };
+        let title = Paragraph::new(title_text)
+            .block(self.title_block())
+            .style(Style::default().fg(Color::White).bg(Color::DarkGray));
+        frame.render_widget(title, chunks[0]);
+
+        // Notes list with enhanced formatting
         let notes_to_display = if self.is_searching { &self.filtered_notes } else { &self.notes };
         let items: Vec<ListItem> = notes_to_display
             .iter()
             .enumerate()
             .map(|(i, note)| {
+                let preview = self.preview(note);
+                let locked = note.locked;
+                // Rows show only summary fields, the same ones `list_note_summaries` loads
+                let note = note.summary();
                 let is_selected = i == self.selected_index;
                 let base_style = if is_selected {
                     Style::default().fg(Color::Yellow).bg(Color::DarkGray)
@@ -696,37 +2890,32 @@ impl App {
                     Style::default().fg(Color::White).bg(Color::Black)
                 };
                 
-                // Format date nicely
-                let date_str = if let Ok(parsed) = chrono::DateTime::parse_from_rfc3339(&note.created_at) {
-                    parsed.format("%Y-%m-%d").to_string()
-                } else {
-                    note.created_at.split('T').next().unwrap_or("").to_string()
-                };
+                let date_str = short_date(&note.created_at);
                 
                 // Build rich text with title, tags, and preview
                 let mut lines = vec![Line::default()];
                 
-                // Title line - 40k theme (eye-friendly)
-                let title_line = if is_selected {
-                    Line::from(vec![
-                        Span::styled("⚔ ", Style::default().fg(Color::Yellow)),
-                        Span::styled(&note.title, Style::default().fg(Color::Yellow)),
-                    ])
+                // Title line - 40k theme (eye-friendly), with a marker for the color label
+                let mut title_spans = if is_selected {
+                    vec![Span::styled("⚔ ", Style::default().fg(Color::Yellow))]
                 } else {
-                    Line::from(vec![
-                        Span::styled("  ", Style::default()),
-                        Span::styled(&note.title, Style::default().fg(Color::White)),
-                    ])
+                    vec![Span::styled("  ", Style::default())]
                 };
-                lines.push(title_line);
+                if self.selected_ids.contains(&note.id) {
+                    title_spans.push(Span::styled("✓ ", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)));
+                }
+                if let Some(color) = note.color.as_deref().and_then(label_color) {
+                    title_spans.push(Span::styled("● ", Style::default().fg(color)));
+                }
+                if locked {
+                    title_spans.push(Span::styled("🔒 ", Style::default().fg(Color::Yellow)));
+                }
+                let title_color = if is_selected { Color::Yellow } else { Color::White };
+                title_spans.push(Span::styled(note.title, Style::default().fg(title_color)));
+                lines.push(Line::from(title_spans));
                 
-                // Preview line (first line of content, truncated)
-                let preview = note.content.lines().next().unwrap_or("").trim();
-                let preview_truncated: String = if preview.len() > 60 {
-                    format!("{}...", &preview[..60])
-                } else {
-                    preview.to_string()
-                };
+                // Preview line (first line or paragraph of content, truncated)
+                let preview_truncated = truncate_chars(&preview, 60);
                 if !preview_truncated.is_empty() {
                     lines.push(Line::from(vec![
                         Span::styled("  ", Style::default()),
@@ -744,8 +2933,25 @@ impl App {
                     meta_parts.push(Span::styled(format!("  [{}] ", tags_str), Style::default().fg(Color::Red)));
                 }
                 meta_parts.push(Span::styled(format!("☠ {}", date_str), Style::default().fg(Color::DarkGray)));
-                if !note.links.is_empty() {
-                    meta_parts.push(Span::styled(format!(" ⚡ {}", note.links.len()), Style::default().fg(Color::Yellow)));
+                if let Some(order) = note.order {
+                    meta_parts.push(Span::styled(format!(" ⇅{}", order), Style::default().fg(Color::Magenta)));
+                }
+                if let Some(due) = note.due.as_deref().and_then(parse_due_at) {
+                    let now = chrono::Utc::now();
+                    let color = if due < now { Color::Red } else { Color::Cyan };
+                    meta_parts.push(Span::styled(format!(" ⏰ {}", due_label(due, now)), Style::default().fg(color)));
+                }
+                if self.stale_days > 0 && is_stale(&note.updated_at, chrono::Utc::now(), self.stale_days) {
+                    let age = age_label(age_days(&note.updated_at, chrono::Utc::now()));
+                    meta_parts.push(Span::styled(format!(" 💤 {}", age), Style::default().fg(Color::DarkGray)));
+                }
+                // Outbound / inbound link counts
+                if note.link_count > 0 {
+                    meta_parts.push(Span::styled(format!(" →{}", note.link_count), Style::default().fg(Color::Yellow)));
+                }
+                let inbound = self.backlink_index.get(&note.id).map_or(0, |ids| ids.len());
+                if inbound > 0 {
+                    meta_parts.push(Span::styled(format!(" ←{}", inbound), Style::default().fg(Color::Red)));
                 }
                 lines.push(Line::from(meta_parts));
                 
@@ -756,34 +2962,122 @@ impl App {
         let mut state = ratatui::widgets::ListState::default();
         state.select(Some(self.selected_index));
         
-        let list_title = if self.is_searching {
-            format!("Notes ({} found)", notes_to_display.len())
+        let mut list_title = if self.is_searching {
+            format!("Notes ({} found, {})", notes_to_display.len(), self.search_description())
+        } else if self.list_order == ListOrder::Manual {
+            format!("Notes ({})", self.list_order.describe())
         } else {
             "Notes".to_string()
         };
+        if !self.selected_ids.is_empty() {
+            list_title.push_str(&format!(" · {} selected", self.selected_ids.len()));
+        }
         
-        let list = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title(list_title))
-            .highlight_style(Style::default().fg(Color::Yellow).bg(Color::DarkGray))
-            .highlight_symbol("⚔ ");
-        frame.render_stateful_widget(list, chunks[1], &mut state);
+        if notes_to_display.is_empty() {
+            // Empty states: first run, or a filter that matches nothing
+            let key = |action| self.keymap.key_hint(KeyContext::List, action);
+            let message = if self.notes.is_empty() {
+                format!(
+                    "No notes yet — press {} to create your first note.\n\nPress {} to start from a template, or {} for all shortcuts.",
+                    key(Action::NewNote),
+                    key(Action::NewFromTemplate),
+                    key(Action::ShowHelp)
+                )
+            } else {
+                format!(
+                    "No notes match \"{}\".\n\nPress {} to clear the filter, or {} to search again.",
+                    self.search_query,
+                    key(Action::Quit),
+                    key(Action::Search)
+                )
+            };
+            let empty = Paragraph::new(message)
+                .block(Block::default().borders(Borders::ALL).title(list_title))
+                .wrap(Wrap { trim: true })
+                .style(Style::default().fg(Color::DarkGray).bg(Color::Black));
+            frame.render_widget(empty, chunks[1]);
+        } else {
+            let list = List::new(items)
+                .block(Block::default().borders(Borders::ALL).title(list_title))
+                .highlight_style(Style::default().fg(Color::Yellow).bg(Color::DarkGray))
+                .highlight_symbol("⚔ ");
+            // Start from the last frame's scroll position; the list only scrolls as far as
+            // it takes to show the selected note
+            *state.offset_mut() = self.list_offset.get();
+            frame.render_stateful_widget(list, chunks[1], &mut state);
+            self.list_offset.set(state.offset());
+        }
 
-        // Help bar - 40k theme (eye-friendly)
-        let help = Paragraph::new("j/k: navigate | n: new | /: search | #: tag search | d: delete | c: duplicate | s: stats | r: refresh | ?: help | Enter: view | Esc: quit")
+        // Help bar - 40k theme (eye-friendly), replaced by the status message until the next key
+        let help_text = if self.selected_ids.is_empty() {
+            self.keymap.help_bar(
+                KeyContext::List,
+                &[
+                    (&[Action::MoveDown, Action::MoveUp], "navigate"),
+                    (&[Action::ToggleSelect], "select"),
+                    (&[Action::NewNote], "new"),
+                    (&[Action::NewFromTemplate], "template"),
+                    (&[Action::Search], "search"),
+                    (&[Action::TagSearch], "tag search"),
+                    (&[Action::SaveSearch, Action::SavedSearches], "save/saved searches"),
+                    (&[Action::ExportResults], "export results"),
+                    (&[Action::TagGroups], "by tag"),
+                    (&[Action::ManageTags], "tags"),
+                    (&[Action::ShowTasks], "tasks"),
+                    (&[Action::ShowDue], "due"),
+                    (&[Action::DeleteNote], "delete"),
+                    (&[Action::UndoLast], "undo"),
+                    (&[Action::ShowLog], "log"),
+                    (&[Action::ShowRepoLog], "repo log"),
+                    (&[Action::DuplicateNote, Action::DuplicateWithBacklinks], "duplicate"),
+                    (&[Action::ShowStatistics], "stats"),
+                    (&[Action::Refresh], "refresh"),
+                    (&[Action::ToggleListOrder], "order"),
+                    (&[Action::OrderUp, Action::OrderDown], "move"),
+                    (&[Action::VaultHealth], "health"),
+                    (&[Action::ShowDashboard], "dashboard"),
+                    (&[Action::ShowHelp], "keys"),
+                    (&[Action::Open], "view"),
+                    (&[Action::Quit], "quit"),
+                ],
+            )
+        } else {
+            self.keymap.help_bar(
+                KeyContext::List,
+                &[
+                    (&[Action::ToggleSelect], "select/unselect"),
+                    (&[Action::TagSelected], "tag selected"),
+                    (&[Action::LinkSelected], "link selected to a note"),
+                    (&[Action::DeleteNote], "delete selected"),
+                    (&[Action::ExportResults], "export selected"),
+                    (&[Action::Quit], "clear selection"),
+                    (&[Action::MoveDown, Action::MoveUp], "navigate"),
+                    (&[Action::Open], "view"),
+                ],
+            )
+        };
+        let help_text = self.status_message.as_deref().unwrap_or(&help_text);
+        let help = Paragraph::new(help_text)
             .block(Block::default().borders(Borders::ALL).title(" IMPERIUM COMMAND PROTOCOLS "))
             .style(Style::default().fg(Color::White).bg(Color::DarkGray));
         frame.render_widget(help, chunks[2]);
     }
 
     fn render_view(&self, frame: &mut Frame) {
+        // Status row only takes space while there is a message, so it never covers the note
+        let constraints = if self.status_message.is_some() {
+            vec![Constraint::Length(3), Constraint::Min(0), Constraint::Length(3), Constraint::Length(3)]
+        } else {
+            vec![Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)]
+        };
         let chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .constraints(constraints)
             .split(frame.area());
 
         // Title bar - 40k theme
         let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
-            .block(Block::default().borders(Borders::ALL).title(" ADEPTUS ADMINISTRATUM "))
+            .block(self.title_block())
             .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
         frame.render_widget(title, chunks[0]);
 
@@ -791,52 +3085,163 @@ impl App {
         if let Some(ref note) = self.current_note {
             // Build rich text with better formatting
             let mut lines: Vec<Line> = Vec::new();
+
+            match self.view_header {
+                ViewHeader::Full => {
+                // Scratch line pinned above everything else
+                if let Some(ref scratch) = note.scratch {
+                    lines.push(Line::from(vec![
+                        Span::styled("✎ Scratch: ", Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)),
+                        Span::styled(scratch.as_str(), Style::default().fg(Color::Magenta).add_modifier(Modifier::ITALIC)),
+                    ]));
+                    lines.push(Line::default());
+                }
+                if let Some(due) = note.due_at() {
+                    let now = chrono::Utc::now();
+                    let color = if due < now { Color::Red } else { Color::Cyan };
+                    lines.push(Line::styled(
+                        format!("⏰ Due {} ({})", due.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"), due_label(due, now)),
+                        Style::default().fg(color).add_modifier(Modifier::BOLD),
+                    ));
+                    lines.push(Line::default());
+                }
             
-            // Format dates
-            let created_date = if let Ok(parsed) = chrono::DateTime::parse_from_rfc3339(&note.created_at) {
-                parsed.format("%Y-%m-%d %H:%M").to_string()
-            } else {
-                note.created_at.split('T').next().unwrap_or("").to_string()
-            };
-            let updated_date = if let Ok(parsed) = chrono::DateTime::parse_from_rfc3339(&note.updated_at) {
-                parsed.format("%Y-%m-%d %H:%M").to_string()
-            } else {
-                note.updated_at.split('T').next().unwrap_or("").to_string()
-            };
-            
-            // Metadata header - 40k theme (eye-friendly)
-            lines.push(Line::from(vec![
-                Span::styled("☠ Created: ", Style::default().fg(Color::Red)),
-                Span::styled(&created_date, Style::default().fg(Color::Yellow)),
-                Span::styled("  |  ", Style::default().fg(Color::DarkGray)),
-                Span::styled("⚡ Updated: ", Style::default().fg(Color::Red)),
-                Span::styled(&updated_date, Style::default().fg(Color::Yellow)),
-            ]));
-            lines.push(Line::default());
+                // Format dates
+                let created_date = if let Ok(parsed) = chrono::DateTime::parse_from_rfc3339(&note.created_at) {
+                    parsed.format("%Y-%m-%d %H:%M").to_string()
+                } else {
+                    note.created_at.split('T').next().unwrap_or("").to_string()
+                };
+                let updated_date = if let Ok(parsed) = chrono::DateTime::parse_from_rfc3339(&note.updated_at) {
+                    parsed.format("%Y-%m-%d %H:%M").to_string()
+                } else {
+                    note.updated_at.split('T').next().unwrap_or("").to_string()
+                };
             
-            // Tags section - 40k theme (eye-friendly)
-            if !note.tags.is_empty() {
-                let mut tag_spans = vec![Span::styled("⚔ Tags: ", Style::default().fg(Color::Red))];
-                for (i, tag) in note.tags.iter().enumerate() {
-                    if i > 0 {
-                        tag_spans.push(Span::styled(" ", Style::default()));
-                    }
-                    tag_spans.push(Span::styled(
-                        format!("#{}", tag),
-                        Style::default().fg(Color::Yellow),
+                // Metadata header - 40k theme (eye-friendly)
+                let mut header = vec![
+                    Span::styled("☠ Created: ", Style::default().fg(Color::Red)),
+                    Span::styled(created_date, Style::default().fg(Color::Yellow)),
+                    Span::styled("  |  ", Style::default().fg(Color::DarkGray)),
+                    Span::styled("⚡ Updated: ", Style::default().fg(Color::Red)),
+                    Span::styled(updated_date, Style::default().fg(Color::Yellow)),
+                ];
+                let now = chrono::Utc::now();
+                if self.stale_days > 0 && is_stale(&note.updated_at, now, self.stale_days) {
+                    header.push(Span::styled("  |  ", Style::default().fg(Color::DarkGray)));
+                    header.push(Span::styled(
+                        format!("💤 stale, {}", age_label(age_days(&note.updated_at, now))),
+                        Style::default().fg(Color::DarkGray),
                     ));
                 }
-                lines.push(Line::from(tag_spans));
+                if let Some(label) = note.color.as_deref()
+                    && let Some(color) = label_color(label)
+                {
+                    header.push(Span::styled("  |  ", Style::default().fg(Color::DarkGray)));
+                    header.push(Span::styled(format!("● {}", label), Style::default().fg(color)));
+                }
+                lines.push(Line::from(header));
                 lines.push(Line::default());
+            
+                // Tags section - 40k theme (eye-friendly)
+                if !note.tags.is_empty() {
+                    let mut tag_spans = vec![Span::styled("⚔ Tags: ", Style::default().fg(Color::Red))];
+                    for (i, tag) in note.tags.iter().enumerate() {
+                        if i > 0 {
+                            tag_spans.push(Span::styled(" ", Style::default()));
+                        }
+                        tag_spans.push(Span::styled(
+                            format!("#{}", tag),
+                            Style::default().fg(Color::Yellow),
+                        ));
+                    }
+                    lines.push(Line::from(tag_spans));
+                    lines.push(Line::default());
+                }
+            
+                // Metadata fields
+                if !note.metadata.is_empty() {
+                    for (key, value) in &note.metadata {
+                        lines.push(Line::from(vec![
+                            Span::styled(format!("◆ {}: ", key), Style::default().fg(Color::Red)),
+                            Span::styled(value, Style::default().fg(Color::Yellow)),
+                        ]));
+                    }
+                    lines.push(Line::default());
+                }
+                }
+                ViewHeader::Compact => {
+                    lines.push(compact_header(note));
+                    lines.push(Line::default());
+                }
+                ViewHeader::Hidden => {}
             }
             
-            // Content
-            for line in note.content.lines() {
-                lines.push(Line::from(Span::styled(line, Style::default().fg(Color::White))));
+            // Content, with unrecognized words underlined when spell checking is on
+            let mut misspelled: HashMap<usize, HashSet<String>> = HashMap::new();
+            if self.spellcheck_enabled
+                && let Some(ref dictionary) = self.dictionary
+            {
+                for (line_index, word) in spellcheck::spellcheck(&note.content, dictionary) {
+                    misspelled.entry(line_index).or_default().insert(word);
+                }
+            }
+            // The note's format decides how plain lines look
+            let rendered = render::renderer_for(note.format()).render(&note.content);
+            let mut content_lines = Vec::new();
+            for ((line_index, line), styled) in note.content.lines().enumerate().zip(rendered) {
+                // Find matches take precedence over spell-check marks on the same line
+                let line_matches: Vec<(usize, &TextMatch)> = self
+                    .find_matches
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, found)| found.line == line_index)
+                    .collect();
+                if !line_matches.is_empty() {
+                    let mut spans = Vec::new();
+                    let mut position = 0;
+                    for (match_index, found) in line_matches {
+                        spans.push(Span::styled(&line[position..found.start], Style::default().fg(Color::White)));
+                        let style = if match_index == self.find_index {
+                            Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD)
+                        } else {
+                            Style::default().fg(Color::Black).bg(Color::DarkGray)
+                        };
+                        spans.push(Span::styled(&line[found.start..found.end], style));
+                        position = found.end;
+                    }
+                    spans.push(Span::styled(&line[position..], Style::default().fg(Color::White)));
+                    content_lines.push(Line::from(spans));
+                    continue;
+                }
+                match misspelled.get(&line_index) {
+                    Some(words) => {
+                        let spans: Vec<Span> = spellcheck::word_segments(line)
+                            .into_iter()
+                            .map(|(segment, is_word)| {
+                                if is_word && words.contains(segment.trim_matches('\'')) {
+                                    Span::styled(segment, Style::default().fg(Color::Red).add_modifier(Modifier::UNDERLINED))
+                                } else {
+                                    Span::styled(segment, Style::default().fg(Color::White))
+                                }
+                            })
+                            .collect();
+                        content_lines.push(Line::from(spans));
+                    }
+                    None => content_lines.push(styled),
+                }
             }
+            // Lines are styled one by one first, so find matches and spell-check marks stay put
+            match self.paragraph_rows(note) {
+                Some(rows) => lines.extend(render::join_soft_breaks(content_lines, &rows)),
+                None => lines.extend(content_lines),
+            }
+            
+            // Links are numbered for digit jumps: forward links first, then backlinks
+            let linked_notes = self.forward_link_notes(note);
             
             // Backlinks section - 40k theme (eye-friendly)
-            let backlinks: Vec<_> = self.service.get_backlinks(&note.id).unwrap_or_default();
+            let backlinks = self.backlinks_of(&note.id);
             if !backlinks.is_empty() {
                 lines.push(Line::default());
                 lines.push(Line::from(Span::styled(
@@ -849,10 +3254,10 @@ impl App {
                     } else {
                         Span::styled("    ", Style::default())
                     };
-                    let title = backlink.title.clone();
                     lines.push(Line::from(vec![
                         prefix,
-                        Span::styled(title, Style::default().fg(Color::White)),
+                        Span::styled(format!("{}. ", linked_notes.len() + i + 1), Style::default().fg(Color::DarkGray)),
+                        Span::styled(self.link_label(backlink), Style::default().fg(Color::White)),
                     ]));
                 }
             }
@@ -864,14 +3269,7 @@ impl App {
                     "⚡ Linked Notes:",
                     Style::default().fg(Color::Yellow),
                 )));
-                let linked_notes: Vec<_> = note.links
-                    .iter()
-                    .filter_map(|link_id| {
-                        self.service.get_note(link_id).ok().flatten()
-                            .map(|n| (link_id.clone(), n.title.clone()))
-                    })
-                    .collect();
-                for (i, (_link_id, linked_title)) in linked_notes.iter().enumerate() {
+                for (i, linked_note) in linked_notes.iter().enumerate() {
                     let prefix = if i == self.link_selected_index {
                         Span::styled("  ⚔ ", Style::default().fg(Color::Yellow))
                     } else {
@@ -879,16 +3277,66 @@ impl App {
                     };
                     lines.push(Line::from(vec![
                         prefix,
-                        Span::styled(linked_title.clone(), Style::default().fg(Color::White)),
+                        Span::styled(format!("{}. ", i + 1), Style::default().fg(Color::DarkGray)),
+                        Span::styled(self.link_label(linked_note), Style::default().fg(Color::White)),
                     ]));
                 }
+                // Dangling links are hidden above; showing ids is for tracking them down
+                if self.link_labels != LinkLabels::Titles {
+                    for link_id in note.links.iter().filter(|id| !linked_notes.iter().any(|n| n.id == **id)) {
+                        lines.push(Line::from(Span::styled(
+                            format!("    ✗ {} (no such note)", link_id),
+                            Style::default().fg(Color::Red),
+                        )));
+                    }
+                }
             }
             
-            let content = Paragraph::new(lines)
-                .block(Block::default().borders(Borders::ALL).title(format!(" ⚔ {} ⚔ ", note.title)))
-                .wrap(Wrap { trim: true })
+            let mut block_title = vec![Span::raw(format!(" ⚔ {} ⚔ ", note.title))];
+            if self.short_id_length > 0 {
+                let short_id: String = note.id.chars().take(self.short_id_length).collect();
+                block_title.push(Span::styled(format!("#{} ", short_id), Style::default().fg(Color::DarkGray)));
+            }
+            let tasks = extract_tasks(&note.content);
+            if !tasks.is_empty() {
+                let done = tasks.iter().filter(|(done, _)| *done).count();
+                block_title.push(Span::styled(format!("☑ {}/{} ", done, tasks.len()), Style::default().fg(Color::Cyan)));
+            }
+            if let Some(ref commit) = note.canonical_commit {
+                block_title.push(Span::styled(format!("★ canonical {} ", commit), Style::default().fg(Color::Green)));
+            }
+            if note.locked {
+                block_title.push(Span::styled("🔒 locked ", Style::default().fg(Color::Yellow)));
+            }
+            let mut block = Block::default().borders(Borders::ALL).title(Line::from(block_title));
+            if self.trail.len() > 1 {
+                // Breadcrumbs of the followed links, on the bottom border so the content does not move
+                let mut crumbs = vec![Span::raw(" ")];
+                for (i, id) in self.trail.iter().enumerate() {
+                    if i > 0 {
+                        crumbs.push(Span::styled(" › ", Style::default().fg(Color::DarkGray)));
+                    }
+                    let style = if self.crumb_cursor == Some(i) {
+                        Style::default().fg(Color::Black).bg(Color::Yellow)
+                    } else if i + 1 == self.trail.len() {
+                        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default().fg(Color::Gray)
+                    };
+                    crumbs.push(Span::styled(self.crumb_title(id), style));
+                }
+                crumbs.push(Span::raw(" "));
+                block = block.title_bottom(Line::from(crumbs));
+            }
+            let content = self
+                .wrap_note_text(Paragraph::new(lines))
+                .block(block)
+                .scroll((self.view_scroll, self.view_hscroll))
                 .style(Style::default().fg(Color::White).bg(Color::Black));
             frame.render_widget(content, chunks[1]);
+            if self.hyperlinks {
+                hyperlink::link_urls(frame.buffer_mut(), chunks[1]);
+            }
         }
 
         // Status message with better styling
@@ -908,21 +3356,59 @@ impl App {
             let status = Paragraph::new(status_text.as_str())
                 .block(Block::default().borders(Borders::ALL).title("Status"))
                 .style(Style::default().fg(status_color));
-            let status_chunk = if chunks.len() > 3 { chunks[2] } else { chunks[chunks.len() - 2] };
-            frame.render_widget(status, status_chunk);
+            frame.render_widget(status, chunks[2]);
         }
 
         // Help bar
-        let help_text = if let Some(ref note) = self.current_note {
-            let has_backlinks = self.service.get_backlinks(&note.id).map(|b| !b.is_empty()).unwrap_or(false);
-            if !note.links.is_empty() || has_backlinks {
-                "e: edit | l: link | t: tag | u: unlink | x: remove tag | h: history | j/k: navigate | Enter: open | E: export | Esc: back"
-            } else {
-                "e: edit | l: link | t: tag | x: remove tag | h: history | E: export | Esc: back"
-            }
-        } else {
-            "e: edit | l: link | t: tag | h: history | E: export | Esc: back"
+        let (has_note, has_links) = match self.current_note {
+            Some(ref note) => (true, !note.links.is_empty() || self.backlink_index.contains_key(&note.id)),
+            None => (false, false),
         };
+        let mut entries: Vec<(&[Action], &str)> = vec![
+            (&[Action::EditNote], "edit"),
+            (&[Action::ExternalEdit], "edit in $EDITOR"),
+            (&[Action::LinkNote], "link"),
+            (&[Action::CreateLinkedNote], "new + link"),
+            (&[Action::AddTag], "tag"),
+        ];
+        if has_links {
+            entries.extend([(&[Action::UnlinkNote][..], "unlink"), (&[Action::ReorderLinks], "reorder links")]);
+        }
+        if has_note {
+            entries.push((&[Action::RemoveTag], "remove tag"));
+        }
+        entries.extend([
+            (&[Action::EditMetadata][..], "metadata"),
+            (&[Action::RenameNote], "rename"),
+            (&[Action::ShowHistory], "history"),
+            (&[Action::MoveNote], "move"),
+            (&[Action::ToggleSpellcheck], "spell"),
+            (&[Action::CycleLinkLabels], "ids"),
+            (&[Action::CycleHeader], "header"),
+            (&[Action::CycleColor], "color"),
+            (&[Action::EditScratch], "scratch"),
+            (&[Action::SetDue], "due"),
+            (&[Action::TogglePin], "pin"),
+            (&[Action::ToggleLock], "lock"),
+            (&[Action::OrderUp, Action::OrderDown], "order"),
+            (&[Action::PickBreadcrumb, Action::TrailBack], "breadcrumbs"),
+            (&[Action::FindInNote], "find"),
+        ]);
+        if has_links {
+            entries.extend([
+                (&[Action::MoveDown, Action::MoveUp][..], "navigate"),
+                (&[], "1-9: jump"),
+                (&[Action::Open], "open"),
+            ]);
+        }
+        entries.extend([
+            (&[Action::ExportNote][..], "export"),
+            (&[Action::ExportWithLinks], "export with links"),
+            (&[Action::CopyNote], "copy"),
+            (&[Action::ShowHelp], "keys"),
+            (&[Action::Back], "back"),
+        ]);
+        let help_text = self.keymap.help_bar(KeyContext::View, &entries);
         let help = Paragraph::new(help_text)
             .block(Block::default().borders(Borders::ALL).title(" IMPERIUM COMMAND PROTOCOLS "))
             .style(Style::default().fg(Color::White).bg(Color::DarkGray));
@@ -938,26 +3424,49 @@ impl App {
 
         // Title bar - 40k theme
         let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
-            .block(Block::default().borders(Borders::ALL).title(" ADEPTUS ADMINISTRATUM "))
+            .block(self.title_block())
             .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
         frame.render_widget(title, chunks[0]);
 
         // Edit content with character count
-        let char_count = self.input_buffer.len();
+        let char_count = self.input_buffer.chars().count();
         let line_count = self.input_buffer.lines().count();
         let title_text = if let Some(ref note) = self.current_note {
             format!("Editing: {} ({} chars, {} lines)", note.title, char_count, line_count)
         } else {
             format!("Editing ({} chars, {} lines)", char_count, line_count)
         };
-        let content = Paragraph::new(self.input_buffer.as_str())
-            .block(Block::default().borders(Borders::ALL).title(title_text))
-            .wrap(Wrap { trim: true })
-            .style(Style::default().fg(Color::White));
-        frame.render_widget(content, chunks[1]);
+        self.render_edit_buffer(frame, chunks[1], title_text, Color::White);
 
-        // Help bar - 40k theme
-        let help = Paragraph::new("Ctrl+S: save | Esc: cancel")
+        // Help bar - 40k theme, replaced by the status message until the next key
+        let help_text = self.status_message.as_deref().unwrap_or("Ctrl+S: save | Ctrl+V: paste | Ctrl+E: $EDITOR | Ctrl+G: word → tag (Alt+G: and cut it) | arrows/Home/End: move cursor | Esc: cancel");
+        let help = Paragraph::new(help_text)
+            .block(Block::default().borders(Borders::ALL).title(" IMPERIUM COMMAND PROTOCOLS "))
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black));
+        frame.render_widget(help, chunks[2]);
+    }
+
+    fn render_raw_edit(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.area());
+
+        // Title bar - 40k theme
+        let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
+
+        let title_text = match self.current_note {
+            Some(ref note) => format!("Raw JSON: {} ({} lines)", note.title, self.input_buffer.lines().count()),
+            None => "Raw JSON".to_string(),
+        };
+        self.render_edit_buffer(frame, chunks[1], title_text, Color::Cyan);
+
+        // Help bar - 40k theme, replaced by the status message (e.g. a JSON error) until the next key
+        let help_text = self.status_message.as_deref().unwrap_or("Ctrl+S: validate and save | Ctrl+V: paste | Ctrl+E: $EDITOR | arrows/Home/End: move cursor | Esc: discard");
+        let help = Paragraph::new(help_text)
             .block(Block::default().borders(Borders::ALL).title(" IMPERIUM COMMAND PROTOCOLS "))
             .style(Style::default().fg(Color::Yellow).bg(Color::Black));
         frame.render_widget(help, chunks[2]);
@@ -971,28 +3480,30 @@ impl App {
 
         // Title bar - 40k theme
         let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
-            .block(Block::default().borders(Borders::ALL).title(" ADEPTUS ADMINISTRATUM "))
+            .block(self.title_block())
             .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
         frame.render_widget(title, chunks[0]);
 
         // Create content with character count and title preview
-        let char_count = self.input_buffer.len();
+        let char_count = self.input_buffer.chars().count();
         let line_count = self.input_buffer.lines().count();
         let first_line = self.input_buffer.lines().next().unwrap_or("").trim();
         let title_preview = if first_line.is_empty() {
-            "Untitled (first line will be title)"
+            "Untitled (first line will be title)".to_string()
         } else {
-            first_line
+            truncate_chars(first_line, 40)
         };
-        let title_text = format!("New Note: {} ({} chars, {} lines)", title_preview, char_count, line_count);
-        let content = Paragraph::new(self.input_buffer.as_str())
-            .block(Block::default().borders(Borders::ALL).title(title_text))
-            .wrap(Wrap { trim: true })
-            .style(Style::default().fg(Color::White));
-        frame.render_widget(content, chunks[1]);
+        let mut title_text = format!("New Note: {} ({} chars, {} lines)", title_preview, char_count, line_count);
+        if self.link_source_id.is_some()
+            && let Some(ref source) = self.current_note
+        {
+            title_text.push_str(&format!(" - will be linked from {}", source.title));
+        }
+        self.render_edit_buffer(frame, chunks[1], title_text, Color::White);
 
         // Help bar - 40k theme
-        let help = Paragraph::new("Ctrl+S: create | Esc: cancel")
+        let help_text = self.status_message.as_deref().unwrap_or("Ctrl+S: create | Ctrl+V: paste | Ctrl+E: $EDITOR | arrows/Home/End: move cursor | Esc: cancel");
+        let help = Paragraph::new(help_text)
             .block(Block::default().borders(Borders::ALL).title(" IMPERIUM COMMAND PROTOCOLS "))
             .style(Style::default().fg(Color::Yellow).bg(Color::Black));
         frame.render_widget(help, chunks[2]);
@@ -1006,35 +3517,42 @@ impl App {
 
         // Title bar - 40k theme
         let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
-            .block(Block::default().borders(Borders::ALL).title(" ADEPTUS ADMINISTRATUM "))
+            .block(self.title_block())
             .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
         frame.render_widget(title, chunks[0]);
 
         // Search input - 40k theme (eye-friendly)
         let search_prompt = format!("⚔ {}", self.input_buffer);
+        let search_title = if self.state.search_history.is_empty() {
+            " INQUISITORIAL SEARCH (Tab: sort | Ctrl+F: fuzzy) "
+        } else {
+            " INQUISITORIAL SEARCH (↑/↓: history | Tab: sort | Ctrl+F: fuzzy) "
+        };
         let search = Paragraph::new(search_prompt.as_str())
-            .block(Block::default().borders(Borders::ALL).title(" INQUISITORIAL SEARCH "))
+            .block(Block::default().borders(Borders::ALL).title(search_title))
             .style(Style::default().fg(Color::White).bg(Color::Black));
         frame.render_widget(search, chunks[1]);
 
         // Results preview with list
         if self.filtered_notes.is_empty() {
-            let results_text = Paragraph::new("No results found. Try a different search term.")
-                .block(Block::default().borders(Borders::ALL).title(format!("Results (0 found)")))
+            let hint = if self.notes.is_empty() {
+                "No notes yet. Press Esc, then n to create your first note."
+            } else if self.input_buffer.starts_with('#') {
+                "No results found. Try another tag, or Backspace to clear the filter and Esc to see all notes."
+            } else {
+                "No results found. Try a different search term, or press Esc to clear the filter and see all notes."
+            };
+            let results_text = Paragraph::new(hint)
+                .block(Block::default().borders(Borders::ALL).title("Results (0 found)"))
                 .style(Style::default().fg(Color::Red))
                 .wrap(Wrap { trim: true });
             frame.render_widget(results_text, chunks[2]);
         } else {
             let results_list: Vec<ListItem> = self.filtered_notes
                 .iter()
-                .take(20) // Show first 20 results for performance
+                .take(20.max(self.selected_index + 1)) // First 20 results for performance, plus the selection
                 .map(|note| {
-                    let preview = note.content.lines().next().unwrap_or("").trim();
-                    let preview_truncated: String = if preview.len() > 50 {
-                        format!("{}...", &preview[..50])
-                    } else {
-                        preview.to_string()
-                    };
+                    let preview_truncated = truncate_chars(&self.preview(note), 50);
                     let note_title = note.title.clone();
                     ListItem::new(vec![
                         Line::from(vec![
@@ -1049,10 +3567,11 @@ impl App {
                 .collect();
             
             let list = List::new(results_list)
-                .block(Block::default().borders(Borders::ALL).title(format!("Results ({} found, showing first 20)", self.filtered_notes.len())))
+                .block(Block::default().borders(Borders::ALL).title(format!("Results ({} found, {}, showing first 20)", self.filtered_notes.len(), self.search_description())))
                 .highlight_style(Style::default().fg(Color::Yellow));
             let mut list_state = ratatui::widgets::ListState::default();
-            list_state.select(Some(0));
+            // The highlighted result is the note that stays selected after Enter
+            list_state.select(Some(self.selected_index));
             frame.render_stateful_widget(list, chunks[2], &mut list_state);
         }
     }
@@ -1065,7 +3584,7 @@ impl App {
 
         // Title bar - 40k theme
         let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
-            .block(Block::default().borders(Borders::ALL).title(" ADEPTUS ADMINISTRATUM "))
+            .block(self.title_block())
             .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
         frame.render_widget(title, chunks[0]);
 
@@ -1096,7 +3615,7 @@ impl App {
 
         // Title bar - 40k theme
         let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
-            .block(Block::default().borders(Borders::ALL).title(" ADEPTUS ADMINISTRATUM "))
+            .block(self.title_block())
             .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
         frame.render_widget(title, chunks[0]);
 
@@ -1125,10 +3644,27 @@ impl App {
         let mut state = ratatui::widgets::ListState::default();
         state.select(Some(self.selected_index));
         
+        // Candidates on the left, preview of the selected one on the right
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+            .split(chunks[1]);
+        
         let list = List::new(items)
             .block(Block::default().borders(Borders::ALL).title("Select Note to Link"))
             .highlight_style(Style::default().fg(Color::Yellow));
-        frame.render_stateful_widget(list, chunks[1], &mut state);
+        frame.render_stateful_widget(list, panes[0], &mut state);
+        
+        let preview_lines = self
+            .notes
+            .get(self.selected_index)
+            .map(|note| note_preview_lines(note, 12))
+            .unwrap_or_default();
+        let preview = Paragraph::new(preview_lines)
+            .block(Block::default().borders(Borders::ALL).title("Preview"))
+            .wrap(Wrap { trim: true })
+            .style(Style::default().fg(Color::White).bg(Color::Black));
+        frame.render_widget(preview, panes[1]);
 
         // Help bar
         let help = Paragraph::new("j/k: navigate | Enter: link | Esc: cancel")
@@ -1145,7 +3681,7 @@ impl App {
 
         // Title bar - 40k theme
         let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
-            .block(Block::default().borders(Borders::ALL).title(" ADEPTUS ADMINISTRATUM "))
+            .block(self.title_block())
             .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
         frame.render_widget(title, chunks[0]);
 
@@ -1180,7 +3716,7 @@ impl App {
 
         // Title bar - 40k theme
         let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
-            .block(Block::default().borders(Borders::ALL).title(" ADEPTUS ADMINISTRATUM "))
+            .block(self.title_block())
             .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
         frame.render_widget(title, chunks[0]);
 
@@ -1215,7 +3751,7 @@ impl App {
 
         // Title bar - 40k theme
         let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
-            .block(Block::default().borders(Borders::ALL).title(" ADEPTUS ADMINISTRATUM "))
+            .block(self.title_block())
             .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
         frame.render_widget(title, chunks[0]);
 
@@ -1231,7 +3767,7 @@ impl App {
                     } else {
                         Style::default()
                     };
-                    ListItem::new(format!("{}", tag)).style(style)
+                    ListItem::new(tag.as_str()).style(style)
                 })
                 .collect();
 
@@ -1254,8 +3790,12 @@ impl App {
     fn handle_statistics_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
         match key {
             crossterm::event::KeyCode::Esc => {
+                self.status_message = None;
                 self.mode = AppMode::List;
             }
+            crossterm::event::KeyCode::Char('c') => self.mode = AppMode::Cycles,
+            crossterm::event::KeyCode::Char('s') => self.mode = AppMode::Stubs,
+            crossterm::event::KeyCode::Char('o') if self.stale_days > 0 => self.mode = AppMode::Stale,
             _ => {}
         }
         Ok(())
@@ -1269,61 +3809,94 @@ impl App {
 
         // Title bar - 40k theme
         let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
-            .block(Block::default().borders(Borders::ALL).title(" ADEPTUS ADMINISTRATUM "))
+            .block(self.title_block())
             .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
         frame.render_widget(title, chunks[0]);
 
         // Statistics
-        if let Ok(stats) = self.service.get_statistics() {
-            let stats_text = format!(
-                "📊 Knowledge Base Statistics\n\n\
-                Total Notes: {}\n\
-                Total Links: {}\n\
-                Total Tags: {}\n\
-                Unique Tags: {}\n\n\
-                Average links per note: {:.2}\n\
-                Average tags per note: {:.2}",
-                stats.total_notes,
-                stats.total_links,
-                stats.total_tags,
-                stats.unique_tags_count,
-                if stats.total_notes > 0 {
-                    stats.total_links as f64 / stats.total_notes as f64
-                } else {
-                    0.0
-                },
-                if stats.total_notes > 0 {
-                    stats.total_tags as f64 / stats.total_notes as f64
-                } else {
-                    0.0
-                }
-            );
-            
-            let stats_para = Paragraph::new(stats_text)
-                .block(Block::default().borders(Borders::ALL).title("Statistics"))
-                .wrap(Wrap { trim: true })
-                .style(Style::default().fg(Color::Yellow));
-            frame.render_widget(stats_para, chunks[1]);
-        }
+        match self.service.get_statistics() {
+            Ok(stats) if stats.total_notes == 0 => {
+                let empty = Paragraph::new("📊 Knowledge Base Statistics\n\nNo notes yet — statistics appear once you create notes.\n\nPress Esc, then n to create your first note.")
+                    .block(Block::default().borders(Borders::ALL).title("Statistics"))
+                    .wrap(Wrap { trim: true })
+                    .style(Style::default().fg(Color::DarkGray));
+                frame.render_widget(empty, chunks[1]);
+            }
+            Ok(stats) => {
+                let stats_text = format!(
+                    "📊 Knowledge Base Statistics\n\n\
+                    Total Notes: {}\n\
+                    Total Links: {}\n\
+                    Total Tags: {}\n\
+                    Unique Tags: {}\n\n\
+                    Average links per note: {:.2}\n\
+                    Average tags per note: {:.2}\n\n\
+                    Link cycles: {} (informational; c: review)\n\
+                    Stub notes: {} (title only or boilerplate; s: review)\n\
+                    Stale notes: {}",
+                    stats.total_notes,
+                    stats.total_links,
+                    stats.total_tags,
+                    stats.unique_tags_count,
+                    stats.total_links as f64 / stats.total_notes as f64,
+                    stats.total_tags as f64 / stats.total_notes as f64,
+                    self.cycles.len(),
+                    self.stubs.len(),
+                    if self.stale_days > 0 {
+                        format!("{} (not updated in {}+ days; o: review)", self.stale_notes.len(), self.stale_days)
+                    } else {
+                        "off (stale_days is 0)".to_string()
+                    }
+                );
+                
+                let stats_para = Paragraph::new(stats_text)
+                    .block(Block::default().borders(Borders::ALL).title("Statistics"))
+                    .wrap(Wrap { trim: true })
+                    .style(Style::default().fg(Color::Yellow));
+                frame.render_widget(stats_para, chunks[1]);
+            }
+            Err(e) => {
+                let error = Paragraph::new(format!("✗ Failed to compute statistics: {}", e))
+                    .block(Block::default().borders(Borders::ALL).title("Statistics"))
+                    .wrap(Wrap { trim: true })
+                    .style(Style::default().fg(Color::Red));
+                frame.render_widget(error, chunks[1]);
+            }
+        }
 
-        // Help bar
-        let help = Paragraph::new("Esc: back")
+        // Help bar, replaced by the status message until the next key
+        let help_text = self.status_message.as_deref().unwrap_or("c: review link cycles | s: review stub notes | o: review stale notes | Esc: back");
+        let help = Paragraph::new(help_text)
             .block(Block::default().borders(Borders::ALL).title("Help"))
             .style(Style::default().fg(Color::DarkGray));
         frame.render_widget(help, chunks[2]);
     }
 
-    fn handle_help_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+    fn handle_cycles_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
         match key {
-            crossterm::event::KeyCode::Esc => {
-                self.mode = AppMode::List;
+            crossterm::event::KeyCode::Esc => self.close_review(AppMode::Statistics),
+            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down
+                if self.cycle_index + 1 < self.cycles.len() =>
+            {
+                self.cycle_index += 1;
+            }
+            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                self.cycle_index = self.cycle_index.saturating_sub(1);
+            }
+            crossterm::event::KeyCode::Enter => {
+                if let Some(id) = self.cycles.get(self.cycle_index).and_then(|cycle| cycle.first())
+                    && let Some(note) = self.service.get_note(id)?
+                {
+                    self.open_note(note);
+                    self.mode = AppMode::View;
+                }
             }
             _ => {}
         }
         Ok(())
     }
 
-    fn render_help(&self, frame: &mut Frame) {
+    fn render_cycles(&self, frame: &mut Frame) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
@@ -1331,42 +3904,150 @@ impl App {
 
         // Title bar - 40k theme
         let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
-            .block(Block::default().borders(Borders::ALL).title(" ADEPTUS ADMINISTRATUM "))
+            .block(self.title_block())
             .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
         frame.render_widget(title, chunks[0]);
 
-        // Help content
-        let help_text = r#"📖 Keyboard Shortcuts
-
-LIST MODE:
-  j / ↓          Navigate down
-  k / ↑          Navigate up
-  n              Create new note
-  /              Search notes
-  #              Search by tag
-  d              Delete note
-  c              Duplicate note
-  s              Show statistics
-  r              Refresh notes
-  ?              Show this help
-  Enter          View selected note
-  Esc            Quit (or clear search)
-
-VIEW MODE:
-  e              Edit note
-  l              Link to another note
-  t              Add tag
-  u              Unlink selected note
-  x              Remove tag
-  h              Show commit history
-  j / ↓          Navigate links (backlinks first)
-  k / ↑          Navigate links (backlinks first)
-  Enter          Open selected link
-  E              Export to markdown
-  Esc            Back to list
+        let block = Block::default().borders(Borders::ALL).title(format!("Link Cycles ({})", self.cycles.len()));
+        if self.cycles.is_empty() {
+            let para = Paragraph::new("No circular link chains. Cycles are allowed; this list only helps to review them.")
+                .block(block)
+                .wrap(Wrap { trim: true })
+                .style(Style::default().fg(Color::DarkGray));
+            frame.render_widget(para, chunks[1]);
+        } else {
+            let title_of = |id: &str| {
+                self.notes
+                    .iter()
+                    .find(|n| n.id == id)
+                    .map(|n| n.title.clone())
+                    .unwrap_or_else(|| id.to_string())
+            };
+            let items: Vec<ListItem> = self
+                .cycles
+                .iter()
+                .map(|cycle| {
+                    // Close the loop by repeating the first note at the end
+                    let chain: Vec<String> = cycle.iter().chain(cycle.first()).map(|id| title_of(id)).collect();
+                    ListItem::new(Line::styled(chain.join(" → "), Style::default().fg(Color::White)))
+                })
+                .collect();
+
+            let mut state = ratatui::widgets::ListState::default();
+            state.select(Some(self.cycle_index));
+
+            let list = List::new(items)
+                .block(block)
+                .highlight_style(Style::default().fg(Color::Yellow).bg(Color::DarkGray))
+                .highlight_symbol("⚔ ");
+            frame.render_stateful_widget(list, chunks[1], &mut state);
+        }
+
+        let help = Paragraph::new("j/k: navigate | Enter: open first note | Esc: back to statistics")
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, chunks[2]);
+    }
+
+    fn handle_help_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        if key == crossterm::event::KeyCode::Esc {
+            self.mode = match self.help_return {
+                KeyContext::List => AppMode::List,
+                KeyContext::View => AppMode::View,
+            };
+        }
+        Ok(())
+    }
+
+    fn open_help_overlay(&mut self, context: KeyContext) {
+        self.help_overlay = Some(context);
+        self.help_scroll = 0;
+    }
+
+    /// Keys for the help popup: j/k scroll, `?` goes on to the help screen for every mode,
+    /// anything else closes it
+    fn handle_help_overlay_key(&mut self, context: KeyContext, key: crossterm::event::KeyCode) {
+        match key {
+            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
+                let rows = self.keymap.help_rows(context).len() as u16;
+                self.help_scroll = (self.help_scroll + 1).min(rows.saturating_sub(1));
+            }
+            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                self.help_scroll = self.help_scroll.saturating_sub(1);
+            }
+            crossterm::event::KeyCode::Char('?') => {
+                self.help_overlay = None;
+                self.help_return = context;
+                self.mode = AppMode::Help;
+            }
+            _ => self.help_overlay = None,
+        }
+    }
+
+    /// The current mode's effective bindings, drawn as a popup over its screen
+    fn render_help_overlay(&self, frame: &mut Frame, context: KeyContext) {
+        let rows = self.keymap.help_rows(context);
+        let text: Vec<Line> = rows
+            .iter()
+            .map(|(keys, description)| {
+                Line::from(vec![
+                    Span::styled(format!("{:<15}", keys), Style::default().fg(Color::Yellow)),
+                    Span::raw(*description),
+                ])
+            })
+            .collect();
+        let area = frame.area();
+        let width = area.width.saturating_sub(4).min(80);
+        let height = (rows.len() as u16 + 2).min(area.height.saturating_sub(2));
+        let popup = Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height);
+        let mode = match context {
+            KeyContext::List => "List",
+            KeyContext::View => "View",
+        };
+        let panel = Paragraph::new(text)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!(" {} mode keys ", mode))
+                    .title_bottom(Line::from(" j/k: scroll | ?: every mode | any key: close ").right_aligned())
+                    .border_style(Style::default().fg(Color::Cyan)),
+            )
+            .scroll((self.help_scroll, 0))
+            .style(Style::default().fg(Color::White).bg(Color::Black));
+        frame.render_widget(Clear, popup);
+        frame.render_widget(panel, popup);
+    }
+
+    fn render_help(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.area());
+
+        // Title bar - 40k theme
+        let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
 
+        // Help content, with List/View keys taken from the effective keymap
+        let mut help_text = String::from("📖 Keyboard Shortcuts\n");
+        for (context, heading) in [(KeyContext::List, "LIST MODE:"), (KeyContext::View, "VIEW MODE:")] {
+            help_text.push('\n');
+            help_text.push_str(heading);
+            help_text.push('\n');
+            for (keys, description) in self.keymap.help_rows(context) {
+                help_text.push_str(&format!("  {:<15}{}\n", keys, description));
+            }
+        }
+        help_text.push_str(r#"
 EDIT/CREATE MODE:
-  Type           Edit content
+  Type           Edit content at the cursor
+  Arrows         Move cursor (↑/↓ by lines)
+  Home / End     Start / end of the line
+  Delete         Delete the character after the cursor
+  Ctrl+G         Tag the note with the word at the cursor (Alt+G also cuts it; Edit only)
+  Ctrl+E         Continue in $EDITOR; its text comes back here when it exits
   Ctrl+S         Save
   Esc            Cancel
 
@@ -1374,8 +4055,12 @@ OTHER:
   Search:        Type to search, Enter to apply
   Tag Search:    #tagname to filter by tag
   Link Select:   j/k to navigate, Enter to link
+  Templates:     templates/*.md in the vault, {{cursor}} marks the start position
   Tag Remove:    j/k to navigate, Enter to remove
-"#;
+  Link Jump:     1-9 in View mode open a numbered link (type more digits + Enter past 9)
+  Metadata:      a to add, Enter to edit, d to delete (key: value)
+  Field Search:  field:value to filter by metadata
+"#);
 
         let help_para = Paragraph::new(help_text)
             .block(Block::default().borders(Borders::ALL).title("Keyboard Shortcuts"))
@@ -1390,16 +4075,86 @@ OTHER:
         frame.render_widget(help, chunks[2]);
     }
 
+    /// Load the current note's commit history for History mode
+    fn load_history(&mut self) {
+        let Some(ref note) = self.current_note else {
+            return;
+        };
+        match self.service.get_note_history(&note.id) {
+            Ok(history) => {
+                self.history = history;
+                self.history_error = None;
+            }
+            Err(e) => {
+                self.history.clear();
+                self.history_error = Some(e.to_string());
+            }
+        }
+    }
+
     fn handle_history_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        self.status_message = None;
         match key {
             crossterm::event::KeyCode::Esc => {
                 self.mode = AppMode::View;
             }
+            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down
+                if self.selected_index + 1 < self.history.len() =>
+            {
+                self.selected_index += 1;
+            }
+            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                self.selected_index = self.selected_index.saturating_sub(1);
+            }
+            crossterm::event::KeyCode::Char('p' | 'R') if self.refuse_read_only() => {}
+            crossterm::event::KeyCode::Char('p') => {
+                // Pin the selected commit as canonical; pinning it again clears the mark
+                let (Some(note), Some(commit)) = (self.current_note.clone(), self.history.get(self.selected_index)) else {
+                    return Ok(());
+                };
+                let pin = (note.canonical_commit.as_deref() != Some(commit.id.as_str())).then(|| commit.id.clone());
+                let updated = self.service.set_canonical_commit(&note.id, pin)?;
+                self.set_status(match updated.canonical_commit {
+                    Some(ref id) => format!("✓ Canonical version: {}", id),
+                    None => "✓ Canonical version cleared".to_string(),
+                });
+                self.current_note = Some(updated);
+                self.reload_notes()?;
+            }
+            crossterm::event::KeyCode::Char('R')
+                if self.current_note.clone().is_some_and(|note| self.refuse_locked(&note)) => {}
+            crossterm::event::KeyCode::Char('R') => {
+                if self.current_note.as_ref().is_some_and(|note| note.canonical_commit.is_some()) {
+                    self.mode = AppMode::Confirm(ConfirmKind::RestoreCanonical);
+                } else {
+                    self.set_status("✗ No canonical version; press p on a commit to mark one".to_string());
+                }
+            }
             _ => {}
         }
         Ok(())
     }
 
+    fn restore_canonical(&mut self) -> Result<()> {
+        self.mode = AppMode::History;
+        let Some(note) = self.current_note.clone() else {
+            return Ok(());
+        };
+        self.record_undo(format!("Restore '{}' to its canonical version", note.title));
+        match self.service.restore_canonical(&note.id) {
+            Ok(restored) => {
+                self.fire_hook(HookEvent::Update, &restored.id);
+                self.set_status(format!("✓ Restored '{}' to {}", restored.title, restored.canonical_commit.as_deref().unwrap_or("")));
+                self.current_note = Some(restored);
+                self.reload_notes()?;
+                self.load_history();
+                self.selected_index = 0;
+            }
+            Err(e) => self.set_status(format!("✗ Restore failed: {}", e)),
+        }
+        Ok(())
+    }
+
     fn render_history(&self, frame: &mut Frame) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
@@ -1408,50 +4163,3938 @@ OTHER:
 
         // Title bar - 40k theme
         let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
-            .block(Block::default().borders(Borders::ALL).title(" ADEPTUS ADMINISTRATUM "))
+            .block(self.title_block())
             .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
         frame.render_widget(title, chunks[0]);
 
         // Commit history
         if let Some(ref note) = self.current_note {
-            let (history_text, error_color) = match self.service.get_note_history(&note.id) {
-                Ok(history) => {
-                    if history.is_empty() {
-                        ("No commit history found for this note.\n\nNote: Make sure you've saved the note at least once.".to_string(), Color::Yellow)
-                    } else {
-                        let text = history
-                            .iter()
-                            .map(|commit| {
-                                format!("{} | {} | {} | {}", 
-                                    commit.id, 
-                                    commit.message, 
-                                    commit.author, 
-                                    commit.timestamp
-                                )
-                            })
-                            .collect::<Vec<String>>()
-                            .join("\n");
-                        (text, Color::Yellow)
-                    }
+            let block = Block::default().borders(Borders::ALL).title(format!("Commit History: {}", note.title));
+            if let Some(ref e) = self.history_error {
+                let error_msg = format!("Failed to load commit history:\n\n{}\n\nMake sure Jujutsu is properly initialized and the note file exists.", e);
+                let history_para = Paragraph::new(error_msg)
+                    .block(block)
+                    .wrap(Wrap { trim: true })
+                    .style(Style::default().fg(Color::Red));
+                frame.render_widget(history_para, chunks[1]);
+            } else if self.history.is_empty() {
+                let history_para = Paragraph::new("No commit history found for this note.\n\nNote: Make sure you've saved the note at least once.")
+                    .block(block)
+                    .wrap(Wrap { trim: true })
+                    .style(Style::default().fg(Color::Yellow));
+                frame.render_widget(history_para, chunks[1]);
+            } else {
+                let items: Vec<ListItem> = self
+                    .history
+                    .iter()
+                    .map(|commit| {
+                        let mut spans = Vec::new();
+                        if note.canonical_commit.as_deref() == Some(commit.id.as_str()) {
+                            spans.push(Span::styled("★ ", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)));
+                        }
+                        spans.push(Span::raw(format!("{} | {} | {} | {}",
+                            commit.id,
+                            commit.message,
+                            commit.author,
+                            commit.timestamp
+                        )));
+                        ListItem::new(Line::from(spans))
+                    })
+                    .collect();
+
+                let mut state = ratatui::widgets::ListState::default();
+                state.select(Some(self.selected_index));
+
+                let list = List::new(items)
+                    .block(block)
+                    .style(Style::default().fg(Color::Yellow))
+                    .highlight_style(Style::default().fg(Color::Yellow).bg(Color::DarkGray))
+                    .highlight_symbol("⚔ ");
+                frame.render_stateful_widget(list, chunks[1], &mut state);
+            }
+        }
+
+        // Help bar, replaced by the status message until the next key
+        let help_text = self
+            .status_message
+            .as_deref()
+            .unwrap_or("j/k: select | p: mark/unmark canonical version | R: restore canonical version | Esc: back");
+        let help = Paragraph::new(help_text)
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, chunks[2]);
+    }
+
+    fn handle_metadata_edit_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        let field_count = self.current_note.as_ref().map(|n| n.metadata.len()).unwrap_or(0);
+        match key {
+            crossterm::event::KeyCode::Esc => {
+                self.mode = AppMode::View;
+                self.selected_index = 0;
+            }
+            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down
+                if self.selected_index + 1 < field_count =>
+            {
+                self.selected_index += 1;
+            }
+            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                self.selected_index = self.selected_index.saturating_sub(1);
+            }
+            crossterm::event::KeyCode::Char('a') => {
+                // Add a new field
+                self.metadata_edit_key = None;
+                self.input_buffer = String::new();
+                self.mode = AppMode::MetadataInput;
+            }
+            crossterm::event::KeyCode::Enter => {
+                // Edit selected field
+                if let Some(ref note) = self.current_note
+                    && let Some((field, value)) = note.metadata.iter().nth(self.selected_index)
+                {
+                    self.metadata_edit_key = Some(field.clone());
+                    self.input_buffer = format!("{}: {}", field, value);
+                    self.mode = AppMode::MetadataInput;
                 }
-                Err(e) => {
-                    let error_msg = format!("Failed to load commit history:\n\n{}\n\nMake sure Jujutsu is properly initialized and the note file exists.", e);
-                    (error_msg, Color::Red)
+            }
+            crossterm::event::KeyCode::Char('d') => {
+                // Delete selected field
+                if let Some(ref note) = self.current_note
+                    && let Some(field) = note.metadata.keys().nth(self.selected_index).cloned()
+                {
+                    let updated_note = self.service.remove_metadata(&note.id, &field)?;
+                    let remaining = updated_note.metadata.len();
+                    self.current_note = Some(updated_note);
+                    // Refresh notes list
+                    self.reload_notes()?;
+                    if self.selected_index >= remaining {
+                        self.selected_index = remaining.saturating_sub(1);
+                    }
+                    self.set_status(format!("✓ Removed field: {}", field));
                 }
-            };
+            }
+            _ => {}
+        }
+        Ok(())
+    }
 
-            let history_para = Paragraph::new(history_text)
-                .block(Block::default().borders(Borders::ALL).title(format!("Commit History: {}", note.title)))
-                .wrap(Wrap { trim: true })
-                .style(Style::default().fg(error_color));
-            frame.render_widget(history_para, chunks[1]);
+    fn render_metadata_edit(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.area());
+
+        // Title bar - 40k theme
+        let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
+
+        // Metadata fields list
+        if let Some(ref note) = self.current_note {
+            if note.metadata.is_empty() {
+                let empty = Paragraph::new("No metadata fields yet. Press a to add one (e.g. status: draft).")
+                    .block(Block::default().borders(Borders::ALL).title(format!("Metadata: {}", note.title)))
+                    .wrap(Wrap { trim: true })
+                    .style(Style::default().fg(Color::DarkGray));
+                frame.render_widget(empty, chunks[1]);
+            } else {
+                let items: Vec<ListItem> = note
+                    .metadata
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (field, value))| {
+                        let style = if i == self.selected_index {
+                            Style::default().fg(Color::Yellow).bg(Color::DarkGray)
+                        } else {
+                            Style::default()
+                        };
+                        ListItem::new(format!("{}: {}", field, value)).style(style)
+                    })
+                    .collect();
+
+                let mut state = ratatui::widgets::ListState::default();
+                state.select(Some(self.selected_index));
+
+                let list = List::new(items)
+                    .block(Block::default().borders(Borders::ALL).title(format!("Metadata: {}", note.title)))
+                    .highlight_style(Style::default().fg(Color::Yellow));
+                frame.render_stateful_widget(list, chunks[1], &mut state);
+            }
         }
 
         // Help bar
-        let help = Paragraph::new("Esc: back")
+        let help = Paragraph::new("j/k: navigate | a: add | Enter: edit | d: delete | Esc: back")
             .block(Block::default().borders(Borders::ALL).title("Help"))
             .style(Style::default().fg(Color::DarkGray));
         frame.render_widget(help, chunks[2]);
     }
-}
 
+    fn handle_metadata_input_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        match key {
+            crossterm::event::KeyCode::Esc => {
+                self.mode = AppMode::MetadataEdit;
+                self.input_buffer = String::new();
+                self.metadata_edit_key = None;
+            }
+            crossterm::event::KeyCode::Enter => {
+                // Save field as "key: value"
+                let (field, value) = match self.input_buffer.split_once(':') {
+                    Some((field, value)) => (field.trim().to_string(), value.trim().to_string()),
+                    None => (self.input_buffer.trim().to_string(), String::new()),
+                };
+                if field.is_empty() {
+                    self.set_status("✗ Field name cannot be empty (use key: value)".to_string());
+                    return Ok(());
+                }
+                if let Some(ref note) = self.current_note {
+                    // Renaming a field drops the old key
+                    if let Some(ref old_field) = self.metadata_edit_key
+                        && *old_field != field
+                    {
+                        self.service.remove_metadata(&note.id, old_field)?;
+                    }
+                    let updated_note = self.service.set_metadata(&note.id, field.clone(), value)?;
+                    self.selected_index = updated_note.metadata.keys().position(|k| *k == field).unwrap_or(0);
+                    self.current_note = Some(updated_note);
+                    // Refresh notes list
+                    self.reload_notes()?;
+                    self.set_status(format!("✓ Saved field: {}", field));
+                }
+                self.input_buffer = String::new();
+                self.metadata_edit_key = None;
+                self.mode = AppMode::MetadataEdit;
+            }
+            crossterm::event::KeyCode::Char(c) => {
+                self.input_buffer.push(c);
+            }
+            crossterm::event::KeyCode::Backspace => {
+                self.input_buffer.pop();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn render_metadata_input(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Length(3), Constraint::Min(0)])
+            .split(frame.area());
+
+        // Title bar - 40k theme
+        let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
+
+        // Field input
+        let input_title = if self.metadata_edit_key.is_some() { "Edit Field (key: value)" } else { "Add Field (key: value)" };
+        let field_prompt = format!("Field: {}", self.input_buffer);
+        let field_input = Paragraph::new(field_prompt.as_str())
+            .block(Block::default().borders(Borders::ALL).title(input_title))
+            .style(Style::default().fg(Color::Yellow));
+        frame.render_widget(field_input, chunks[1]);
+
+        // Status or current fields
+        let info_text = if let Some(ref message) = self.status_message {
+            message.clone()
+        } else if let Some(ref note) = self.current_note {
+            if note.metadata.is_empty() {
+                "No fields yet".to_string()
+            } else {
+                let fields: Vec<String> = note.metadata.iter().map(|(k, v)| format!("{}: {}", k, v)).collect();
+                format!("Current fields: {}", fields.join(", "))
+            }
+        } else {
+            String::new()
+        };
+        let info = Paragraph::new(info_text)
+            .block(Block::default().borders(Borders::ALL).title("Fields"))
+            .wrap(Wrap { trim: true });
+        frame.render_widget(info, chunks[2]);
+    }
+
+    fn handle_prompt_key(&mut self, kind: PromptKind, key: crossterm::event::KeyCode) -> Result<()> {
+        match key {
+            crossterm::event::KeyCode::Esc => {
+                self.input_buffer = String::new();
+                self.mode = kind.return_mode();
+            }
+            crossterm::event::KeyCode::Enter => {
+                let input = self.input_buffer.trim().to_string();
+                self.input_buffer = String::new();
+                self.mode = kind.return_mode();
+                self.submit_prompt(kind, input)?;
+            }
+            crossterm::event::KeyCode::Char(c) => {
+                self.input_buffer.push(c);
+            }
+            crossterm::event::KeyCode::Backspace => {
+                self.input_buffer.pop();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn submit_prompt(&mut self, kind: PromptKind, input: String) -> Result<()> {
+        match kind {
+            PromptKind::RenameNote => {
+                if input.is_empty() {
+                    self.set_status("✗ Title cannot be empty".to_string());
+                    return Ok(());
+                }
+                if let Some(ref note) = self.current_note {
+                    if note.title == input {
+                        return Ok(());
+                    }
+                    let renamed = match self.service.rename_note(&note.id, input) {
+                        Ok(renamed) => renamed,
+                        Err(e) => {
+                            self.set_status(format!("✗ Failed to rename: {}", e));
+                            return Ok(());
+                        }
+                    };
+                    self.fire_hook(HookEvent::Update, &renamed.id);
+                    self.set_status(format!("✓ Renamed to: {}", renamed.title));
+                    self.current_note = Some(renamed);
+                    // Refresh notes list
+                    self.reload_notes()?;
+                }
+            }
+            PromptKind::MoveToVault => {
+                if input.is_empty() {
+                    self.set_status("✗ Destination path cannot be empty".to_string());
+                    return Ok(());
+                }
+                if let Some(note) = self.current_note.clone() {
+                    self.record_undo(format!("Move '{}' to {}", note.title, input));
+                    let destination = NoteService::new(&input, resolve_notes_dir(|name| std::env::var(name).ok(), &input))
+                        .with_auto_commit(self.service.auto_commit());
+                    let result = destination
+                        .initialize()
+                        .and_then(|_| self.service.move_note_to(&note.id, &destination));
+                    match result {
+                        Ok(stripped) => {
+                            self.set_status(if stripped > 0 {
+                                format!("⚠ Moved to {} ({} cross-vault link(s) removed)", input, stripped)
+                            } else {
+                                format!("✓ Moved to {}", input)
+                            });
+                            self.current_note = None;
+                            self.mode = AppMode::List;
+                            self.reload_notes()?;
+                            self.selected_index = self.selected_index.min(self.filtered_notes.len().saturating_sub(1));
+                        }
+                        Err(e) => {
+                            self.set_status(format!("✗ Failed to move: {}", e));
+                        }
+                    }
+                }
+            }
+            PromptKind::MergeStub => {
+                let Some(stub) = self.stubs.get(self.stub_index).cloned() else {
+                    return Ok(());
+                };
+                let candidates = self.service.resolve_title(input.trim())?;
+                let into = match candidates.as_slice() {
+                    [] => {
+                        self.set_status(format!("✗ No note titled '{}'", input.trim()));
+                        return Ok(());
+                    }
+                    [into] => into.clone(),
+                    _ => {
+                        self.set_status(format!("✗ Several notes are titled '{}'; rename one first", input.trim()));
+                        return Ok(());
+                    }
+                };
+                if self.refuse_locked(&into) {
+                    return Ok(());
+                }
+                self.record_undo(format!("Merge '{}' into '{}'", stub.note.title, into.title));
+                match self.service.merge_note_into(&stub.note.id, &into.id) {
+                    Ok(merged) => {
+                        self.fire_hook(HookEvent::Update, &merged.id);
+                        self.fire_hook(HookEvent::Delete, &stub.note.id);
+                        self.set_status(format!("✓ Merged '{}' into '{}'", stub.note.title, merged.title));
+                        self.reload_stubs()?;
+                    }
+                    Err(e) => self.set_status(format!("✗ Merge failed: {}", e)),
+                }
+            }
+            PromptKind::ExportWithLinks => {
+                let Ok(depth) = input.trim().parse::<usize>() else {
+                    self.set_status(format!("✗ Not a number of hops: {}", input));
+                    return Ok(());
+                };
+                if let Some(ref note) = self.current_note {
+                    let id = note.id.clone();
+                    let filename = format!("{}-linked.md", slugify_title(&note.title));
+                    self.start_job("Exported", false, move |service, progress, cancel| {
+                        let written = service
+                            .export_subgraph_markdown(&id, depth, progress, cancel)
+                            .and_then(|md| Ok(std::fs::write(&filename, md)?));
+                        match written {
+                            Ok(()) => format!("✓ Exported with links {} hops deep to {}", depth, filename),
+                            Err(e) if e.is::<Cancelled>() => "✗ Export cancelled; nothing was written".to_string(),
+                            Err(e) => format!("✗ Export failed: {}", e),
+                        }
+                    });
+                }
+            }
+            PromptKind::Scratch => {
+                if let Some(ref note) = self.current_note {
+                    let scratch = (!input.is_empty()).then_some(input);
+                    let updated = self.service.set_scratch(&note.id, scratch)?;
+                    self.current_note = Some(updated);
+                    self.reload_notes()?;
+                }
+            }
+            PromptKind::Due => {
+                if let Some(ref note) = self.current_note {
+                    let due = if input.is_empty() {
+                        None
+                    } else {
+                        match parse_due(&input) {
+                            Ok(due) => Some(due),
+                            Err(e) => {
+                                self.set_status(format!("✗ {}", e));
+                                return Ok(());
+                            }
+                        }
+                    };
+                    let updated = match self.service.set_due(&note.id, due) {
+                        Ok(updated) => updated,
+                        Err(e) => {
+                            self.set_status(format!("✗ Failed to set the due date: {}", e));
+                            return Ok(());
+                        }
+                    };
+                    self.set_status(match updated.due_at() {
+                        Some(due) => format!("✓ Due {}", due.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")),
+                        None => "✓ Due date cleared".to_string(),
+                    });
+                    self.current_note = Some(updated);
+                    self.reload_notes()?;
+                }
+            }
+            PromptKind::RenameTag => {
+                let Some((old, _)) = self.tag_counts.get(self.tag_manager_index).cloned() else {
+                    return Ok(());
+                };
+                if input.trim_start_matches('#').trim().is_empty() {
+                    self.set_status("✗ Tag cannot be empty".to_string());
+                    return Ok(());
+                }
+                self.record_undo(format!("Rename tag #{} to {}", old, input));
+                match self.service.rename_tag_everywhere(&old, &input) {
+                    Ok(change) => self.set_status(format!("✓ Renamed #{} in {} notes{}", old, change.changed, locked_skipped(change.locked))),
+                    Err(e) => self.set_status(format!("✗ Tag rename failed: {}", e)),
+                }
+                self.refresh_tag_counts()?;
+            }
+            PromptKind::MergeTags => {
+                let Some((selected, _)) = self.tag_counts.get(self.tag_manager_index).cloned() else {
+                    return Ok(());
+                };
+                if input.trim_start_matches('#').trim().is_empty() {
+                    self.set_status("✗ Tag cannot be empty".to_string());
+                    return Ok(());
+                }
+                let mut from = std::mem::take(&mut self.merge_marks);
+                if !from.contains(&selected) {
+                    from.push(selected);
+                }
+                let merged: Vec<String> = from.iter().map(|t| format!("#{}", t)).collect();
+                self.record_undo(format!("Merge tags {} into {}", merged.join(", "), input));
+                match self.service.merge_tags(&from, &input) {
+                    Ok(change) => self.set_status(format!(
+                        "✓ Merged {} into #{} in {} notes{}",
+                        merged.join(", "),
+                        input.trim_start_matches('#').trim(),
+                        change.changed,
+                        locked_skipped(change.locked)
+                    )),
+                    Err(e) => self.set_status(format!("✗ Tag merge failed: {}", e)),
+                }
+                self.refresh_tag_counts()?;
+            }
+            PromptKind::NormalizeTag => {
+                let Some(variants) = self.selected_tag_variants().cloned() else {
+                    return Ok(());
+                };
+                let into = input.trim_start_matches('#').trim();
+                if !variants.contains(into) {
+                    self.set_status(format!("✗ #{} is not a spelling of #{}; use m to merge different tags", into, variants.canonical()));
+                    return Ok(());
+                }
+                let from: Vec<String> = variants.spellings.iter().map(|(tag, _)| tag.clone()).collect();
+                let spelled: Vec<String> = from.iter().filter(|tag| *tag != into).map(|t| format!("#{}", t)).collect();
+                self.record_undo(format!("Normalize tag {} to #{}", spelled.join(", "), into));
+                match self.service.merge_tags(&from, into) {
+                    Ok(change) => self.set_status(format!("✓ Normalized {} to #{} in {} notes{}", spelled.join(", "), into, change.changed, locked_skipped(change.locked))),
+                    Err(e) => self.set_status(format!("✗ Tag normalize failed: {}", e)),
+                }
+                self.refresh_tag_counts()?;
+            }
+            PromptKind::SaveSearch => {
+                if input.is_empty() {
+                    self.set_status("✗ Name cannot be empty".to_string());
+                    return Ok(());
+                }
+                self.state.save_search(&input, &self.search_query);
+                match self.state.save() {
+                    Ok(()) => self.set_status(format!("✓ Saved search '{}': {}", input, self.search_query)),
+                    Err(e) => self.set_status(format!("✗ Search not saved: {}", e)),
+                }
+            }
+            PromptKind::ExportResults | PromptKind::ExportSelected => {
+                let input = input.trim();
+                if input.is_empty() {
+                    self.set_status("✗ Title or file name cannot be empty".to_string());
+                    return Ok(());
+                }
+                let (query, results) = if kind == PromptKind::ExportSelected {
+                    ("selected notes".to_string(), self.selected_notes())
+                } else {
+                    (self.search_query.clone(), self.filtered_notes.clone())
+                };
+                let count = results.len();
+                if input.to_lowercase().ends_with(".md") {
+                    let md = self.service.export_results_markdown(&query, &results);
+                    match std::fs::write(input, md) {
+                        Ok(_) => self.set_status(format!("✓ Exported {} notes to {}", count, input)),
+                        Err(e) => self.set_status(format!("✗ Export failed: {}", e)),
+                    }
+                } else if !self.refuse_read_only() {
+                    let note = match self.service.create_results_note(input.to_string(), &query, &results) {
+                        Ok(note) => note,
+                        Err(e) if e.is::<DuplicateTitle>() => {
+                            self.set_status(format!("✗ Not created: {}", e));
+                            return Ok(());
+                        }
+                        Err(e) => return Err(e),
+                    };
+                    self.fire_hook(HookEvent::Create, &note.id);
+                    self.set_status(format!("✓ Created '{}' linking {} notes", note.title, note.links.len()));
+                    self.reload_notes()?;
+                }
+            }
+            PromptKind::TagSelected => {
+                let tag = input.trim().trim_start_matches('#').to_string();
+                if tag.is_empty() {
+                    self.set_status("✗ Tag cannot be empty".to_string());
+                    return Ok(());
+                }
+                let (selected, locked) = self.unlocked_selection();
+                self.record_undo(format!("Tag {} selected notes #{}", selected.len(), tag));
+                let tag_lower = tag.to_lowercase();
+                let mut tagged = 0;
+                for note in &selected {
+                    if !note.tags.iter().any(|t| t.to_lowercase() == tag_lower) {
+                        if let Err(e) = self.service.add_tag(&note.id, tag.clone()) {
+                            self.reload_notes()?;
+                            self.set_status(format!("✗ Failed to tag '{}': {}", note.title, e));
+                            return Ok(());
+                        }
+                        self.fire_hook(HookEvent::Update, &note.id);
+                        tagged += 1;
+                    }
+                }
+                self.reload_notes()?;
+                let skipped = locked_skipped(locked);
+                self.set_status(match selected.len() - tagged {
+                    0 => format!("✓ Tagged {} notes #{}{}", tagged, tag, skipped),
+                    already => format!("✓ Tagged {} notes #{} ({} already had it){}", tagged, tag, already, skipped),
+                });
+            }
+            PromptKind::LinkSelected => {
+                let candidates = self.service.resolve_title(input.trim())?;
+                let target = match candidates.as_slice() {
+                    [] => {
+                        self.set_status(format!("✗ No note titled '{}'", input.trim()));
+                        return Ok(());
+                    }
+                    [target] => target.clone(),
+                    _ => {
+                        self.set_status(format!("✗ Several notes are titled '{}'; rename one first", input.trim()));
+                        return Ok(());
+                    }
+                };
+                let mut linked = 0;
+                let mut failures = Vec::new();
+                let (selected, locked) = self.unlocked_selection();
+                for note in selected.iter().filter(|note| note.id != target.id) {
+                    match self.service.link_notes(&note.id, &target.id) {
+                        Ok(()) => {
+                            self.fire_hook(HookEvent::Update, &note.id);
+                            linked += 1;
+                        }
+                        Err(e) => failures.push(format!("'{}': {}", note.title, e)),
+                    }
+                }
+                self.reload_notes()?;
+                let skipped = locked_skipped(locked);
+                if failures.is_empty() {
+                    self.set_status(format!("✓ Linked {} notes to '{}'{}", linked, target.title, skipped));
+                } else {
+                    self.set_status(format!("⚠ Linked {} notes to '{}'{}; not linked {}", linked, target.title, skipped, failures.join("; ")));
+                }
+            }
+            PromptKind::FindInNote => {
+                self.clear_find();
+                if input.is_empty() {
+                    return Ok(());
+                }
+                if let Some(ref note) = self.current_note {
+                    self.find_matches = find_matches(&note.content, &input);
+                }
+                self.find_query = input;
+                if self.find_matches.is_empty() {
+                    self.set_status(format!("✗ No matches for \"{}\"", self.find_query));
+                } else {
+                    self.scroll_to_match();
+                }
+            }
+            PromptKind::CommitNow => {
+                if input.trim().is_empty() {
+                    self.set_status("✗ Commit message cannot be empty".to_string());
+                    return Ok(());
+                }
+                match self.service.commit_now(input.trim()) {
+                    Ok(Some(commit_id)) => self.set_status(format!("✓ Committed {}", commit_id.chars().take(12).collect::<String>())),
+                    Ok(None) => self.set_status("ℹ Nothing to commit".to_string()),
+                    Err(e) => self.set_status(format!("✗ Commit failed: {}", e)),
+                }
+                self.refresh_vcs_status();
+            }
+            PromptKind::BackupArchive => {
+                if input.is_empty() {
+                    self.set_status("✗ Archive path cannot be empty".to_string());
+                    return Ok(());
+                }
+                self.start_job("Backed up", false, move |service, progress, cancel| {
+                    match service.backup_to_archive(std::path::Path::new(&input), progress, cancel) {
+                        Ok(count) => format!("✓ Backed up {} notes to {}", count, input),
+                        Err(e) if e.is::<Cancelled>() => "✗ Backup cancelled; no archive was written".to_string(),
+                        Err(e) => format!("✗ Backup failed: {}", e),
+                    }
+                });
+            }
+            PromptKind::RestoreArchive => {
+                if input.is_empty() {
+                    self.set_status("✗ Archive path cannot be empty".to_string());
+                    return Ok(());
+                }
+                self.record_undo(format!("Restore from {}", input));
+                self.start_job("Restored", true, move |service, progress, cancel| {
+                    match service.restore_from_archive(std::path::Path::new(&input), progress, cancel) {
+                        Ok(restored) => format!("✓ Restored {} notes from {}{}", restored.notes, input, locked_skipped(restored.locked)),
+                        Err(e) if e.is::<Cancelled>() => "✗ Restore cancelled; the vault is as it was".to_string(),
+                        Err(e) => format!("✗ Restore failed: {}", e),
+                    }
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn render_prompt(&self, frame: &mut Frame, kind: PromptKind) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Length(3), Constraint::Min(0)])
+            .split(frame.area());
+
+        // Title bar - 40k theme
+        let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
+
+        // Input line
+        let prompt = format!("{}: {}", kind.label(), self.input_buffer);
+        let input = Paragraph::new(prompt.as_str())
+            .block(Block::default().borders(Borders::ALL).title(kind.title()))
+            .style(Style::default().fg(Color::Yellow));
+        frame.render_widget(input, chunks[1]);
+
+        // Help
+        let help = Paragraph::new("Enter: confirm | Esc: cancel")
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .wrap(Wrap { trim: true })
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, chunks[2]);
+    }
+
+    fn handle_template_select_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        match key {
+            crossterm::event::KeyCode::Esc => {
+                self.mode = AppMode::List;
+            }
+            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down
+                if self.template_selected_index + 1 < self.templates.len() =>
+            {
+                self.template_selected_index += 1;
+            }
+            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                self.template_selected_index = self.template_selected_index.saturating_sub(1);
+            }
+            crossterm::event::KeyCode::Enter => {
+                // Start a new note from the template with the caret at its {{cursor}} marker
+                if let Some(template) = self.templates.get(self.template_selected_index) {
+                    self.input_buffer = template.content.clone();
+                    self.cursor_pos = template.cursor;
+                    // Number the title line if a note from this template already has it
+                    let title_end = self.input_buffer.find('\n').unwrap_or(self.input_buffer.len());
+                    let title = self.input_buffer[..title_end].to_string();
+                    if !title.trim().is_empty() {
+                        let unique = self.service.unique_title(&title)?;
+                        if unique != title.trim() {
+                            self.input_buffer.replace_range(..title_end, &unique);
+                            if self.cursor_pos >= title_end {
+                                self.cursor_pos = self.cursor_pos - title_end + unique.len();
+                            } else {
+                                self.cursor_pos = self.cursor_pos.min(unique.len());
+                            }
+                        }
+                    }
+                    self.mode = AppMode::Create;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn render_template_select(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.area());
+
+        // Title bar - 40k theme
+        let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
+
+        // Template list with the first line of each as a preview
+        let items: Vec<ListItem> = self
+            .templates
+            .iter()
+            .enumerate()
+            .map(|(i, template)| {
+                let style = if i == self.template_selected_index {
+                    Style::default().fg(Color::Yellow).bg(Color::DarkGray)
+                } else {
+                    Style::default()
+                };
+                let preview = template.content.lines().next().unwrap_or("").trim();
+                ListItem::new(format!("{} - {}", template.name, preview)).style(style)
+            })
+            .collect();
+
+        let mut state = ratatui::widgets::ListState::default();
+        state.select(Some(self.template_selected_index));
+
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("New Note from Template"))
+            .highlight_style(Style::default().fg(Color::Yellow));
+        frame.render_stateful_widget(list, chunks[1], &mut state);
+
+        // Help bar
+        let help = Paragraph::new("j/k: navigate | Enter: use template | Esc: cancel")
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, chunks[2]);
+    }
+
+    /// Visible TagGroups rows: (group index, None) for a header, (group index, Some(note id)) for a note
+    fn tag_group_rows(&self) -> Vec<(usize, Option<&str>)> {
+        let mut rows = Vec::new();
+        for (i, (tag, ids)) in self.tag_groups.iter().enumerate() {
+            rows.push((i, None));
+            if !self.collapsed_tags.contains(tag) {
+                rows.extend(ids.iter().map(|id| (i, Some(id.as_str()))));
+            }
+        }
+        rows
+    }
+
+    fn handle_tag_groups_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        let rows = self.tag_group_rows();
+        match key {
+            crossterm::event::KeyCode::Esc => {
+                self.mode = AppMode::List;
+            }
+            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down
+                if self.tag_group_index + 1 < rows.len() =>
+            {
+                self.tag_group_index += 1;
+            }
+            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                self.tag_group_index = self.tag_group_index.saturating_sub(1);
+            }
+            crossterm::event::KeyCode::Enter => match rows.get(self.tag_group_index).copied() {
+                Some((group, None)) => {
+                    // Headers collapse and expand; the selection stays on the header
+                    let tag = self.tag_groups[group].0.clone();
+                    if !self.collapsed_tags.remove(&tag) {
+                        self.collapsed_tags.insert(tag);
+                    }
+                }
+                Some((_, Some(id))) => {
+                    if let Some(note) = self.notes.iter().find(|n| n.id == id).cloned() {
+                        self.open_note(note);
+                        self.mode = AppMode::View;
+                    }
+                }
+                None => {}
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn render_tag_groups(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.area());
+
+        // Title bar - 40k theme
+        let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
+
+        if self.tag_groups.is_empty() {
+            let empty = Paragraph::new("No notes yet — press Esc and n to create your first note.")
+                .block(Block::default().borders(Borders::ALL).title("Notes by Tag"))
+                .wrap(Wrap { trim: true })
+                .style(Style::default().fg(Color::DarkGray).bg(Color::Black));
+            frame.render_widget(empty, chunks[1]);
+        } else {
+            let items: Vec<ListItem> = self
+                .tag_group_rows()
+                .into_iter()
+                .enumerate()
+                .map(|(i, (group, id))| {
+                    let style = if i == self.tag_group_index {
+                        Style::default().fg(Color::Yellow).bg(Color::DarkGray)
+                    } else {
+                        Style::default()
+                    };
+                    let (tag, ids) = &self.tag_groups[group];
+                    let line = match id {
+                        None => {
+                            let marker = if self.collapsed_tags.contains(tag) { "▸" } else { "▾" };
+                            let name = if group + 1 == self.tag_groups.len() && tag == UNTAGGED_GROUP {
+                                tag.clone()
+                            } else {
+                                format!("#{}", tag)
+                            };
+                            Line::from(vec![
+                                Span::styled(format!("{} {} ", marker, name), Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+                                Span::styled(format!("({})", ids.len()), Style::default().fg(Color::DarkGray)),
+                            ])
+                        }
+                        Some(id) => {
+                            let title = self.notes.iter().find(|n| n.id == id).map_or("", |n| n.title.as_str());
+                            Line::from(vec![Span::raw("    "), Span::styled(title.to_string(), Style::default().fg(Color::White))])
+                        }
+                    };
+                    ListItem::new(line).style(style)
+                })
+                .collect();
+
+            let mut state = ratatui::widgets::ListState::default();
+            state.select(Some(self.tag_group_index));
+
+            let list = List::new(items)
+                .block(Block::default().borders(Borders::ALL).title("Notes by Tag"))
+                .highlight_style(Style::default().fg(Color::Yellow));
+            frame.render_stateful_widget(list, chunks[1], &mut state);
+        }
+
+        // Help bar
+        let help = Paragraph::new("j/k: navigate | Enter: open note / collapse group | Esc: back")
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, chunks[2]);
+    }
+
+    /// Reload notes and tag counts after a vault-wide tag change, keeping the selection in range
+    fn refresh_tag_counts(&mut self) -> Result<()> {
+        self.reload_notes()?;
+        self.tag_counts = self.service.tag_counts(&self.notes);
+        self.tag_variants = self.service.case_variant_tags(&self.notes);
+        self.tag_manager_index = self.tag_manager_index.min(self.tag_counts.len().saturating_sub(1));
+        Ok(())
+    }
+
+    /// Case variants of the tag selected in TagManager mode, if it has any
+    fn selected_tag_variants(&self) -> Option<&TagVariants> {
+        let (tag, _) = self.tag_counts.get(self.tag_manager_index)?;
+        self.tag_variants.iter().find(|variants| variants.contains(tag))
+    }
+
+    fn delete_selected_tag(&mut self) -> Result<()> {
+        self.mode = AppMode::TagManager;
+        let Some((tag, _)) = self.tag_counts.get(self.tag_manager_index).cloned() else {
+            return Ok(());
+        };
+        self.record_undo(format!("Delete tag #{}", tag));
+        match self.service.delete_tag_everywhere(&tag) {
+            Ok(change) => self.set_status(format!("✓ Removed #{} from {} notes{}", tag, change.changed, locked_skipped(change.locked))),
+            Err(e) => self.set_status(format!("✗ Tag delete failed: {}", e)),
+        }
+        self.refresh_tag_counts()
+    }
+
+    fn handle_tag_manager_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        self.status_message = None;
+        let last = self.tag_counts.len().saturating_sub(1);
+        match key {
+            crossterm::event::KeyCode::Esc => self.close_review(AppMode::List),
+            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
+                self.tag_manager_index = (self.tag_manager_index + 1).min(last);
+            }
+            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                self.tag_manager_index = self.tag_manager_index.saturating_sub(1);
+            }
+            crossterm::event::KeyCode::PageDown => {
+                self.tag_manager_index = (self.tag_manager_index + 10).min(last);
+            }
+            crossterm::event::KeyCode::PageUp => {
+                self.tag_manager_index = self.tag_manager_index.saturating_sub(10);
+            }
+            crossterm::event::KeyCode::Char('r' | 'd' | 'm' | 'n') if self.refuse_read_only() => {}
+            crossterm::event::KeyCode::Char('r') => {
+                if let Some((tag, _)) = self.tag_counts.get(self.tag_manager_index) {
+                    self.input_buffer = tag.clone();
+                    self.mode = AppMode::Prompt(PromptKind::RenameTag);
+                }
+            }
+            crossterm::event::KeyCode::Char('d') if !self.tag_counts.is_empty() => {
+                self.mode = AppMode::Confirm(ConfirmKind::DeleteTag);
+            }
+            crossterm::event::KeyCode::Char(' ') => {
+                if let Some((tag, _)) = self.tag_counts.get(self.tag_manager_index) {
+                    match self.merge_marks.iter().position(|t| t == tag) {
+                        Some(i) => {
+                            self.merge_marks.remove(i);
+                        }
+                        None => self.merge_marks.push(tag.clone()),
+                    }
+                }
+            }
+            crossterm::event::KeyCode::Char('m') => {
+                // The selected tag is the suggested target; the merge includes it either way
+                if let Some((tag, _)) = self.tag_counts.get(self.tag_manager_index) {
+                    self.input_buffer = tag.clone();
+                    self.mode = AppMode::Prompt(PromptKind::MergeTags);
+                }
+            }
+            crossterm::event::KeyCode::Char('n') => {
+                if let Some(variants) = self.selected_tag_variants() {
+                    self.input_buffer = variants.canonical().to_string();
+                    self.mode = AppMode::Prompt(PromptKind::NormalizeTag);
+                } else {
+                    self.set_status("ℹ This tag has only one spelling".to_string());
+                }
+            }
+            crossterm::event::KeyCode::Enter => {
+                // Show the notes with this tag in the list, as a #tag search
+                if let Some((tag, _)) = self.tag_counts.get(self.tag_manager_index) {
+                    self.search_query = format!("#={}", tag);
+                    self.filtered_notes = self.run_search(&self.search_query)?;
+                    self.is_searching = true;
+                    self.selected_index = 0;
+                    self.mode = AppMode::List;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_saved_searches_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        self.status_message = None;
+        let count = self.state.saved_searches.len();
+        match key {
+            crossterm::event::KeyCode::Esc => {
+                self.mode = AppMode::List;
+            }
+            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down
+                if self.saved_search_index + 1 < count =>
+            {
+                self.saved_search_index += 1;
+            }
+            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                self.saved_search_index = self.saved_search_index.saturating_sub(1);
+            }
+            crossterm::event::KeyCode::Enter => {
+                if let Some(search) = self.state.saved_searches.get(self.saved_search_index).cloned() {
+                    self.apply_search(&search.query)?;
+                }
+            }
+            crossterm::event::KeyCode::Char(c @ '1'..='9') => {
+                // Apply a saved search by its number without selecting it first
+                let index = c as usize - '1' as usize;
+                if let Some(search) = self.state.saved_searches.get(index).cloned() {
+                    self.apply_search(&search.query)?;
+                }
+            }
+            crossterm::event::KeyCode::Char('d') if self.saved_search_index < count => {
+                let removed = self.state.saved_searches.remove(self.saved_search_index);
+                self.saved_search_index = self.saved_search_index.min(count.saturating_sub(2));
+                match self.state.save() {
+                    Ok(()) => self.set_status(format!("✓ Deleted saved search '{}'", removed.name)),
+                    Err(e) => self.set_status(format!("✗ Could not save state: {}", e)),
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn render_saved_searches(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.area());
+
+        // Title bar - 40k theme
+        let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
+
+        let list_title = format!("Saved Searches ({})", self.state.saved_searches.len());
+        if self.state.saved_searches.is_empty() {
+            let empty = Paragraph::new(format!(
+                "No saved searches yet. {}, then press {} in the list to save the search under a name.",
+                self.search_first_hint(),
+                self.keymap.key_hint(KeyContext::List, Action::SaveSearch)
+            ))
+                .block(Block::default().borders(Borders::ALL).title(list_title))
+                .wrap(Wrap { trim: true })
+                .style(Style::default().fg(Color::DarkGray).bg(Color::Black));
+            frame.render_widget(empty, chunks[1]);
+        } else {
+            let items: Vec<ListItem> = self
+                .state
+                .saved_searches
+                .iter()
+                .enumerate()
+                .map(|(i, search)| {
+                    let number = if i < 9 { format!("{}. ", i + 1) } else { "   ".to_string() };
+                    ListItem::new(Line::from(vec![
+                        Span::styled(number, Style::default().fg(Color::DarkGray)),
+                        Span::styled(search.name.as_str(), Style::default().fg(Color::White)),
+                        Span::styled(format!("  {}", search.query), Style::default().fg(Color::Cyan)),
+                    ]))
+                })
+                .collect();
+
+            let mut state = ratatui::widgets::ListState::default();
+            state.select(Some(self.saved_search_index));
+
+            let list = List::new(items)
+                .block(Block::default().borders(Borders::ALL).title(list_title))
+                .highlight_style(Style::default().fg(Color::Yellow).bg(Color::DarkGray))
+                .highlight_symbol("⚔ ");
+            frame.render_stateful_widget(list, chunks[1], &mut state);
+        }
+
+        // Help bar, replaced by the status message until the next key
+        let help_text = self
+            .status_message
+            .as_deref()
+            .unwrap_or("j/k: select | Enter/1-9: apply | d: delete | Esc: back");
+        let help = Paragraph::new(help_text)
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, chunks[2]);
+    }
+
+    /// Dashboard tiles in display order: pinned notes, recently updated notes, saved searches
+    fn dashboard_tiles(&self) -> [(&'static str, Vec<DashboardEntry<'_>>); 3] {
+        let pinned = self
+            .state
+            .pinned_notes
+            .iter()
+            .filter_map(|id| self.notes.iter().find(|n| &n.id == id))
+            .map(DashboardEntry::Note)
+            .collect();
+        // The list may be in manual order, so sort a copy most recently updated first
+        let mut recent: Vec<&Note> = self.notes.iter().collect();
+        recent.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        let recent = recent.into_iter().take(DASHBOARD_RECENT).map(DashboardEntry::Note).collect();
+        let searches = self.state.saved_searches.iter().map(DashboardEntry::Search).collect();
+        [("★ Pinned", pinned), ("◷ Recent", recent), ("⌕ Saved Searches", searches)]
+    }
+
+    /// The dashboard entry at `dashboard_index`, counting through the tiles in order
+    fn selected_dashboard_entry(&self) -> Option<DashboardEntry<'_>> {
+        self.dashboard_tiles().into_iter().flat_map(|(_, entries)| entries).nth(self.dashboard_index)
+    }
+
+    fn handle_dashboard_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        self.status_message = None;
+        let tile_sizes = self.dashboard_tiles().map(|(_, entries)| entries.len());
+        let count: usize = tile_sizes.iter().sum();
+        match key {
+            crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Char('D') => {
+                self.mode = AppMode::List;
+            }
+            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down
+                if self.dashboard_index + 1 < count =>
+            {
+                self.dashboard_index += 1;
+            }
+            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                self.dashboard_index = self.dashboard_index.saturating_sub(1);
+            }
+            crossterm::event::KeyCode::Tab if count > 0 => {
+                // First entry of the next tile that has any, wrapping around
+                let mut starts = Vec::new();
+                let mut start = 0;
+                for size in tile_sizes {
+                    if size > 0 {
+                        starts.push(start);
+                    }
+                    start += size;
+                }
+                self.dashboard_index = starts
+                    .iter()
+                    .copied()
+                    .find(|&tile_start| tile_start > self.dashboard_index)
+                    .unwrap_or(starts[0]);
+            }
+            crossterm::event::KeyCode::Enter => {
+                let note_id = match self.selected_dashboard_entry() {
+                    Some(DashboardEntry::Note(note)) => note.id.clone(),
+                    Some(DashboardEntry::Search(search)) => {
+                        let query = search.query.clone();
+                        return self.apply_search(&query);
+                    }
+                    None => return Ok(()),
+                };
+                if let Some(note) = self.service.get_note(&note_id)? {
+                    self.open_note(note);
+                    self.mode = AppMode::View;
+                }
+            }
+            crossterm::event::KeyCode::Char('p') => {
+                let Some(DashboardEntry::Note(note)) = self.selected_dashboard_entry() else {
+                    return Ok(());
+                };
+                let (id, title) = (note.id.clone(), note.title.clone());
+                let pinned = self.state.toggle_pin(&id);
+                match self.state.save() {
+                    Ok(()) if pinned => self.set_status(format!("✓ Pinned '{}'", title)),
+                    Ok(()) => self.set_status(format!("✓ Unpinned '{}'", title)),
+                    Err(e) => self.set_status(format!("✗ Could not save state: {}", e)),
+                }
+                let count: usize = self.dashboard_tiles().iter().map(|(_, entries)| entries.len()).sum();
+                self.dashboard_index = self.dashboard_index.min(count.saturating_sub(1));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn render_dashboard(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.area());
+
+        // Title bar - 40k theme
+        let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Ratio(1, 3), Constraint::Ratio(1, 3), Constraint::Ratio(1, 3)])
+            .split(chunks[1]);
+        let empty_texts = [
+            format!(
+                "Nothing pinned yet. Press {} on an open note, or p on a recent note here, to pin it.",
+                self.keymap.key_hint(KeyContext::View, Action::TogglePin)
+            ),
+            format!("No notes yet. Press D, then {} to create one.", self.keymap.key_hint(KeyContext::List, Action::NewNote)),
+            format!(
+                "No saved searches. Press {} in the list after searching to save one.",
+                self.keymap.key_hint(KeyContext::List, Action::SaveSearch)
+            ),
+        ];
+
+        let mut tile_start = 0;
+        for (i, (name, entries)) in self.dashboard_tiles().into_iter().enumerate() {
+            let block = Block::default().borders(Borders::ALL).title(format!("{} ({})", name, entries.len()));
+            if entries.is_empty() {
+                let empty = Paragraph::new(empty_texts[i].as_str())
+                    .block(block)
+                    .wrap(Wrap { trim: true })
+                    .style(Style::default().fg(Color::DarkGray));
+                frame.render_widget(empty, columns[i]);
+                continue;
+            }
+
+            let items: Vec<ListItem> = entries
+                .iter()
+                .map(|entry| match entry {
+                    DashboardEntry::Note(note) => ListItem::new(Line::from(vec![
+                        Span::styled(note.title.as_str(), Style::default().fg(Color::White)),
+                        Span::styled(
+                            format!("  {}", note.updated_at.get(..10).unwrap_or(&note.updated_at)),
+                            Style::default().fg(Color::DarkGray),
+                        ),
+                    ])),
+                    DashboardEntry::Search(search) => ListItem::new(Line::from(vec![
+                        Span::styled(search.name.as_str(), Style::default().fg(Color::White)),
+                        Span::styled(format!("  {}", search.query), Style::default().fg(Color::Cyan)),
+                    ])),
+                })
+                .collect();
+
+            // Only the tile holding the selection shows it
+            let mut state = ratatui::widgets::ListState::default();
+            if (tile_start..tile_start + entries.len()).contains(&self.dashboard_index) {
+                state.select(Some(self.dashboard_index - tile_start));
+            }
+            tile_start += entries.len();
+
+            let list = List::new(items)
+                .block(block)
+                .highlight_style(Style::default().fg(Color::Yellow).bg(Color::DarkGray))
+                .highlight_symbol("⚔ ");
+            frame.render_stateful_widget(list, columns[i], &mut state);
+        }
+
+        // Help bar, replaced by the status message until the next key
+        let help_text = self
+            .status_message
+            .as_deref()
+            .unwrap_or("j/k: select | Tab: next tile | Enter: open | p: pin/unpin note | D/Esc: note list");
+        let help = Paragraph::new(help_text)
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, chunks[2]);
+    }
+
+    fn handle_disambiguate_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        self.status_message = None;
+        match key {
+            crossterm::event::KeyCode::Esc => {
+                // Leave this wikilink unlinked
+                self.ambiguous_titles.remove(0);
+                self.next_disambiguation()?;
+            }
+            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down
+                if self.candidate_index + 1 < self.title_candidates.len() =>
+            {
+                self.candidate_index += 1;
+            }
+            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                self.candidate_index = self.candidate_index.saturating_sub(1);
+            }
+            crossterm::event::KeyCode::Enter => {
+                let (Some(note), Some(target)) = (self.current_note.clone(), self.title_candidates.get(self.candidate_index).cloned()) else {
+                    return Ok(());
+                };
+                match self.service.link_notes(&note.id, &target.id) {
+                    Ok(()) => {
+                        if let Some(updated) = self.service.get_note(&note.id)? {
+                            self.current_note = Some(updated);
+                        }
+                        self.reload_notes()?;
+                        self.set_status(format!("✓ [[{}]] linked to the note created {}", target.title, short_date(&target.created_at)));
+                    }
+                    Err(e) => self.set_status(format!("✗ Not linked: {}", e)),
+                }
+                self.ambiguous_titles.remove(0);
+                self.next_disambiguation()?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn render_disambiguate(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.area());
+
+        // Title bar - 40k theme
+        let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
+
+        let wikilink = self.ambiguous_titles.first().map(String::as_str).unwrap_or("");
+        let items: Vec<ListItem> = self
+            .title_candidates
+            .iter()
+            .map(|note| {
+                let preview = note.content.lines().find(|line| {
+                    let line = line.trim();
+                    !line.is_empty() && line != note.title.trim()
+                });
+                let mut lines = vec![Line::from(vec![
+                    Span::styled(note.title.as_str(), Style::default().fg(Color::White)),
+                    Span::styled(
+                        format!("  created {} · updated {}", short_date(&note.created_at), short_date(&note.updated_at)),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ])];
+                if let Some(preview) = preview {
+                    lines.push(Line::styled(format!("  {}", preview.trim()), Style::default().fg(Color::DarkGray)));
+                }
+                ListItem::new(lines)
+            })
+            .collect();
+
+        let mut state = ratatui::widgets::ListState::default();
+        state.select(Some(self.candidate_index));
+
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(format!(
+                "[[{}]] matches {} notes — which one is meant?",
+                wikilink,
+                self.title_candidates.len()
+            )))
+            .highlight_style(Style::default().fg(Color::Yellow).bg(Color::DarkGray))
+            .highlight_symbol("⚔ ");
+        frame.render_stateful_widget(list, chunks[1], &mut state);
+
+        // Help bar, replaced by the status message until the next key
+        let help_text = self
+            .status_message
+            .as_deref()
+            .unwrap_or("j/k: select | Enter: link this note | Esc: leave this wikilink unlinked");
+        let help = Paragraph::new(help_text)
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, chunks[2]);
+    }
+
+    fn handle_tasks_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        self.status_message = None;
+        let last = self.tasks.len().saturating_sub(1);
+        match key {
+            crossterm::event::KeyCode::Esc => {
+                self.mode = AppMode::List;
+            }
+            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
+                self.task_index = (self.task_index + 1).min(last);
+            }
+            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                self.task_index = self.task_index.saturating_sub(1);
+            }
+            crossterm::event::KeyCode::PageDown => {
+                self.task_index = (self.task_index + 10).min(last);
+            }
+            crossterm::event::KeyCode::PageUp => {
+                self.task_index = self.task_index.saturating_sub(10);
+            }
+            crossterm::event::KeyCode::Char('a') => {
+                self.show_done_tasks = !self.show_done_tasks;
+                self.tasks = self.service.tasks(&self.notes, self.show_done_tasks);
+                self.task_index = 0;
+            }
+            crossterm::event::KeyCode::Char(' ') | crossterm::event::KeyCode::Char('x') => {
+                let Some(task) = self.tasks.get(self.task_index).cloned() else {
+                    return Ok(());
+                };
+                if let Some(note) = self.notes.iter().find(|note| note.id == task.note_id).cloned()
+                    && self.refuse_locked(&note)
+                {
+                    return Ok(());
+                }
+                match self.service.toggle_task(&task) {
+                    Ok(note) => {
+                        self.fire_hook(HookEvent::Update, &note.id);
+                        self.set_status(format!("✓ {}: {}", if task.done { "Reopened" } else { "Done" }, task.text));
+                        self.reload_notes()?;
+                        self.tasks = self.service.tasks(&self.notes, self.show_done_tasks);
+                        self.task_index = self.task_index.min(self.tasks.len().saturating_sub(1));
+                    }
+                    Err(e) => self.set_status(format!("✗ {}", e)),
+                }
+            }
+            crossterm::event::KeyCode::Enter => {
+                if let Some(task) = self.tasks.get(self.task_index)
+                    && let Some(note) = self.service.get_note(&task.note_id)?
+                {
+                    self.open_note(note);
+                    self.mode = AppMode::View;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_due_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        self.status_message = None;
+        let last = self.due_notes.len().saturating_sub(1);
+        match key {
+            crossterm::event::KeyCode::Esc => {
+                self.mode = AppMode::List;
+            }
+            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
+                self.due_index = (self.due_index + 1).min(last);
+            }
+            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                self.due_index = self.due_index.saturating_sub(1);
+            }
+            crossterm::event::KeyCode::Char('x') if !self.refuse_read_only() => {
+                let Some(note) = self.due_notes.get(self.due_index) else {
+                    return Ok(());
+                };
+                let title = note.title.clone();
+                match self.service.set_due(&note.id, None) {
+                    Ok(updated) => {
+                        self.fire_hook(HookEvent::Update, &updated.id);
+                        self.set_status(format!("✓ Cleared the due date of '{}'", title));
+                        self.reload_notes()?;
+                        self.due_notes = self.service.due_notes(&self.notes);
+                        self.due_index = self.due_index.min(self.due_notes.len().saturating_sub(1));
+                    }
+                    Err(e) => self.set_status(format!("✗ {}", e)),
+                }
+            }
+            crossterm::event::KeyCode::Enter => {
+                if let Some(note) = self.due_notes.get(self.due_index).cloned() {
+                    self.open_note(note);
+                    self.mode = AppMode::View;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_stubs_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        self.status_message = None;
+        let last = self.stubs.len().saturating_sub(1);
+        match key {
+            crossterm::event::KeyCode::Esc => self.close_review(AppMode::Statistics),
+            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
+                self.stub_index = (self.stub_index + 1).min(last);
+            }
+            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                self.stub_index = self.stub_index.saturating_sub(1);
+            }
+            crossterm::event::KeyCode::Char('d' | 'm')
+                if self.refuse_read_only()
+                    || self.stubs.get(self.stub_index).cloned().is_some_and(|stub| self.refuse_locked(&stub.note)) => {}
+            crossterm::event::KeyCode::Char('d') if !self.stubs.is_empty() => {
+                self.mode = AppMode::Confirm(ConfirmKind::DeleteStub);
+            }
+            crossterm::event::KeyCode::Char('m') => {
+                let Some(stub) = self.stubs.get(self.stub_index) else {
+                    return Ok(());
+                };
+                // A boilerplate stub most likely belongs with the note sharing its text
+                self.input_buffer = match stub.reason {
+                    StubReason::SameAs(ref other) => self.title_of(other).unwrap_or_default(),
+                    StubReason::Empty => String::new(),
+                };
+                self.mode = AppMode::Prompt(PromptKind::MergeStub);
+            }
+            crossterm::event::KeyCode::Enter => {
+                if let Some(stub) = self.stubs.get(self.stub_index).cloned() {
+                    self.open_note(stub.note);
+                    self.mode = AppMode::View;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Title of the loaded note `id`
+    fn title_of(&self, id: &str) -> Option<String> {
+        self.notes.iter().find(|note| note.id == id).map(|note| note.title.clone())
+    }
+
+    /// The notes picked with Space, in list order
+    fn selected_notes(&self) -> Vec<Note> {
+        self.notes.iter().filter(|note| self.selected_ids.contains(&note.id)).cloned().collect()
+    }
+
+    /// The selected notes a batch operation may change, and how many are skipped as locked
+    fn unlocked_selection(&self) -> (Vec<Note>, usize) {
+        let (locked, unlocked): (Vec<Note>, Vec<Note>) = self.selected_notes().into_iter().partition(|note| note.locked);
+        (unlocked, locked.len())
+    }
+
+    /// Delete every selected note, after the confirmation, as one undo step
+    fn delete_selected_notes(&mut self) -> Result<()> {
+        self.mode = AppMode::List;
+        let (selected, locked) = self.unlocked_selection();
+        self.record_undo(format!("Delete {} selected notes", selected.len()));
+        for note in &selected {
+            if let Err(e) = self.service.delete_note(&note.id) {
+                self.reload_notes()?;
+                self.set_status(format!("✗ Failed to delete '{}': {}", note.title, e));
+                return Ok(());
+            }
+            self.fire_hook(HookEvent::Delete, &note.id);
+        }
+        self.selected_ids.clear();
+        self.reload_notes()?;
+        let visible = if self.is_searching { self.filtered_notes.len() } else { self.notes.len() };
+        self.selected_index = self.selected_index.min(visible.saturating_sub(1));
+        self.set_status(format!("✓ Deleted {} notes{}", selected.len(), locked_skipped(locked)));
+        Ok(())
+    }
+
+    /// Delete the stub selected in Stubs mode, after the confirmation
+    fn delete_selected_stub(&mut self) -> Result<()> {
+        self.mode = AppMode::Stubs;
+        let Some(stub) = self.stubs.get(self.stub_index).cloned() else {
+            return Ok(());
+        };
+        self.record_undo(format!("Delete '{}'", stub.note.title));
+        if let Err(e) = self.service.delete_note(&stub.note.id) {
+            self.set_status(format!("✗ Failed to delete: {}", e));
+            return Ok(());
+        }
+        self.fire_hook(HookEvent::Delete, &stub.note.id);
+        self.set_status(format!("✓ Deleted '{}'", stub.note.title));
+        self.reload_stubs()
+    }
+
+    /// Reload the notes and look for stubs again after one was deleted or merged
+    fn reload_stubs(&mut self) -> Result<()> {
+        self.reload_notes()?;
+        self.stubs = self.service.stub_notes(&self.notes);
+        self.stub_index = self.stub_index.min(self.stubs.len().saturating_sub(1));
+        Ok(())
+    }
+
+    fn render_stubs(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.area());
+
+        // Title bar - 40k theme
+        let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
+
+        let list_title = format!("Stub Notes ({})", self.stubs.len());
+        if self.stubs.is_empty() {
+            let empty = Paragraph::new("No stub notes. Every note has text of its own past its title.")
+                .block(Block::default().borders(Borders::ALL).title(list_title))
+                .wrap(Wrap { trim: true })
+                .style(Style::default().fg(Color::DarkGray).bg(Color::Black));
+            frame.render_widget(empty, chunks[1]);
+        } else {
+            let items: Vec<ListItem> = self
+                .stubs
+                .iter()
+                .map(|stub| {
+                    let reason = match stub.reason {
+                        StubReason::Empty => "title only".to_string(),
+                        StubReason::SameAs(ref other) => {
+                            format!("same text as '{}'", self.title_of(other).unwrap_or_else(|| other.clone()))
+                        }
+                    };
+                    ListItem::new(Line::from(vec![
+                        Span::styled(stub.note.title.clone(), Style::default().fg(Color::White)),
+                        Span::styled(format!("  {}", reason), Style::default().fg(Color::DarkGray)),
+                    ]))
+                })
+                .collect();
+
+            let mut state = ratatui::widgets::ListState::default();
+            state.select(Some(self.stub_index));
+
+            let list = List::new(items)
+                .block(Block::default().borders(Borders::ALL).title(list_title))
+                .highlight_style(Style::default().fg(Color::Yellow).bg(Color::DarkGray))
+                .highlight_symbol("⚔ ");
+            frame.render_stateful_widget(list, chunks[1], &mut state);
+        }
+
+        // Help bar, replaced by the status message until the next key
+        let help_text = self.status_message.as_deref().unwrap_or(
+            "j/k: navigate | Enter: open note | d: delete | m: merge into another note | Esc: back",
+        );
+        let help = Paragraph::new(help_text)
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, chunks[2]);
+    }
+
+    fn handle_stale_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        let last = self.stale_notes.len().saturating_sub(1);
+        match key {
+            crossterm::event::KeyCode::Esc => {
+                self.mode = AppMode::Statistics;
+            }
+            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
+                self.stale_index = (self.stale_index + 1).min(last);
+            }
+            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                self.stale_index = self.stale_index.saturating_sub(1);
+            }
+            crossterm::event::KeyCode::Enter => {
+                if let Some(note) = self.stale_notes.get(self.stale_index).cloned() {
+                    self.open_note(note);
+                    self.mode = AppMode::View;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn render_stale(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.area());
+
+        // Title bar - 40k theme
+        let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
+
+        let list_title = format!("Stale Notes ({}, not updated in {}+ days)", self.stale_notes.len(), self.stale_days);
+        if self.stale_notes.is_empty() {
+            let empty = Paragraph::new(format!("No stale notes. Every note was updated in the last {} days.", self.stale_days))
+                .block(Block::default().borders(Borders::ALL).title(list_title))
+                .wrap(Wrap { trim: true })
+                .style(Style::default().fg(Color::DarkGray).bg(Color::Black));
+            frame.render_widget(empty, chunks[1]);
+        } else {
+            let now = chrono::Utc::now();
+            let items: Vec<ListItem> = self
+                .stale_notes
+                .iter()
+                .map(|note| {
+                    let (age, updated) = match chrono::DateTime::parse_from_rfc3339(&note.updated_at) {
+                        Ok(updated) => (
+                            age_label(age_days(&note.updated_at, now)),
+                            format!("updated {}", updated.with_timezone(&chrono::Local).format("%Y-%m-%d")),
+                        ),
+                        Err(_) => ("?".to_string(), format!("unreadable update time {:?}", note.updated_at)),
+                    };
+                    ListItem::new(Line::from(vec![
+                        Span::styled(format!("💤 {:>6} ", age), Style::default().fg(Color::Cyan)),
+                        Span::styled(note.title.clone(), Style::default().fg(Color::White)),
+                        Span::styled(format!("  {}", updated), Style::default().fg(Color::DarkGray)),
+                    ]))
+                })
+                .collect();
+
+            let mut state = ratatui::widgets::ListState::default();
+            state.select(Some(self.stale_index));
+
+            let list = List::new(items)
+                .block(Block::default().borders(Borders::ALL).title(list_title))
+                .highlight_style(Style::default().fg(Color::Yellow).bg(Color::DarkGray))
+                .highlight_symbol("⚔ ");
+            frame.render_stateful_widget(list, chunks[1], &mut state);
+        }
+
+        // Help bar, replaced by the status message until the next key
+        let help_text = self.status_message.as_deref().unwrap_or("j/k: navigate | Enter: open note | Esc: back");
+        let help = Paragraph::new(help_text)
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, chunks[2]);
+    }
+
+    /// Run every hygiene check and show the vault health report
+    fn open_health(&mut self) {
+        self.health_review = false;
+        match self.service.vault_health(&self.notes) {
+            Ok(health) => {
+                self.health = health;
+                self.mode = AppMode::Health;
+            }
+            Err(e) => self.set_status(format!("✗ Health check failed: {}", e)),
+        }
+    }
+
+    /// Leave a review screen: back to the health report if it was opened from there
+    /// (checking the vault again, since the review may have fixed something), else to `mode`
+    fn close_review(&mut self, mode: AppMode) {
+        if self.health_review {
+            self.open_health();
+        } else {
+            self.mode = mode;
+        }
+    }
+
+    fn handle_health_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        self.status_message = None;
+        match key {
+            crossterm::event::KeyCode::Esc => self.mode = AppMode::List,
+            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
+                self.health_index = (self.health_index + 1).min(HealthCheck::ALL.len() - 1);
+            }
+            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                self.health_index = self.health_index.saturating_sub(1);
+            }
+            crossterm::event::KeyCode::Char('r') => {
+                self.reload_notes()?;
+                self.open_health();
+            }
+            crossterm::event::KeyCode::Enter => self.review_health_check(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Open the screen listing what the selected health check found
+    fn review_health_check(&mut self) {
+        let check = HealthCheck::ALL[self.health_index];
+        if check.count(&self.health) == 0 {
+            self.set_status("✓ Nothing to review: this check found nothing".to_string());
+            return;
+        }
+        match check {
+            HealthCheck::Stubs => {
+                self.stubs = self.health.stubs.clone();
+                self.stub_index = 0;
+                self.mode = AppMode::Stubs;
+            }
+            HealthCheck::Cycles => {
+                self.cycles = self.health.cycles.clone();
+                self.cycle_index = 0;
+                self.mode = AppMode::Cycles;
+            }
+            HealthCheck::TagVariants => {
+                self.tag_counts = self.service.tag_counts(&self.notes);
+                self.tag_variants = self.health.tag_variants.clone();
+                let variants = &self.tag_variants;
+                self.tag_manager_index = self
+                    .tag_counts
+                    .iter()
+                    .position(|(tag, _)| variants.iter().any(|group| group.contains(tag)))
+                    .unwrap_or(0);
+                self.merge_marks.clear();
+                self.mode = AppMode::TagManager;
+            }
+            _ => {
+                self.health_check = check;
+                self.health_notes_index = 0;
+                self.mode = AppMode::HealthNotes;
+            }
+        }
+        self.health_review = true;
+    }
+
+    fn render_health(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.area());
+
+        // Title bar - 40k theme
+        let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
+
+        // Cycles are allowed, so only the other checks count as problems
+        let problems = HealthCheck::ALL
+            .iter()
+            .filter(|&&check| check != HealthCheck::Cycles && check.count(&self.health) > 0)
+            .count();
+        let list_title = match problems {
+            0 => format!("Vault Health ({} notes, all checks pass)", self.notes.len()),
+            n => format!("Vault Health ({} notes, {} of {} checks found something)", self.notes.len(), n, HealthCheck::ALL.len() - 1),
+        };
+        let items: Vec<ListItem> = HealthCheck::ALL
+            .iter()
+            .map(|&check| {
+                let count = check.count(&self.health);
+                let (marker, color) = match count {
+                    0 => ("✓", Color::Green),
+                    _ if check == HealthCheck::Cycles => ("ℹ", Color::Cyan),
+                    _ => ("⚠", Color::Yellow),
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{} {:>4}  ", marker, count), Style::default().fg(color)),
+                    Span::styled(check.label(), Style::default().fg(Color::White)),
+                ]))
+            })
+            .collect();
+
+        let mut state = ratatui::widgets::ListState::default();
+        state.select(Some(self.health_index));
+
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(list_title))
+            .highlight_style(Style::default().fg(Color::Yellow).bg(Color::DarkGray))
+            .highlight_symbol("⚔ ");
+        frame.render_stateful_widget(list, chunks[1], &mut state);
+
+        // Help bar, replaced by the status message until the next key
+        let help_text = self.status_message.as_deref().unwrap_or("j/k: navigate | Enter: review | r: check again | Esc: back");
+        let help = Paragraph::new(help_text)
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, chunks[2]);
+    }
+
+    /// Notes the health check under review found, each with what it found about the note
+    fn health_rows(&self) -> Vec<(&Note, String)> {
+        let short = |id: &str| id.chars().take(DEFAULT_SHORT_ID_LENGTH).collect::<String>();
+        match self.health_check {
+            HealthCheck::Orphans => {
+                self.health.orphans.iter().map(|note| (note, "no links in or out".to_string())).collect()
+            }
+            HealthCheck::BrokenLinks => self
+                .health
+                .broken_links
+                .iter()
+                .map(|(note, missing)| {
+                    let ids: Vec<String> = missing.iter().map(|id| short(id)).collect();
+                    (note, format!("links to missing {}", ids.join(", ")))
+                })
+                .collect(),
+            HealthCheck::DuplicateTitles => self
+                .health
+                .duplicate_titles
+                .iter()
+                .flat_map(|group| {
+                    group.iter().map(move |note| {
+                        (note, format!("{} notes with this title; created {}, id {}", group.len(), short_date(&note.created_at), short(&note.id)))
+                    })
+                })
+                .collect(),
+            HealthCheck::MissingHistory => self
+                .health
+                .missing_history
+                .iter()
+                .map(|note| (note, "changes not committed yet".to_string()))
+                .collect(),
+            HealthCheck::Stubs | HealthCheck::Cycles | HealthCheck::TagVariants => Vec::new(),
+        }
+    }
+
+    fn handle_health_notes_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        let last = self.health_rows().len().saturating_sub(1);
+        match key {
+            crossterm::event::KeyCode::Esc => self.close_review(AppMode::Health),
+            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
+                self.health_notes_index = (self.health_notes_index + 1).min(last);
+            }
+            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                self.health_notes_index = self.health_notes_index.saturating_sub(1);
+            }
+            crossterm::event::KeyCode::Enter => {
+                if let Some(note) = self.health_rows().get(self.health_notes_index).map(|(note, _)| (*note).clone()) {
+                    self.open_note(note);
+                    self.mode = AppMode::View;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn render_health_notes(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.area());
+
+        // Title bar - 40k theme
+        let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
+
+        let rows = self.health_rows();
+        let items: Vec<ListItem> = rows
+            .iter()
+            .map(|(note, found)| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(note.title.clone(), Style::default().fg(Color::White)),
+                    Span::styled(format!("  {}", found), Style::default().fg(Color::DarkGray)),
+                ]))
+            })
+            .collect();
+
+        let mut state = ratatui::widgets::ListState::default();
+        state.select(Some(self.health_notes_index));
+
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(format!("{} ({})", self.health_check.label(), rows.len())))
+            .highlight_style(Style::default().fg(Color::Yellow).bg(Color::DarkGray))
+            .highlight_symbol("⚔ ");
+        frame.render_stateful_widget(list, chunks[1], &mut state);
+
+        // Help bar, replaced by the status message until the next key
+        let help_text = self.status_message.as_deref().unwrap_or(match self.health_check {
+            HealthCheck::MissingHistory => "j/k: navigate | Enter: open note | Esc: back (jjzettel --backfill commits them all)",
+            _ => "j/k: navigate | Enter: open note | Esc: back",
+        });
+        let help = Paragraph::new(help_text)
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, chunks[2]);
+    }
+
+    fn render_due(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.area());
+
+        // Title bar - 40k theme
+        let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
+
+        let now = chrono::Utc::now();
+        let overdue = self.due_notes.iter().filter(|note| note.is_overdue(now)).count();
+        let list_title = format!("Due ({}, {} overdue)", self.due_notes.len(), overdue);
+        if self.due_notes.is_empty() {
+            let empty = Paragraph::new(format!(
+                "No notes with a due date. Press {} in View mode to give a note one.",
+                self.keymap.key_hint(KeyContext::View, Action::SetDue)
+            ))
+                .block(Block::default().borders(Borders::ALL).title(list_title))
+                .wrap(Wrap { trim: true })
+                .style(Style::default().fg(Color::DarkGray).bg(Color::Black));
+            frame.render_widget(empty, chunks[1]);
+        } else {
+            let items: Vec<ListItem> = self
+                .due_notes
+                .iter()
+                .filter_map(|note| Some((note, note.due_at()?)))
+                .map(|(note, due)| {
+                    let color = if note.is_overdue(now) { Color::Red } else { Color::Cyan };
+                    ListItem::new(Line::from(vec![
+                        Span::styled(
+                            format!("⏰ {} ", due.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")),
+                            Style::default().fg(color),
+                        ),
+                        Span::styled(format!("{:<15} ", due_label(due, now)), Style::default().fg(color)),
+                        Span::styled(note.title.clone(), Style::default().fg(Color::White)),
+                    ]))
+                })
+                .collect();
+
+            let mut state = ratatui::widgets::ListState::default();
+            state.select(Some(self.due_index));
+
+            let list = List::new(items)
+                .block(Block::default().borders(Borders::ALL).title(list_title))
+                .highlight_style(Style::default().fg(Color::Yellow).bg(Color::DarkGray))
+                .highlight_symbol("⚔ ");
+            frame.render_stateful_widget(list, chunks[1], &mut state);
+        }
+
+        // Help bar, replaced by the status message until the next key
+        let help_text = self.status_message.as_deref().unwrap_or(
+            "j/k: navigate | x: clear due date | Enter: open note | Esc: back",
+        );
+        let help = Paragraph::new(help_text)
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, chunks[2]);
+    }
+
+    fn render_tasks(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.area());
+
+        // Title bar - 40k theme
+        let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
+
+        let list_title = if self.show_done_tasks {
+            format!("All Tasks ({})", self.tasks.len())
+        } else {
+            format!("Open Tasks ({})", self.tasks.len())
+        };
+        if self.tasks.is_empty() {
+            let empty = Paragraph::new("No tasks. Add `- [ ] something` lines to a note to see them here.")
+                .block(Block::default().borders(Borders::ALL).title(list_title))
+                .wrap(Wrap { trim: true })
+                .style(Style::default().fg(Color::DarkGray).bg(Color::Black));
+            frame.render_widget(empty, chunks[1]);
+        } else {
+            let items: Vec<ListItem> = self
+                .tasks
+                .iter()
+                .map(|task| {
+                    let (checkbox, text_style) = if task.done {
+                        ("☑ ", Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT))
+                    } else {
+                        ("☐ ", Style::default().fg(Color::White))
+                    };
+                    ListItem::new(Line::from(vec![
+                        Span::styled(checkbox, Style::default().fg(Color::Cyan)),
+                        Span::styled(task.text.clone(), text_style),
+                        Span::styled(format!("  — {}", task.note_title), Style::default().fg(Color::DarkGray)),
+                    ]))
+                })
+                .collect();
+
+            let mut state = ratatui::widgets::ListState::default();
+            state.select(Some(self.task_index));
+
+            let list = List::new(items)
+                .block(Block::default().borders(Borders::ALL).title(list_title))
+                .highlight_style(Style::default().fg(Color::Yellow).bg(Color::DarkGray))
+                .highlight_symbol("⚔ ");
+            frame.render_stateful_widget(list, chunks[1], &mut state);
+        }
+
+        // Help bar, replaced by the status message until the next key
+        let help_text = self.status_message.as_deref().unwrap_or(
+            "j/k/PgUp/PgDn: navigate | Space/x: toggle done | a: show/hide done | Enter: open note | Esc: back",
+        );
+        let help = Paragraph::new(help_text)
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, chunks[2]);
+    }
+
+    fn render_tag_manager(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.area());
+
+        // Title bar - 40k theme
+        let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
+
+        let list_title = format!("Tags ({})", self.tag_counts.len());
+        if self.tag_counts.is_empty() {
+            let empty = Paragraph::new(format!(
+                "No tags yet. Open a note and press {} to tag it.",
+                self.keymap.key_hint(KeyContext::View, Action::AddTag)
+            ))
+                .block(Block::default().borders(Borders::ALL).title(list_title))
+                .wrap(Wrap { trim: true })
+                .style(Style::default().fg(Color::DarkGray).bg(Color::Black));
+            frame.render_widget(empty, chunks[1]);
+        } else {
+            let items: Vec<ListItem> = self
+                .tag_counts
+                .iter()
+                .map(|(tag, count)| {
+                    let mark = if self.merge_marks.contains(tag) { "◆ " } else { "  " };
+                    let mut spans = vec![
+                        Span::styled(mark, Style::default().fg(Color::Green)),
+                        Span::styled(format!("#{}", tag), Style::default().fg(Color::Red)),
+                        Span::styled(format!("  ({} notes)", count), Style::default().fg(Color::DarkGray)),
+                    ];
+                    if let Some(variants) = self.tag_variants.iter().find(|variants| variants.contains(tag)) {
+                        let spellings: Vec<String> = variants
+                            .spellings
+                            .iter()
+                            .map(|(spelling, count)| format!("#{} {}", spelling, count))
+                            .collect();
+                        spans.push(Span::styled(format!("  ⚠ {}", spellings.join(", ")), Style::default().fg(Color::Yellow)));
+                    }
+                    ListItem::new(Line::from(spans))
+                })
+                .collect();
+
+            // ListState keeps the selected row in view, so long tag lists scroll
+            let mut state = ratatui::widgets::ListState::default();
+            state.select(Some(self.tag_manager_index));
+
+            let list = List::new(items)
+                .block(Block::default().borders(Borders::ALL).title(list_title))
+                .highlight_style(Style::default().fg(Color::Yellow).bg(Color::DarkGray))
+                .highlight_symbol("⚔ ");
+            frame.render_stateful_widget(list, chunks[1], &mut state);
+        }
+
+        // Help bar, replaced by the status message until the next key
+        let help_text = self.status_message.as_deref().unwrap_or(
+            "j/k/PgUp/PgDn: navigate | Enter: show notes | r: rename everywhere | d: delete everywhere | Space: mark | m: merge marked + selected | n: normalize case | Esc: back",
+        );
+        let help = Paragraph::new(help_text)
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, chunks[2]);
+    }
+
+    /// Move the selected link one step up or down and keep it selected
+    fn shift_selected_link(&mut self, up: bool) -> Result<()> {
+        let Some(ref note) = self.current_note else {
+            return Ok(());
+        };
+        let from = self.link_selected_index;
+        let to = if up {
+            from.saturating_sub(1)
+        } else {
+            (from + 1).min(note.links.len().saturating_sub(1))
+        };
+        if from == to {
+            return Ok(());
+        }
+        match self.service.reorder_link(&note.id, from, to) {
+            Ok(updated) => {
+                self.current_note = Some(updated);
+                self.link_selected_index = to;
+                self.reload_notes()?;
+            }
+            Err(e) => self.set_status(format!("✗ Reorder failed: {}", e)),
+        }
+        Ok(())
+    }
+
+    fn handle_link_order_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        let count = self.current_note.as_ref().map_or(0, |note| note.links.len());
+        match key {
+            crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Enter => {
+                self.mode = AppMode::View;
+            }
+            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
+                self.link_selected_index = (self.link_selected_index + 1).min(count.saturating_sub(1));
+            }
+            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                self.link_selected_index = self.link_selected_index.saturating_sub(1);
+            }
+            crossterm::event::KeyCode::Char('J') => self.shift_selected_link(false)?,
+            crossterm::event::KeyCode::Char('K') => self.shift_selected_link(true)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn render_link_order(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.area());
+
+        let Some(ref note) = self.current_note else {
+            return;
+        };
+
+        let title = Paragraph::new(format!("⚔ Reorder links: {} ⚔", note.title))
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
+
+        let items: Vec<ListItem> = note
+            .links
+            .iter()
+            .enumerate()
+            .map(|(i, link_id)| {
+                let title = match self.service.get_note(link_id) {
+                    Ok(Some(linked)) => linked.title,
+                    _ => format!("(missing note {})", link_id),
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{}. ", i + 1), Style::default().fg(Color::DarkGray)),
+                    Span::styled(title, Style::default().fg(Color::White)),
+                ]))
+            })
+            .collect();
+
+        let mut state = ratatui::widgets::ListState::default();
+        state.select(Some(self.link_selected_index));
+
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Linked Notes"))
+            .highlight_style(Style::default().fg(Color::Yellow).bg(Color::DarkGray))
+            .highlight_symbol("⚔ ");
+        frame.render_stateful_widget(list, chunks[1], &mut state);
+
+        let help_text = self.status_message.as_deref().unwrap_or(
+            "j/k: select | J/K: move link down/up (each move is committed) | Enter/Esc: back",
+        );
+        let help = Paragraph::new(help_text)
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, chunks[2]);
+    }
+
+    fn handle_capture_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        let Some(ref mut input) = self.capture_input else {
+            return Ok(());
+        };
+        match key {
+            crossterm::event::KeyCode::Esc => {
+                self.capture_input = None;
+            }
+            crossterm::event::KeyCode::Enter => {
+                let text = self.capture_input.take().unwrap_or_default();
+                if text.trim().is_empty() {
+                    return Ok(());
+                }
+                match self.service.append_to_inbox(&text) {
+                    Ok(inbox) => {
+                        let is_new = !self.notes.iter().any(|note| note.id == inbox.id);
+                        self.fire_hook(if is_new { HookEvent::Create } else { HookEvent::Update }, &inbox.id);
+                        if self.current_note.as_ref().is_some_and(|note| note.id == inbox.id) && matches!(self.mode, AppMode::View) {
+                            self.current_note = Some(inbox.clone());
+                        }
+                        self.reload_notes()?;
+                        self.set_status(format!("✓ Captured to '{}'", inbox.title));
+                    }
+                    Err(e) => self.set_status(format!("✗ Capture failed: {}", e)),
+                }
+            }
+            crossterm::event::KeyCode::Backspace => {
+                input.pop();
+            }
+            crossterm::event::KeyCode::Char(c) => {
+                input.push(c);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Quick-capture input drawn as a popup over the current screen
+    fn render_capture(&self, frame: &mut Frame) {
+        let area = frame.area();
+        let width = area.width.saturating_sub(4).min(70);
+        let popup = Rect::new(area.x + (area.width - width) / 2, area.y + area.height / 3, width, 3.min(area.height));
+        let input = self.capture_input.as_deref().unwrap_or("");
+        let text = Paragraph::new(format!("{}█", input))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Capture to inbox (Enter: add, Esc: cancel) ")
+                    .border_style(Style::default().fg(Color::Yellow)),
+            )
+            .style(Style::default().fg(Color::White).bg(Color::Black));
+        frame.render_widget(Clear, popup);
+        frame.render_widget(text, popup);
+    }
+
+    fn handle_log_key(&mut self, key: crossterm::event::KeyCode) {
+        match key {
+            crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Char('M') => {
+                self.mode = AppMode::List;
+            }
+            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down
+                if self.log_selected_index + 1 < self.log.len() =>
+            {
+                self.log_selected_index += 1;
+            }
+            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                self.log_selected_index = self.log_selected_index.saturating_sub(1);
+            }
+            crossterm::event::KeyCode::Char('g') | crossterm::event::KeyCode::Home => {
+                self.log_selected_index = 0;
+            }
+            crossterm::event::KeyCode::Char('G') | crossterm::event::KeyCode::End => {
+                self.log_selected_index = self.log.len().saturating_sub(1);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_repo_log_key(&mut self, key: crossterm::event::KeyCode) {
+        self.status_message = None;
+        if self.repo_log_diff.is_some() {
+            // Diff of the selected commit is open; it scrolls until closed
+            match key {
+                crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Enter => {
+                    self.repo_log_diff = None;
+                }
+                crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
+                    self.repo_log_scroll = self.repo_log_scroll.saturating_add(1);
+                }
+                crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                    self.repo_log_scroll = self.repo_log_scroll.saturating_sub(1);
+                }
+                crossterm::event::KeyCode::PageDown => {
+                    self.repo_log_scroll = self.repo_log_scroll.saturating_add(10);
+                }
+                crossterm::event::KeyCode::PageUp => {
+                    self.repo_log_scroll = self.repo_log_scroll.saturating_sub(10);
+                }
+                _ => {}
+            }
+            return;
+        }
+        match key {
+            crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Char('H') => {
+                self.mode = AppMode::List;
+            }
+            crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down
+                if self.repo_log_index + 1 < self.repo_log.len() =>
+            {
+                self.repo_log_index += 1;
+            }
+            crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                self.repo_log_index = self.repo_log_index.saturating_sub(1);
+            }
+            crossterm::event::KeyCode::Char('g') | crossterm::event::KeyCode::Home => {
+                self.repo_log_index = 0;
+            }
+            crossterm::event::KeyCode::Char('G') | crossterm::event::KeyCode::End => {
+                self.repo_log_index = self.repo_log.len().saturating_sub(1);
+            }
+            crossterm::event::KeyCode::Enter => {
+                let Some(commit) = self.repo_log.get(self.repo_log_index) else {
+                    return;
+                };
+                match self.service.commit_diff(&commit.id) {
+                    Ok(diff) => {
+                        self.repo_log_diff = Some(diff);
+                        self.repo_log_scroll = 0;
+                    }
+                    Err(e) => self.set_status(format!("✗ {}", e)),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn render_repo_log(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.area());
+
+        // Title bar - 40k theme
+        let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
+
+        if let Some(ref diff) = self.repo_log_diff {
+            let commit_id = self.repo_log.get(self.repo_log_index).map(|c| c.id.as_str()).unwrap_or("");
+            let lines: Vec<Line> = if diff.trim().is_empty() {
+                vec![Line::styled("(no file changes)", Style::default().fg(Color::DarkGray))]
+            } else {
+                diff.lines()
+                    .map(|line| {
+                        let color = if line.starts_with("+++") || line.starts_with("---") || line.starts_with("diff ") {
+                            Color::Yellow
+                        } else if line.starts_with('+') {
+                            Color::Green
+                        } else if line.starts_with('-') {
+                            Color::Red
+                        } else if line.starts_with("@@") {
+                            Color::Cyan
+                        } else {
+                            Color::White
+                        };
+                        Line::styled(line, Style::default().fg(color))
+                    })
+                    .collect()
+            };
+            let diff_para = Paragraph::new(lines)
+                .block(Block::default().borders(Borders::ALL).title(format!("Diff: {}", commit_id)))
+                .scroll((self.repo_log_scroll, 0));
+            frame.render_widget(diff_para, chunks[1]);
+
+            let help = Paragraph::new("j/k/PgUp/PgDn: scroll | Enter/Esc: back to log")
+                .block(Block::default().borders(Borders::ALL).title("Help"))
+                .style(Style::default().fg(Color::DarkGray));
+            frame.render_widget(help, chunks[2]);
+            return;
+        }
+
+        let block = Block::default().borders(Borders::ALL).title(format!("Repo Log ({})", self.repo_log.len()));
+        if let Some(ref e) = self.repo_log_error {
+            let error_msg = format!("Failed to load the repo log:\n\n{}", e);
+            let para = Paragraph::new(error_msg)
+                .block(block)
+                .wrap(Wrap { trim: true })
+                .style(Style::default().fg(Color::Red));
+            frame.render_widget(para, chunks[1]);
+        } else if self.repo_log.is_empty() {
+            let para = Paragraph::new("No commits yet. The vault may not be a Jujutsu repository; saving a note creates one.")
+                .block(block)
+                .wrap(Wrap { trim: true })
+                .style(Style::default().fg(Color::Yellow));
+            frame.render_widget(para, chunks[1]);
+        } else {
+            let items: Vec<ListItem> = self
+                .repo_log
+                .iter()
+                .map(|commit| {
+                    ListItem::new(Line::from(vec![
+                        Span::styled(format!("{} ", commit.id), Style::default().fg(Color::Cyan)),
+                        Span::styled(commit.message.as_str(), Style::default().fg(Color::White)),
+                        Span::styled(format!("  {} · {}", commit.author, commit.timestamp), Style::default().fg(Color::DarkGray)),
+                    ]))
+                })
+                .collect();
+
+            let mut state = ratatui::widgets::ListState::default();
+            state.select(Some(self.repo_log_index));
+
+            let list = List::new(items)
+                .block(block)
+                .highlight_style(Style::default().fg(Color::Yellow).bg(Color::DarkGray))
+                .highlight_symbol("⚔ ");
+            frame.render_stateful_widget(list, chunks[1], &mut state);
+        }
+
+        // Help bar, replaced by the status message until the next key
+        let help_text = self
+            .status_message
+            .as_deref()
+            .unwrap_or("j/k: select | g/G: newest/oldest | Enter: show diff | Esc/H: close");
+        let help = Paragraph::new(help_text)
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, chunks[2]);
+    }
+
+    fn render_log(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.area());
+
+        // Title bar - 40k theme
+        let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
+
+        let log_title = format!("Message Log ({})", self.log.len());
+        if self.log.is_empty() {
+            let empty = Paragraph::new("No messages yet. Results of saves, moves, backups and other actions are collected here.")
+                .block(Block::default().borders(Borders::ALL).title(log_title))
+                .wrap(Wrap { trim: true })
+                .style(Style::default().fg(Color::DarkGray).bg(Color::Black));
+            frame.render_widget(empty, chunks[1]);
+        } else {
+            let items: Vec<ListItem> = self
+                .log
+                .iter()
+                .map(|entry| {
+                    let color = if entry.message.starts_with('✗') {
+                        Color::Red
+                    } else if entry.message.starts_with('⚠') {
+                        Color::Yellow
+                    } else {
+                        Color::White
+                    };
+                    ListItem::new(Line::from(vec![
+                        Span::styled(format!("{}  ", entry.time), Style::default().fg(Color::DarkGray)),
+                        Span::styled(entry.message.as_str(), Style::default().fg(color)),
+                    ]))
+                })
+                .collect();
+
+            let mut state = ratatui::widgets::ListState::default();
+            state.select(Some(self.log_selected_index));
+
+            let list = List::new(items)
+                .block(Block::default().borders(Borders::ALL).title(log_title))
+                .highlight_style(Style::default().bg(Color::DarkGray));
+            frame.render_stateful_widget(list, chunks[1], &mut state);
+        }
+
+        // Help bar
+        let help = Paragraph::new("j/k: scroll | g/G: oldest/newest | Esc/M: close")
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, chunks[2]);
+    }
+
+    fn handle_confirm_key(&mut self, kind: ConfirmKind, key: crossterm::event::KeyCode) -> Result<()> {
+        if kind == ConfirmKind::EditConflict {
+            return self.handle_edit_conflict_key(key);
+        }
+        if kind == ConfirmKind::UnsavedEdit {
+            return self.handle_unsaved_edit_key(key);
+        }
+        if kind == ConfirmKind::DuplicateTitle {
+            return self.handle_duplicate_title_key(key);
+        }
+        match key {
+            crossterm::event::KeyCode::Char('y') | crossterm::event::KeyCode::Enter => match kind {
+                ConfirmKind::SaveEmptyNewNote => self.create_from_buffer()?,
+                ConfirmKind::SaveEmptyEdit => self.save_edited_note()?,
+                ConfirmKind::CreateLink => self.link_selected_note()?,
+                ConfirmKind::EditConflict | ConfirmKind::UnsavedEdit | ConfirmKind::DuplicateTitle => {}
+                ConfirmKind::Undo => self.undo_last()?,
+                ConfirmKind::DeleteTag => self.delete_selected_tag()?,
+                ConfirmKind::RestoreCanonical => self.restore_canonical()?,
+                ConfirmKind::DeleteStub => self.delete_selected_stub()?,
+                ConfirmKind::DeleteSelected => self.delete_selected_notes()?,
+            },
+            crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Char('n') => {
+                self.mode = kind.cancel_mode();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn render_confirm(&self, frame: &mut Frame, kind: ConfirmKind) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.area());
+
+        // Title bar - 40k theme
+        let title = Paragraph::new("⚔ jjzettel - IMPERIUM KNOWLEDGE BASE ⚔")
+            .block(self.title_block())
+            .style(Style::default().fg(Color::Yellow).bg(Color::Black).add_modifier(Modifier::BOLD));
+        frame.render_widget(title, chunks[0]);
+
+        // Question
+        let message = match kind {
+            ConfirmKind::SaveEmptyNewNote | ConfirmKind::SaveEmptyEdit => {
+                let title_line = self.input_buffer.lines().next().unwrap_or("").trim();
+                format!(
+                    "\"{}\" has no content besides its title.\n\nSave anyway? Press Enter/y to save, Esc/n to keep editing",
+                    title_line
+                )
+            }
+            ConfirmKind::CreateLink => {
+                let source = self.current_note.as_ref().map(|n| n.title.as_str()).unwrap_or("");
+                let target = self.notes.get(self.selected_index).map(|n| n.title.as_str()).unwrap_or("");
+                format!("Link '{}' → '{}'?\n\nPress Enter/y to link, Esc/n to pick another note", source, target)
+            }
+            ConfirmKind::DeleteStub => {
+                let title = self.stubs.get(self.stub_index).map(|stub| stub.note.title.as_str()).unwrap_or("");
+                format!(
+                    "Delete the stub note '{}'?\n\nU in List mode can undo it.\n\nPress Enter/y to delete, Esc/n to cancel",
+                    title
+                )
+            }
+            ConfirmKind::DeleteSelected => {
+                let titles: Vec<String> = self.selected_notes().into_iter().map(|note| format!("'{}'", note.title)).collect();
+                format!(
+                    "Delete the {} selected notes?\n\n{}\n\nU in List mode can undo it.\n\nPress Enter/y to delete, Esc/n to cancel",
+                    titles.len(),
+                    titles.join(", ")
+                )
+            }
+            ConfirmKind::DeleteTag => {
+                let (tag, count) = self.tag_counts.get(self.tag_manager_index).cloned().unwrap_or_default();
+                format!(
+                    "Remove #{} from all {} notes that use it?\n\nThe notes themselves are kept.\n\nPress Enter/y to delete the tag, Esc/n to cancel",
+                    tag, count
+                )
+            }
+            ConfirmKind::RestoreCanonical => {
+                let (title, commit) = self
+                    .current_note
+                    .as_ref()
+                    .map(|n| (n.title.as_str(), n.canonical_commit.as_deref().unwrap_or("")))
+                    .unwrap_or_default();
+                format!(
+                    "Restore '{}' to its canonical version {}?\n\nThe current text is replaced (and committed); it stays in the history.\n\nPress Enter/y to restore, Esc/n to cancel",
+                    title, commit
+                )
+            }
+            ConfirmKind::Undo => {
+                let description = self.last_undo.as_ref().map(|p| p.description.as_str()).unwrap_or("");
+                format!(
+                    "Undo: {}?\n\nEvery note goes back to how it was just before this, so changes made since are undone too.\n\nPress Enter/y to undo, Esc/n to cancel",
+                    description
+                )
+            }
+            ConfirmKind::UnsavedEdit => {
+                let title = self.current_note.as_ref().map(|n| n.title.as_str()).unwrap_or("");
+                format!(
+                    "'{}' has unsaved changes.\n\n\
+                     s/y/Enter: save them\n\
+                     d: discard them\n\
+                     Esc: keep editing",
+                    title
+                )
+            }
+            ConfirmKind::DuplicateTitle => {
+                let (title, created) = self
+                    .duplicate_of
+                    .as_ref()
+                    .map(|n| (n.title.trim(), short_date(&n.created_at)))
+                    .unwrap_or_default();
+                let create = if self.duplicate_title_policy == DuplicateTitlePolicy::Block {
+                    "Titles must be unique in this vault (\"duplicate_titles\": \"block\").\n\n\
+                     o/Enter: open the existing note (discards this draft)\n"
+                } else {
+                    "y/Enter: create another note with this title\n\
+                     o: open the existing note (discards this draft)\n"
+                };
+                format!("A note titled '{}' already exists, created {}.\n\n{}Esc: keep editing", title, created, create)
+            }
+            ConfirmKind::EditConflict => {
+                let title = self.current_note.as_ref().map(|n| n.title.as_str()).unwrap_or("");
+                format!(
+                    "'{}' was changed outside jjzettel while you were editing it.\n\n\
+                     o: overwrite it with your version\n\
+                     r: reload it from disk (discards your edits)\n\
+                     m: merge - keep editing with both versions in the buffer\n\
+                     Esc: keep editing without saving",
+                    title
+                )
+            }
+        };
+        let confirm = Paragraph::new(message)
+            .block(Block::default().borders(Borders::ALL).title(kind.title()))
+            .wrap(Wrap { trim: true })
+            .style(Style::default().fg(Color::Yellow));
+        frame.render_widget(confirm, chunks[1]);
+
+        // Help bar
+        let help_text = if kind == ConfirmKind::EditConflict {
+            "o: overwrite | r: reload | m: merge | Esc: keep editing"
+        } else if kind == ConfirmKind::UnsavedEdit {
+            "s/y/Enter: save | d: discard | Esc: keep editing"
+        } else if kind == ConfirmKind::DuplicateTitle && self.duplicate_title_policy == DuplicateTitlePolicy::Block {
+            "o/Enter: open existing | Esc: keep editing"
+        } else if kind == ConfirmKind::DuplicateTitle {
+            "y/Enter: create anyway | o: open existing | Esc: keep editing"
+        } else {
+            "Enter/y: confirm | Esc/n: cancel"
+        };
+        let help = Paragraph::new(help_text)
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, chunks[2]);
+    }
+
+    /// Remember the vault state before a destructive operation; without a repo there is nothing to return to
+    fn record_undo(&mut self, description: String) {
+        self.last_undo = self.service.undo_point(description).ok();
+    }
+
+    /// In a read-only vault, say so and return true instead of letting a change fail
+    fn refuse_read_only(&mut self) -> bool {
+        if !self.service.is_read_only() {
+            return false;
+        }
+        self.set_status("✗ The vault is read-only; changes are disabled".to_string());
+        true
+    }
+
+    /// "Search first (/ or #)", with the keys of the two List mode searches
+    fn search_first_hint(&self) -> String {
+        format!(
+            "Search first ({} or {})",
+            self.keymap.key_hint(KeyContext::List, Action::Search),
+            self.keymap.key_hint(KeyContext::List, Action::TagSearch)
+        )
+    }
+
+    /// For a locked note, say so and return true instead of changing it
+    fn refuse_locked(&mut self, note: &Note) -> bool {
+        if !note.locked {
+            return false;
+        }
+        let message = match self.keymap.key_label(KeyContext::View, Action::ToggleLock) {
+            Some(key) => format!("🔒 '{}' is locked; unlock it with {} in View mode first", note.title, key),
+            None => format!("🔒 '{}' is locked; unlock it first", note.title),
+        };
+        self.set_status(message);
+        true
+    }
+
+    /// Revert to the state recorded before the last destructive operation
+    fn undo_last(&mut self) -> Result<()> {
+        self.mode = AppMode::List;
+        let Some(point) = self.last_undo.take() else {
+            return Ok(());
+        };
+        match self.service.undo(&point) {
+            Ok(()) => {
+                self.reload_notes()?;
+                self.selected_index = self.selected_index.min(self.filtered_notes.len().saturating_sub(1));
+                self.set_status(format!("✓ Undone: {}", point.description));
+            }
+            Err(e) => {
+                self.set_status(format!("✗ Undo failed: {}", e));
+                self.last_undo = Some(point);
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolve a save whose note file changed on disk since Edit mode was entered
+    fn handle_edit_conflict_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        let Some(id) = self.current_note.as_ref().map(|n| n.id.clone()) else {
+            self.mode = AppMode::View;
+            return Ok(());
+        };
+        match key {
+            crossterm::event::KeyCode::Char('o') => self.write_edited_note()?,
+            crossterm::event::KeyCode::Char('r') | crossterm::event::KeyCode::Char('m') => {
+                let Some(on_disk) = self.service.get_note(&id)? else {
+                    // Nothing to reload or merge; overwriting recreates the file
+                    self.mode = AppMode::Edit;
+                    self.set_status("✗ The note was deleted on disk; press Ctrl+S and o to save it again".to_string());
+                    return Ok(());
+                };
+                if key == crossterm::event::KeyCode::Char('m') {
+                    self.input_buffer = merge_conflict_text(&self.input_buffer, &on_disk.content);
+                    self.set_status("ℹ Both versions are in the buffer; resolve the markers and save".to_string());
+                } else {
+                    self.input_buffer = on_disk.content.clone();
+                    self.set_status("ℹ Reloaded the version on disk".to_string());
+                }
+                self.cursor_pos = self.input_buffer.len();
+                self.edit_fingerprint = self.service.note_fingerprint(&id)?;
+                self.current_note = Some(on_disk);
+                self.mode = AppMode::Edit;
+            }
+            crossterm::event::KeyCode::Esc => {
+                self.mode = AppMode::Edit;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_duplicate_title_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        let block = self.duplicate_title_policy == DuplicateTitlePolicy::Block;
+        match key {
+            crossterm::event::KeyCode::Char('y') | crossterm::event::KeyCode::Enter if !block => {
+                self.duplicate_of = None;
+                self.mode = AppMode::Create;
+                self.save_new_note()?;
+            }
+            crossterm::event::KeyCode::Char('o') | crossterm::event::KeyCode::Enter => {
+                let Some(existing) = self.duplicate_of.take() else {
+                    return Ok(());
+                };
+                self.input_buffer.clear();
+                self.cursor_pos = 0;
+                self.link_source_id = None;
+                self.open_note(existing);
+                self.mode = AppMode::View;
+                self.set_status("ℹ Opened the note with that title; the draft was discarded".to_string());
+            }
+            crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Char('n') => {
+                self.duplicate_of = None;
+                self.mode = AppMode::Create;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_unsaved_edit_key(&mut self, key: crossterm::event::KeyCode) -> Result<()> {
+        match key {
+            crossterm::event::KeyCode::Char('s') | crossterm::event::KeyCode::Char('y') | crossterm::event::KeyCode::Enter => {
+                self.mode = AppMode::Edit;
+                self.request_edit_save()?;
+            }
+            crossterm::event::KeyCode::Char('d') => {
+                self.input_buffer.clear();
+                self.cursor_pos = 0;
+                self.mode = AppMode::View;
+                self.set_status("ℹ Discarded unsaved changes".to_string());
+            }
+            crossterm::event::KeyCode::Esc => {
+                self.mode = AppMode::Edit;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// `text` cut to at most `max` characters, with `...` when something was cut
+fn truncate_chars(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Rows `line` takes when word-wrapped into `width` columns, close to how the edit pane
+/// wraps it: a word that does not fit moves to the next row, and a word longer than a
+/// whole row is split across rows. Always at least one row.
+fn wrapped_rows(line: &str, width: usize, trim: bool) -> usize {
+    if width == 0 {
+        return 1;
+    }
+    let line = if trim { line.trim_start() } else { line };
+    let mut rows = 1;
+    let mut used = 0;
+    for word in line.split_inclusive(' ') {
+        let visible = word.trim_end_matches(' ').chars().count();
+        let spaces = word.chars().count() - visible;
+        if used > 0 && used + visible > width {
+            rows += 1;
+            used = 0;
+        }
+        used += visible;
+        while used > width {
+            rows += 1;
+            used -= width;
+        }
+        // Spaces after a word may hang past the edge instead of starting a row
+        used = (used + spaces).min(width);
+    }
+    rows
+}
+
+/// Row and column, within `line` word-wrapped into `width` columns as `wrapped_rows` counts
+/// its rows, of a caret before the character at index `caret` (past the end: after the last
+/// one). Spaces hanging past the edge leave the caret at the edge.
+fn wrapped_position(line: &str, width: usize, trim: bool, caret: usize) -> (usize, usize) {
+    if width == 0 {
+        return (0, 0);
+    }
+    let shown = if trim { line.trim_start() } else { line };
+    let mut caret = caret.saturating_sub(line.chars().count() - shown.chars().count());
+    let mut row = 0;
+    let mut used = 0;
+    for word in shown.split_inclusive(' ') {
+        let visible = word.trim_end_matches(' ').chars().count();
+        if used > 0 && used + visible > width {
+            row += 1;
+            used = 0;
+        }
+        for i in 0..word.chars().count() {
+            if i < visible && used == width {
+                row += 1;
+                used = 0;
+            }
+            if caret == 0 {
+                return (row, used);
+            }
+            caret -= 1;
+            used = if i < visible { used + 1 } else { (used + 1).min(width) };
+        }
+    }
+    (row, used)
+}
+
+/// Byte offset of the start of the line `pos` is on
+fn line_start(text: &str, pos: usize) -> usize {
+    text[..pos].rfind('\n').map_or(0, |i| i + 1)
+}
+
+/// Byte offset of the end of the line `pos` is on, before its line break
+fn line_end(text: &str, pos: usize) -> usize {
+    text[pos..].find('\n').map_or(text.len(), |i| pos + i)
+}
+
+/// Byte offset on the line above (`up`) or below the one `pos` is on, in the same column
+/// or at the end of a shorter line. Past the first or last line it is the start or end of
+/// the text.
+fn line_step(text: &str, pos: usize, up: bool) -> usize {
+    let start = line_start(text, pos);
+    let column = text[start..pos].chars().count();
+    let target = if up {
+        if start == 0 {
+            return 0;
+        }
+        line_start(text, start - 1)
+    } else {
+        let end = line_end(text, pos);
+        if end == text.len() {
+            return text.len();
+        }
+        end + 1
+    };
+    let target_end = line_end(text, target);
+    target + text[target..target_end].chars().take(column).map(char::len_utf8).sum::<usize>()
+}
+
+/// Byte range of the word the caret is in or right after: letters, digits, `-` and `_`
+fn word_range(text: &str, pos: usize) -> Option<std::ops::Range<usize>> {
+    let pos = pos.min(text.len());
+    let is_word = |c: char| c.is_alphanumeric() || c == '-' || c == '_';
+    let start = text[..pos]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word(*c))
+        .last()
+        .map_or(pos, |(i, _)| i);
+    let end = pos + text[pos..].chars().take_while(|c| is_word(*c)).map(char::len_utf8).sum::<usize>();
+    (start < end).then_some(start..end)
+}
+
+/// One-line vault size for the title bar, e.g. `42 notes · 17 tags`. Tags differing only in
+/// case count once, as in the tag groups.
+fn vault_summary(notes: &[Note]) -> String {
+    let tags: HashSet<String> = notes.iter().flat_map(|note| note.tags.iter().map(|tag| tag.to_lowercase())).collect();
+    let count = |n: usize, noun: &str| format!("{} {}{}", n, noun, if n == 1 { "" } else { "s" });
+    format!("{} · {}", count(notes.len(), "note"), count(tags.len(), "tag"))
+}
+
+/// Date part of an RFC 3339 timestamp, e.g. `2024-05-01`
+fn short_date(timestamp: &str) -> String {
+    if let Ok(parsed) = chrono::DateTime::parse_from_rfc3339(timestamp) {
+        parsed.format("%Y-%m-%d").to_string()
+    } else {
+        timestamp.split('T').next().unwrap_or("").to_string()
+    }
+}
+
+/// View mode's one-line header: update date, color, due date, scratch marker, tags and
+/// the number of metadata fields
+fn compact_header(note: &Note) -> Line<'_> {
+    let separator = || Span::styled("  |  ", Style::default().fg(Color::DarkGray));
+    let updated = chrono::DateTime::parse_from_rfc3339(&note.updated_at)
+        .map(|parsed| parsed.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|_| note.updated_at.split('T').next().unwrap_or("").to_string());
+    let mut spans = vec![
+        Span::styled("⚡ ", Style::default().fg(Color::Red)),
+        Span::styled(updated, Style::default().fg(Color::Yellow)),
+    ];
+    if let Some(label) = note.color.as_deref()
+        && let Some(color) = label_color(label)
+    {
+        spans.push(separator());
+        spans.push(Span::styled(format!("● {}", label), Style::default().fg(color)));
+    }
+    if let Some(due) = note.due_at() {
+        let now = chrono::Utc::now();
+        let color = if due < now { Color::Red } else { Color::Cyan };
+        spans.push(separator());
+        spans.push(Span::styled(format!("⏰ {}", due_label(due, now)), Style::default().fg(color)));
+    }
+    if note.scratch.is_some() {
+        spans.push(separator());
+        spans.push(Span::styled("✎", Style::default().fg(Color::Magenta)));
+    }
+    if !note.tags.is_empty() {
+        let tags: Vec<String> = note.tags.iter().map(|tag| format!("#{}", tag)).collect();
+        spans.push(separator());
+        spans.push(Span::styled(tags.join(" "), Style::default().fg(Color::Yellow)));
+    }
+    if !note.metadata.is_empty() {
+        spans.push(separator());
+        let fields = if note.metadata.len() == 1 { "field" } else { "fields" };
+        spans.push(Span::styled(format!("◆ {} {}", note.metadata.len(), fields), Style::default().fg(Color::Red)));
+    }
+    Line::from(spans)
+}
+
+/// Status suffix for notes a batch operation left alone because they are locked
+fn locked_skipped(count: usize) -> String {
+    match count {
+        0 => String::new(),
+        count => format!(", {} locked skipped", count),
+    }
+}
+
+/// Time since a note's last update for the stale markers, e.g. `120d`
+fn age_label(days: Option<i64>) -> String {
+    match days {
+        Some(days) => format!("{}d", days),
+        None => "?".to_string(),
+    }
+}
+
+/// Tags of `note` not in `tags_before`, i.e. added by auto-tag rules on save, as `#a, #b`
+fn auto_tag_list(tags_before: &[String], note: &Note) -> Option<String> {
+    let added: Vec<String> = note
+        .tags
+        .iter()
+        .filter(|tag| !tags_before.contains(tag))
+        .map(|tag| format!("#{}", tag))
+        .collect();
+    (!added.is_empty()).then(|| added.join(", "))
+}
+
+/// Due date relative to `now`, e.g. `in 3d`, `in 5h` or `20m overdue`
+fn due_label(due: chrono::DateTime<chrono::FixedOffset>, now: chrono::DateTime<chrono::Utc>) -> String {
+    let delta = due.with_timezone(&chrono::Utc) - now;
+    let minutes = delta.num_minutes().abs();
+    let amount = if minutes >= 24 * 60 {
+        format!("{}d", minutes / (24 * 60))
+    } else if minutes >= 60 {
+        format!("{}h", minutes / 60)
+    } else {
+        format!("{}m", minutes)
+    };
+    if delta < chrono::TimeDelta::zero() {
+        format!("{} overdue", amount)
+    } else {
+        format!("in {}", amount)
+    }
+}
+
+/// Terminal color for a note color label, `None` for labels outside the palette
+fn label_color(label: &str) -> Option<Color> {
+    match label {
+        "red" => Some(Color::Red),
+        "green" => Some(Color::Green),
+        "yellow" => Some(Color::Yellow),
+        "blue" => Some(Color::Blue),
+        "magenta" => Some(Color::Magenta),
+        "cyan" => Some(Color::Cyan),
+        _ => None,
+    }
+}
+
+/// Edit buffer and on-disk content joined with conflict markers, or the buffer alone if they match
+fn merge_conflict_text(mine: &str, theirs: &str) -> String {
+    if mine == theirs {
+        return mine.to_string();
+    }
+    format!(
+        "<<<<<<< editing\n{}\n=======\n{}\n>>>>>>> on disk\n",
+        mine.trim_end_matches('\n'),
+        theirs.trim_end_matches('\n')
+    )
+}
+
+/// Small xorshift64 generator; seedable so picks are reproducible
+struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // Zero is a fixed point of xorshift
+        XorShift { state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed } }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    /// Uniform index in `0..len`, never `avoid` unless it is the only choice
+    fn pick_index(&mut self, len: usize, avoid: Option<usize>) -> Option<usize> {
+        match (len, avoid) {
+            (0, _) => None,
+            (1, _) => Some(0),
+            // Draw from the other len - 1 slots and skip over the avoided one
+            (_, Some(avoid)) if avoid < len => {
+                let index = (self.next_u64() % (len as u64 - 1)) as usize;
+                Some(if index >= avoid { index + 1 } else { index })
+            }
+            _ => Some((self.next_u64() % len as u64) as usize),
+        }
+    }
+}
+
+/// Short preview of a note: title, tags and the first `max_lines` lines of the body
+fn note_preview_lines(note: &Note, max_lines: usize) -> Vec<Line<'_>> {
+    let mut lines = vec![Line::from(Span::styled(
+        note.title.as_str(),
+        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+    ))];
+    if !note.tags.is_empty() {
+        let tags = note.tags.iter().map(|t| format!("#{}", t)).collect::<Vec<_>>().join(" ");
+        lines.push(Line::from(Span::styled(tags, Style::default().fg(Color::Red))));
+    }
+    lines.push(Line::default());
+    
+    // The first content line usually repeats the title
+    let mut body = note.content.lines().peekable();
+    if body.peek().is_some_and(|first| first.trim() == note.title.trim()) {
+        body.next();
+    }
+    for line in body.take(max_lines) {
+        lines.push(Line::from(Span::styled(line, Style::default().fg(Color::White))));
+    }
+    lines
+}
+
+/// Number of header lines `render_view` draws above the note content
+fn view_content_offset(note: &Note, header: ViewHeader) -> usize {
+    match header {
+        ViewHeader::Full => {}
+        ViewHeader::Compact => return 2, // Header line and blank line
+        ViewHeader::Hidden => return 0,
+    }
+    let mut offset = 2; // Dates line and blank line
+    if note.scratch.is_some() {
+        offset += 2;
+    }
+    if note.due_at().is_some() {
+        offset += 2;
+    }
+    if !note.tags.is_empty() {
+        offset += 2;
+    }
+    if !note.metadata.is_empty() {
+        offset += note.metadata.len() + 1;
+    }
+    offset
+}
+
+/// Case-insensitive, non-overlapping occurrences of `query` in `content`, line by line
+fn find_matches(content: &str, query: &str) -> Vec<TextMatch> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let mut matches = Vec::new();
+    for (line_index, line) in content.lines().enumerate() {
+        let mut search_from = 0;
+        for (start, _) in line.char_indices() {
+            if start < search_from {
+                continue;
+            }
+            if let Some(end) = match_end(line, start, &query) {
+                matches.push(TextMatch { line: line_index, start, end });
+                search_from = end;
+            }
+        }
+    }
+    matches
+}
+
+/// End offset of `query` (already lowercase) if it occurs in `line` at `start`
+fn match_end(line: &str, start: usize, query: &[char]) -> Option<usize> {
+    let mut expected = query.iter().peekable();
+    for (offset, c) in line[start..].char_indices() {
+        for lower in c.to_lowercase() {
+            if expected.next() != Some(&lower) {
+                return None;
+            }
+        }
+        if expected.peek().is_none() {
+            return Some(start + offset + c.len_utf8());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyModifiers};
+    use std::ops::{Deref, DerefMut};
+    use std::path::PathBuf;
+
+    /// An app over a vault in a fresh temporary directory, removed again when dropped
+    struct TestApp {
+        dir: PathBuf,
+        app: App,
+    }
+
+    impl TestApp {
+        /// App with the default config, or `None` (skipping the test) when jj is not installed
+        fn new(name: &str) -> Option<Self> {
+            Self::with_config(name, "{}")
+        }
+
+        /// App set up by the config file content `config`, with the walkthrough already done
+        fn with_config(name: &str, config: &str) -> Option<Self> {
+            Self::build(name, config, true)
+        }
+
+        /// App as on its very first run, walkthrough included
+        fn first_run(name: &str) -> Option<Self> {
+            Self::build(name, "{}", false)
+        }
+
+        fn build(name: &str, config: &str, tutorial_done: bool) -> Option<Self> {
+            if std::process::Command::new("jj").arg("--version").output().is_err() {
+                eprintln!("jj not found, skipping");
+                return None;
+            }
+            let dir = std::env::temp_dir().join(format!("jjzettel-app-test-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            let config: Config = serde_json::from_str(config).expect("test config parses");
+            let mut state = AppState::load_or_default(dir.join("state.json"));
+            state.tutorial_done = tutorial_done;
+            let app = App::open(&dir.to_string_lossy(), dir.join("notes"), config, state).unwrap();
+            Some(TestApp { dir, app })
+        }
+
+        fn press(&mut self, code: KeyCode) {
+            self.app.handle_key(code, KeyModifiers::NONE).unwrap();
+        }
+
+        fn press_ctrl(&mut self, c: char) {
+            self.app.handle_key(KeyCode::Char(c), KeyModifiers::CONTROL).unwrap();
+        }
+
+        /// Press the key of each character of `keys` in turn
+        fn type_keys(&mut self, keys: &str) {
+            for c in keys.chars() {
+                self.press(KeyCode::Char(c));
+            }
+        }
+
+        /// Note titled `title` with `body` under it, made through the service as if from
+        /// outside the app, with the list reloaded
+        fn note(&mut self, title: &str, body: &str) -> Note {
+            let note = self.app.service.create_note(title.to_string(), format!("{}\n\n{}", title, body)).unwrap();
+            self.app.reload_notes().unwrap();
+            note
+        }
+
+        /// Select `note` in the list and open it in View mode with Enter
+        fn open(&mut self, note: &Note) {
+            self.app.mode = AppMode::List;
+            self.app.selected_index = self.app.filtered_notes.iter().position(|n| n.id == note.id).expect("note in the list");
+            self.press(KeyCode::Enter);
+            assert!(matches!(self.app.mode, AppMode::View));
+        }
+
+        fn stored(&self, note: &Note) -> Note {
+            self.app.service.get_note(&note.id).unwrap().expect("note still exists")
+        }
+
+        fn titles(&self) -> Vec<String> {
+            let mut titles: Vec<String> = self.app.service.list_notes().unwrap().into_iter().map(|n| n.title).collect();
+            titles.sort();
+            titles
+        }
+
+        fn current_id(&self) -> Option<&str> {
+            self.app.current_note.as_ref().map(|note| note.id.as_str())
+        }
+
+        fn status(&self) -> &str {
+            self.app.status_message.as_deref().unwrap_or_default()
+        }
+
+        /// The app drawn on a 160×40 screen, one line of text per row
+        fn screen(&self) -> String {
+            let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(160, 40)).unwrap();
+            terminal.draw(|frame| self.app.render(frame)).unwrap();
+            let buffer = terminal.backend().buffer();
+            (0..buffer.area.height)
+                .map(|y| (0..buffer.area.width).map(|x| buffer[(x, y)].symbol()).collect::<String>())
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    }
+
+    impl Deref for TestApp {
+        type Target = App;
+
+        fn deref(&self) -> &App {
+            &self.app
+        }
+    }
+
+    impl DerefMut for TestApp {
+        fn deref_mut(&mut self) -> &mut App {
+            &mut self.app
+        }
+    }
+
+    impl Drop for TestApp {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn digit_in_view_mode_opens_that_numbered_link() {
+        let Some(mut t) = TestApp::new("link-number") else { return };
+        let hub = t.note("Hub", "links");
+        let first = t.note("First", "one");
+        let second = t.note("Second", "two");
+        let referrer = t.note("Referrer", "three");
+        t.service.link_notes(&hub.id, &first.id).unwrap();
+        t.service.link_notes(&hub.id, &second.id).unwrap();
+        t.service.link_notes(&referrer.id, &hub.id).unwrap();
+        t.reload_notes().unwrap();
+
+        t.open(&hub);
+        t.type_keys("2");
+        assert_eq!(t.current_id(), Some(second.id.as_str()), "forward links come first");
+        t.open(&hub);
+        t.type_keys("3");
+        assert_eq!(t.current_id(), Some(referrer.id.as_str()), "then backlinks");
+    }
+
+    #[test]
+    fn out_of_range_link_number_stays_on_the_note() {
+        let Some(mut t) = TestApp::new("link-number-range") else { return };
+        let hub = t.note("Hub", "links");
+        let target = t.note("Target", "one");
+        t.service.link_notes(&hub.id, &target.id).unwrap();
+        t.reload_notes().unwrap();
+
+        t.open(&hub);
+        t.type_keys("7");
+        assert_eq!(t.current_id(), Some(hub.id.as_str()));
+        assert_eq!(t.status(), "✗ No link #7");
+        t.type_keys("0");
+        assert_eq!(t.current_id(), Some(hub.id.as_str()));
+        assert_eq!(t.status(), "✗ No link #0");
+    }
+
+    #[test]
+    fn two_digit_link_number_waits_for_the_second_digit() {
+        let Some(mut t) = TestApp::with_config("link-number-two-digits", r#"{"auto_commit": false}"#) else { return };
+        let hub = t.note("Hub", "links");
+        let targets: Vec<Note> = (1..=12).map(|i| t.note(&format!("Target {}", i), "target")).collect();
+        for target in &targets {
+            t.service.link_notes(&hub.id, &target.id).unwrap();
+        }
+        t.reload_notes().unwrap();
+
+        t.open(&hub);
+        t.type_keys("1");
+        assert_eq!(t.current_id(), Some(hub.id.as_str()), "1 could still become 10, 11 or 12");
+        assert_eq!(t.status(), "ℹ Go to link 1… (Enter to open)");
+        t.type_keys("2");
+        assert_eq!(t.current_id(), Some(targets[11].id.as_str()));
+
+        t.open(&hub);
+        t.type_keys("1");
+        t.press(KeyCode::Enter);
+        assert_eq!(t.current_id(), Some(targets[0].id.as_str()), "Enter opens the number typed so far");
+        t.open(&hub);
+        t.type_keys("1");
+        t.press(KeyCode::Esc);
+        assert_eq!(t.current_id(), Some(hub.id.as_str()));
+        assert!(t.link_number_input.is_empty());
+    }
+
+    #[test]
+    fn create_and_link_saves_the_note_and_links_the_source_to_it() {
+        let Some(mut t) = TestApp::new("create-linked") else { return };
+        let source = t.note("Source", "start here");
+
+        t.open(&source);
+        t.type_keys("L");
+        assert!(matches!(t.mode, AppMode::Create));
+        t.type_keys("Target\n\nmore");
+        t.press_ctrl('s');
+
+        assert!(matches!(t.mode, AppMode::View));
+        assert_eq!(t.current_id(), Some(source.id.as_str()), "back on the note it was started from");
+        assert_eq!(t.status(), "✓ Created and linked: Target");
+        let target = t.notes.iter().find(|n| n.title == "Target").cloned().expect("note created");
+        assert_eq!(target.content, "Target\n\nmore");
+        assert_eq!(t.stored(&source).links, vec![target.id.clone()]);
+        assert_eq!(t.current_note.as_ref().unwrap().links, vec![target.id]);
+    }
+
+    #[test]
+    fn cancelled_create_and_link_leaves_nothing_behind() {
+        let Some(mut t) = TestApp::new("create-linked-cancel") else { return };
+        let source = t.note("Source", "start here");
+
+        t.open(&source);
+        t.type_keys("LTarget");
+        t.press(KeyCode::Esc);
+
+        assert!(matches!(t.mode, AppMode::View));
+        assert_eq!(t.current_id(), Some(source.id.as_str()));
+        assert_eq!(t.titles(), vec!["Source"]);
+        assert!(t.stored(&source).links.is_empty());
+
+        // A later plain note is not linked either
+        t.press(KeyCode::Esc);
+        t.type_keys("nPlain\n\nbody");
+        t.press_ctrl('s');
+        assert_eq!(t.titles(), vec!["Plain", "Source"]);
+        assert!(t.stored(&source).links.is_empty());
+    }
+
+    #[test]
+    fn create_and_link_from_a_vanished_note_reports_it_and_creates_nothing() {
+        let Some(mut t) = TestApp::new("create-linked-gone") else { return };
+        let source = t.note("Source", "start here");
+
+        t.open(&source);
+        t.type_keys("LTarget\n\nmore");
+        t.service.delete_note(&source.id).unwrap();
+        t.press_ctrl('s');
+
+        assert!(matches!(t.mode, AppMode::Create), "the buffer is kept");
+        assert!(t.status().starts_with("✗ Failed to create note: "), "{}", t.status());
+        assert!(t.titles().is_empty());
+    }
+
+    #[test]
+    fn saving_a_note_without_a_body_asks_first() {
+        let Some(mut t) = TestApp::new("empty-body") else { return };
+        t.type_keys("nJust a title\n");
+        t.press_ctrl('s');
+        assert!(matches!(t.mode, AppMode::Confirm(ConfirmKind::SaveEmptyNewNote)));
+        assert!(t.titles().is_empty());
+
+        t.press(KeyCode::Esc);
+        assert!(matches!(t.mode, AppMode::Create), "keep editing");
+        assert_eq!(t.input_buffer, "Just a title\n");
+        t.type_keys("body");
+        t.press_ctrl('s');
+        assert!(matches!(t.mode, AppMode::View), "a note with a body saves at once");
+
+        t.press(KeyCode::Esc);
+        t.type_keys("nAnother title");
+        t.press_ctrl('s');
+        t.type_keys("y");
+        assert!(matches!(t.mode, AppMode::View), "saved anyway");
+        assert_eq!(t.titles(), vec!["Another title", "Just a title"]);
+    }
+
+    #[test]
+    fn allow_policy_saves_a_note_without_a_body_at_once() {
+        let Some(mut t) = TestApp::with_config("empty-body-allow", r#"{"empty_body": "allow"}"#) else { return };
+        t.type_keys("nJust a title");
+        t.press_ctrl('s');
+        assert!(matches!(t.mode, AppMode::View));
+        assert_eq!(t.titles(), vec!["Just a title"]);
+    }
+
+    /// Start linking the current note with `l` and select `target` in the note picker
+    fn pick_link_target(t: &mut TestApp, target: &Note) {
+        t.type_keys("l");
+        assert!(matches!(t.mode, AppMode::LinkSelect));
+        t.selected_index = t.notes.iter().position(|n| n.id == target.id).expect("target in the list");
+        t.press(KeyCode::Enter);
+    }
+
+    #[test]
+    fn confirmed_link_is_created() {
+        let Some(mut t) = TestApp::with_config("confirm-link", r#"{"confirm_links": true}"#) else { return };
+        let source = t.note("Source", "from");
+        let target = t.note("Target", "to");
+
+        t.open(&source);
+        pick_link_target(&mut t, &target);
+        assert!(matches!(t.mode, AppMode::Confirm(ConfirmKind::CreateLink)));
+        assert!(t.stored(&source).links.is_empty(), "nothing linked before the answer");
+        t.type_keys("y");
+
+        assert!(matches!(t.mode, AppMode::View));
+        assert_eq!(t.status(), "✓ Note linked");
+        assert_eq!(t.stored(&source).links, vec![target.id.clone()]);
+        assert_eq!(t.current_note.as_ref().unwrap().links, vec![target.id]);
+    }
+
+    #[test]
+    fn declined_link_goes_back_to_the_picker() {
+        let Some(mut t) = TestApp::with_config("decline-link", r#"{"confirm_links": true}"#) else { return };
+        let source = t.note("Source", "from");
+        let target = t.note("Target", "to");
+
+        t.open(&source);
+        pick_link_target(&mut t, &target);
+        t.type_keys("n");
+        assert!(matches!(t.mode, AppMode::LinkSelect));
+        t.press(KeyCode::Esc);
+
+        assert!(matches!(t.mode, AppMode::View));
+        assert!(t.stored(&source).links.is_empty());
+    }
+
+    #[test]
+    fn links_are_made_without_asking_unless_configured() {
+        let Some(mut t) = TestApp::new("link-no-confirm") else { return };
+        let source = t.note("Source", "from");
+        let target = t.note("Target", "to");
+
+        t.open(&source);
+        pick_link_target(&mut t, &target);
+
+        assert!(matches!(t.mode, AppMode::View));
+        assert_eq!(t.stored(&source).links, vec![target.id]);
+    }
+
+    #[test]
+    fn fuzzy_reranking_keeps_the_selected_note_while_typing() {
+        let Some(mut t) = TestApp::with_config("fuzzy-select", r#"{"fuzzy_search": true}"#) else { return };
+        let wanted = t.note("Gadget plan", "x");
+        t.note("Plan", "x");
+        t.note("Pupil", "x");
+
+        t.type_keys("/p");
+        assert_eq!(t.filtered_notes.len(), 3);
+        t.selected_index = t.filtered_notes.iter().position(|n| n.id == wanted.id).unwrap();
+        let mut positions = vec![t.selected_index];
+        for c in "lan".chars() {
+            t.press(KeyCode::Char(c));
+            assert_eq!(t.filtered_notes[t.selected_index].id, wanted.id, "selection after typing {:?}", c);
+            positions.push(t.selected_index);
+        }
+        assert_ne!(positions.first(), positions.last(), "the note moved up the ranking: {:?}", positions);
+
+        t.press(KeyCode::Backspace);
+        t.press(KeyCode::Backspace);
+        t.press(KeyCode::Backspace);
+        assert_eq!(t.filtered_notes.len(), 3);
+        assert_eq!(t.filtered_notes[t.selected_index].id, wanted.id);
+    }
+
+    #[test]
+    fn leaving_a_changed_note_asks_and_saves_it() {
+        let Some(mut t) = TestApp::new("leave-edit-save") else { return };
+        let note = t.note("Draft", "first line");
+        let commits = t.service.get_note_history(&note.id).unwrap().len();
+
+        t.open(&note);
+        t.type_keys("e more");
+        t.press(KeyCode::Esc);
+        assert!(matches!(t.mode, AppMode::Confirm(ConfirmKind::UnsavedEdit)));
+        assert_eq!(t.stored(&note).content, note.content, "nothing written before the answer");
+        t.type_keys("s");
+
+        assert!(matches!(t.mode, AppMode::View));
+        assert_eq!(t.stored(&note).content, "Draft\n\nfirst line more");
+        assert_eq!(t.service.get_note_history(&note.id).unwrap().len(), commits + 1);
+    }
+
+    #[test]
+    fn leaving_a_changed_note_can_discard_or_keep_editing() {
+        let Some(mut t) = TestApp::new("leave-edit-discard") else { return };
+        let note = t.note("Draft", "first line");
+
+        t.open(&note);
+        t.type_keys("e more");
+        t.press(KeyCode::Esc);
+        t.press(KeyCode::Esc);
+        assert!(matches!(t.mode, AppMode::Edit), "Esc at the prompt goes back to editing");
+        assert!(t.input_buffer.ends_with(" more"));
+
+        t.press(KeyCode::Esc);
+        t.type_keys("d");
+        assert!(matches!(t.mode, AppMode::View));
+        assert_eq!(t.status(), "ℹ Discarded unsaved changes");
+        assert_eq!(t.stored(&note).content, note.content);
+    }
+
+    #[test]
+    fn leaving_an_unchanged_note_neither_asks_nor_commits() {
+        let Some(mut t) = TestApp::new("leave-edit-clean") else { return };
+        let note = t.note("Draft", "first line");
+        let commits = t.service.get_note_history(&note.id).unwrap().len();
+
+        t.open(&note);
+        t.type_keys("e");
+        t.press(KeyCode::Esc);
+
+        assert!(matches!(t.mode, AppMode::View));
+        let stored = t.stored(&note);
+        assert_eq!(stored.updated_at, note.updated_at);
+        assert_eq!(t.service.get_note_history(&note.id).unwrap().len(), commits);
+    }
+
+    #[test]
+    fn word_at_the_cursor_becomes_a_tag_and_the_text_stays() {
+        let Some(mut t) = TestApp::new("word-to-tag") else { return };
+        let note = t.note("Draft", "learn rust today");
+
+        t.open(&note);
+        t.type_keys("e");
+        t.cursor_pos = t.input_buffer.find("rust").unwrap() + 2;
+        t.press_ctrl('g');
+
+        assert!(matches!(t.mode, AppMode::Edit));
+        assert_eq!(t.status(), "✓ Tagged #rust");
+        assert_eq!(t.stored(&note).tags, vec!["rust".to_string()]);
+        assert_eq!(t.input_buffer, note.content);
+    }
+
+    #[test]
+    fn cut_word_leaves_the_text_and_saves_without_a_conflict() {
+        let Some(mut t) = TestApp::new("word-to-tag-cut") else { return };
+        let note = t.note("Draft", "learn #rust today");
+
+        t.open(&note);
+        t.type_keys("e");
+        t.cursor_pos = t.input_buffer.find("rust").unwrap();
+        t.handle_key(KeyCode::Char('g'), KeyModifiers::ALT).unwrap();
+        assert_eq!(t.status(), "✓ Tagged #rust and removed it from the text");
+        assert_eq!(t.input_buffer, "Draft\n\nlearn today");
+
+        // The tag written meanwhile is not taken for an outside change
+        t.press_ctrl('s');
+        assert!(matches!(t.mode, AppMode::View));
+        let stored = t.stored(&note);
+        assert_eq!(stored.content, "Draft\n\nlearn today");
+        assert_eq!(stored.tags, vec!["rust".to_string()]);
+    }
+
+    #[test]
+    fn no_word_at_the_cursor_adds_no_tag() {
+        let Some(mut t) = TestApp::new("word-to-tag-none") else { return };
+        let note = t.note("Draft", "two  spaces");
+
+        t.open(&note);
+        t.type_keys("e");
+        t.cursor_pos = t.input_buffer.find("  ").unwrap() + 1;
+        t.press_ctrl('g');
+
+        assert_eq!(t.status(), "ℹ No word at the cursor");
+        assert!(t.stored(&note).tags.is_empty());
+    }
+
+    /// Select the note at `index` of the list scrolled down `offset` entries, add a note from
+    /// outside the app (so it comes first, newest) and press `r`
+    fn refresh_after_a_new_note(t: &mut TestApp, index: usize, offset: usize) {
+        t.selected_index = index;
+        t.list_offset.set(offset);
+        t.service.create_note("Newcomer".to_string(), "Newcomer\n\nfrom a sync".to_string()).unwrap();
+        t.type_keys("r");
+        assert_eq!(t.status(), "✓ Notes refreshed: 1 added, 0 removed");
+    }
+
+    #[test]
+    fn refresh_keeps_the_selected_note_on_its_row() {
+        let Some(mut t) = TestApp::new("refresh-follow") else { return };
+        for title in ["One", "Two", "Three"] {
+            t.note(title, "x");
+        }
+        let selected = t.notes[1].clone();
+
+        refresh_after_a_new_note(&mut t, 1, 1);
+
+        assert_eq!(t.notes[t.selected_index].id, selected.id);
+        assert_eq!(t.selected_index, 2);
+        assert_eq!(t.list_offset.get(), 2, "scrolled along with the note");
+    }
+
+    #[test]
+    fn refresh_can_keep_the_list_position_instead() {
+        let Some(mut t) = TestApp::with_config("refresh-position", r#"{"refresh_keeps_selection": false}"#) else { return };
+        for title in ["One", "Two", "Three"] {
+            t.note(title, "x");
+        }
+
+        refresh_after_a_new_note(&mut t, 1, 1);
+
+        assert_eq!(t.selected_index, 1);
+        assert_eq!(t.list_offset.get(), 1);
+    }
+
+    #[test]
+    fn list_scrolls_only_to_keep_the_selection_in_view() {
+        let Some(mut t) = TestApp::new("list-scroll") else { return };
+        for i in 0..20 {
+            t.note(&format!("Note {}", i), "x");
+        }
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(60, 20)).unwrap();
+
+        t.selected_index = 15;
+        terminal.draw(|frame| t.render(frame)).unwrap();
+        let offset = t.list_offset.get();
+        assert!(offset > 0 && offset <= 15, "scrolled to show the selection: {}", offset);
+
+        t.type_keys("k");
+        terminal.draw(|frame| t.render(frame)).unwrap();
+        assert_eq!(t.list_offset.get(), offset, "moving up within view does not scroll");
+    }
+
+    /// Three notes with the middle one locked, all picked with Space, and one left out
+    fn mixed_selection(t: &mut TestApp) -> (Vec<Note>, Note) {
+        let picked: Vec<Note> = ["First", "Locked", "Third"].iter().map(|title| t.note(title, "x")).collect();
+        let left_out = t.note("Left out", "x");
+        t.service.set_locked(&picked[1].id, true).unwrap();
+        t.reload_notes().unwrap();
+        for note in &picked {
+            t.selected_index = t.notes.iter().position(|n| n.id == note.id).unwrap();
+            t.press(KeyCode::Char(' '));
+        }
+        (picked, left_out)
+    }
+
+    #[test]
+    fn space_adds_a_note_to_the_selection_and_takes_it_out_again() {
+        let Some(mut t) = TestApp::new("toggle-select") else { return };
+        t.note("One", "x");
+        t.note("Two", "x");
+        let first = t.notes[0].id.clone();
+
+        t.press(KeyCode::Char(' '));
+        assert_eq!(t.selected_ids, HashSet::from([first.clone()]));
+        assert_eq!(t.selected_index, 1, "moves on to the next note");
+
+        t.type_keys("k ");
+        assert!(t.selected_ids.is_empty());
+    }
+
+    #[test]
+    fn batch_delete_skips_the_locked_note_and_can_be_undone() {
+        let Some(mut t) = TestApp::new("batch-delete") else { return };
+        let (picked, _) = mixed_selection(&mut t);
+        assert_eq!(t.selected_ids.len(), 3);
+
+        t.type_keys("d");
+        assert!(matches!(t.mode, AppMode::Confirm(ConfirmKind::DeleteSelected)));
+        t.type_keys("y");
+
+        assert_eq!(t.status(), "✓ Deleted 2 notes, 1 locked skipped");
+        assert_eq!(t.titles(), vec!["Left out", "Locked"]);
+        assert!(t.selected_ids.is_empty());
+        assert_eq!(t.last_undo.as_ref().unwrap().description, "Delete 2 selected notes");
+
+        t.type_keys("U");
+        t.type_keys("y");
+        assert_eq!(t.status(), "✓ Undone: Delete 2 selected notes");
+        assert_eq!(t.titles(), vec!["First", "Left out", "Locked", "Third"]);
+        assert!(t.stored(&picked[1]).locked);
+    }
+
+    #[test]
+    fn batch_tag_touches_only_the_unlocked_selected_notes() {
+        let Some(mut t) = TestApp::new("batch-tag") else { return };
+        let (picked, left_out) = mixed_selection(&mut t);
+
+        t.type_keys("+#project");
+        t.press(KeyCode::Enter);
+
+        assert_eq!(t.status(), "✓ Tagged 2 notes #project, 1 locked skipped");
+        let tag = vec!["project".to_string()];
+        assert_eq!(t.stored(&picked[0]).tags, tag);
+        assert!(t.stored(&picked[1]).tags.is_empty());
+        assert_eq!(t.stored(&picked[2]).tags, tag);
+        assert!(t.stored(&left_out).tags.is_empty());
+        assert_eq!(t.last_undo.as_ref().unwrap().description, "Tag 2 selected notes #project");
+    }
+
+    /// A help overlay row as drawn: the keys padded to a column, then what they do
+    fn help_row(keys: &str, description: &str) -> String {
+        format!("{:<15}{}", keys, description)
+    }
+
+    #[test]
+    fn view_help_overlay_lists_the_edit_link_and_tag_keys() {
+        let Some(mut t) = TestApp::new("help-overlay") else { return };
+        let note = t.note("Note", "x");
+
+        t.open(&note);
+        t.type_keys("?");
+        assert_eq!(t.help_overlay, Some(KeyContext::View));
+        let screen = t.screen();
+        for row in [help_row("e", "Edit note"), help_row("l", "Link to another note"), help_row("t", "Add tag")] {
+            assert!(screen.contains(&row), "{:?} missing from\n{}", row, screen);
+        }
+
+        t.press(KeyCode::Esc);
+        assert_eq!(t.help_overlay, None);
+        assert!(matches!(t.mode, AppMode::View), "closing the overlay stays in the note");
+    }
+
+    #[test]
+    fn remapped_keys_show_in_help_and_hints() {
+        let config = r#"{"keybindings": {"list": {"new_note": "a"}, "view": {"edit": "w"}}}"#;
+        let Some(mut t) = TestApp::with_config("help-remapped", config) else { return };
+
+        assert!(t.screen().contains("No notes yet — press a to create your first note."));
+        t.type_keys("+");
+        assert_eq!(t.status(), "ℹ Select notes with Space first");
+
+        let note = t.note("Note", "x");
+        t.open(&note);
+        let screen = t.screen();
+        assert!(screen.contains("w: edit | Ctrl+e: edit in $EDITOR | l: link"), "view help bar:\n{}", screen);
+        t.type_keys("?");
+        let screen = t.screen();
+        assert!(screen.contains(&help_row("w", "Edit note")), "{}", screen);
+        assert!(!screen.contains(&help_row("e", "Edit note")));
+    }
+
+    /// Matched text of each match of `query` in `content`, with its line
+    fn matched<'a>(content: &'a str, query: &str) -> Vec<(usize, &'a str)> {
+        let lines: Vec<&str> = content.lines().collect();
+        find_matches(content, query).into_iter().map(|m| (m.line, &lines[m.line][m.start..m.end])).collect()
+    }
+
+    #[test]
+    fn matches_ignore_case_and_do_not_overlap() {
+        assert_eq!(matched("Foo foo\nbar FOO", "foo"), [(0, "Foo"), (0, "foo"), (1, "FOO")]);
+        assert_eq!(matched("aaaa aaa", "aa"), [(0, "aa"), (0, "aa"), (0, "aa")]);
+        assert!(matched("anything", "").is_empty());
+        assert!(matched("", "x").is_empty());
+    }
+
+    #[test]
+    fn matches_are_byte_ranges_on_character_boundaries() {
+        assert_eq!(matched("naïve Über ÜBER", "über"), [(0, "Über"), (0, "ÜBER")]);
+        assert_eq!(matched("→ café ←", "É"), [(0, "é")]);
+    }
+
+    #[test]
+    fn letters_that_lowercase_to_several_characters_match_whole() {
+        // İ lowercases to i and a combining dot: it matches as a whole, never as a bare i
+        assert_eq!(matched("İstanbul", "i̇stanbul"), [(0, "İstanbul")]);
+        assert_eq!(matched("İstanbul", "İSTANBUL"), [(0, "İstanbul")]);
+        assert!(matched("İstanbul", "istanbul").is_empty());
+        // Lowercasing leaves ß as it is, so it matches ß and ẞ but not ss
+        assert_eq!(matched("Straße STRAẞE", "straße"), [(0, "Straße"), (0, "STRAẞE")]);
+        assert!(matched("Straße", "strasse").is_empty());
+    }
+
+    #[test]
+    fn random_pick_avoids_the_previous_one_unless_it_is_all_there_is() {
+        let mut rng = XorShift::new(42);
+        assert_eq!(rng.pick_index(0, None), None);
+        assert_eq!(rng.pick_index(1, Some(0)), Some(0));
+
+        let picks: HashSet<usize> = (0..200).map(|_| rng.pick_index(4, Some(2)).unwrap()).collect();
+        assert_eq!(picks, HashSet::from([0, 1, 3]));
+        let picks: HashSet<usize> = (0..200).map(|_| rng.pick_index(3, Some(7)).unwrap()).collect();
+        assert_eq!(picks, HashSet::from([0, 1, 2]), "an avoided index out of range avoids nothing");
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_picks() {
+        let (mut a, mut b) = (XorShift::new(7), XorShift::new(7));
+        let draws = |rng: &mut XorShift| (0..20).map(|_| rng.pick_index(10, None)).collect::<Vec<_>>();
+        assert_eq!(draws(&mut a), draws(&mut b));
+        assert_ne!(XorShift::new(0).next_u64(), 0, "a zero seed still moves");
+    }
+
+    #[test]
+    fn random_note_opens_a_different_note_each_time() {
+        let Some(mut t) = TestApp::new("random-note") else { return };
+        t.type_keys("R");
+        assert!(matches!(t.mode, AppMode::List), "nothing to open in an empty vault");
+
+        let only = t.note("Only", "x");
+        t.type_keys("R");
+        assert_eq!(t.current_id(), Some(only.id.as_str()));
+
+        t.note("Other", "x");
+        let mut previous = t.current_id().unwrap().to_string();
+        for _ in 0..5 {
+            t.mode = AppMode::List;
+            t.type_keys("R");
+            assert!(matches!(t.mode, AppMode::View));
+            let picked = t.current_id().unwrap().to_string();
+            assert_ne!(picked, previous);
+            previous = picked;
+        }
+    }
+
+    #[test]
+    fn multi_line_paste_lands_at_a_mid_line_cursor() {
+        let Some(mut t) = TestApp::new("paste-mid-line") else { return };
+        let note = t.note("Draft", "before after");
+
+        t.open(&note);
+        t.type_keys("e");
+        t.cursor_pos = t.input_buffer.find(" after").unwrap();
+        t.handle_paste(" first\r\nsecond\r\nthird").unwrap();
+
+        assert_eq!(t.input_buffer, "Draft\n\nbefore first\nsecond\nthird after");
+        assert_eq!(&t.input_buffer[t.cursor_pos..], " after", "the cursor ends after the pasted text");
+        t.type_keys("!");
+        assert!(t.input_buffer.ends_with("third! after"));
+    }
+
+    #[test]
+    fn paste_into_a_single_line_input_joins_the_lines() {
+        let Some(mut t) = TestApp::new("paste-search") else { return };
+        t.note("alpha beta", "x");
+
+        t.type_keys("/");
+        t.handle_paste("alpha\nbeta").unwrap();
+
+        assert_eq!(t.input_buffer, "alpha beta");
+        assert_eq!(t.filtered_notes.len(), 1, "the search runs on the pasted query");
+    }
+
+    #[test]
+    fn walkthrough_moves_on_as_each_step_is_tried() {
+        let Some(mut t) = TestApp::first_run("tutorial") else { return };
+        assert_eq!(t.tutorial, Some(TutorialStep::Welcome));
+
+        t.press(KeyCode::Enter);
+        assert_eq!(t.titles(), ["Linking notes", "Welcome to jjzettel"]);
+        assert_eq!(t.current_note.as_ref().unwrap().title, "Welcome to jjzettel");
+        assert_eq!(t.tutorial, Some(TutorialStep::AddTag));
+
+        t.type_keys("ttutorial");
+        t.press(KeyCode::Enter);
+        assert_eq!(t.tutorial, Some(TutorialStep::AddLink));
+
+        t.type_keys("l");
+        t.selected_index = t.notes.iter().position(|n| n.title == "Linking notes").unwrap();
+        t.press(KeyCode::Enter);
+        assert_eq!(t.tutorial, Some(TutorialStep::Search));
+
+        t.press(KeyCode::Esc);
+        assert_eq!(t.tutorial, Some(TutorialStep::Search), "going back to the list is not a search yet");
+        t.type_keys("/welcome");
+        t.press(KeyCode::Enter);
+        assert_eq!(t.tutorial, Some(TutorialStep::Finished));
+
+        t.press(KeyCode::Enter);
+        assert_eq!(t.tutorial, None);
+        let saved = AppState::load_or_default(t.dir.join("state.json"));
+        assert!(saved.tutorial_done, "not shown again on the next run");
+    }
+
+    #[test]
+    fn walkthrough_can_be_skipped_at_the_start() {
+        let Some(mut t) = TestApp::first_run("tutorial-skip") else { return };
+        t.press(KeyCode::Esc);
+
+        assert_eq!(t.tutorial, None);
+        assert!(t.titles().is_empty(), "no sample notes");
+        assert!(AppState::load_or_default(t.dir.join("state.json")).tutorial_done);
+    }
+
+    #[test]
+    fn up_and_down_recall_submitted_queries_newest_first() {
+        let Some(mut t) = TestApp::new("search-history") else { return };
+        for query in ["alpha", "beta", "alpha"] {
+            t.type_keys(&format!("/{}", query));
+            t.press(KeyCode::Enter);
+            t.press(KeyCode::Esc);
+        }
+        assert_eq!(t.state.search_history, ["alpha", "beta", "alpha"]);
+
+        t.type_keys("/");
+        let mut recalled = Vec::new();
+        for _ in 0..4 {
+            t.press(KeyCode::Up);
+            recalled.push(t.input_buffer.clone());
+        }
+        assert_eq!(recalled, ["alpha", "beta", "alpha", "alpha"], "stops at the oldest");
+
+        t.press(KeyCode::Down);
+        assert_eq!(t.input_buffer, "beta");
+        t.press(KeyCode::Down);
+        t.press(KeyCode::Down);
+        assert_eq!(t.input_buffer, "", "past the newest query the input is empty again");
+        let saved = AppState::load_or_default(t.dir.join("state.json"));
+        assert_eq!(saved.search_history, ["alpha", "beta", "alpha"], "kept in the state file");
+    }
+
+    #[test]
+    fn saved_search_is_kept_and_applied_again_from_its_screen() {
+        let Some(mut t) = TestApp::new("saved-search") else { return };
+        t.note("Alpha plan", "x");
+        t.note("Other", "x");
+
+        t.type_keys("/alpha");
+        t.press(KeyCode::Enter);
+        t.type_keys("SAlphas");
+        t.press(KeyCode::Enter);
+        t.press(KeyCode::Esc);
+        assert!(!t.is_searching);
+        let saved = AppState::load_or_default(t.dir.join("state.json"));
+        assert_eq!(saved.saved_searches, [SavedSearch { name: "Alphas".to_string(), query: "alpha".to_string() }]);
+
+        t.type_keys("F");
+        assert!(matches!(t.mode, AppMode::SavedSearches));
+        t.type_keys("1");
+        assert!(matches!(t.mode, AppMode::List));
+        assert!(t.is_searching);
+        assert_eq!(t.search_query, "alpha");
+        assert_eq!(t.filtered_notes.iter().map(|n| n.title.as_str()).collect::<Vec<_>>(), ["Alpha plan"]);
+    }
+}
@@ -0,0 +1,111 @@
+use anyhow::Result;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Read the system clipboard with the platform's clipboard tool: `pbpaste` on macOS,
+/// PowerShell on Windows, and `wl-paste`, `xclip` or `xsel` on Linux (the first that works)
+pub fn read() -> Result<String> {
+    let candidates: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+        &[("pbpaste", &[])]
+    } else if cfg!(windows) {
+        &[("powershell", &["-NoProfile", "-Command", "Get-Clipboard -Raw"])]
+    } else {
+        &[
+            ("wl-paste", &["--no-newline"]),
+            ("xclip", &["-selection", "clipboard", "-o"]),
+            ("xsel", &["--clipboard", "--output"]),
+            ("powershell.exe", &["-NoProfile", "-Command", "Get-Clipboard -Raw"]), // WSL
+        ]
+    };
+
+    let mut last_error = None;
+    for (program, args) in candidates {
+        match Command::new(program).args(*args).output() {
+            Ok(output) if output.status.success() => {
+                return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+            }
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                last_error = Some(format!("{}: {}", program, stderr.lines().next().unwrap_or("failed").trim()));
+            }
+            // Tool not installed; try the next one
+            Err(_) => {}
+        }
+    }
+    match last_error {
+        Some(e) => anyhow::bail!("Clipboard not readable ({})", e),
+        None => anyhow::bail!("No clipboard tool found (install wl-clipboard, xclip or xsel)"),
+    }
+}
+
+/// Put `text` on the system clipboard with the platform's clipboard tool: `pbcopy` on macOS,
+/// `clip` on Windows, and `wl-copy`, `xclip` or `xsel` on Linux (the first that works)
+pub fn write(text: &str) -> Result<()> {
+    let candidates: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else if cfg!(windows) {
+        &[("clip", &[])]
+    } else {
+        &[
+            ("wl-copy", &[]),
+            ("xclip", &["-selection", "clipboard", "-i"]),
+            ("xsel", &["--clipboard", "--input"]),
+            ("clip.exe", &[]), // WSL
+        ]
+    };
+
+    let mut last_error = None;
+    for (program, args) in candidates {
+        // The X11 and Wayland tools stay around in the background to serve the selection,
+        // so their output must not be piped back or waiting for it would never end
+        let child = Command::new(program)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let Ok(mut child) = child else {
+            // Tool not installed; try the next one
+            continue;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        let status = child.wait()?;
+        if status.success() {
+            return Ok(());
+        }
+        last_error = Some(format!("{}: {}", program, status));
+    }
+    match last_error {
+        Some(e) => anyhow::bail!("Clipboard not writable ({})", e),
+        None => anyhow::bail!("No clipboard tool found (install wl-clipboard, xclip or xsel)"),
+    }
+}
+
+/// Pasted text with Windows and old-Mac line endings turned into `\n` and other control
+/// characters (except tabs) dropped, ready to insert into a note
+pub fn normalize(text: &str) -> String {
+    text.replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .chars()
+        .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_endings_become_newlines() {
+        assert_eq!(normalize("one\r\ntwo\rthree\nfour"), "one\ntwo\nthree\nfour");
+        assert_eq!(normalize("\r\n\r\n"), "\n\n", "CRLF is one line break, not two");
+    }
+
+    #[test]
+    fn control_characters_are_dropped_but_tabs_kept() {
+        assert_eq!(normalize("a\tb\x1b[31mc\x07\x00d\u{9b}e"), "a\tb[31mcde");
+        assert_eq!(normalize("plain ünïcode"), "plain ünïcode");
+    }
+}
@@ -0,0 +1,69 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Text handed to the editor in `$EDITOR` through a temporary file. The app prepares it,
+/// the main loop gives the terminal to the editor for `run` and then hands the terminal
+/// back, and the app reads the result with `read_back`. The file is removed on drop.
+pub struct ExternalEdit {
+    editor: String,
+    path: PathBuf,
+    final_newline: bool, // Whether the text handed over ended with a line break
+}
+
+impl ExternalEdit {
+    /// Write `text` to a temporary file ending in `.{extension}`, so the editor picks the
+    /// right syntax. Fails when `$EDITOR` is not set.
+    pub fn prepare(text: &str, extension: &str) -> Result<Self> {
+        let editor = std::env::var("EDITOR")
+            .ok()
+            .filter(|editor| !editor.trim().is_empty())
+            .ok_or_else(|| anyhow::anyhow!("$EDITOR is not set; set it (e.g. EDITOR=vim) to edit in an external editor"))?;
+        let stamp = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+        let path = std::env::temp_dir().join(format!("jjzettel-{}-{}.{}", std::process::id(), stamp, extension));
+        std::fs::write(&path, text).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(ExternalEdit { editor, path, final_newline: text.ends_with('\n') })
+    }
+
+    /// The editor's program name, e.g. `vim` for `EDITOR="vim -u NONE"`
+    pub fn program(&self) -> &str {
+        self.editor.split_whitespace().next().unwrap_or_default()
+    }
+
+    /// Run the editor on the file and wait for it to exit. The terminal has to be out of
+    /// raw mode and the alternate screen while it runs.
+    pub fn run(&self) -> Result<()> {
+        // `$EDITOR` may carry arguments, e.g. `code --wait`
+        let mut words = self.editor.split_whitespace();
+        let program = words.next().unwrap_or_default();
+        let status = Command::new(program)
+            .args(words)
+            .arg(&self.path)
+            .status()
+            .map_err(|e| anyhow::anyhow!("Failed to start {}: {}", program, e))?;
+        if !status.success() {
+            anyhow::bail!("{} exited with {}", program, status);
+        }
+        Ok(())
+    }
+
+    /// The file's text after editing. Editors like vim end the file with a line break;
+    /// it is dropped again if the text handed over had none.
+    pub fn read_back(&self) -> Result<String> {
+        let mut text = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        if !self.final_newline && text.ends_with('\n') {
+            text.pop();
+            if text.ends_with('\r') {
+                text.pop();
+            }
+        }
+        Ok(text)
+    }
+}
+
+impl Drop for ExternalEdit {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,140 @@
+use crate::config::HyperlinkMode;
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+
+/// Wrap `text` in an OSC 8 escape sequence so supporting terminals show it as a link to `url`.
+/// Control characters are dropped from both: an ESC or BEL would end the sequence early and
+/// hand the rest to the terminal as commands.
+pub fn osc8(url: &str, text: &str) -> String {
+    let clean = |s: &str| s.chars().filter(|c| !c.is_control()).collect::<String>();
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", clean(url), clean(text))
+}
+
+/// Whether to emit hyperlinks for this run, detecting support in `auto` mode
+pub fn enabled(mode: HyperlinkMode) -> bool {
+    match mode {
+        HyperlinkMode::Always => true,
+        HyperlinkMode::Never => false,
+        HyperlinkMode::Auto => terminal_supports_osc8(|name| std::env::var(name).ok()),
+    }
+}
+
+/// Terminals have no standard way to report OSC 8 support, so this goes by the
+/// environment variables of terminals known to handle it
+fn terminal_supports_osc8(env: impl Fn(&str) -> Option<String>) -> bool {
+    if ["KITTY_WINDOW_ID", "WEZTERM_EXECUTABLE", "WT_SESSION"].iter().any(|name| env(name).is_some()) {
+        return true;
+    }
+    if let Some(program) = env("TERM_PROGRAM")
+        && ["iTerm.app", "WezTerm", "vscode", "ghostty", "Hyper"].contains(&program.as_str())
+    {
+        return true;
+    }
+    // GNOME Terminal, Tilix and other VTE terminals since 0.50
+    if let Some(vte) = env("VTE_VERSION")
+        && vte.parse::<u32>().is_ok_and(|version| version >= 5000)
+    {
+        return true;
+    }
+    env("TERM").is_some_and(|term| ["kitty", "alacritty", "foot", "ghostty", "wezterm"].iter().any(|t| term.contains(t)))
+}
+
+/// Turn every `http://` or `https://` URL drawn in `area` into a hyperlink. Runs after the
+/// text is rendered, so wrapping and scrolling are already applied; a URL split across
+/// lines only links its visible part.
+pub fn link_urls(buffer: &mut Buffer, area: Rect) {
+    let area = area.intersection(buffer.area);
+    for y in area.top()..area.bottom() {
+        // One char per cell; wide or empty cells end a URL
+        let row: Vec<char> = (area.left()..area.right())
+            .map(|x| {
+                let mut chars = buffer[(x, y)].symbol().chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => ' ',
+                }
+            })
+            .collect();
+
+        let mut start = 0;
+        while start < row.len() {
+            let Some(len) = url_length(&row[start..]) else {
+                start += 1;
+                continue;
+            };
+            let end = start + len;
+            let url: String = row[start..end].iter().collect();
+            // Two cells per escape: ratatui measures the sequence as two columns wide
+            // and skips drawing the cell after it
+            for chunk in (start..end).step_by(2) {
+                let text: String = row[chunk..(chunk + 2).min(end)].iter().collect();
+                buffer[(area.left() + chunk as u16, y)].set_symbol(&osc8(&url, &text));
+            }
+            start = end;
+        }
+    }
+}
+
+/// Length of the URL at the start of `text`, if it starts with one. Trailing punctuation
+/// (as in "see https://example.com.") is not part of the URL.
+fn url_length(text: &[char]) -> Option<usize> {
+    let starts_with = |prefix: &str| text.iter().take(prefix.len()).copied().eq(prefix.chars());
+    let scheme = if starts_with("https://") {
+        "https://".len()
+    } else if starts_with("http://") {
+        "http://".len()
+    } else {
+        return None;
+    };
+    let mut len = text
+        .iter()
+        .position(|c| !c.is_ascii_graphic() || matches!(c, '"' | '<' | '>' | '`'))
+        .unwrap_or(text.len());
+    while len > scheme && matches!(text[len - 1], '.' | ',' | ';' | ':' | '!' | '?' | ')' | ']' | '\'') {
+        len -= 1;
+    }
+    (len > scheme).then_some(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn osc8_wraps_the_text_in_a_link_sequence() {
+        assert_eq!(
+            osc8("https://example.com/a?b=c", "ex"),
+            "\x1b]8;;https://example.com/a?b=c\x1b\\ex\x1b]8;;\x1b\\"
+        );
+    }
+
+    #[test]
+    fn osc8_drops_control_characters() {
+        let link = osc8("https://evil.example\x1b]0;pwned\x07/x", "t\x1b[2Jx");
+        assert_eq!(link, "\x1b]8;;https://evil.example]0;pwned/x\x1b\\t[2Jx\x1b]8;;\x1b\\");
+        assert_eq!(link.matches('\x1b').count(), 4, "only the sequence's own escapes");
+        assert!(!link.contains('\x07') && !osc8("https://a.example\u{9b}", "t").contains('\u{9b}'));
+    }
+
+    #[test]
+    fn urls_end_before_spaces_quotes_and_trailing_punctuation() {
+        let len = |text: &str| url_length(&text.chars().collect::<Vec<_>>());
+        assert_eq!(len("https://example.com/x. More"), Some("https://example.com/x".len()));
+        assert_eq!(len("http://a.example\"quoted"), Some("http://a.example".len()));
+        assert_eq!(len("(https://a.example/b)"), None, "must start at the scheme");
+        assert_eq!(len("https://"), None);
+        assert_eq!(len("https://x.example/\x1b]8"), Some("https://x.example/".len()));
+    }
+
+    #[test]
+    fn support_is_detected_from_known_terminals() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| vars.iter().find(|(var, _)| *var == name).map(|(_, value)| value.to_string())
+        };
+        assert!(terminal_supports_osc8(env(&[("TERM_PROGRAM", "WezTerm")])));
+        assert!(terminal_supports_osc8(env(&[("VTE_VERSION", "6003")])));
+        assert!(!terminal_supports_osc8(env(&[("VTE_VERSION", "4802")])));
+        assert!(terminal_supports_osc8(env(&[("TERM", "xterm-kitty")])));
+        assert!(!terminal_supports_osc8(env(&[("TERM", "xterm-256color")])));
+    }
+}
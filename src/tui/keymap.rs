@@ -0,0 +1,707 @@
+use crate::config::KeySpec;
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyModifiers};
+use std::collections::HashMap;
+
+/// Modes whose keys can be remapped from the config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyContext {
+    List,
+    View,
+}
+
+impl KeyContext {
+    pub const ALL: [KeyContext; 2] = [KeyContext::List, KeyContext::View];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyContext::List => "list",
+            KeyContext::View => "view",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|context| context.name() == name)
+    }
+
+    /// Actions available in this mode with their default keys, in help-screen order
+    fn defaults(self) -> &'static [(Action, &'static [&'static str])] {
+        match self {
+            KeyContext::List => &[
+                (Action::MoveDown, &["j", "Down"]),
+                (Action::MoveUp, &["k", "Up"]),
+                (Action::ToggleSelect, &["Space"]),
+                (Action::TagSelected, &["+"]),
+                (Action::LinkSelected, &["l"]),
+                (Action::NewNote, &["n"]),
+                (Action::NewFromTemplate, &["T"]),
+                (Action::Search, &["/"]),
+                (Action::TagSearch, &["#"]),
+                (Action::SaveSearch, &["S"]),
+                (Action::SavedSearches, &["F"]),
+                (Action::ExportResults, &["X"]),
+                (Action::ToggleSearchOrder, &["O"]),
+                (Action::ToggleListOrder, &["m"]),
+                (Action::OrderUp, &["["]),
+                (Action::OrderDown, &["]"]),
+                (Action::DeleteNote, &["d"]),
+                (Action::DuplicateNote, &["c"]),
+                (Action::DuplicateWithBacklinks, &["C"]),
+                (Action::ShowStatistics, &["s"]),
+                (Action::Refresh, &["r"]),
+                (Action::RandomNote, &["R"]),
+                (Action::TagGroups, &["G"]),
+                (Action::ManageTags, &["t"]),
+                (Action::ShowTasks, &["o"]),
+                (Action::ShowDue, &["A"]),
+                (Action::UndoLast, &["U"]),
+                (Action::ShowLog, &["M"]),
+                (Action::ShowRepoLog, &["H"]),
+                (Action::BackupVault, &["B"]),
+                (Action::RestoreBackup, &["I"]),
+                (Action::CommitNow, &["W"]),
+                (Action::ToggleClock, &["K"]),
+                (Action::ShowDashboard, &["D"]),
+                (Action::VaultHealth, &["V"]),
+                (Action::ShowHelp, &["?"]),
+                (Action::Open, &["Enter"]),
+                (Action::Quit, &["Esc"]),
+            ],
+            KeyContext::View => &[
+                (Action::EditNote, &["e"]),
+                (Action::ExternalEdit, &["Ctrl+e"]),
+                (Action::LinkNote, &["l"]),
+                (Action::CreateLinkedNote, &["L"]),
+                (Action::AddTag, &["t"]),
+                (Action::UnlinkNote, &["u"]),
+                (Action::ReorderLinks, &["o"]),
+                (Action::RemoveTag, &["x"]),
+                (Action::EditMetadata, &["m"]),
+                (Action::RenameNote, &["r"]),
+                (Action::ShowHistory, &["h"]),
+                (Action::MoveNote, &["M"]),
+                (Action::ToggleSpellcheck, &["S"]),
+                (Action::CycleLinkLabels, &["I"]),
+                (Action::CycleHeader, &["i"]),
+                (Action::CycleColor, &["c"]),
+                (Action::EditScratch, &["z"]),
+                (Action::SetDue, &["d"]),
+                (Action::TogglePin, &["p"]),
+                (Action::ToggleLock, &["K"]),
+                (Action::ToggleLineBreaks, &["B"]),
+                (Action::OrderUp, &["["]),
+                (Action::OrderDown, &["]"]),
+                (Action::EditRawJson, &["J"]),
+                (Action::FindInNote, &["/"]),
+                (Action::NextMatch, &["n"]),
+                (Action::PrevMatch, &["N"]),
+                (Action::ScrollDown, &["PageDown"]),
+                (Action::ScrollUp, &["PageUp"]),
+                (Action::ScrollRight, &["Right"]),
+                (Action::ScrollLeft, &["Left"]),
+                (Action::MoveDown, &["j", "Down"]),
+                (Action::MoveUp, &["k", "Up"]),
+                (Action::Open, &["Enter"]),
+                (Action::TrailBack, &["Backspace"]),
+                (Action::PickBreadcrumb, &["b"]),
+                (Action::ExportNote, &["E"]),
+                (Action::CopyNote, &["Y"]),
+                (Action::ExportWithLinks, &["X"]),
+                (Action::ShowHelp, &["?"]),
+                (Action::Back, &["Esc"]),
+            ],
+        }
+    }
+}
+
+/// Something a key can trigger in List or View mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveDown,
+    MoveUp,
+    Open,
+    ToggleSelect,
+    TagSelected,
+    LinkSelected,
+    NewNote,
+    NewFromTemplate,
+    Search,
+    TagSearch,
+    SaveSearch,
+    SavedSearches,
+    ExportResults,
+    ToggleSearchOrder,
+    ToggleListOrder,
+    OrderUp,
+    OrderDown,
+    DeleteNote,
+    DuplicateNote,
+    DuplicateWithBacklinks,
+    ShowStatistics,
+    Refresh,
+    RandomNote,
+    TagGroups,
+    ManageTags,
+    ShowTasks,
+    ShowDue,
+    UndoLast,
+    ShowLog,
+    ShowRepoLog,
+    BackupVault,
+    RestoreBackup,
+    CommitNow,
+    ToggleClock,
+    ShowDashboard,
+    VaultHealth,
+    ShowHelp,
+    Quit,
+    EditNote,
+    ExternalEdit,
+    LinkNote,
+    CreateLinkedNote,
+    AddTag,
+    UnlinkNote,
+    ReorderLinks,
+    RemoveTag,
+    EditMetadata,
+    RenameNote,
+    ShowHistory,
+    MoveNote,
+    ToggleSpellcheck,
+    CycleLinkLabels,
+    CycleHeader,
+    CycleColor,
+    EditScratch,
+    SetDue,
+    TogglePin,
+    ToggleLock,
+    ToggleLineBreaks,
+    EditRawJson,
+    FindInNote,
+    NextMatch,
+    PrevMatch,
+    ScrollDown,
+    ScrollUp,
+    ScrollRight,
+    ScrollLeft,
+    TrailBack,
+    PickBreadcrumb,
+    ExportNote,
+    CopyNote,
+    ExportWithLinks,
+    Back,
+}
+
+impl Action {
+    /// Name used for this action in the config file
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveDown => "move_down",
+            Action::MoveUp => "move_up",
+            Action::Open => "open",
+            Action::ToggleSelect => "select",
+            Action::TagSelected => "tag_selected",
+            Action::LinkSelected => "link_selected",
+            Action::NewNote => "new_note",
+            Action::NewFromTemplate => "new_from_template",
+            Action::Search => "search",
+            Action::TagSearch => "tag_search",
+            Action::SaveSearch => "save_search",
+            Action::SavedSearches => "saved_searches",
+            Action::ExportResults => "export_results",
+            Action::ToggleSearchOrder => "search_order",
+            Action::ToggleListOrder => "list_order",
+            Action::OrderUp => "order_up",
+            Action::OrderDown => "order_down",
+            Action::DeleteNote => "delete",
+            Action::DuplicateNote => "duplicate",
+            Action::DuplicateWithBacklinks => "duplicate_with_backlinks",
+            Action::ShowStatistics => "statistics",
+            Action::Refresh => "refresh",
+            Action::RandomNote => "random",
+            Action::TagGroups => "tag_groups",
+            Action::ManageTags => "manage_tags",
+            Action::ShowTasks => "tasks",
+            Action::ShowDue => "due_dates",
+            Action::UndoLast => "undo",
+            Action::ShowLog => "log",
+            Action::ShowRepoLog => "repo_log",
+            Action::BackupVault => "backup",
+            Action::RestoreBackup => "restore",
+            Action::CommitNow => "commit",
+            Action::ToggleClock => "clock",
+            Action::ShowDashboard => "dashboard",
+            Action::VaultHealth => "health",
+            Action::ShowHelp => "help",
+            Action::Quit => "quit",
+            Action::EditNote => "edit",
+            Action::ExternalEdit => "external_edit",
+            Action::LinkNote => "link",
+            Action::CreateLinkedNote => "create_linked",
+            Action::AddTag => "add_tag",
+            Action::UnlinkNote => "unlink",
+            Action::ReorderLinks => "reorder_links",
+            Action::RemoveTag => "remove_tag",
+            Action::EditMetadata => "metadata",
+            Action::RenameNote => "rename",
+            Action::ShowHistory => "history",
+            Action::MoveNote => "move",
+            Action::ToggleSpellcheck => "spellcheck",
+            Action::CycleLinkLabels => "link_ids",
+            Action::CycleHeader => "header",
+            Action::CycleColor => "color",
+            Action::EditScratch => "scratch",
+            Action::SetDue => "due",
+            Action::TogglePin => "pin",
+            Action::ToggleLock => "lock",
+            Action::ToggleLineBreaks => "line_breaks",
+            Action::EditRawJson => "raw_json",
+            Action::FindInNote => "find",
+            Action::NextMatch => "next_match",
+            Action::PrevMatch => "prev_match",
+            Action::ScrollDown => "scroll_down",
+            Action::ScrollUp => "scroll_up",
+            Action::ScrollRight => "scroll_right",
+            Action::ScrollLeft => "scroll_left",
+            Action::TrailBack => "trail_back",
+            Action::PickBreadcrumb => "breadcrumbs",
+            Action::ExportNote => "export",
+            Action::CopyNote => "copy",
+            Action::ExportWithLinks => "export_with_links",
+            Action::Back => "back",
+        }
+    }
+
+    /// Whether the action writes to the vault, so it is refused while the vault is read-only
+    pub fn changes_notes(self) -> bool {
+        matches!(
+            self,
+            Action::NewNote
+                | Action::TagSelected
+                | Action::LinkSelected
+                | Action::NewFromTemplate
+                | Action::DeleteNote
+                | Action::DuplicateNote
+                | Action::DuplicateWithBacklinks
+                | Action::UndoLast
+                | Action::RestoreBackup
+                | Action::CommitNow
+                | Action::EditNote
+                | Action::ExternalEdit
+                | Action::LinkNote
+                | Action::CreateLinkedNote
+                | Action::AddTag
+                | Action::UnlinkNote
+                | Action::ReorderLinks
+                | Action::RemoveTag
+                | Action::EditMetadata
+                | Action::RenameNote
+                | Action::MoveNote
+                | Action::CycleColor
+                | Action::EditScratch
+                | Action::SetDue
+                | Action::ToggleLock
+                | Action::ToggleLineBreaks
+                | Action::OrderUp
+                | Action::OrderDown
+                | Action::EditRawJson
+        )
+    }
+
+    /// Whether the action changes the open note's text, title, tags or links, or deletes
+    /// or moves it, so it is refused while the note is locked. Color, scratch line and due
+    /// date only organize the note and stay available.
+    pub fn edits_locked_note(self) -> bool {
+        matches!(
+            self,
+            Action::EditNote
+                | Action::ExternalEdit
+                | Action::LinkNote
+                | Action::CreateLinkedNote
+                | Action::AddTag
+                | Action::UnlinkNote
+                | Action::ReorderLinks
+                | Action::RemoveTag
+                | Action::EditMetadata
+                | Action::RenameNote
+                | Action::MoveNote
+                | Action::EditRawJson
+        )
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::MoveDown => "Navigate down",
+            Action::MoveUp => "Navigate up",
+            Action::Open => "Open selected note",
+            Action::ToggleSelect => "Add the note to or drop it from the selection for batch operations",
+            Action::TagSelected => "Tag the selected notes",
+            Action::LinkSelected => "Link the selected notes to a note",
+            Action::NewNote => "Create new note",
+            Action::NewFromTemplate => "Create note from template",
+            Action::Search => "Search notes",
+            Action::TagSearch => "Search by tag",
+            Action::SaveSearch => "Save the current search under a name",
+            Action::SavedSearches => "Show saved searches",
+            Action::ExportResults => "Export search results (or the selected notes) to a note or markdown file",
+            Action::ToggleSearchOrder => "Sort search results by relevance or newest first",
+            Action::ToggleListOrder => "Sort the list newest first or in manual order",
+            Action::OrderUp => "Move up the manual order",
+            Action::OrderDown => "Move down the manual order",
+            Action::DeleteNote => "Delete note (or the selected notes)",
+            Action::DuplicateNote => "Duplicate note",
+            Action::DuplicateWithBacklinks => "Duplicate note, linking its backlinks to the copy",
+            Action::ShowStatistics => "Show statistics",
+            Action::Refresh => "Refresh notes",
+            Action::RandomNote => "Open a random note",
+            Action::TagGroups => "Browse notes grouped by tag",
+            Action::ManageTags => "Manage tags (rename/delete everywhere)",
+            Action::ShowTasks => "Show open tasks from all notes",
+            Action::ShowDue => "Show notes with a due date, soonest first",
+            Action::UndoLast => "Undo the last delete, move, restore or tag change",
+            Action::ShowLog => "Show message log",
+            Action::ShowRepoLog => "Show the vault's commit log",
+            Action::BackupVault => "Backup vault to .tar.gz",
+            Action::RestoreBackup => "Restore from backup",
+            Action::CommitNow => "Commit the working copy now",
+            Action::ToggleClock => "Show or hide the clock and vault summary",
+            Action::ShowDashboard => "Show the dashboard of pinned notes, recent notes and saved searches",
+            Action::VaultHealth => "Vault health: every hygiene check with its count",
+            Action::ShowHelp => "Show this mode's keys (? again: every mode)",
+            Action::Quit => "Quit (or clear the selection or search)",
+            Action::EditNote => "Edit note",
+            Action::ExternalEdit => "Edit note in $EDITOR",
+            Action::LinkNote => "Link to another note",
+            Action::CreateLinkedNote => "Create a new note and link to it",
+            Action::AddTag => "Add tag",
+            Action::UnlinkNote => "Unlink selected note",
+            Action::ReorderLinks => "Reorder links",
+            Action::RemoveTag => "Remove tag",
+            Action::EditMetadata => "Edit metadata fields",
+            Action::RenameNote => "Rename note",
+            Action::ShowHistory => "Show commit history",
+            Action::MoveNote => "Move to another vault",
+            Action::ToggleSpellcheck => "Toggle spell check",
+            Action::CycleLinkLabels => "Show link titles, titles with ids, or raw ids",
+            Action::CycleHeader => "Show the note header in full, as one line, or not at all",
+            Action::CycleColor => "Cycle color label",
+            Action::EditScratch => "Edit scratch line",
+            Action::SetDue => "Set or clear the due date",
+            Action::TogglePin => "Pin to or unpin from the dashboard",
+            Action::ToggleLock => "Lock the note against edits and deletion, or unlock it",
+            Action::ToggleLineBreaks => "Show this note's line breaks as written, or join paragraph lines",
+            Action::EditRawJson => "Edit the note's raw JSON",
+            Action::FindInNote => "Find in note",
+            Action::NextMatch => "Next match",
+            Action::PrevMatch => "Previous match",
+            Action::ScrollDown => "Scroll down",
+            Action::ScrollUp => "Scroll up",
+            Action::ScrollRight => "Scroll right (wrapping off)",
+            Action::ScrollLeft => "Scroll left (wrapping off)",
+            Action::TrailBack => "Back to the previous note on the breadcrumb trail",
+            Action::PickBreadcrumb => "Pick a note on the breadcrumb trail to go back to",
+            Action::ExportNote => "Export to markdown",
+            Action::CopyNote => "Copy as markdown to the clipboard",
+            Action::ExportWithLinks => "Export with the notes it links to",
+            Action::Back => "Back to list",
+        }
+    }
+}
+
+/// A single key press, e.g. `Ctrl+s` or `Enter`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    code: KeyCode,
+    ctrl: bool,
+    alt: bool,
+}
+
+impl KeyBinding {
+    /// Parse a key name such as `j`, `E`, `Enter`, `Down` or `Ctrl+s`
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut ctrl = false;
+        let mut alt = false;
+        let mut rest = spec;
+        loop {
+            if let Some(stripped) = rest.strip_prefix("Ctrl+") {
+                ctrl = true;
+                rest = stripped;
+            } else if let Some(stripped) = rest.strip_prefix("Alt+") {
+                alt = true;
+                rest = stripped;
+            } else {
+                break;
+            }
+        }
+
+        let code = match rest {
+            "Enter" => KeyCode::Enter,
+            "Esc" => KeyCode::Esc,
+            "Tab" => KeyCode::Tab,
+            "Backspace" => KeyCode::Backspace,
+            "Delete" => KeyCode::Delete,
+            "Space" => KeyCode::Char(' '),
+            "Up" => KeyCode::Up,
+            "Down" => KeyCode::Down,
+            "Left" => KeyCode::Left,
+            "Right" => KeyCode::Right,
+            "Home" => KeyCode::Home,
+            "End" => KeyCode::End,
+            "PageUp" => KeyCode::PageUp,
+            "PageDown" => KeyCode::PageDown,
+            _ => {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(if ctrl { c.to_ascii_lowercase() } else { c }),
+                    _ => anyhow::bail!("Unknown key '{}'", spec),
+                }
+            }
+        };
+
+        Ok(KeyBinding { code, ctrl, alt })
+    }
+
+    fn from_event(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+        let code = match code {
+            KeyCode::Char(c) if ctrl => KeyCode::Char(c.to_ascii_lowercase()),
+            other => other,
+        };
+        KeyBinding {
+            code,
+            ctrl,
+            alt: modifiers.contains(KeyModifiers::ALT),
+        }
+    }
+
+    /// Human-readable key name for the help screen
+    pub fn label(&self) -> String {
+        let key = match self.code {
+            KeyCode::Enter => "Enter".to_string(),
+            KeyCode::Esc => "Esc".to_string(),
+            KeyCode::Tab => "Tab".to_string(),
+            KeyCode::Backspace => "Backspace".to_string(),
+            KeyCode::Delete => "Delete".to_string(),
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Up => "↑".to_string(),
+            KeyCode::Down => "↓".to_string(),
+            KeyCode::Left => "←".to_string(),
+            KeyCode::Right => "→".to_string(),
+            KeyCode::Home => "Home".to_string(),
+            KeyCode::End => "End".to_string(),
+            KeyCode::PageUp => "PageUp".to_string(),
+            KeyCode::PageDown => "PageDown".to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            _ => "?".to_string(),
+        };
+        let mut label = String::new();
+        if self.ctrl {
+            label.push_str("Ctrl+");
+        }
+        if self.alt {
+            label.push_str("Alt+");
+        }
+        label.push_str(&key);
+        label
+    }
+}
+
+/// Effective key → action mapping for each remappable mode
+pub struct Keymap {
+    bindings: HashMap<KeyContext, Vec<(Action, Vec<KeyBinding>)>>,
+}
+
+impl Keymap {
+    /// Build the keymap from config overrides, filling unspecified actions with defaults.
+    /// Fails if two actions in the same mode end up on the same key.
+    pub fn from_config(overrides: &HashMap<String, HashMap<String, KeySpec>>) -> Result<Self> {
+        for (context_name, actions) in overrides {
+            let context = KeyContext::from_name(context_name).ok_or_else(|| {
+                anyhow::anyhow!("Unknown keybinding mode '{}' (expected list or view)", context_name)
+            })?;
+            for action_name in actions.keys() {
+                if !context.defaults().iter().any(|(action, _)| action.name() == action_name) {
+                    anyhow::bail!("Unknown action '{}' in {} keybindings", action_name, context.name());
+                }
+            }
+        }
+
+        let mut bindings = HashMap::new();
+        for context in KeyContext::ALL {
+            let context_overrides = overrides.get(context.name());
+            let mut actions = Vec::new();
+            let mut seen: HashMap<KeyBinding, Action> = HashMap::new();
+
+            for (action, default_keys) in context.defaults() {
+                let keys: Vec<String> = match context_overrides.and_then(|o| o.get(action.name())) {
+                    Some(spec) => spec.keys(),
+                    None => default_keys.iter().map(|k| k.to_string()).collect(),
+                };
+                let mut parsed = Vec::new();
+                for key in keys {
+                    let binding = KeyBinding::parse(&key)?;
+                    if let Some(other) = seen.get(&binding)
+                        && other != action
+                    {
+                        anyhow::bail!(
+                            "Key '{}' is bound to both '{}' and '{}' in {} mode",
+                            key,
+                            other.name(),
+                            action.name(),
+                            context.name()
+                        );
+                    }
+                    seen.insert(binding, *action);
+                    parsed.push(binding);
+                }
+                actions.push((*action, parsed));
+            }
+            bindings.insert(context, actions);
+        }
+
+        Ok(Keymap { bindings })
+    }
+
+    /// Take every key off `action`, for features turned off in the config
+    pub fn unbind(&mut self, action: Action) {
+        for actions in self.bindings.values_mut() {
+            for (bound, keys) in actions.iter_mut() {
+                if *bound == action {
+                    keys.clear();
+                }
+            }
+        }
+    }
+
+    /// Look up the action bound to a key press in the given mode
+    pub fn action(&self, context: KeyContext, code: KeyCode, modifiers: KeyModifiers) -> Option<Action> {
+        let pressed = KeyBinding::from_event(code, modifiers);
+        // Shift is implied by the character itself ('E' vs 'e'), so it is not compared
+        self.bindings
+            .get(&context)?
+            .iter()
+            .find_map(|(action, keys)| keys.contains(&pressed).then_some(*action))
+    }
+
+    /// Label of the first key bound to `action` in a mode, for hints naming the key to
+    /// press; `None` if the action has no key there
+    pub fn key_label(&self, context: KeyContext, action: Action) -> Option<String> {
+        self.bindings
+            .get(&context)?
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .and_then(|(_, keys)| keys.first())
+            .map(|key| key.label())
+    }
+
+    /// Key to name for `action` in hints and messages: its first key, or the config name to
+    /// bind when it has none
+    pub fn key_hint(&self, context: KeyContext, action: Action) -> String {
+        self.key_label(context, action).unwrap_or_else(|| format!("a key bound to {}", action.name()))
+    }
+
+    /// Footer help bar of `keys: what` entries, the keys of each entry's actions taken from
+    /// the keymap and joined by `/`. Entries whose actions are all unbound are left out; an
+    /// entry without actions is shown as written.
+    pub fn help_bar(&self, context: KeyContext, entries: &[(&[Action], &str)]) -> String {
+        entries
+            .iter()
+            .filter_map(|(actions, what)| {
+                if actions.is_empty() {
+                    return Some(what.to_string());
+                }
+                let keys: Vec<String> = actions.iter().filter_map(|&action| self.key_label(context, action)).collect();
+                (!keys.is_empty()).then(|| format!("{}: {}", keys.join("/"), what))
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+
+    /// Effective bindings of a mode as `(keys, description)` rows for the help screen
+    pub fn help_rows(&self, context: KeyContext) -> Vec<(String, &'static str)> {
+        self.bindings
+            .get(&context)
+            .map(|actions| {
+                actions
+                    .iter()
+                    .filter(|(_, keys)| !keys.is_empty())
+                    .map(|(action, keys)| {
+                        let labels: Vec<String> = keys.iter().map(|k| k.label()).collect();
+                        (labels.join(" / "), action.description())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn keymap(json: &str) -> Result<Keymap> {
+        let config: Config = serde_json::from_str(json).expect("test config parses");
+        Keymap::from_config(&config.keybindings)
+    }
+
+    fn press(keymap: &Keymap, context: KeyContext, c: char) -> Option<Action> {
+        keymap.action(context, KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn remapped_action_moves_to_its_new_key() {
+        let keymap = keymap(r#"{"keybindings": {"list": {"new_note": "N"}}}"#).unwrap();
+        assert_eq!(press(&keymap, KeyContext::List, 'N'), Some(Action::NewNote));
+        assert_eq!(press(&keymap, KeyContext::List, 'n'), None);
+        assert!(keymap.help_rows(KeyContext::List).contains(&("N".to_string(), Action::NewNote.description())));
+    }
+
+    #[test]
+    fn remap_onto_a_taken_key_is_rejected() {
+        let err = keymap(r#"{"keybindings": {"view": {"edit": "l"}}}"#).err().expect("conflict is an error");
+        assert_eq!(err.to_string(), "Key 'l' is bound to both 'edit' and 'link' in view mode");
+    }
+
+    #[test]
+    fn unspecified_actions_keep_their_defaults() {
+        let keymap = keymap(r#"{"keybindings": {"list": {"new_note": ["N", "Ctrl+n"]}}}"#).unwrap();
+        assert_eq!(press(&keymap, KeyContext::List, 'j'), Some(Action::MoveDown));
+        assert_eq!(keymap.action(KeyContext::List, KeyCode::Down, KeyModifiers::NONE), Some(Action::MoveDown));
+        assert_eq!(keymap.action(KeyContext::List, KeyCode::Char('n'), KeyModifiers::CONTROL), Some(Action::NewNote));
+        // The other mode is untouched
+        assert_eq!(press(&keymap, KeyContext::View, 'e'), Some(Action::EditNote));
+        assert_eq!(press(&keymap, KeyContext::View, 'l'), Some(Action::LinkNote));
+    }
+
+    #[test]
+    fn key_labels_follow_remaps_and_unbinding() {
+        let mut keymap = keymap(r#"{"keybindings": {"view": {"lock": ["Ctrl+l", "U"]}}}"#).unwrap();
+        assert_eq!(keymap.key_label(KeyContext::View, Action::ToggleLock).as_deref(), Some("Ctrl+l"));
+        assert_eq!(keymap.key_label(KeyContext::View, Action::EditNote).as_deref(), Some("e"));
+        assert_eq!(keymap.key_label(KeyContext::List, Action::ToggleLock), None, "not a list action");
+        keymap.unbind(Action::ToggleLock);
+        assert_eq!(keymap.key_label(KeyContext::View, Action::ToggleLock), None);
+    }
+
+    #[test]
+    fn help_bar_shows_the_bound_keys_and_drops_unbound_actions() {
+        let mut keymap = keymap(r#"{"keybindings": {"list": {"new_note": "a"}}}"#).unwrap();
+        let entries: &[(&[Action], &str)] = &[
+            (&[Action::MoveDown, Action::MoveUp], "navigate"),
+            (&[Action::NewNote], "new"),
+            (&[Action::Refresh], "refresh"),
+            (&[], "1-9: jump"),
+        ];
+        assert_eq!(keymap.help_bar(KeyContext::List, entries), "j/k: navigate | a: new | r: refresh | 1-9: jump");
+        keymap.unbind(Action::Refresh);
+        assert_eq!(keymap.help_bar(KeyContext::List, entries), "j/k: navigate | a: new | 1-9: jump");
+        assert_eq!(keymap.key_hint(KeyContext::List, Action::Refresh), "a key bound to refresh");
+    }
+
+    #[test]
+    fn unknown_actions_and_modes_are_rejected() {
+        assert!(keymap(r#"{"keybindings": {"list": {"fly": "f"}}}"#).is_err());
+        assert!(keymap(r#"{"keybindings": {"edit": {"new_note": "N"}}}"#).is_err());
+    }
+}
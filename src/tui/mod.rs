@@ -1,2 +1,7 @@
 pub mod app;
-
+pub mod clipboard;
+pub mod editor;
+pub mod hyperlink;
+pub mod keymap;
+pub mod render;
+pub mod tutorial;
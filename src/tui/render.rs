@@ -0,0 +1,291 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Styles note content for View mode. Renderers only color the text, they never add or
+/// drop characters, and return exactly one `Line` per content line, so find matches,
+/// spell-check marks and scroll positions keep lining up with the content.
+pub trait Renderer {
+    fn render<'a>(&self, content: &'a str) -> Vec<Line<'a>>;
+}
+
+/// Renderer for a note's `format`; formats without one of their own are shown as plain text
+pub fn renderer_for(format: &str) -> &'static dyn Renderer {
+    match format {
+        "markdown" => &Markdown,
+        "org" => &Outline { heading: '*' },
+        "asciidoc" => &Outline { heading: '=' },
+        _ => &PlainText,
+    }
+}
+
+/// Every line as it is
+pub struct PlainText;
+
+impl Renderer for PlainText {
+    fn render<'a>(&self, content: &'a str) -> Vec<Line<'a>> {
+        content.lines().map(|line| Line::from(Span::styled(line, text_style()))).collect()
+    }
+}
+
+/// Headings, fenced code, quotes, list markers, `inline code` and **bold**
+pub struct Markdown;
+
+impl Renderer for Markdown {
+    fn render<'a>(&self, content: &'a str) -> Vec<Line<'a>> {
+        let mut in_code = false;
+        content
+            .lines()
+            .map(|line| {
+                let trimmed = line.trim_start();
+                if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                    in_code = !in_code;
+                    return Line::from(Span::styled(line, Style::default().fg(Color::DarkGray)));
+                }
+                if in_code {
+                    return Line::from(Span::styled(line, code_style()));
+                }
+                let hashes = trimmed.len() - trimmed.trim_start_matches('#').len();
+                if (1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ') {
+                    let style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+                    let style = if hashes == 1 { style.add_modifier(Modifier::UNDERLINED) } else { style };
+                    return Line::from(Span::styled(line, style));
+                }
+                if trimmed.starts_with('>') {
+                    return Line::from(Span::styled(line, Style::default().fg(Color::Gray).add_modifier(Modifier::ITALIC)));
+                }
+                let marker = list_marker_len(trimmed).map_or(0, |len| line.len() - trimmed.len() + len);
+                let mut spans = Vec::new();
+                if marker > 0 {
+                    spans.push(Span::styled(&line[..marker], Style::default().fg(Color::Red)));
+                }
+                spans.extend(inline_spans(&line[marker..]));
+                Line::from(spans)
+            })
+            .collect()
+    }
+}
+
+/// Stand-in for org and asciidoc until they get a renderer of their own: headings (lines
+/// starting with `heading` markers and a space) stand out, everything else is plain text
+pub struct Outline {
+    heading: char,
+}
+
+impl Renderer for Outline {
+    fn render<'a>(&self, content: &'a str) -> Vec<Line<'a>> {
+        content
+            .lines()
+            .map(|line| {
+                let markers = line.len() - line.trim_start_matches(self.heading).len();
+                if markers > 0 && line[markers..].starts_with(' ') {
+                    Line::from(Span::styled(line, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)))
+                } else {
+                    Line::from(Span::styled(line, text_style()))
+                }
+            })
+            .collect()
+    }
+}
+
+/// Content lines grouped into the rows paragraph mode shows, as ranges of line indices.
+/// As in markdown, a single line break inside a paragraph is soft: the line joins the row
+/// before it. Blank lines, headings, list items, quotes, table rows, indented or fenced code
+/// and the line after a hard break (two trailing spaces or a `\`) start a row of their own.
+/// The rows cover every line once and in order, so splitting them back up gives the content.
+pub fn paragraph_rows(content: &str) -> Vec<Range<usize>> {
+    let mut rows: Vec<Range<usize>> = Vec::new();
+    let mut in_code = false;
+    let mut joins_next = false; // The previous line lets the next one continue its row
+    for (i, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let fence = trimmed.starts_with("```") || trimmed.starts_with("~~~");
+        let plain = !in_code
+            && !fence
+            && !trimmed.is_empty()
+            && !is_heading(trimmed)
+            && !trimmed.starts_with(['>', '|'])
+            && list_marker_len(trimmed).is_none()
+            && line.len() - trimmed.len() < 4;
+        match rows.last_mut() {
+            Some(row) if joins_next && plain => row.end = i + 1,
+            _ => rows.push(i..i + 1),
+        }
+        if fence {
+            in_code = !in_code;
+        }
+        let hard_break = line.ends_with("  ") || line.ends_with('\\');
+        joins_next = !in_code
+            && !fence
+            && !trimmed.is_empty()
+            && !is_heading(trimmed)
+            && !trimmed.starts_with('|')
+            && !hard_break
+            && line.len() - trimmed.len() < 4;
+    }
+    rows
+}
+
+/// Styled content lines (one per content line) merged into `rows` from `paragraph_rows`,
+/// each soft break shown as a space and the indentation of joined lines dropped
+pub fn join_soft_breaks<'a>(lines: Vec<Line<'a>>, rows: &[Range<usize>]) -> Vec<Line<'a>> {
+    let mut lines = lines.into_iter();
+    rows.iter()
+        .filter_map(|row| {
+            let mut joined = lines.next()?;
+            for mut line in lines.by_ref().take(row.len() - 1) {
+                if let Some(first) = line.spans.first_mut() {
+                    first.content = match std::mem::take(&mut first.content) {
+                        Cow::Borrowed(text) => Cow::Borrowed(text.trim_start()),
+                        Cow::Owned(text) => Cow::Owned(text.trim_start().to_string()),
+                    };
+                }
+                joined.spans.push(Span::styled(" ", text_style()));
+                joined.spans.extend(line.spans);
+            }
+            Some(joined)
+        })
+        .collect()
+}
+
+fn text_style() -> Style {
+    Style::default().fg(Color::White)
+}
+
+fn code_style() -> Style {
+    Style::default().fg(Color::Green)
+}
+
+/// Whether `line` (without its indentation) is a `#` to `######` heading
+fn is_heading(line: &str) -> bool {
+    let hashes = line.len() - line.trim_start_matches('#').len();
+    (1..=6).contains(&hashes) && line[hashes..].starts_with(' ')
+}
+
+/// Length of the bullet (`- `, `* `, `+ `) or number (`1. `, `2) `) starting `line`
+fn list_marker_len(line: &str) -> Option<usize> {
+    if line.starts_with(['-', '*', '+']) && line[1..].starts_with(' ') {
+        return Some(2);
+    }
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let rest = &line[digits..];
+    (digits > 0 && (rest.starts_with(". ") || rest.starts_with(") "))).then_some(digits + 2)
+}
+
+/// `text` split into plain, `code` and **bold** spans; an unclosed marker stays plain
+fn inline_spans(text: &str) -> Vec<Span<'_>> {
+    let mut spans = Vec::new();
+    let mut plain_start = 0;
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        let (marker, style) = if rest.starts_with('`') {
+            ("`", code_style())
+        } else if rest.starts_with("**") {
+            ("**", text_style().add_modifier(Modifier::BOLD))
+        } else {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+            continue;
+        };
+        let Some(close) = rest[marker.len()..].find(marker) else {
+            i += marker.len();
+            continue;
+        };
+        let end = i + marker.len() + close + marker.len();
+        if plain_start < i {
+            spans.push(Span::styled(&text[plain_start..i], text_style()));
+        }
+        spans.push(Span::styled(&text[i..end], style));
+        plain_start = end;
+        i = end;
+    }
+    if plain_start < text.len() || spans.is_empty() {
+        spans.push(Span::styled(&text[plain_start..], text_style()));
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The rows paragraph mode shows for `content`, as plain text
+    fn joined(content: &str) -> Vec<String> {
+        let rows = paragraph_rows(content);
+        join_soft_breaks(PlainText.render(content), &rows).iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn soft_breaks_join_into_one_row() {
+        assert_eq!(joined("One line\n  broken in two\n\nNext paragraph"), ["One line broken in two", "", "Next paragraph"]);
+    }
+
+    #[test]
+    fn hard_breaks_and_block_lines_keep_their_rows() {
+        let content = "Hard break  \nafter it\nbackslash\\\nafter it\n# Heading\ntext\n- item\n- item\n> quote\n|a|b|\n    indented code";
+        assert_eq!(
+            joined(content),
+            ["Hard break  ", "after it backslash\\", "after it", "# Heading", "text", "- item", "- item", "> quote", "|a|b|", "    indented code"]
+        );
+    }
+
+    #[test]
+    fn fenced_code_is_never_joined() {
+        assert_eq!(joined("Before\n```\nlet a = 1;\nlet b = 2;\n```\nafter"), ["Before", "```", "let a = 1;", "let b = 2;", "```", "after"]);
+    }
+
+    #[test]
+    fn rows_cover_every_line_once_in_order() {
+        let content = "Title\n\nsoft\nwrapped  \nhard\n```\ncode\n```\n- a\n  continued\n";
+        let rows = paragraph_rows(content);
+        let covered: Vec<usize> = rows.iter().flat_map(|row| row.clone()).collect();
+        assert_eq!(covered, (0..content.lines().count()).collect::<Vec<_>>());
+        let lines: Vec<&str> = content.lines().collect();
+        let split_back: Vec<&str> = rows.iter().flat_map(|row| lines[row.clone()].iter().copied()).collect();
+        assert_eq!(split_back.join("\n") + "\n", content, "splitting the rows back up gives the content");
+    }
+
+    fn heading_style() -> Style {
+        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+    }
+
+    /// Style of each line as a whole, for lines rendered as a single span
+    fn line_styles(lines: &[Line]) -> Vec<Style> {
+        lines
+            .iter()
+            .map(|line| {
+                assert_eq!(line.spans.len(), 1, "{:?}", line);
+                line.spans[0].style
+            })
+            .collect()
+    }
+
+    #[test]
+    fn plaintext_is_shown_verbatim_and_markdown_styled() {
+        let content = "# Not a heading\n**not bold**";
+        let plain = renderer_for("plaintext").render(content);
+        assert_eq!(line_styles(&plain), [text_style(), text_style()]);
+        let markdown = renderer_for("markdown").render(content);
+        assert_eq!(markdown[0].spans[0].style, heading_style().add_modifier(Modifier::UNDERLINED));
+        assert_eq!(markdown[1].spans[0].style, text_style().add_modifier(Modifier::BOLD));
+    }
+
+    #[test]
+    fn org_and_asciidoc_headings_stand_out() {
+        let org = renderer_for("org").render("* Top\n** Sub\n*bold* text\n= not org");
+        assert_eq!(line_styles(&org), [heading_style(), heading_style(), text_style(), text_style()]);
+        let asciidoc = renderer_for("asciidoc").render("= Document\n== Section\n==no space\n* item");
+        assert_eq!(line_styles(&asciidoc), [heading_style(), heading_style(), text_style(), text_style()]);
+    }
+
+    #[test]
+    fn links_are_kept_as_written_in_every_format() {
+        let content = "See [[https://example.com][the site]] and link:https://example.com[docs]\n[[Other note]] https://example.com/x";
+        for format in ["markdown", "plaintext", "org", "asciidoc", "unknown"] {
+            let lines = renderer_for(format).render(content);
+            let text: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+            assert_eq!(text, content.lines().collect::<Vec<_>>(), "{}", format);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::tui::keymap::{Action, KeyContext, Keymap};
+
+/// Steps of the first-run walkthrough, in order. Each waits for the user to try the
+/// feature it explains; the app checks for that after every key press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TutorialStep {
+    Welcome,
+    AddTag,
+    AddLink,
+    Search,
+    Finished,
+}
+
+/// Title and text of the sample notes the walkthrough creates
+pub const SAMPLE_NOTES: [(&str, &str); 2] = [
+    (
+        "Linking notes",
+        "Linking notes\n\nNotes link to each other by id, so links survive renames. \
+         In View mode, l links the open note to another one and u removes a link.",
+    ),
+    (
+        "Welcome to jjzettel",
+        "Welcome to jjzettel\n\nEvery note is a JSON file in the vault, and every change is a Jujutsu commit, \
+         so nothing is ever lost. Press e to edit this note and Ctrl+S to save it.",
+    ),
+];
+
+impl TutorialStep {
+    /// Text shown in the walkthrough panel, naming the keys `keymap` binds
+    pub fn text(self, keymap: &Keymap) -> String {
+        match self {
+            TutorialStep::Welcome => {
+                "Welcome! This short tour creates two sample notes and shows tagging, linking and search.\n\n\
+                 Enter: start | Esc: skip the tour"
+                    .to_string()
+            }
+            TutorialStep::AddTag => format!(
+                "This is a note in View mode. Press {}, type a tag such as tutorial, and press Enter.",
+                keymap.key_hint(KeyContext::View, Action::AddTag)
+            ),
+            TutorialStep::AddLink => format!(
+                "Tagged! Now press {}, pick 'Linking notes' with j/k, and press Enter to link to it.",
+                keymap.key_hint(KeyContext::View, Action::LinkNote)
+            ),
+            TutorialStep::Search => format!(
+                "Linked! Press {} to go back to the list, then {} to search: type welcome and press Enter.",
+                keymap.key_hint(KeyContext::View, Action::Back),
+                keymap.key_hint(KeyContext::List, Action::Search)
+            ),
+            TutorialStep::Finished => format!(
+                "That's the tour. Press {} in the list for every key, and Ctrl+N anywhere to capture a thought.\n\n\
+                 Enter: finish",
+                keymap.key_hint(KeyContext::List, Action::ShowHelp)
+            ),
+        }
+    }
+
+    /// The step after this one; the last step has none
+    pub fn next(self) -> Option<TutorialStep> {
+        match self {
+            TutorialStep::Welcome => Some(TutorialStep::AddTag),
+            TutorialStep::AddTag => Some(TutorialStep::AddLink),
+            TutorialStep::AddLink => Some(TutorialStep::Search),
+            TutorialStep::Search => Some(TutorialStep::Finished),
+            TutorialStep::Finished => None,
+        }
+    }
+
+    /// Welcome and Finished wait for Enter; the other steps let keys through to the app
+    pub fn is_modal(self) -> bool {
+        matches!(self, TutorialStep::Welcome | TutorialStep::Finished)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn steps_run_in_order_and_end() {
+        let mut steps = vec![TutorialStep::Welcome];
+        while let Some(next) = steps.last().unwrap().next() {
+            steps.push(next);
+        }
+        assert_eq!(
+            steps,
+            [TutorialStep::Welcome, TutorialStep::AddTag, TutorialStep::AddLink, TutorialStep::Search, TutorialStep::Finished]
+        );
+        let modal: Vec<bool> = steps.iter().map(|step| step.is_modal()).collect();
+        assert_eq!(modal, [true, false, false, false, true]);
+    }
+
+    #[test]
+    fn step_text_names_the_configured_keys() {
+        let config: Config = serde_json::from_str(r#"{"keybindings": {"view": {"add_tag": "T"}}}"#).unwrap();
+        let keymap = Keymap::from_config(&config.keybindings).unwrap();
+        assert!(TutorialStep::AddTag.text(&keymap).starts_with("This is a note in View mode. Press T,"));
+        assert!(TutorialStep::AddLink.text(&keymap).starts_with("Tagged! Now press l,"));
+    }
+}